@@ -1,7 +1,9 @@
+use crate::node_pb;
 use crate::spawn;
 use crate::EncodedBlock;
 
 use candid::CandidType;
+use ic_base_types::PrincipalId;
 use ic_types::ic00::{Method, IC_00};
 use ic_types::CanisterId;
 use serde::{Deserialize, Serialize};
@@ -11,6 +13,9 @@ use std::collections::VecDeque;
 const ARCHIVE_NODE_BYTECODE: &[u8] =
     std::include_bytes!("./wasm/ledger-archive-node-canister.wasm");
 
+/// How many blocks are read from an archive node at once when rebalancing.
+const REBALANCE_FETCH_LENGTH: u64 = 1_000;
+
 #[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
 pub struct ArchiveOptions {
     /// The number of blocks which, when exceeded, will trigger an archiving
@@ -197,13 +202,30 @@ impl Archive {
     async fn create_and_initialize_node_canister(
         &mut self,
     ) -> Result<(CanisterId, usize, usize), FailedToArchiveBlocks> {
-        print("[archive] calling create_canister()");
-        let node_canister_id: CanisterId = spawn::create_canister().await;
         let node_block_height_offset: u64 = self
             .nodes_block_ranges
             .last()
             .map(|(_, height_to)| *height_to + 1)
             .unwrap_or(0);
+        let node_canister_id = self.create_node_canister(node_block_height_offset).await?;
+
+        self.nodes.push(node_canister_id);
+
+        let node_index = self.last_node_index();
+
+        let remaining_capacity = remaining_capacity(node_canister_id).await?;
+
+        Ok((node_canister_id, node_index, remaining_capacity))
+    }
+
+    // Helper function to create an archive node whose first block is at
+    // `node_block_height_offset`. The node is not added to the index.
+    async fn create_node_canister(
+        &self,
+        node_block_height_offset: u64,
+    ) -> Result<CanisterId, FailedToArchiveBlocks> {
+        print("[archive] calling create_canister()");
+        let node_canister_id: CanisterId = spawn::create_canister().await;
         print("[archive] calling install_code()");
 
         // We don't inspect the result here because according to MW the install canister
@@ -245,20 +267,7 @@ impl Archive {
             FailedToArchiveBlocks(s)
         })?;
 
-        self.nodes.push(node_canister_id);
-
-        let node_index = self.last_node_index();
-
-        let remaining_capacity: usize = dfn_core::api::call_with_cleanup(
-            node_canister_id,
-            "remaining_capacity",
-            dfn_candid::candid_one,
-            (),
-        )
-        .await
-        .map_err(|(_, msg)| FailedToArchiveBlocks(msg))?;
-
-        Ok((node_canister_id, node_index, remaining_capacity))
+        Ok(node_canister_id)
     }
 
     /// Helper function to find the CanisterId of the node that can accept
@@ -324,6 +333,167 @@ impl Archive {
             .zip(self.nodes.clone())
             .collect()
     }
+
+    /// Sets the capacity of archive nodes created from now on. Existing
+    /// nodes keep their capacity until the archive is rebalanced.
+    pub fn set_node_max_memory_size_bytes(&mut self, node_max_memory_size_bytes: usize) {
+        self.node_max_memory_size_bytes = node_max_memory_size_bytes;
+    }
+
+    /// Copies the archived blocks into fresh archive nodes of the current
+    /// `node_max_memory_size_bytes`, packing each node as full as it goes,
+    /// and then switches the index over to the new nodes.
+    ///
+    /// The index keeps pointing to the old nodes, which keep all their
+    /// blocks, until every block was copied, so lookups are answered
+    /// throughout and a failure leaves the index untouched. The old nodes
+    /// are returned so that the controller can delete them. Only the
+    /// controller of the archive nodes may rebalance them.
+    pub async fn rebalance_archives(
+        &mut self,
+        caller: PrincipalId,
+    ) -> Result<RebalanceSummary, String> {
+        if caller != self.controller_id.get() {
+            return Err("Only the controller of the archive nodes can rebalance them".to_string());
+        }
+        print("[archive] rebalance_archives(): start");
+
+        let old_index = self.index();
+        let mut new_index: Vec<((u64, u64), CanisterId)> = vec![];
+        // The node blocks are copied to and its remaining capacity
+        let mut target: Option<(CanisterId, usize)> = None;
+
+        for ((height_from, height_to), node) in old_index.iter().copied() {
+            let mut height = height_from;
+            while height <= height_to {
+                let length = REBALANCE_FETCH_LENGTH.min(height_to - height + 1);
+                let mut blocks: VecDeque<EncodedBlock> =
+                    fetch_blocks(node, height, length).await?.into();
+                if blocks.is_empty() {
+                    return Err(format!(
+                        "Archive node {} returned no blocks at height {}",
+                        node, height
+                    ));
+                }
+
+                while !blocks.is_empty() {
+                    let needed = blocks[0].size_bytes();
+                    let (target_id, capacity) = match target {
+                        Some((target_id, capacity)) if capacity >= needed => (target_id, capacity),
+                        _ => {
+                            let target_id = self
+                                .create_node_canister(height)
+                                .await
+                                .map_err(|FailedToArchiveBlocks(msg)| msg)?;
+                            let capacity = remaining_capacity(target_id)
+                                .await
+                                .map_err(|FailedToArchiveBlocks(msg)| msg)?;
+                            print(format!(
+                                "[archive] created node {} for blocks from height {}",
+                                target_id, height
+                            ));
+                            (target_id, capacity)
+                        }
+                    };
+
+                    let chunk = take_prefix(&mut blocks, capacity.min(self.max_message_size_bytes));
+                    if chunk.is_empty() {
+                        return Err(format!(
+                            "Block {} of {} bytes does not fit into an archive node",
+                            height, needed
+                        ));
+                    }
+                    let chunk_len = chunk.len() as u64;
+                    let chunk_size: usize = chunk.iter().map(|b| b.size_bytes()).sum();
+                    dfn_core::api::call_with_cleanup(
+                        target_id,
+                        "append_blocks",
+                        dfn_candid::candid_one,
+                        chunk,
+                    )
+                    .await
+                    .map_err(|(_, msg)| msg)?;
+
+                    match new_index.last_mut() {
+                        Some(((_, end), canister_id)) if *canister_id == target_id => {
+                            *end = height + chunk_len - 1
+                        }
+                        _ => new_index.push(((height, height + chunk_len - 1), target_id)),
+                    }
+                    target = Some((target_id, capacity - chunk_size));
+                    height += chunk_len;
+                }
+            }
+        }
+
+        let old_end = old_index.last().map(|((_, end), _)| *end);
+        let new_end = new_index.last().map(|((_, end), _)| *end);
+        if old_end != new_end {
+            return Err(format!(
+                "Rebalancing copied blocks up to {:?} instead of {:?}",
+                new_end, old_end
+            ));
+        }
+
+        let (nodes_block_ranges, nodes) = new_index.iter().copied().unzip();
+        self.nodes_block_ranges = nodes_block_ranges;
+        self.nodes = nodes;
+        print("[archive] rebalance_archives() done");
+
+        Ok(RebalanceSummary {
+            nodes: new_index,
+            retired_nodes: old_index.into_iter().map(|(_, node)| node).collect(),
+        })
+    }
+}
+
+/// The outcome of rebalancing the archive nodes.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct RebalanceSummary {
+    /// The archive nodes now holding the archived blocks, with the range of
+    /// heights each holds.
+    pub nodes: Vec<((u64, u64), CanisterId)>,
+    /// The archive nodes that are no longer part of the index. They still
+    /// hold their blocks until the controller deletes them.
+    pub retired_nodes: Vec<CanisterId>,
+}
+
+/// Helper function to ask an archive node how many more bytes it can store.
+async fn remaining_capacity(node_canister_id: CanisterId) -> Result<usize, FailedToArchiveBlocks> {
+    dfn_core::api::call_with_cleanup(
+        node_canister_id,
+        "remaining_capacity",
+        dfn_candid::candid_one,
+        (),
+    )
+    .await
+    .map_err(|(_, msg)| FailedToArchiveBlocks(msg))
+}
+
+/// Helper function to read `length` blocks starting at `start` from an
+/// archive node.
+async fn fetch_blocks(
+    node_canister_id: CanisterId,
+    start: u64,
+    length: u64,
+) -> Result<Vec<EncodedBlock>, String> {
+    let response: node_pb::GetBlocksResponse = dfn_core::api::call_with_cleanup(
+        node_canister_id,
+        "get_blocks_pb",
+        dfn_protobuf::protobuf,
+        node_pb::GetBlocksRequest { start, length },
+    )
+    .await
+    .map_err(|(_, msg)| msg)?;
+    match response.get_blocks_content {
+        Some(node_pb::GetBlocksContent::Blocks(blocks)) => Ok(blocks
+            .blocks
+            .into_iter()
+            .map(|b| EncodedBlock::from(b.block.into_boxed_slice()))
+            .collect()),
+        Some(node_pb::GetBlocksContent::Error(msg)) => Err(msg),
+        None => Err("Archive node returned an empty response".to_string()),
+    }
 }
 
 /// Extract longest prefix from `blocks` which fits in `max_size`
@@ -1,4 +1,5 @@
 pub mod archive;
+mod node_pb;
 pub mod spawn;
 
 use serde::{
@@ -0,0 +1,37 @@
+//! The protobuf messages of the archive node's get_blocks_pb endpoint. They
+//! mirror GetBlocksRequest and GetBlocksResponse of ic_ledger.pb.v1, which
+//! this crate cannot depend on.
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetBlocksRequest {
+    #[prost(uint64, tag = "1")]
+    pub start: u64,
+    #[prost(uint64, tag = "2")]
+    pub length: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EncodedBlock {
+    #[prost(bytes = "vec", tag = "1")]
+    pub block: ::prost::alloc::vec::Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EncodedBlocks {
+    #[prost(message, repeated, tag = "1")]
+    pub blocks: ::prost::alloc::vec::Vec<EncodedBlock>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetBlocksResponse {
+    #[prost(oneof = "GetBlocksContent", tags = "1, 2")]
+    pub get_blocks_content: ::core::option::Option<GetBlocksContent>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Oneof)]
+pub enum GetBlocksContent {
+    #[prost(message, tag = "1")]
+    Blocks(EncodedBlocks),
+    #[prost(string, tag = "2")]
+    Error(::prost::alloc::string::String),
+}
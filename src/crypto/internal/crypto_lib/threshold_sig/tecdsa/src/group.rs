@@ -2,12 +2,15 @@ use crate::*;
 use fe::EccFieldElement;
 use rand_core::{CryptoRng, RngCore};
 use std::fmt;
+use std::sync::atomic;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::Zeroize;
 
 /// Elliptic curve type enum
 ///
 /// Enumerates the curves supported by this library, currently K256 (aka
 /// secp256k1) and P256 (aka secp256r1)
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum EccCurveType {
     K256,
     P256,
@@ -195,6 +198,29 @@ impl fmt::Debug for EccScalar {
     }
 }
 
+impl ConstantTimeEq for EccScalar {
+    /// Compares two scalars without branching on their values
+    ///
+    /// Scalars of different curves are never equal.
+    fn ct_eq(&self, other: &Self) -> Choice {
+        match (self, other) {
+            (Self::K256(a), Self::K256(b)) => a.ct_eq(b),
+            (Self::P256(a), Self::P256(b)) => a.ct_eq(b),
+            (_, _) => Choice::from(0),
+        }
+    }
+}
+
+impl Zeroize for EccScalar {
+    fn zeroize(&mut self) {
+        let zero = Self::zero(self.curve_type());
+        // SAFETY: self is a valid, aligned reference. The write is volatile so
+        // that it is not elided even if self is never read again.
+        unsafe { std::ptr::write_volatile(self, zero) };
+        atomic::compiler_fence(atomic::Ordering::SeqCst);
+    }
+}
+
 impl EccScalar {
     pub fn curve(&self) -> EccCurve {
         match self {
@@ -421,6 +447,14 @@ impl EccScalar {
             EccCurveType::P256 => Self::P256(p256::Scalar::one()),
         }
     }
+
+    /// Return a small integer as a scalar
+    pub fn from_u64(curve: EccCurveType, n: u64) -> Self {
+        let mut bytes = vec![0u8; curve.scalar_bytes()];
+        let len = bytes.len();
+        bytes[len - 8..].copy_from_slice(&n.to_be_bytes());
+        Self::deserialize(curve, &bytes).expect("A u64 is smaller than the group order")
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
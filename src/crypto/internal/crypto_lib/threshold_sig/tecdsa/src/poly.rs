@@ -1,5 +1,9 @@
 use crate::*;
 use rand_core::{CryptoRng, RngCore};
+use std::collections::hash_map::{Entry, HashMap};
+use std::convert::TryFrom;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::Zeroize;
 
 /// A Polynomial whose coefficients are scalars in an elliptic curve group
 ///
@@ -14,6 +18,7 @@ pub struct Polynomial {
 impl Eq for Polynomial {}
 
 impl PartialEq for Polynomial {
+    /// Compares the polynomials without branching on their coefficients
     fn eq(&self, other: &Self) -> bool {
         if self.curve != other.curve {
             return false;
@@ -22,13 +27,28 @@ impl PartialEq for Polynomial {
         // Accept leading zero elements
         let max_coef = std::cmp::max(self.coefficients.len(), other.coefficients.len());
 
+        let mut equal = Choice::from(1);
         for i in 0..max_coef {
-            if self.coeff(i) != other.coeff(i) {
-                return false;
-            }
+            equal &= self.coeff(i).ct_eq(&other.coeff(i));
+        }
+
+        bool::from(equal)
+    }
+}
+
+impl Zeroize for Polynomial {
+    fn zeroize(&mut self) {
+        for coefficient in self.coefficients.iter_mut() {
+            coefficient.zeroize();
         }
+    }
+}
 
-        true
+/// Polynomials usually hold secret shares, so their coefficients are wiped
+/// when they go out of scope.
+impl Drop for Polynomial {
+    fn drop(&mut self) {
+        self.zeroize();
     }
 }
 
@@ -87,6 +107,63 @@ impl Polynomial {
         self.curve
     }
 
+    /// Serialize the polynomial
+    ///
+    /// The encoding is the number of coefficients as a big-endian u64,
+    /// followed by the coefficients from a_0 upwards, each in the fixed
+    /// length SEC1 format of the scalar. High zero coefficients are left
+    /// out, so polynomials that compare equal serialize identically. The
+    /// curve is not encoded; the caller must know it when deserializing.
+    pub fn serialize(&self) -> Vec<u8> {
+        let coefficients = self.non_zero_coefficients();
+        let scalar_bytes = self.curve.scalar_bytes();
+
+        let mut output = Vec::with_capacity(8 + coefficients.len() * scalar_bytes);
+        output.extend_from_slice(&(coefficients.len() as u64).to_be_bytes());
+        for coefficient in &coefficients {
+            output.extend_from_slice(&coefficient.serialize());
+        }
+        output
+    }
+
+    /// Deserialize a polynomial produced by `serialize`
+    ///
+    /// Fails if the input is truncated, carries trailing bytes, or any of
+    /// the coefficients is not a valid scalar of `curve`.
+    pub fn deserialize(curve: EccCurveType, bytes: &[u8]) -> ThresholdEcdsaResult<Self> {
+        if bytes.len() < 8 {
+            return Err(ThresholdEcdsaError::InvalidArguments(
+                "Polynomial encoding is too short".to_string(),
+            ));
+        }
+        let (count, coefficient_bytes) = bytes.split_at(8);
+        let mut count_bytes = [0u8; 8];
+        count_bytes.copy_from_slice(count);
+        let count = u64::from_be_bytes(count_bytes);
+
+        let scalar_bytes = curve.scalar_bytes();
+        let expected_len = usize::try_from(count)
+            .ok()
+            .and_then(|count| count.checked_mul(scalar_bytes));
+        if expected_len != Some(coefficient_bytes.len()) {
+            return Err(ThresholdEcdsaError::InvalidArguments(format!(
+                "Polynomial encoding of {} coefficients has {} bytes of coefficients",
+                count,
+                coefficient_bytes.len()
+            )));
+        }
+
+        let coefficients = coefficient_bytes
+            .chunks(scalar_bytes)
+            .map(|bits| EccScalar::deserialize(curve, bits))
+            .collect::<ThresholdEcdsaResult<Vec<_>>>()?;
+        Self::new(curve, coefficients)
+    }
+
+    /// Return the coefficient of x^idx
+    ///
+    /// This only branches on the index, which is public, and not on the
+    /// value of the coefficient.
     fn coeff(&self, idx: usize) -> EccScalar {
         match self.coefficients.get(idx) {
             Some(s) => *s,
@@ -97,21 +174,31 @@ impl Polynomial {
     /// Return the coefficients
     ///
     /// Our internal representation allows high zero coefficients,
-    /// which are removed here.
+    /// which are removed here. All coefficients are inspected, so the
+    /// running time depends only on the number of coefficients, but the
+    /// length of the result reveals the degree.
     pub fn non_zero_coefficients(&self) -> Vec<EccScalar> {
-        let zeros = self
-            .coefficients
-            .iter()
-            .rev()
-            .take_while(|c| c.is_zero())
-            .count();
+        let mut zeros = 0;
+        let mut only_zeros_so_far = Choice::from(1);
+        for coefficient in self.coefficients.iter().rev() {
+            only_zeros_so_far &= Choice::from(coefficient.is_zero() as u8);
+            zeros += only_zeros_so_far.unwrap_u8() as usize;
+        }
 
         let len = self.coefficients.len() - zeros;
         self.coefficients[0..len].to_vec()
     }
 
+    /// Return the degree of the polynomial
+    ///
+    /// High zero coefficients do not count; the zero polynomial is
+    /// considered to have degree 0.
+    pub fn degree(&self) -> usize {
+        self.non_zero_coefficients().len().saturating_sub(1)
+    }
+
     /// Polynomial addition
-    fn add(&self, rhs: &Self) -> ThresholdEcdsaResult<Self> {
+    pub fn add(&self, rhs: &Self) -> ThresholdEcdsaResult<Self> {
         if self.curve_type() != rhs.curve_type() {
             return Err(ThresholdEcdsaError::CurveMismatch);
         }
@@ -127,12 +214,58 @@ impl Polynomial {
         Self::new(self.curve_type(), res)
     }
 
+    /// Polynomial subtraction
+    pub fn sub(&self, rhs: &Self) -> ThresholdEcdsaResult<Self> {
+        if self.curve_type() != rhs.curve_type() {
+            return Err(ThresholdEcdsaError::CurveMismatch);
+        }
+
+        let max_coef = std::cmp::max(self.coefficients.len(), rhs.coefficients.len());
+
+        let mut res = Vec::with_capacity(max_coef);
+        for idx in 0..max_coef {
+            let x = self.coeff(idx);
+            let y = rhs.coeff(idx);
+            res.push(x.sub(&y)?);
+        }
+        Self::new(self.curve_type(), res)
+    }
+
+    /// Polynomial negation
+    pub fn negate(&self) -> ThresholdEcdsaResult<Self> {
+        let coeffs = self
+            .coefficients
+            .iter()
+            .map(|c| c.negate())
+            .collect::<ThresholdEcdsaResult<Vec<_>>>()?;
+        Self::new(self.curve_type(), coeffs)
+    }
+
+    /// Compute the sum of a polynomial and a scalar, ie add the scalar to
+    /// the constant coefficient
+    pub fn add_scalar(&self, scalar: &EccScalar) -> ThresholdEcdsaResult<Self> {
+        if self.curve_type() != scalar.curve_type() {
+            return Err(ThresholdEcdsaError::CurveMismatch);
+        }
+
+        let mut coeffs = self.coefficients.clone();
+        match coeffs.first_mut() {
+            Some(constant) => *constant = constant.add(scalar)?,
+            None => coeffs.push(*scalar),
+        }
+        Self::new(self.curve_type(), coeffs)
+    }
+
     /// Compute product of a polynomial and a polynomial
-    fn mul(&self, rhs: &Self) -> ThresholdEcdsaResult<Self> {
+    pub fn mul(&self, rhs: &Self) -> ThresholdEcdsaResult<Self> {
         if self.curve_type() != rhs.curve_type() {
             return Err(ThresholdEcdsaError::CurveMismatch);
         }
 
+        if self.coefficients.is_empty() || rhs.coefficients.is_empty() {
+            return Self::zero(self.curve_type());
+        }
+
         let n_coeffs = self.coefficients.len() + rhs.coefficients.len() - 1;
         let curve = self.curve_type();
 
@@ -148,7 +281,7 @@ impl Polynomial {
     }
 
     /// Compute product of a polynomial and a scalar
-    fn mul_scalar(&self, scalar: &EccScalar) -> ThresholdEcdsaResult<Self> {
+    pub fn mul_scalar(&self, scalar: &EccScalar) -> ThresholdEcdsaResult<Self> {
         if self.curve_type() != scalar.curve_type() {
             return Err(ThresholdEcdsaError::CurveMismatch);
         }
@@ -244,3 +377,302 @@ impl Polynomial {
         Ok(poly)
     }
 }
+
+/// Evaluates the polynomial "in the exponent" whose coefficients are the
+/// committed points, using Horner's method
+fn evaluate_points_at(points: &[EccPoint], x: &EccScalar) -> ThresholdEcdsaResult<EccPoint> {
+    let mut points = points.iter().rev();
+    let mut ans = match points.next() {
+        Some(point) => *point,
+        None => return Ok(x.curve().neutral_element()),
+    };
+    for point in points {
+        ans = ans.scalar_mul(x)?.add_points(point)?;
+    }
+    Ok(ans)
+}
+
+/// A Feldman commitment to a polynomial: the points `a_i*G` for each
+/// coefficient `a_i`
+///
+/// It reveals `a_0*G`, so it only hides secrets that are uniformly random.
+#[derive(Clone, Debug)]
+pub struct FeldmanCommitment {
+    points: Vec<EccPoint>,
+}
+
+impl FeldmanCommitment {
+    /// Return the committed points, from the constant term upwards
+    pub fn points(&self) -> &[EccPoint] {
+        &self.points
+    }
+
+    /// Return the commitment to the evaluation of the polynomial at `x`
+    pub fn evaluate_at(&self, x: &EccScalar) -> ThresholdEcdsaResult<EccPoint> {
+        evaluate_points_at(&self.points, x)
+    }
+}
+
+/// A Pedersen commitment to a polynomial: the points `a_i*G + b_i*H`, where
+/// the `b_i` are the coefficients of a random masking polynomial
+///
+/// Unlike a Feldman commitment it reveals nothing about the polynomial.
+#[derive(Clone, Debug)]
+pub struct PedersenCommitment {
+    points: Vec<EccPoint>,
+}
+
+impl PedersenCommitment {
+    /// Return the committed points, from the constant term upwards
+    pub fn points(&self) -> &[EccPoint] {
+        &self.points
+    }
+
+    /// Return the commitment to the evaluations of the polynomial and the
+    /// masking polynomial at `x`
+    pub fn evaluate_at(&self, x: &EccScalar) -> ThresholdEcdsaResult<EccPoint> {
+        evaluate_points_at(&self.points, x)
+    }
+}
+
+impl Polynomial {
+    /// Return the Feldman commitment to this polynomial
+    pub fn commitment(&self) -> ThresholdEcdsaResult<FeldmanCommitment> {
+        let g = EccCurve::new(self.curve).generator_g()?;
+        let points = self
+            .coefficients
+            .iter()
+            .map(|c| g.scalar_mul(c))
+            .collect::<ThresholdEcdsaResult<Vec<_>>>()?;
+        Ok(FeldmanCommitment { points })
+    }
+
+    /// Return the Pedersen commitment to this polynomial, masked by
+    /// `masking`
+    ///
+    /// The masking polynomial must be random and at least of the same degree
+    /// for the commitment to hide this polynomial.
+    pub fn pedersen_commitment(&self, masking: &Self) -> ThresholdEcdsaResult<PedersenCommitment> {
+        if self.curve != masking.curve {
+            return Err(ThresholdEcdsaError::CurveMismatch);
+        }
+        let curve = EccCurve::new(self.curve);
+        let g = curve.generator_g()?;
+        let h = curve.generator_h()?;
+
+        let num_coefficients = std::cmp::max(self.coefficients.len(), masking.coefficients.len());
+        let points = (0..num_coefficients)
+            .map(|i| g.mul_points(&self.coeff(i), &h, &masking.coeff(i)))
+            .collect::<ThresholdEcdsaResult<Vec<_>>>()?;
+        Ok(PedersenCommitment { points })
+    }
+}
+
+/// Checks that `share` is the evaluation at node `index` of the polynomial
+/// `commitment` commits to
+pub fn verify_share(
+    index: NodeIndex,
+    share: &EccScalar,
+    commitment: &FeldmanCommitment,
+) -> ThresholdEcdsaResult<bool> {
+    let curve = EccCurve::new(share.curve_type());
+    let x = EccScalar::from_u64(curve.curve_type(), index as u64 + 1);
+    let expected = commitment.evaluate_at(&x)?;
+    let actual = curve.generator_g()?.scalar_mul(share)?;
+    Ok(expected.serialize() == actual.serialize())
+}
+
+/// Checks that `share` and `masking_share` are the evaluations at node
+/// `index` of the polynomial and the masking polynomial `commitment` commits
+/// to
+pub fn verify_pedersen_share(
+    index: NodeIndex,
+    share: &EccScalar,
+    masking_share: &EccScalar,
+    commitment: &PedersenCommitment,
+) -> ThresholdEcdsaResult<bool> {
+    let curve = EccCurve::new(share.curve_type());
+    let x = EccScalar::from_u64(curve.curve_type(), index as u64 + 1);
+    let expected = commitment.evaluate_at(&x)?;
+    let actual = curve
+        .generator_g()?
+        .mul_points(share, &curve.generator_h()?, masking_share)?;
+    Ok(expected.serialize() == actual.serialize())
+}
+
+/// The index of a node taking part in a threshold protocol
+///
+/// The share of node `i` is the evaluation of the shared polynomial at
+/// `x = i + 1`, as the polynomial's value at zero is the secret itself.
+pub type NodeIndex = u32;
+
+/// Lagrange coefficients for interpolating at a fixed point from the
+/// evaluations at a fixed set of nodes
+///
+/// Given the evaluations `y_i` of a polynomial of degree less than the
+/// number of nodes, its value at `x` is `sum(coefficients[i] * y_i)`.
+/// Computing the coefficients once and applying them to many sets of
+/// evaluations is much cheaper than interpolating a Polynomial each time.
+#[derive(Clone, Debug)]
+pub struct LagrangeCoefficients {
+    node_indexes: Vec<NodeIndex>,
+    coefficients: Vec<EccScalar>,
+}
+
+impl LagrangeCoefficients {
+    /// Computes the coefficients for interpolating at `x` from the
+    /// evaluations at `node_indexes`
+    ///
+    /// The node indexes must be non-empty and distinct.
+    pub fn at_value(x: &EccScalar, node_indexes: &[NodeIndex]) -> ThresholdEcdsaResult<Self> {
+        if node_indexes.is_empty() {
+            return Err(ThresholdEcdsaError::InvalidArguments(
+                "Interpolation requires at least one node".to_string(),
+            ));
+        }
+        let mut sorted = node_indexes.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        if sorted.len() != node_indexes.len() {
+            return Err(ThresholdEcdsaError::InvalidArguments(
+                "Interpolation requires distinct node indexes".to_string(),
+            ));
+        }
+
+        let curve = x.curve_type();
+        let samples = node_indexes
+            .iter()
+            .map(|&i| EccScalar::from_u64(curve, i as u64 + 1))
+            .collect::<Vec<_>>();
+
+        // coefficients[i] = prod_{j != i} (x - x_j) / (x_i - x_j)
+        let mut coefficients = Vec::with_capacity(samples.len());
+        for (i, x_i) in samples.iter().enumerate() {
+            let mut numerator = EccScalar::one(curve);
+            let mut denominator = EccScalar::one(curve);
+            for (j, x_j) in samples.iter().enumerate() {
+                if i != j {
+                    numerator = numerator.mul(&x.sub(x_j)?)?;
+                    denominator = denominator.mul(&x_i.sub(x_j)?)?;
+                }
+            }
+            coefficients.push(numerator.mul(&denominator.invert()?)?);
+        }
+
+        Ok(Self {
+            node_indexes: node_indexes.to_vec(),
+            coefficients,
+        })
+    }
+
+    /// Computes the coefficients for interpolating the value at zero, ie
+    /// for recombining the shared secret from shares
+    pub fn at_zero(curve: EccCurveType, node_indexes: &[NodeIndex]) -> ThresholdEcdsaResult<Self> {
+        Self::at_value(&EccScalar::zero(curve), node_indexes)
+    }
+
+    /// Return the node indexes, in the order the coefficients are for
+    pub fn node_indexes(&self) -> &[NodeIndex] {
+        &self.node_indexes
+    }
+
+    /// Return the coefficients
+    pub fn coefficients(&self) -> &[EccScalar] {
+        &self.coefficients
+    }
+
+    /// Interpolates the value at the point the coefficients were computed
+    /// for, given the evaluations at the nodes in the same order
+    pub fn interpolate_scalar(&self, y: &[EccScalar]) -> ThresholdEcdsaResult<EccScalar> {
+        self.check_num_evaluations(y.len())?;
+        let mut result = EccScalar::zero(self.coefficients[0].curve_type());
+        for (coefficient, y_i) in self.coefficients.iter().zip(y) {
+            result = result.add(&coefficient.mul(y_i)?)?;
+        }
+        Ok(result)
+    }
+
+    /// Interpolates in the exponent, given the evaluations at the nodes
+    /// multiplied by a common point, in the same order
+    pub fn interpolate_point(&self, y: &[EccPoint]) -> ThresholdEcdsaResult<EccPoint> {
+        self.check_num_evaluations(y.len())?;
+        let mut result = self.coefficients[0].curve().neutral_element();
+        for (coefficient, y_i) in self.coefficients.iter().zip(y) {
+            result = result.add_points(&y_i.scalar_mul(coefficient)?)?;
+        }
+        Ok(result)
+    }
+
+    fn check_num_evaluations(&self, num_evaluations: usize) -> ThresholdEcdsaResult<()> {
+        if num_evaluations != self.coefficients.len() {
+            return Err(ThresholdEcdsaError::InvalidArguments(format!(
+                "Expected {} evaluations, got {}",
+                self.coefficients.len(),
+                num_evaluations
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Recombines the secret shared by a polynomial from the shares of nodes
+///
+/// The shares must all be on the same curve and come from distinct nodes,
+/// and there must be more of them than the degree of the polynomial for
+/// the result to be the shared secret.
+pub fn reconstruct_secret(shares: &[(NodeIndex, EccScalar)]) -> ThresholdEcdsaResult<EccScalar> {
+    let curve = match shares.first() {
+        Some((_, share)) => share.curve_type(),
+        None => {
+            return Err(ThresholdEcdsaError::InvalidArguments(
+                "Reconstructing a secret requires at least one share".to_string(),
+            ))
+        }
+    };
+    if shares.iter().any(|(_, share)| share.curve_type() != curve) {
+        return Err(ThresholdEcdsaError::CurveMismatch);
+    }
+
+    let (node_indexes, shares): (Vec<NodeIndex>, Vec<EccScalar>) = shares.iter().copied().unzip();
+    LagrangeCoefficients::at_zero(curve, &node_indexes)?.interpolate_scalar(&shares)
+}
+
+/// Remembers the Lagrange coefficients computed for each point and node set
+///
+/// Recombining shares repeatedly happens with the same signers, so the
+/// coefficients only have to be computed once per set of signers.
+#[derive(Clone, Debug, Default)]
+pub struct LagrangeCoefficientsCache {
+    cache: HashMap<(EccCurveType, Vec<u8>, Vec<NodeIndex>), LagrangeCoefficients>,
+}
+
+impl LagrangeCoefficientsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the coefficients for interpolating at `x` from the evaluations
+    /// at `node_indexes`, computing them unless they are cached
+    pub fn at_value(
+        &mut self,
+        x: &EccScalar,
+        node_indexes: &[NodeIndex],
+    ) -> ThresholdEcdsaResult<&LagrangeCoefficients> {
+        let key = (x.curve_type(), x.serialize(), node_indexes.to_vec());
+        match self.cache.entry(key) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                Ok(entry.insert(LagrangeCoefficients::at_value(x, node_indexes)?))
+            }
+        }
+    }
+
+    /// Return the number of cached coefficient sets
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
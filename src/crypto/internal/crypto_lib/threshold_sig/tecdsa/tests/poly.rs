@@ -175,3 +175,414 @@ fn poly_threshold_secret_sharing() -> ThresholdEcdsaResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn poly_serialization_round_trips() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        for degree in 0..10 {
+            let poly = Polynomial::random(curve, degree, &mut rng)?;
+            let bytes = poly.serialize();
+            assert_eq!(bytes.len(), 8 + (degree + 1) * curve.scalar_bytes());
+            assert_eq!(Polynomial::deserialize(curve, &bytes)?, poly);
+        }
+
+        let zero = Polynomial::zero(curve)?;
+        assert_eq!(zero.serialize(), vec![0; 8]);
+        assert_eq!(Polynomial::deserialize(curve, &zero.serialize())?, zero);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_serialization_omits_high_zero_coefficients() -> ThresholdEcdsaResult<()> {
+    for curve in EccCurveType::all() {
+        let one = EccScalar::one(curve);
+        let zero = EccScalar::zero(curve);
+        let padded = Polynomial::new(curve, vec![one, one, zero, zero])?;
+        let poly = Polynomial::new(curve, vec![one, one])?;
+        assert_eq!(padded.serialize(), poly.serialize());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_deserialization_rejects_malformed_input() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        let bytes = Polynomial::random(curve, 3, &mut rng)?.serialize();
+
+        // Truncated length prefix and truncated coefficients
+        assert!(Polynomial::deserialize(curve, &bytes[..7]).is_err());
+        assert!(Polynomial::deserialize(curve, &bytes[..bytes.len() - 1]).is_err());
+
+        // Trailing garbage
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(Polynomial::deserialize(curve, &trailing).is_err());
+
+        // A count that would overflow the expected length
+        let mut huge_count = bytes.clone();
+        huge_count[..8].copy_from_slice(&u64::MAX.to_be_bytes());
+        assert!(Polynomial::deserialize(curve, &huge_count).is_err());
+
+        // A coefficient that is not a valid scalar
+        let mut invalid = bytes.clone();
+        for b in &mut invalid[8..8 + curve.scalar_bytes()] {
+            *b = 0xff;
+        }
+        assert_eq!(
+            Polynomial::deserialize(curve, &invalid),
+            Err(ThresholdEcdsaError::InvalidScalar)
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_lagrange_coefficients_at_zero_recover_the_constant() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        for degree in 0..10 {
+            let poly = Polynomial::random(curve, degree, &mut rng)?;
+            let node_indexes = (0..=degree as NodeIndex)
+                .map(|i| 3 * i + 1)
+                .collect::<Vec<_>>();
+            let shares = node_indexes
+                .iter()
+                .map(|&i| poly.evaluate_at(&EccScalar::from_u64(curve, i as u64 + 1)))
+                .collect::<ThresholdEcdsaResult<Vec<_>>>()?;
+
+            let coefficients = LagrangeCoefficients::at_zero(curve, &node_indexes)?;
+            assert_eq!(
+                coefficients.interpolate_scalar(&shares)?,
+                poly.evaluate_at(&EccScalar::zero(curve))?
+            );
+
+            let g = EccCurve::new(curve).generator_g()?;
+            let public_shares = shares
+                .iter()
+                .map(|s| g.scalar_mul(s))
+                .collect::<ThresholdEcdsaResult<Vec<_>>>()?;
+            let public_key = g.scalar_mul(&poly.evaluate_at(&EccScalar::zero(curve))?)?;
+            assert_eq!(
+                coefficients.interpolate_point(&public_shares)?.serialize(),
+                public_key.serialize()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_lagrange_coefficients_match_interpolate() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        let poly = Polynomial::random(curve, 4, &mut rng)?;
+        let node_indexes = [7, 2, 0, 11, 5];
+        let samples = node_indexes
+            .iter()
+            .map(|&i| {
+                let x = EccScalar::from_u64(curve, i as u64 + 1);
+                Ok((x, poly.evaluate_at(&x)?))
+            })
+            .collect::<ThresholdEcdsaResult<Vec<_>>>()?;
+        let y = samples.iter().map(|(_, y)| *y).collect::<Vec<_>>();
+        let interpolated = Polynomial::interpolate(curve, &samples)?;
+
+        let x = EccScalar::random(curve, &mut rng)?;
+        let coefficients = LagrangeCoefficients::at_value(&x, &node_indexes)?;
+        assert_eq!(
+            coefficients.interpolate_scalar(&y)?,
+            interpolated.evaluate_at(&x)?
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_lagrange_coefficients_reject_invalid_node_sets() {
+    for curve in EccCurveType::all() {
+        assert!(LagrangeCoefficients::at_zero(curve, &[]).is_err());
+        assert!(LagrangeCoefficients::at_zero(curve, &[1, 2, 1]).is_err());
+
+        let coefficients = LagrangeCoefficients::at_zero(curve, &[0, 1]).unwrap();
+        assert!(coefficients
+            .interpolate_scalar(&[EccScalar::one(curve)])
+            .is_err());
+    }
+}
+
+#[test]
+fn poly_lagrange_coefficients_are_cached() -> ThresholdEcdsaResult<()> {
+    let mut cache = LagrangeCoefficientsCache::new();
+
+    for curve in EccCurveType::all() {
+        let zero = EccScalar::zero(curve);
+        let first = cache.at_value(&zero, &[0, 1, 2])?.coefficients().to_vec();
+        let second = cache.at_value(&zero, &[0, 1, 2])?.coefficients().to_vec();
+        assert_eq!(first, second);
+        cache.at_value(&zero, &[0, 1, 3])?;
+    }
+    assert_eq!(cache.len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn poly_feldman_commitment_verifies_shares() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        let poly = Polynomial::random(curve, 3, &mut rng)?;
+        let commitment = poly.commitment()?;
+        assert_eq!(commitment.points().len(), 4);
+
+        for index in 0..10 {
+            let share = poly.evaluate_at(&EccScalar::from_u64(curve, index as u64 + 1))?;
+            assert!(verify_share(index, &share, &commitment)?);
+            assert!(!verify_share(index + 1, &share, &commitment)?);
+
+            let wrong_share = share.add(&EccScalar::one(curve))?;
+            assert!(!verify_share(index, &wrong_share, &commitment)?);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_pedersen_commitment_verifies_shares() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        let poly = Polynomial::random(curve, 3, &mut rng)?;
+        let masking = Polynomial::random(curve, 3, &mut rng)?;
+        let commitment = poly.pedersen_commitment(&masking)?;
+
+        for index in 0..10 {
+            let x = EccScalar::from_u64(curve, index as u64 + 1);
+            let share = poly.evaluate_at(&x)?;
+            let masking_share = masking.evaluate_at(&x)?;
+            assert!(verify_pedersen_share(
+                index,
+                &share,
+                &masking_share,
+                &commitment
+            )?);
+            assert!(!verify_pedersen_share(
+                index,
+                &masking_share,
+                &share,
+                &commitment
+            )?);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_commitments_reject_mixed_curves() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    let k256 = Polynomial::random(EccCurveType::K256, 2, &mut rng)?;
+    let p256 = Polynomial::random(EccCurveType::P256, 2, &mut rng)?;
+    assert_eq!(
+        k256.pedersen_commitment(&p256).unwrap_err(),
+        ThresholdEcdsaError::CurveMismatch
+    );
+
+    let share = EccScalar::one(EccCurveType::P256);
+    assert_eq!(
+        verify_share(0, &share, &k256.commitment()?),
+        Err(ThresholdEcdsaError::CurveMismatch)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn poly_zeroize_wipes_the_coefficients() -> ThresholdEcdsaResult<()> {
+    use zeroize::Zeroize;
+
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        let mut poly = Polynomial::random(curve, 5, &mut rng)?;
+        assert!(!poly.non_zero_coefficients().is_empty());
+
+        poly.zeroize();
+        assert!(poly.non_zero_coefficients().is_empty());
+        assert_eq!(poly, Polynomial::zero(curve)?);
+        assert_eq!(poly.serialize(), vec![0; 8]);
+        assert_eq!(poly.curve_type(), curve);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_zeroize_wipes_a_scalar() -> ThresholdEcdsaResult<()> {
+    use zeroize::Zeroize;
+
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        let mut scalar = EccScalar::random(curve, &mut rng)?;
+        scalar.zeroize();
+        assert!(scalar.is_zero());
+        assert_eq!(scalar.serialize(), vec![0; curve.scalar_bytes()]);
+        assert_eq!(scalar.curve_type(), curve);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_equality_ignores_high_zero_coefficients() -> ThresholdEcdsaResult<()> {
+    for curve in EccCurveType::all() {
+        let one = EccScalar::one(curve);
+        let zero = EccScalar::zero(curve);
+        let poly = Polynomial::new(curve, vec![one, zero, one])?;
+
+        assert_eq!(poly, Polynomial::new(curve, vec![one, zero, one, zero])?);
+        assert_ne!(poly, Polynomial::new(curve, vec![one, zero, zero])?);
+        assert_ne!(poly, Polynomial::new(curve, vec![one, zero, one, one])?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_arithmetic_is_consistent_with_evaluation() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        let a = Polynomial::random(curve, 4, &mut rng)?;
+        let b = Polynomial::random(curve, 2, &mut rng)?;
+        let s = EccScalar::random(curve, &mut rng)?;
+        let x = EccScalar::random(curve, &mut rng)?;
+
+        let a_x = a.evaluate_at(&x)?;
+        let b_x = b.evaluate_at(&x)?;
+
+        assert_eq!(a.add(&b)?.evaluate_at(&x)?, a_x.add(&b_x)?);
+        assert_eq!(a.sub(&b)?.evaluate_at(&x)?, a_x.sub(&b_x)?);
+        assert_eq!(a.mul(&b)?.evaluate_at(&x)?, a_x.mul(&b_x)?);
+        assert_eq!(a.negate()?.evaluate_at(&x)?, a_x.negate()?);
+        assert_eq!(a.add_scalar(&s)?.evaluate_at(&x)?, a_x.add(&s)?);
+        assert_eq!(a.mul_scalar(&s)?.evaluate_at(&x)?, a_x.mul(&s)?);
+
+        assert_eq!(a.sub(&a)?, Polynomial::zero(curve)?);
+        assert_eq!(a.add(&a.negate()?)?, Polynomial::zero(curve)?);
+        assert_eq!(Polynomial::zero(curve)?.add_scalar(&s)?.evaluate_at(&x)?, s);
+        assert_eq!(a.mul(&Polynomial::zero(curve)?)?, Polynomial::zero(curve)?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_degree_ignores_high_zero_coefficients() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        let one = EccScalar::one(curve);
+        let zero = EccScalar::zero(curve);
+
+        assert_eq!(Polynomial::zero(curve)?.degree(), 0);
+        assert_eq!(Polynomial::new(curve, vec![one])?.degree(), 0);
+        assert_eq!(Polynomial::new(curve, vec![one, one, zero])?.degree(), 1);
+
+        let a = Polynomial::random(curve, 4, &mut rng)?;
+        let b = Polynomial::random(curve, 2, &mut rng)?;
+        assert_eq!(a.degree(), 4);
+        assert_eq!(a.mul(&b)?.degree(), 6);
+        assert_eq!(a.sub(&a)?.degree(), 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_arithmetic_rejects_mixed_curves() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    let k256 = Polynomial::random(EccCurveType::K256, 2, &mut rng)?;
+    let p256 = Polynomial::random(EccCurveType::P256, 2, &mut rng)?;
+    let p256_scalar = EccScalar::one(EccCurveType::P256);
+
+    assert_eq!(k256.add(&p256), Err(ThresholdEcdsaError::CurveMismatch));
+    assert_eq!(k256.sub(&p256), Err(ThresholdEcdsaError::CurveMismatch));
+    assert_eq!(k256.mul(&p256), Err(ThresholdEcdsaError::CurveMismatch));
+    assert_eq!(
+        k256.add_scalar(&p256_scalar),
+        Err(ThresholdEcdsaError::CurveMismatch)
+    );
+    assert_eq!(
+        k256.mul_scalar(&p256_scalar),
+        Err(ThresholdEcdsaError::CurveMismatch)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn poly_reconstruct_secret_from_any_sufficient_subset() -> ThresholdEcdsaResult<()> {
+    let mut rng = rand::thread_rng();
+
+    for curve in EccCurveType::all() {
+        let secret = EccScalar::random(curve, &mut rng)?;
+        let poly = Polynomial::random_with_constant(secret, 2, &mut rng)?;
+        let shares = (0..6 as NodeIndex)
+            .map(|i| {
+                Ok((
+                    i,
+                    poly.evaluate_at(&EccScalar::from_u64(curve, i as u64 + 1))?,
+                ))
+            })
+            .collect::<ThresholdEcdsaResult<Vec<_>>>()?;
+
+        assert_eq!(reconstruct_secret(&shares[..3])?, secret);
+        assert_eq!(reconstruct_secret(&shares[3..])?, secret);
+        assert_eq!(reconstruct_secret(&shares)?, secret);
+        assert_eq!(
+            reconstruct_secret(&[shares[5], shares[0], shares[2]])?,
+            secret
+        );
+
+        // Too few shares do not determine the secret
+        assert_ne!(reconstruct_secret(&shares[..2])?, secret);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poly_reconstruct_secret_rejects_invalid_shares() {
+    let k256_share = EccScalar::one(EccCurveType::K256);
+    let p256_share = EccScalar::one(EccCurveType::P256);
+
+    assert!(matches!(
+        reconstruct_secret(&[]),
+        Err(ThresholdEcdsaError::InvalidArguments(_))
+    ));
+    assert!(matches!(
+        reconstruct_secret(&[(1, k256_share), (1, k256_share)]),
+        Err(ThresholdEcdsaError::InvalidArguments(_))
+    ));
+    assert_eq!(
+        reconstruct_secret(&[(1, k256_share), (2, p256_share)]),
+        Err(ThresholdEcdsaError::CurveMismatch)
+    );
+}
@@ -0,0 +1,216 @@
+//! Command line client for a deployed ledger canister, for operators who
+//! would otherwise script `dfx canister call` by hand.
+
+use candid::{Decode, Encode};
+use clap::{Parser, Subcommand};
+use ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport;
+use ic_agent::export::Principal;
+use ic_agent::identity::{AnonymousIdentity, BasicIdentity};
+use ic_agent::{Agent, Identity};
+use ic_base_types::PrincipalId;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::time::Duration;
+use token_canister::client::{
+    verify_chain, AccountBalanceArgs, AccountIdentifier, BlockHeight, GetBlocksArgs,
+    GetBlocksRes, LedgerInfo, LedgerMetrics, Memo, SendArgs, TOKENs, TRANSACTION_FEE,
+};
+
+#[derive(Parser)]
+#[clap(name = "ledger-cli", about = "Queries and operates a deployed ledger canister")]
+struct Opts {
+    /// The URL of the replica to talk to.
+    #[clap(long, default_value = "http://127.0.0.1:8000")]
+    url: String,
+    /// The id of the ledger canister.
+    #[clap(long)]
+    canister_id: String,
+    /// PEM file of the identity to call the ledger with. Calls are anonymous
+    /// without it.
+    #[clap(long)]
+    identity: Option<PathBuf>,
+    /// Fetch the root key from the replica. Only use this against a local
+    /// replica.
+    #[clap(long)]
+    fetch_root_key: bool,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the balance of an account, by default the caller's.
+    Balance {
+        #[clap(long)]
+        account: Option<AccountIdentifier>,
+    },
+    /// Sends tokens from the caller's default account, paying the standard
+    /// fee.
+    Send {
+        #[clap(long)]
+        to: AccountIdentifier,
+        #[clap(long)]
+        amount_e8s: u64,
+        #[clap(long, default_value = "0")]
+        memo: u64,
+    },
+    /// Fetches a range of blocks, optionally checking that they are linked
+    /// to each other.
+    Blocks {
+        #[clap(long)]
+        start: BlockHeight,
+        #[clap(long, default_value = "10")]
+        length: usize,
+        #[clap(long)]
+        verify: bool,
+    },
+    /// Prints the ledger's configuration and counters.
+    Metrics,
+}
+
+struct Ledger {
+    agent: Agent,
+    canister_id: Principal,
+}
+
+impl Ledger {
+    async fn query(&self, method: &str, arg: Vec<u8>) -> Result<Vec<u8>, String> {
+        self.agent
+            .query(&self.canister_id, method)
+            .with_arg(arg)
+            .call()
+            .await
+            .map_err(|e| format!("Calling {} failed: {}", method, e))
+    }
+
+    async fn update(&self, method: &str, arg: Vec<u8>) -> Result<Vec<u8>, String> {
+        let waiter = garcon::Delay::builder()
+            .throttle(Duration::from_millis(500))
+            .timeout(Duration::from_secs(300))
+            .build();
+        self.agent
+            .update(&self.canister_id, method)
+            .with_arg(arg)
+            .call_and_wait(waiter)
+            .await
+            .map_err(|e| format!("Calling {} failed: {}", method, e))
+    }
+
+    async fn info(&self) -> Result<LedgerInfo, String> {
+        let res = self.query("ledger_info", Encode!().unwrap()).await?;
+        Decode!(&res, LedgerInfo).map_err(|e| e.to_string())
+    }
+
+    fn caller_account(&self) -> Result<AccountIdentifier, String> {
+        let principal = self.agent.get_principal()?;
+        let principal = PrincipalId::try_from(principal.as_slice()).map_err(|e| e.to_string())?;
+        Ok(AccountIdentifier::new(principal, None))
+    }
+}
+
+async fn run(opts: Opts) -> Result<(), String> {
+    let identity: Box<dyn Identity + Send + Sync> = match &opts.identity {
+        Some(path) => Box::new(
+            BasicIdentity::from_pem_file(path)
+                .map_err(|e| format!("Could not read {}: {}", path.display(), e))?,
+        ),
+        None => Box::new(AnonymousIdentity),
+    };
+    let transport = ReqwestHttpReplicaV2Transport::create(opts.url.clone())
+        .map_err(|e| e.to_string())?;
+    let agent = Agent::builder()
+        .with_transport(transport)
+        .with_boxed_identity(identity)
+        .build()
+        .map_err(|e| e.to_string())?;
+    if opts.fetch_root_key {
+        agent.fetch_root_key().await.map_err(|e| e.to_string())?;
+    }
+    let ledger = Ledger {
+        agent,
+        canister_id: Principal::from_text(&opts.canister_id).map_err(|e| e.to_string())?,
+    };
+
+    match opts.command {
+        Command::Balance { account } => {
+            let account = match account {
+                Some(account) => account,
+                None => ledger.caller_account()?,
+            };
+            let arg = Encode!(&AccountBalanceArgs::new(account)).unwrap();
+            let res = ledger.query("account_balance_dfx", arg).await?;
+            let balance = Decode!(&res, TOKENs).map_err(|e| e.to_string())?;
+            let info = ledger.info().await?;
+            println!(
+                "{} {}",
+                balance.to_string_with_decimals(info.decimals),
+                info.symbol
+            );
+        }
+        Command::Send {
+            to,
+            amount_e8s,
+            memo,
+        } => {
+            let args = SendArgs {
+                memo: Memo(memo),
+                amount: TOKENs::from_e8s(amount_e8s),
+                fee: TRANSACTION_FEE,
+                from_subaccount: None,
+                to,
+                created_at_time: None,
+                idempotency_key: None,
+                encrypted_memo: None,
+                expires_at: None,
+            };
+            let res = ledger.update("send_dfx", Encode!(&args).unwrap()).await?;
+            let height = Decode!(&res, BlockHeight).map_err(|e| e.to_string())?;
+            println!("Transfer recorded in block {}", height);
+        }
+        Command::Blocks {
+            start,
+            length,
+            verify,
+        } => {
+            let arg = Encode!(&GetBlocksArgs::new(start, length)).unwrap();
+            let res = ledger.query("get_blocks", arg).await?;
+            let res = Decode!(&res, GetBlocksRes).map_err(|e| e.to_string())?;
+            let blocks = res.blocks.map_err(|e| e.to_string())?;
+            for (height, encoded) in (res.first_block_index..).zip(blocks.iter()) {
+                let block = encoded.decode()?;
+                println!(
+                    "{} {} {:?} memo={}{}",
+                    height,
+                    block.timestamp.as_nanos(),
+                    block.transaction.operation,
+                    block.transaction.memo.to_display(),
+                    match &block.transaction.memo_blob {
+                        Some(blob) => format!(" memo_blob={}", blob.to_display()),
+                        None => String::new(),
+                    }
+                );
+            }
+            if verify {
+                if let Some(first) = blocks.first() {
+                    verify_chain(first.decode()?.parent_hash, &blocks)?;
+                }
+                println!("Verified {} blocks", blocks.len());
+            }
+        }
+        Command::Metrics => {
+            println!("{:#?}", ledger.info().await?);
+            let res = ledger.query("ledger_metrics", Encode!().unwrap()).await?;
+            let metrics = Decode!(&res, LedgerMetrics).map_err(|e| e.to_string())?;
+            println!("{:#?}", metrics);
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run(Opts::parse()).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,228 @@
+//! Harness installing the ledger and archive canisters in a PocketIC
+//! instance, so that tests can call their exported endpoints the way clients
+//! do.
+//!
+//! The wasm modules are read from `TOKEN_CANISTER_WASM_PATH` and
+//! `ARCHIVE_CANISTER_WASM_PATH`, which default to what the build.sh scripts
+//! produce. PocketIC itself locates its server binary through
+//! `POCKET_IC_BIN`.
+
+use candid::{CandidType, Decode, Encode};
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+use ic_base_types::{CanisterId, PrincipalId};
+use ic_principal::Principal;
+use pocket_ic::{PocketIc, WasmResult};
+use serde::de::DeserializeOwned;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use token_canister::ic_block::{GetBlocksArgs, GetBlocksRes};
+use token_canister::signed_transfer::{
+    SignatureScheme, SignedTransferPayload, SubmitSignedTransferArgs,
+};
+use token_canister::{
+    AccountIdentifier, BlockHeight, LedgerCanisterInitPayload, LedgerCanisterUpgradePayload,
+    LedgerInfo, Memo, SendArgs, TOKENs, TimeStamp, TRANSACTION_FEE,
+};
+
+const CANISTER_CYCLES: u128 = 2_000_000_000_000;
+
+/// DER prefix of an Ed25519 `SubjectPublicKeyInfo`.
+const ED25519_DER_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+fn read_wasm(env_var: &str, name: &str) -> Vec<u8> {
+    let path = match std::env::var(env_var) {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../../target/wasm32-unknown-unknown/release")
+            .join(format!("{}.wasm", name)),
+    };
+    std::fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "Could not read {}: {}. Build it with src/{}/build.sh or set {}",
+            path.display(),
+            e,
+            name,
+            env_var
+        )
+    })
+}
+
+pub fn ledger_wasm() -> Vec<u8> {
+    read_wasm("TOKEN_CANISTER_WASM_PATH", "token_canister")
+}
+
+pub fn archive_wasm() -> Vec<u8> {
+    read_wasm("ARCHIVE_CANISTER_WASM_PATH", "archive_canister")
+}
+
+/// The `n`-th test user.
+pub fn user(n: u64) -> Principal {
+    Principal::from_slice(PrincipalId::new_user_test_id(n).as_slice())
+}
+
+/// The default account of `principal`.
+pub fn account(principal: Principal) -> AccountIdentifier {
+    AccountIdentifier::new(PrincipalId::try_from(principal.as_slice()).unwrap(), None)
+}
+
+fn signer_keypair() -> Keypair {
+    let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+    let public = PublicKey::from(&secret);
+    Keypair { secret, public }
+}
+
+fn signer_public_key() -> Vec<u8> {
+    [&ED25519_DER_PREFIX[..], signer_keypair().public.as_bytes()].concat()
+}
+
+/// The self-authenticating principal of the Ed25519 key that signs the
+/// transfers of `LedgerEnv::submit_signed_transfer`.
+pub fn signer() -> Principal {
+    Principal::self_authenticating(signer_public_key())
+}
+
+/// A ledger installed in its own PocketIC instance.
+pub struct LedgerEnv {
+    pub pic: PocketIc,
+    pub ledger_id: Principal,
+}
+
+impl LedgerEnv {
+    pub fn new(payload: LedgerCanisterInitPayload) -> Self {
+        let pic = PocketIc::new();
+        let ledger_id = pic.create_canister();
+        pic.add_cycles(ledger_id, CANISTER_CYCLES);
+        pic.install_canister(ledger_id, ledger_wasm(), Encode!(&payload).unwrap(), None);
+        LedgerEnv { pic, ledger_id }
+    }
+
+    /// Installs the archive canister next to the ledger and returns its id.
+    pub fn install_archive(&self) -> Principal {
+        let archive_id = self.pic.create_canister();
+        self.pic.add_cycles(archive_id, CANISTER_CYCLES);
+        self.pic
+            .install_canister(archive_id, archive_wasm(), Encode!().unwrap(), None);
+        archive_id
+    }
+
+    /// Upgrades the ledger to the same wasm, going through pre_upgrade and
+    /// post_upgrade.
+    pub fn upgrade(&self, payload: Option<LedgerCanisterUpgradePayload>) {
+        let arg = match payload {
+            Some(payload) => Encode!(&payload).unwrap(),
+            None => vec![],
+        };
+        self.pic
+            .upgrade_canister(self.ledger_id, ledger_wasm(), arg, None)
+            .unwrap_or_else(|e| panic!("Upgrading the ledger failed: {:?}", e));
+    }
+
+    pub fn update<R: CandidType + DeserializeOwned>(
+        &self,
+        sender: Principal,
+        method: &str,
+        payload: Vec<u8>,
+    ) -> Result<R, String> {
+        let result = self
+            .pic
+            .update_call(self.ledger_id, sender, method, payload)
+            .map_err(|e| e.to_string())?;
+        decode_reply(result)
+    }
+
+    pub fn query<R: CandidType + DeserializeOwned>(
+        &self,
+        method: &str,
+        payload: Vec<u8>,
+    ) -> Result<R, String> {
+        let result = self
+            .pic
+            .query_call(self.ledger_id, Principal::anonymous(), method, payload)
+            .map_err(|e| e.to_string())?;
+        decode_reply(result)
+    }
+
+    /// Sends `amount` from the default account of `from` to `to`, paying the
+    /// standard fee.
+    pub fn send(
+        &self,
+        from: Principal,
+        to: AccountIdentifier,
+        amount: TOKENs,
+        memo: u64,
+        created_at_time: Option<TimeStamp>,
+    ) -> Result<BlockHeight, String> {
+        let args = SendArgs {
+            memo: Memo(memo),
+            amount,
+            fee: TRANSACTION_FEE,
+            from_subaccount: None,
+            to,
+            created_at_time,
+            idempotency_key: None,
+            encrypted_memo: None,
+            expires_at: None,
+        };
+        self.update(from, "send_dfx", Encode!(&args).unwrap())
+    }
+
+    /// Submits a transfer of `amount` from the default account of `signer()`
+    /// to `to`, paying the standard fee, on behalf of `relayer`.
+    pub fn submit_signed_transfer(
+        &self,
+        relayer: Principal,
+        to: AccountIdentifier,
+        amount: TOKENs,
+        nonce: u64,
+    ) -> Result<BlockHeight, String> {
+        let payload = SignedTransferPayload {
+            memo: Memo(nonce),
+            amount,
+            fee: TRANSACTION_FEE,
+            from_subaccount: None,
+            to,
+            created_at_time: self.ledger_time(),
+            nonce,
+            ledger_canister_id: CanisterId::try_from(
+                PrincipalId::try_from(self.ledger_id.as_slice()).unwrap(),
+            )
+            .unwrap(),
+        };
+        let signature = signer_keypair().sign(&payload.signed_bytes());
+        let args = SubmitSignedTransferArgs {
+            payload,
+            scheme: SignatureScheme::Ed25519,
+            public_key: signer_public_key(),
+            signature: signature.to_bytes().to_vec(),
+        };
+        self.update(relayer, "submit_signed_transfer", Encode!(&args).unwrap())
+    }
+
+    pub fn balance(&self, account: AccountIdentifier) -> TOKENs {
+        let args = token_canister::AccountBalanceArgs { account };
+        self.query("account_balance_dfx", Encode!(&args).unwrap())
+            .unwrap()
+    }
+
+    pub fn ledger_info(&self) -> LedgerInfo {
+        self.query("ledger_info", Encode!().unwrap()).unwrap()
+    }
+
+    pub fn ledger_time(&self) -> TimeStamp {
+        self.query("ledger_time", Encode!().unwrap()).unwrap()
+    }
+
+    pub fn get_blocks(&self, start: BlockHeight, length: usize) -> GetBlocksRes {
+        self.query("get_blocks", Encode!(&GetBlocksArgs::new(start, length)).unwrap())
+            .unwrap()
+    }
+}
+
+fn decode_reply<R: CandidType + DeserializeOwned>(result: WasmResult) -> Result<R, String> {
+    match result {
+        WasmResult::Reply(bytes) => Ok(Decode!(&bytes, R).map_err(|e| e.to_string())?),
+        WasmResult::Reject(message) => Err(message),
+    }
+}
@@ -0,0 +1,105 @@
+use candid::Encode;
+use ic_base_types::{CanisterId, PrincipalId};
+use ledger_integration_tests::{account, signer, user, LedgerEnv};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use token_canister::hooks::{HookMode, HookPhase, HookedOperation, OperationHook};
+use token_canister::{
+    AccountIdentifier, LedgerCanisterInitPayload, Memo, Subaccount, SweepSubaccountsArgs, TOKENs,
+    TRANSACTION_FEE,
+};
+
+const INITIAL_BALANCE: TOKENs = TOKENs::from_e8s(1_000_000_000);
+
+fn setup() -> LedgerEnv {
+    let mut initial_values = HashMap::new();
+    initial_values.insert(account(signer()), INITIAL_BALANCE);
+    initial_values.insert(
+        AccountIdentifier::new(
+            PrincipalId::try_from(user(1).as_slice()).unwrap(),
+            Some(Subaccount([1; 32])),
+        ),
+        INITIAL_BALANCE,
+    );
+    LedgerEnv::new(LedgerCanisterInitPayload::new(
+        "TOK".to_string(),
+        account(user(0)),
+        initial_values,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    ))
+}
+
+fn set_hooks(env: &LedgerEnv, hooks: Vec<OperationHook>) {
+    env.update::<()>(
+        ic_principal::Principal::anonymous(),
+        "set_operation_hooks",
+        Encode!(&hooks).unwrap(),
+    )
+    .unwrap();
+}
+
+fn blocking_transfer_hook(env: &LedgerEnv) -> OperationHook {
+    OperationHook {
+        canister_id: CanisterId::try_from(PrincipalId::try_from(env.ledger_id.as_slice()).unwrap())
+            .unwrap(),
+        method: "approve".to_string(),
+        operation: HookedOperation::Transfer,
+        phase: HookPhase::Before,
+        mode: HookMode::Blocking,
+    }
+}
+
+fn sweep(env: &LedgerEnv) -> Result<Vec<u64>, String> {
+    let args = SweepSubaccountsArgs {
+        to_subaccount: None,
+        from_subaccounts: vec![Subaccount([1; 32])],
+        memo: Memo(0),
+    };
+    env.update(user(1), "sweep_subaccounts", Encode!(&args).unwrap())
+}
+
+/// Signed transfers and sweeps do not call hooks, so they must not apply
+/// transfers that a blocking hook has to approve.
+#[test]
+fn blocking_hooks_veto_signed_transfers_and_sweeps() {
+    let env = setup();
+    let amount = TOKENs::from_e8s(100);
+    set_hooks(&env, vec![blocking_transfer_hook(&env)]);
+
+    let res = env.submit_signed_transfer(user(3), account(user(2)), amount, 0);
+    assert!(
+        res.as_ref()
+            .unwrap_err()
+            .contains("must be approved by hook"),
+        "Signed transfer bypassed the hook: {:?}",
+        res
+    );
+    let res = sweep(&env);
+    assert!(
+        res.as_ref()
+            .unwrap_err()
+            .contains("must be approved by hook"),
+        "Sweep bypassed the hook: {:?}",
+        res
+    );
+    assert_eq!(env.balance(account(signer())), INITIAL_BALANCE);
+    assert_eq!(env.balance(account(user(1))), TOKENs::ZERO);
+    assert_eq!(env.ledger_info().chain_length, 2);
+
+    set_hooks(&env, vec![]);
+    env.submit_signed_transfer(user(3), account(user(2)), amount, 0)
+        .unwrap();
+    assert_eq!(sweep(&env).unwrap().len(), 1);
+    assert_eq!(env.balance(account(user(2))), amount);
+    assert_eq!(
+        env.balance(account(user(1))),
+        (INITIAL_BALANCE - TRANSACTION_FEE).unwrap()
+    );
+}
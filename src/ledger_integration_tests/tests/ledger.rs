@@ -0,0 +1,207 @@
+use ledger_integration_tests::{account, user, LedgerEnv};
+use std::collections::HashMap;
+use token_canister::{
+    LedgerCanisterInitPayload, Operation, TOKENs, TimeStamp, TRANSACTION_FEE,
+};
+
+const INITIAL_BALANCE: TOKENs = TOKENs::from_e8s(1_000_000_000);
+
+fn minter() -> ic_principal::Principal {
+    user(0)
+}
+
+fn setup() -> LedgerEnv {
+    let mut initial_values = HashMap::new();
+    initial_values.insert(account(user(1)), INITIAL_BALANCE);
+    LedgerEnv::new(LedgerCanisterInitPayload::new(
+        "TOK".to_string(),
+        account(minter()),
+        initial_values,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    ))
+}
+
+#[test]
+fn init_should_mint_initial_values() {
+    let env = setup();
+
+    assert_eq!(env.balance(account(user(1))), INITIAL_BALANCE);
+    assert_eq!(env.balance(account(user(2))), TOKENs::ZERO);
+
+    let info = env.ledger_info();
+    assert_eq!(info.symbol, "TOK");
+    assert_eq!(info.minting_account, Some(account(minter())));
+    assert_eq!(info.chain_length, 1);
+
+    let res = env.get_blocks(0, 10);
+    let blocks = res.blocks.unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(res.chain_length, 1);
+    assert_eq!(
+        blocks[0].decode().unwrap().transaction.operation,
+        Operation::Mint {
+            to: account(user(1)),
+            amount: INITIAL_BALANCE,
+            fee: TOKENs::ZERO,
+        }
+    );
+}
+
+#[test]
+fn send_should_move_tokens_and_burn_the_fee() {
+    let env = setup();
+    let amount = TOKENs::from_e8s(100_000);
+
+    let height = env
+        .send(user(1), account(user(2)), amount, 1, None)
+        .unwrap();
+    assert_eq!(height, 1);
+
+    let spent = (amount + TRANSACTION_FEE).unwrap();
+    assert_eq!(
+        env.balance(account(user(1))),
+        (INITIAL_BALANCE - spent).unwrap()
+    );
+    assert_eq!(env.balance(account(user(2))), amount);
+
+    let blocks = env.get_blocks(height, 1).blocks.unwrap();
+    assert_eq!(
+        blocks[0].decode().unwrap().transaction.operation,
+        Operation::Transfer {
+            from: account(user(1)),
+            to: account(user(2)),
+            amount,
+            fee: TRANSACTION_FEE,
+        }
+    );
+}
+
+#[test]
+fn send_should_reject_overdrafts() {
+    let env = setup();
+
+    let res = env.send(user(1), account(user(2)), INITIAL_BALANCE, 1, None);
+    assert!(res.is_err(), "Overdraft was accepted: {:?}", res);
+    assert_eq!(env.balance(account(user(1))), INITIAL_BALANCE);
+    assert_eq!(env.ledger_info().chain_length, 1);
+}
+
+#[test]
+fn send_should_reject_anonymous_callers() {
+    let env = setup();
+
+    let res = env.send(
+        ic_principal::Principal::anonymous(),
+        account(user(2)),
+        TOKENs::from_e8s(1),
+        1,
+        None,
+    );
+    assert!(res.is_err(), "Anonymous send was accepted: {:?}", res);
+}
+
+#[test]
+fn get_blocks_should_report_the_chain_bounds() {
+    let env = setup();
+    for memo in 0..3 {
+        env.send(user(1), account(user(2)), TOKENs::from_e8s(1), memo, None)
+            .unwrap();
+    }
+
+    let res = env.get_blocks(1, 100);
+    assert_eq!(res.first_block_index, 1);
+    assert_eq!(res.chain_length, 4);
+    assert_eq!(res.blocks.unwrap().len(), 3);
+
+    let res = env.get_blocks(10, 1);
+    assert!(res.blocks.is_err());
+    assert_eq!(res.chain_length, 4);
+}
+
+#[test]
+fn upgrade_should_preserve_balances_blocks_and_deduplication() {
+    let env = setup();
+    let created_at_time: TimeStamp = env.ledger_time();
+    env.send(
+        user(1),
+        account(user(2)),
+        TOKENs::from_e8s(100),
+        7,
+        Some(created_at_time),
+    )
+    .unwrap();
+
+    let balances_before = (
+        env.balance(account(user(1))),
+        env.balance(account(user(2))),
+    );
+    let info_before = env.ledger_info();
+    let blocks_before = env.get_blocks(0, 100);
+
+    env.upgrade(None);
+
+    assert_eq!(
+        (
+            env.balance(account(user(1))),
+            env.balance(account(user(2))),
+        ),
+        balances_before
+    );
+    assert_eq!(env.ledger_info(), info_before);
+    assert_eq!(env.get_blocks(0, 100), blocks_before);
+
+    // The transaction window survives the upgrade.
+    let res = env.send(
+        user(1),
+        account(user(2)),
+        TOKENs::from_e8s(100),
+        7,
+        Some(created_at_time),
+    );
+    assert!(
+        res.as_ref().unwrap_err().contains("already exists on chain"),
+        "Duplicate was not rejected: {:?}",
+        res
+    );
+
+    // The upgraded ledger keeps extending the same chain.
+    let height = env
+        .send(user(1), account(user(2)), TOKENs::from_e8s(100), 8, None)
+        .unwrap();
+    assert_eq!(height, info_before.chain_length);
+    let block = env.get_blocks(height, 1).blocks.unwrap()[0].decode().unwrap();
+    let previous = &blocks_before.blocks.unwrap()[height as usize - 1];
+    assert_eq!(block.parent_hash, Some(previous.hash()));
+}
+
+/// The archive canister does not export any endpoints yet, so the ledger
+/// keeps every block. This checks that the archive installs alongside the
+/// ledger and that the ledger serves its whole, correctly linked chain.
+#[test]
+fn ledger_should_serve_all_blocks_next_to_an_installed_archive() {
+    let env = setup();
+    env.install_archive();
+    for memo in 0..5 {
+        env.send(user(1), account(user(2)), TOKENs::from_e8s(1), memo, None)
+            .unwrap();
+    }
+
+    assert_eq!(env.ledger_info().num_archived_blocks, 0);
+    let res = env.get_blocks(0, 100);
+    assert_eq!(res.first_block_index, 0);
+    let blocks = res.blocks.unwrap();
+    assert_eq!(blocks.len() as u64, res.chain_length);
+    for pair in blocks.windows(2) {
+        assert_eq!(
+            pair[1].decode().unwrap().parent_hash,
+            Some(pair[0].hash())
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use candid::Encode;
+use ic_base_types::PrincipalId;
+use ledger_integration_tests::{account, signer, user, LedgerEnv};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::time::Duration;
+use token_canister::{
+    AccessPolicyConfig, LedgerCanisterInitPayload, SendWhitelist, TOKENs, TRANSACTION_FEE,
+};
+
+const INITIAL_BALANCE: TOKENs = TOKENs::from_e8s(1_000_000_000);
+
+fn setup(access_policy: Option<AccessPolicyConfig>) -> LedgerEnv {
+    let mut initial_values = HashMap::new();
+    initial_values.insert(account(signer()), INITIAL_BALANCE);
+    LedgerEnv::new(LedgerCanisterInitPayload::new(
+        "TOK".to_string(),
+        account(user(0)),
+        initial_values,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        access_policy,
+        None,
+    ))
+}
+
+#[test]
+fn signed_transfers_count_against_the_spend_cap_of_the_owner() {
+    let amount = TOKENs::from_e8s(100_000);
+    let mut spend_caps = BTreeMap::new();
+    spend_caps.insert(
+        PrincipalId::try_from(signer().as_slice()).unwrap(),
+        (amount + TRANSACTION_FEE).unwrap(),
+    );
+    let env = setup(Some(AccessPolicyConfig::Whitelist(SendWhitelist {
+        spend_caps,
+        ..Default::default()
+    })));
+
+    env.submit_signed_transfer(user(3), account(user(2)), amount, 0)
+        .unwrap();
+    let res = env.submit_signed_transfer(user(3), account(user(2)), amount, 1);
+    assert!(
+        res.as_ref().unwrap_err().contains("Spend cap exceeded"),
+        "Signed transfer exceeded the spend cap: {:?}",
+        res
+    );
+    assert_eq!(env.balance(account(user(2))), amount);
+}
+
+#[test]
+fn signed_transfers_are_rejected_while_the_ledger_is_read_only() {
+    let env = setup(None);
+    let until = env.ledger_time() + Duration::from_secs(3600);
+    env.update::<()>(
+        ic_principal::Principal::anonymous(),
+        "set_read_only",
+        Encode!(&Some(until)).unwrap(),
+    )
+    .unwrap();
+
+    let res = env.submit_signed_transfer(user(3), account(user(2)), TOKENs::from_e8s(100), 0);
+    assert!(
+        res.as_ref()
+            .unwrap_err()
+            .contains("read-only for a migration"),
+        "Signed transfer was accepted while read-only: {:?}",
+        res
+    );
+    assert_eq!(env.balance(account(signer())), INITIAL_BALANCE);
+}
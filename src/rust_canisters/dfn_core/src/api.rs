@@ -36,6 +36,9 @@ pub mod ic0 {
         pub fn debug_print(offset: u32, size: u32);
         pub fn msg_arg_data_copy(dst: u32, offset: u32, size: u32);
         pub fn msg_arg_data_size() -> u32;
+        pub fn msg_method_name_copy(dst: u32, offset: u32, size: u32);
+        pub fn msg_method_name_size() -> u32;
+        pub fn accept_message();
         pub fn msg_caller_copy(dst: u32, offset: u32, size: u32);
         pub fn msg_caller_size() -> u32;
         pub fn msg_reject(src: u32, size: u32);
@@ -131,6 +134,15 @@ pub mod ic0 {
     pub unsafe fn msg_arg_data_size() -> u32 {
         wrong_arch("msg_arg_data_size")
     }
+    pub unsafe fn msg_method_name_copy(_dst: u32, _offset: u32, _size: u32) {
+        wrong_arch("msg_method_name_copy")
+    }
+    pub unsafe fn msg_method_name_size() -> u32 {
+        wrong_arch("msg_method_name_size")
+    }
+    pub unsafe fn accept_message() {
+        wrong_arch("accept_message")
+    }
     pub unsafe fn msg_caller_copy(_dst: u32, _offset: u32, _size: u32) {
         wrong_arch("msg_caller_copy")
     }
@@ -632,6 +644,22 @@ pub fn arg_data() -> Vec<u8> {
     bytes
 }
 
+/// Returns the name of the method called, in canister_inspect_message.
+pub fn method_name() -> String {
+    let len: u32 = unsafe { ic0::msg_method_name_size() };
+    let mut bytes = vec![0; len as usize];
+    unsafe {
+        ic0::msg_method_name_copy(bytes.as_mut_ptr() as u32, 0, len);
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Accepts the ingress message being inspected in canister_inspect_message.
+/// Messages that are not accepted are rejected.
+pub fn accept_message() {
+    unsafe { ic0::accept_message() }
+}
+
 /// Returns the caller of the current call.
 pub fn caller() -> PrincipalId {
     let len: u32 = unsafe { ic0::msg_caller_size() };
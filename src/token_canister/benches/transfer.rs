@@ -0,0 +1,177 @@
+use candid::Encode;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use dfn_protobuf::ProtoBuf;
+use on_wire::IntoWire;
+use std::sync::Arc;
+use token_canister::{
+    get_blocks, AccountIdentifier, Ledger, ManualTimeSource, Memo, Operation, PaymentOptions,
+    TOKENs, TimeStamp, TRANSACTION_FEE,
+};
+
+/// Ledger sizes the account-dependent benchmarks run against. The largest
+/// one needs a few GB of memory.
+const NUM_ACCOUNTS: [u64; 2] = [1_000_000, 10_000_000];
+/// How many transactions the deduplication benchmark keeps in the window.
+const NUM_WINDOW_TRANSACTIONS: [u64; 2] = [10_000, 100_000];
+const NUM_BLOCKS: u64 = 10_000;
+const GET_BLOCKS_LENGTH: usize = 2_000;
+/// The default number of accounts the ledger trims at once.
+const TRIM_QUANTITY: usize = 100_000;
+const GENESIS_TIME_NANOS: u64 = 1_600_000_000_000_000_000;
+const INITIAL_BALANCE: TOKENs = TOKENs::from_e8s(100_000_000_000);
+
+fn account(i: u64) -> AccountIdentifier {
+    // Offset by one, as the ledger refuses to credit the all-zero hash.
+    let mut hash = [0; 28];
+    hash[..8].copy_from_slice(&(i + 1).to_be_bytes());
+    AccountIdentifier { hash }
+}
+
+fn transfer(i: u64, num_accounts: u64) -> Operation {
+    Operation::Transfer {
+        from: account(i % num_accounts),
+        to: account((i + 1) % num_accounts),
+        amount: TOKENs::from_e8s(1),
+        fee: TRANSACTION_FEE,
+    }
+}
+
+fn time(i: u64) -> TimeStamp {
+    TimeStamp::from_nanos(GENESIS_TIME_NANOS + i)
+}
+
+/// A ledger whose balances hold `num_accounts` accounts. The balances are
+/// credited directly, without blocks, to keep the setup fast.
+fn ledger_with_accounts(num_accounts: u64, clock: Arc<ManualTimeSource>) -> Ledger {
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(clock);
+    ledger
+        .set_account_limits(Some(2 * num_accounts as usize), Some(1))
+        .unwrap();
+    for i in 0..num_accounts {
+        ledger
+            .balances
+            .add_payment(&Operation::Mint {
+                to: account(i),
+                amount: INITIAL_BALANCE,
+                fee: TOKENs::ZERO,
+            })
+            .unwrap();
+    }
+    ledger
+}
+
+/// A ledger with `num_blocks` transfers in its chain and transaction window.
+fn ledger_with_blocks(num_blocks: u64, clock: Arc<ManualTimeSource>) -> Ledger {
+    let num_accounts = 1_000;
+    let mut ledger = ledger_with_accounts(num_accounts, clock.clone());
+    for i in 0..num_blocks {
+        clock.set(time(i));
+        ledger
+            .add_payment(
+                Memo(i),
+                transfer(i, num_accounts),
+                PaymentOptions::default(),
+            )
+            .unwrap();
+    }
+    ledger
+}
+
+fn bench_add_payment(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_payment");
+    for num_accounts in NUM_ACCOUNTS.iter().copied() {
+        let clock = Arc::new(ManualTimeSource::new(time(0)));
+        let mut ledger = ledger_with_accounts(num_accounts, clock.clone());
+        let mut i = 0;
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_accounts),
+            &num_accounts,
+            |b, &num_accounts| {
+                b.iter(|| {
+                    i += 1;
+                    clock.set(time(i));
+                    ledger
+                        .add_payment(
+                            Memo(i),
+                            transfer(i, num_accounts),
+                            PaymentOptions::default(),
+                        )
+                        .unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_dedup_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dedup_lookup");
+    for num_transactions in NUM_WINDOW_TRANSACTIONS.iter().copied() {
+        let clock = Arc::new(ManualTimeSource::new(time(0)));
+        let mut ledger = ledger_with_blocks(num_transactions, clock);
+        let created_at_time = time(num_transactions / 2);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_transactions),
+            &num_transactions,
+            |b, &num_transactions| {
+                // Resubmits a transaction from the middle of the window, which
+                // is rejected as a duplicate.
+                b.iter(|| {
+                    ledger
+                        .add_payment(
+                            Memo(num_transactions / 2),
+                            transfer(num_transactions / 2, 1_000),
+                            PaymentOptions {
+                                created_at_time: Some(created_at_time),
+                                ..Default::default()
+                            },
+                        )
+                        .unwrap_err()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_select_accounts_to_trim(c: &mut Criterion) {
+    let mut group = c.benchmark_group("select_accounts_to_trim");
+    group.sample_size(10);
+    for num_accounts in NUM_ACCOUNTS.iter().copied() {
+        let clock = Arc::new(ManualTimeSource::new(time(0)));
+        let mut ledger = ledger_with_accounts(num_accounts, clock);
+        group.bench_function(BenchmarkId::from_parameter(num_accounts), |b| {
+            b.iter(|| ledger.balances.select_accounts_to_trim(TRIM_QUANTITY))
+        });
+    }
+    group.finish();
+}
+
+fn bench_get_blocks(c: &mut Criterion) {
+    let ledger = ledger_with_blocks(NUM_BLOCKS, Arc::new(ManualTimeSource::new(time(0))));
+    let blocks = &ledger.blockchain.blocks;
+    let start = NUM_BLOCKS - GET_BLOCKS_LENGTH as u64;
+
+    let mut group = c.benchmark_group("get_blocks");
+    group.bench_function("candid", |b| {
+        b.iter(|| Encode!(&get_blocks(blocks, 0, start, GET_BLOCKS_LENGTH)).unwrap())
+    });
+    group.bench_function("protobuf", |b| {
+        b.iter(|| {
+            ProtoBuf::new(get_blocks(blocks, 0, start, GET_BLOCKS_LENGTH))
+                .into_bytes()
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_add_payment,
+    bench_dedup_lookup,
+    bench_select_accounts_to_trim,
+    bench_get_blocks
+);
+criterion_main!(benches);
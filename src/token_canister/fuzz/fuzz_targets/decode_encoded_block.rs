@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use token_canister::EncodedBlock;
+
+// Blocks are decoded from bytes handed to the ledger by archives and
+// clients, so malformed input must produce an error rather than a trap.
+fuzz_target!(|data: &[u8]| {
+    let encoded = EncodedBlock::from(data.to_vec().into_boxed_slice());
+    encoded.hash();
+    if let Ok(block) = encoded.decode() {
+        let reencoded = block.clone().encode().expect("decoded block failed to encode");
+        assert_eq!(reencoded.decode(), Ok(block));
+    }
+});
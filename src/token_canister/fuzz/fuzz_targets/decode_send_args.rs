@@ -0,0 +1,13 @@
+#![no_main]
+use dfn_candid::CandidOne;
+use dfn_protobuf::ProtoBuf;
+use libfuzzer_sys::fuzz_target;
+use on_wire::FromWire;
+use token_canister::SendArgs;
+
+// Decodes the argument of send_dfx (candid) and send_pb (protobuf) the same
+// way the endpoints do.
+fuzz_target!(|data: &[u8]| {
+    let _ = CandidOne::<SendArgs>::from_bytes(data.to_vec());
+    let _ = ProtoBuf::<SendArgs>::from_bytes(data.to_vec());
+});
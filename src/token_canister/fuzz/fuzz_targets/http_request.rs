@@ -0,0 +1,14 @@
+#![no_main]
+use dfn_candid::CandidOne;
+use libfuzzer_sys::fuzz_target;
+use on_wire::FromWire;
+use token_canister::http::{serve, HttpRequest, RequestContext};
+use token_canister::Ledger;
+
+// Decodes the argument of http_request and routes it the way the endpoint
+// does, against an empty ledger.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(CandidOne(request)) = CandidOne::<HttpRequest>::from_bytes(data.to_vec()) {
+        let _ = serve(&request, &Ledger::default(), &RequestContext::default());
+    }
+});
@@ -39,6 +39,13 @@ pub struct SendRequest {
     pub created_at: ::core::option::Option<BlockHeight>,
     #[prost(message, optional, tag="7")]
     pub created_at_time: ::core::option::Option<TimeStamp>,
+    #[prost(message, optional, tag="8")]
+    pub idempotency_key: ::core::option::Option<IdempotencyKey>,
+    /// Opaque to the ledger and deliberately not displayed on hardware wallets.
+    #[prost(bytes="vec", tag="9")]
+    pub encrypted_memo: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, optional, tag="10")]
+    pub expires_at: ::core::option::Option<TimeStamp>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SendResponse {
@@ -189,9 +196,21 @@ pub struct EncodedBlocks {
     #[prost(message, repeated, tag="1")]
     pub blocks: ::prost::alloc::vec::Vec<EncodedBlock>,
 }
+/// The requested blocks were dropped by the ledger's retention policy.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PrunedBlocks {
+    #[prost(uint64, tag="1")]
+    pub earliest_available: u64,
+}
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct GetBlocksResponse {
-    #[prost(oneof="get_blocks_response::GetBlocksContent", tags="1, 2")]
+    /// The height of the first returned block or, on error, of the first block
+    /// still available. Older clients ignore these fields.
+    #[prost(uint64, tag="3")]
+    pub first_block_index: u64,
+    #[prost(uint64, tag="4")]
+    pub chain_length: u64,
+    #[prost(oneof="get_blocks_response::GetBlocksContent", tags="1, 2, 5")]
     pub get_blocks_content: ::core::option::Option<get_blocks_response::GetBlocksContent>,
 }
 /// Nested message and enum types in `GetBlocksResponse`.
@@ -202,6 +221,8 @@ pub mod get_blocks_response {
         Blocks(super::EncodedBlocks),
         #[prost(string, tag="2")]
         Error(::prost::alloc::string::String),
+        #[prost(message, tag="5")]
+        Pruned(super::PrunedBlocks),
     }
 }
 /// Iterate through blocks
@@ -285,6 +306,10 @@ pub struct Block {
     pub timestamp: ::core::option::Option<TimeStamp>,
     #[prost(message, optional, tag="3")]
     pub transaction: ::core::option::Option<Transaction>,
+    /// The account the fee was credited to. Left out when the fee returned to
+    /// the pool.
+    #[prost(message, optional, tag="4")]
+    pub fee_collector: ::core::option::Option<AccountIdentifier>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Hash {
@@ -307,7 +332,11 @@ pub struct Transaction {
     pub created_at: ::core::option::Option<BlockHeight>,
     #[prost(message, optional, tag="6")]
     pub created_at_time: ::core::option::Option<TimeStamp>,
-    #[prost(oneof="transaction::Transfer", tags="1, 2, 3")]
+    #[prost(bytes="vec", tag="7")]
+    pub encrypted_memo: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes="vec", tag="8")]
+    pub memo_blob: ::prost::alloc::vec::Vec<u8>,
+    #[prost(oneof="transaction::Transfer", tags="1, 2, 3, 9")]
     pub transfer: ::core::option::Option<transaction::Transfer>,
 }
 /// Nested message and enum types in `Transaction`.
@@ -320,6 +349,10 @@ pub mod transaction {
         Mint(super::Mint),
         #[prost(message, tag="3")]
         Send(super::Send),
+        #[prost(message, tag="9")]
+        SetMintingAccount(super::SetMintingAccount),
+        #[prost(message, tag="10")]
+        AdminAudit(super::AdminAudit),
     }
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -339,6 +372,9 @@ pub struct Mint {
     pub to: ::core::option::Option<AccountIdentifier>,
     #[prost(message, optional, tag="3")]
     pub amount: ::core::option::Option<TokeNs>,
+    /// Left out when zero.
+    #[prost(message, optional, tag="4")]
+    pub fee: ::core::option::Option<TokeNs>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Burn {
@@ -346,6 +382,25 @@ pub struct Burn {
     pub from: ::core::option::Option<AccountIdentifier>,
     #[prost(message, optional, tag="3")]
     pub amount: ::core::option::Option<TokeNs>,
+    /// Left out when zero.
+    #[prost(message, optional, tag="4")]
+    pub fee: ::core::option::Option<TokeNs>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetMintingAccount {
+    #[prost(message, optional, tag="1")]
+    pub previous: ::core::option::Option<AccountIdentifier>,
+    #[prost(message, optional, tag="2")]
+    pub new: ::core::option::Option<AccountIdentifier>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AdminAudit {
+    #[prost(uint64, tag="1")]
+    pub action_id: u64,
+    #[prost(enumeration="AdminActionStatus", tag="2")]
+    pub status: i32,
+    #[prost(string, tag="3")]
+    pub description: ::prost::alloc::string::String,
 }
 #[derive(candid::CandidType, candid::Deserialize)] #[cfg_attr(feature = "test", derive(comparable::Comparable))]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -373,7 +428,19 @@ pub struct TimeStamp {
     pub timestamp_nanos: u64,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IdempotencyKey {
+    #[prost(bytes="vec", tag="1")]
+    pub key: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Certification {
     #[prost(bytes="vec", tag="1")]
     pub certification: ::prost::alloc::vec::Vec<u8>,
 }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum AdminActionStatus {
+    Proposed = 0,
+    Executed = 1,
+    Cancelled = 2,
+}
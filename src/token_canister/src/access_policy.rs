@@ -0,0 +1,255 @@
+use crate::ic_token::TOKENs;
+use crate::TimeStamp;
+
+use candid::CandidType;
+use ic_base_types::PrincipalId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::time::Duration;
+
+/// The rolling window over which the spend caps of canisters apply.
+pub const SPEND_CAP_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+/// The most canisters the notify whitelist may hold.
+pub const MAX_NOTIFY_WHITELIST_LENGTH: usize = 10_000;
+/// The most principals the reader list may hold.
+pub const MAX_READER_LIST_LENGTH: usize = 10_000;
+
+/// Decides which principals may send tokens from their accounts and which
+/// canisters may be notified of a payment.
+pub trait AccessPolicy {
+    fn can_send(&self, principal_id: &PrincipalId) -> bool;
+
+    fn can_be_notified(&self, canister_id: &PrincipalId) -> bool;
+}
+
+/// Any principal except the anonymous one may send, and any canister may be
+/// notified.
+pub struct OpenPolicy;
+
+impl AccessPolicy for OpenPolicy {
+    fn can_send(&self, principal_id: &PrincipalId) -> bool {
+        !principal_id.is_anonymous()
+    }
+
+    fn can_be_notified(&self, _canister_id: &PrincipalId) -> bool {
+        true
+    }
+}
+
+/// The canisters allowed to send under the whitelist policy.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SendWhitelist {
+    pub canisters: BTreeSet<PrincipalId>,
+    /// Lets every canister send, not only the listed ones.
+    pub allow_all_canisters: bool,
+    /// The maximum amount each canister may send, fees included, within
+    /// `SPEND_CAP_WINDOW`. Canisters without an entry are not capped.
+    pub spend_caps: BTreeMap<PrincipalId, TOKENs>,
+}
+
+/// The canisters that may be notified under the whitelist policy. Being
+/// listed does not let a canister send, and being allowed to send does not
+/// let it be notified.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NotifyWhitelist {
+    canisters: BTreeSet<PrincipalId>,
+}
+
+impl NotifyWhitelist {
+    pub fn new(canisters: BTreeSet<PrincipalId>) -> Self {
+        Self { canisters }
+    }
+
+    /// Lists `canister_id`. Listing a canister twice is not an error.
+    pub fn add(&mut self, canister_id: PrincipalId) -> Result<(), String> {
+        if !self.canisters.contains(&canister_id)
+            && self.canisters.len() >= MAX_NOTIFY_WHITELIST_LENGTH
+        {
+            return Err(format!(
+                "The notify whitelist is full: it holds {} canisters",
+                MAX_NOTIFY_WHITELIST_LENGTH
+            ));
+        }
+        self.canisters.insert(canister_id);
+        Ok(())
+    }
+
+    /// Removes `canister_id`, returning whether it was listed.
+    pub fn remove(&mut self, canister_id: &PrincipalId) -> bool {
+        self.canisters.remove(canister_id)
+    }
+
+    pub fn contains(&self, canister_id: &PrincipalId) -> bool {
+        self.canisters.contains(canister_id)
+    }
+
+    pub fn list(&self) -> Vec<PrincipalId> {
+        self.canisters.iter().copied().collect()
+    }
+}
+
+/// Who may call the queries of a private deployment. While reads are
+/// restricted, only the controller and the listed readers may call them, and
+/// http_request only serves the public routes.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReaderList {
+    restricted: bool,
+    readers: BTreeSet<PrincipalId>,
+}
+
+impl ReaderList {
+    pub fn is_restricted(&self) -> bool {
+        self.restricted
+    }
+
+    /// Restricts reads to the listed readers, or opens them to everybody.
+    /// The list is kept either way.
+    pub fn set_restricted(&mut self, restricted: bool) {
+        self.restricted = restricted;
+    }
+
+    /// Lists `reader`. Listing a principal twice is not an error.
+    pub fn add(&mut self, reader: PrincipalId) -> Result<(), String> {
+        if !self.readers.contains(&reader) && self.readers.len() >= MAX_READER_LIST_LENGTH {
+            return Err(format!(
+                "The reader list is full: it holds {} principals",
+                MAX_READER_LIST_LENGTH
+            ));
+        }
+        self.readers.insert(reader);
+        Ok(())
+    }
+
+    /// Removes `reader`, returning whether it was listed.
+    pub fn remove(&mut self, reader: &PrincipalId) -> bool {
+        self.readers.remove(reader)
+    }
+
+    /// Whether `principal_id` may read, not counting the controller, which
+    /// always may.
+    pub fn can_read(&self, principal_id: &PrincipalId) -> bool {
+        !self.restricted || self.readers.contains(principal_id)
+    }
+
+    pub fn list(&self) -> Vec<PrincipalId> {
+        self.readers.iter().copied().collect()
+    }
+}
+
+/// Self-authenticating principals (users) and the whitelisted canisters may
+/// send; only the canisters on the notify whitelist may be notified. This is
+/// the model of the ICP ledger.
+pub struct WhitelistPolicy<'a> {
+    pub whitelist: &'a SendWhitelist,
+    pub notify_whitelist: &'a NotifyWhitelist,
+}
+
+impl AccessPolicy for WhitelistPolicy<'_> {
+    fn can_send(&self, principal_id: &PrincipalId) -> bool {
+        principal_id.is_self_authenticating()
+            || (self.whitelist.allow_all_canisters && !principal_id.is_anonymous())
+            || self.whitelist.canisters.contains(principal_id)
+    }
+
+    fn can_be_notified(&self, canister_id: &PrincipalId) -> bool {
+        self.notify_whitelist.contains(canister_id)
+    }
+}
+
+/// Any principal except the anonymous one and the listed ones may send, and
+/// any canister that is not listed may be notified.
+pub struct DenyListPolicy<'a> {
+    pub denied: &'a BTreeSet<PrincipalId>,
+}
+
+impl AccessPolicy for DenyListPolicy<'_> {
+    fn can_send(&self, principal_id: &PrincipalId) -> bool {
+        !principal_id.is_anonymous() && !self.denied.contains(principal_id)
+    }
+
+    fn can_be_notified(&self, canister_id: &PrincipalId) -> bool {
+        !self.denied.contains(canister_id)
+    }
+}
+
+/// The access policy a deployment selected at init.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum AccessPolicyConfig {
+    Open,
+    Whitelist(SendWhitelist),
+    DenyList(BTreeSet<PrincipalId>),
+}
+
+impl Default for AccessPolicyConfig {
+    fn default() -> Self {
+        Self::Open
+    }
+}
+
+impl AccessPolicyConfig {
+    /// The selected policy. `notify_whitelist` only matters under the
+    /// whitelist policy.
+    pub fn policy<'a>(
+        &'a self,
+        notify_whitelist: &'a NotifyWhitelist,
+    ) -> Box<dyn AccessPolicy + 'a> {
+        match self {
+            Self::Open => Box::new(OpenPolicy),
+            Self::Whitelist(whitelist) => Box::new(WhitelistPolicy {
+                whitelist,
+                notify_whitelist,
+            }),
+            Self::DenyList(denied) => Box::new(DenyListPolicy { denied }),
+        }
+    }
+
+    /// The amount `principal_id` may send within `SPEND_CAP_WINDOW`, if it is
+    /// capped.
+    pub fn spend_cap(&self, principal_id: &PrincipalId) -> Option<TOKENs> {
+        match self {
+            Self::Whitelist(whitelist) => whitelist.spend_caps.get(principal_id).copied(),
+            Self::Open | Self::DenyList(_) => None,
+        }
+    }
+}
+
+/// The recent spending of capped canisters.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SpendTracker {
+    spends: BTreeMap<PrincipalId, VecDeque<(TimeStamp, TOKENs)>>,
+}
+
+impl SpendTracker {
+    /// Records that `principal_id` spends `amount` at `now`, unless that would
+    /// take its spending within the window over `cap`.
+    pub fn spend(
+        &mut self,
+        principal_id: PrincipalId,
+        amount: TOKENs,
+        cap: TOKENs,
+        now: TimeStamp,
+    ) -> Result<(), String> {
+        let spends = self.spends.entry(principal_id).or_default();
+        while let Some((timestamp, _)) = spends.front() {
+            if !timestamp.has_elapsed(SPEND_CAP_WINDOW, now) {
+                break;
+            }
+            spends.pop_front();
+        }
+        let spent = spends
+            .iter()
+            .try_fold(TOKENs::ZERO, |acc, (_, amount)| acc + *amount)?;
+        let total = (spent + amount)?;
+        if total > cap {
+            return Err(format!(
+                "{} already spent {} of its cap of {} in the last {} hours",
+                principal_id,
+                spent,
+                cap,
+                SPEND_CAP_WINDOW.as_secs() / 3600
+            ));
+        }
+        spends.push_back((now, amount));
+        Ok(())
+    }
+}
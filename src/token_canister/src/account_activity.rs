@@ -0,0 +1,124 @@
+use crate::account_identifier::AccountIdentifier;
+use crate::ic_block::BlockHeight;
+use crate::ic_token::TOKENs;
+use crate::types::Operation;
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Response of the account_summary endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct AccountSummary {
+    pub balance: TOKENs,
+    /// The height of the first block that credited or debited the account.
+    pub first_seen_height: Option<BlockHeight>,
+    /// The height of the last block that credited or debited the account.
+    pub last_activity_height: Option<BlockHeight>,
+    /// Everything the account received, net of the fees of mints.
+    pub total_received: TOKENs,
+    /// Everything debited from the account, fees included.
+    pub total_sent: TOKENs,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct AccountActivity {
+    first_seen_height: BlockHeight,
+    last_activity_height: BlockHeight,
+    total_received: TOKENs,
+    total_sent: TOKENs,
+}
+
+/// The activity of every account that holds tokens, kept up to date as
+/// blocks are added so that explorers need not scan the chain. Like its
+/// balance, the activity of an account is forgotten once its balance drops
+/// to zero, and activity from before the summaries were kept is not counted.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AccountActivities {
+    activities: HashMap<AccountIdentifier, AccountActivity>,
+}
+
+impl AccountActivities {
+    /// Counts `operation`, applied in the block at `height`.
+    pub fn record(&mut self, operation: &Operation, height: BlockHeight) {
+        match operation {
+            Operation::Transfer {
+                from,
+                to,
+                amount,
+                fee,
+            } => {
+                self.add(
+                    *from,
+                    height,
+                    TOKENs::ZERO,
+                    (*amount + *fee).unwrap_or(TOKENs::MAX),
+                );
+                self.add(*to, height, *amount, TOKENs::ZERO);
+            }
+            Operation::Burn { from, amount, fee } => {
+                self.add(
+                    *from,
+                    height,
+                    TOKENs::ZERO,
+                    (*amount + *fee).unwrap_or(TOKENs::MAX),
+                );
+            }
+            Operation::Mint { to, amount, fee } => {
+                self.add(
+                    *to,
+                    height,
+                    (*amount - *fee).unwrap_or(TOKENs::ZERO),
+                    TOKENs::ZERO,
+                );
+            }
+            Operation::SetMintingAccount { .. } | Operation::AdminAudit { .. } => {}
+        }
+    }
+
+    fn add(
+        &mut self,
+        account: AccountIdentifier,
+        height: BlockHeight,
+        received: TOKENs,
+        sent: TOKENs,
+    ) {
+        let activity = self
+            .activities
+            .entry(account)
+            .or_insert_with(|| AccountActivity {
+                first_seen_height: height,
+                last_activity_height: height,
+                total_received: TOKENs::ZERO,
+                total_sent: TOKENs::ZERO,
+            });
+        activity.last_activity_height = height;
+        activity.total_received = (activity.total_received + received).unwrap_or(TOKENs::MAX);
+        activity.total_sent = (activity.total_sent + sent).unwrap_or(TOKENs::MAX);
+    }
+
+    /// Forgets the activity of `account`, whose balance dropped to zero.
+    pub fn forget(&mut self, account: &AccountIdentifier) {
+        self.activities.remove(account);
+    }
+
+    /// The summary of `account`, which holds `balance`.
+    pub fn summary(&self, account: &AccountIdentifier, balance: TOKENs) -> AccountSummary {
+        match self.activities.get(account) {
+            Some(activity) => AccountSummary {
+                balance,
+                first_seen_height: Some(activity.first_seen_height),
+                last_activity_height: Some(activity.last_activity_height),
+                total_received: activity.total_received,
+                total_sent: activity.total_sent,
+            },
+            None => AccountSummary {
+                balance,
+                first_seen_height: None,
+                last_activity_height: None,
+                total_received: TOKENs::ZERO,
+                total_sent: TOKENs::ZERO,
+            },
+        }
+    }
+}
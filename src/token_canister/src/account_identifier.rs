@@ -1,6 +1,5 @@
 use candid::CandidType;
-use dfn_core::CanisterId;
-use ic_base_types::{CanisterIdError, PrincipalId, PrincipalIdError};
+use ic_base_types::{CanisterId, CanisterIdError, PrincipalId, PrincipalIdError};
 use ic_crypto_sha::Sha224;
 use serde::{de, de::Error, Deserialize, Serialize};
 use std::{
@@ -112,6 +111,13 @@ impl AccountIdentifier {
         hasher.update(&self.hash);
         hasher.finalize().to_be_bytes()
     }
+
+    /// Whether tokens sent to this account are lost because nobody controls
+    /// it: the default account of the anonymous principal, or the all-zero
+    /// hash that clients produce when they forget to fill it in.
+    pub fn is_unspendable(&self) -> bool {
+        self.hash == [0; 28] || *self == AccountIdentifier::new(PrincipalId::new_anonymous(), None)
+    }
 }
 
 impl Display for AccountIdentifier {
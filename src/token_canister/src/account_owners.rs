@@ -0,0 +1,75 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+
+use ic_base_types::PrincipalId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The maximum number of accounts a single principal may register.
+pub const MAX_REGISTERED_ACCOUNTS_PER_OWNER: usize = 1_000;
+
+/// An opt-in mapping from accounts to the principals they are derived from.
+/// Account identifiers are hashes, so only the owner can register an account,
+/// by naming the subaccount it is derived from. Exchanges can then check that
+/// a withdrawal address belongs to the user claiming it.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AccountOwners {
+    owners: BTreeMap<AccountIdentifier, PrincipalId>,
+    num_accounts_by_owner: BTreeMap<PrincipalId, usize>,
+}
+
+impl AccountOwners {
+    /// Records that the account of `owner` with the given subaccount belongs
+    /// to `owner`. Registering an account twice is a no-op.
+    pub fn register(
+        &mut self,
+        owner: PrincipalId,
+        subaccount: Option<Subaccount>,
+    ) -> Result<AccountIdentifier, String> {
+        let account = AccountIdentifier::new(owner, subaccount);
+        if self.owners.contains_key(&account) {
+            return Ok(account);
+        }
+        let num_accounts = self.num_accounts_by_owner.entry(owner).or_insert(0);
+        if *num_accounts >= MAX_REGISTERED_ACCOUNTS_PER_OWNER {
+            return Err(format!(
+                "{} already registered the maximum of {} accounts",
+                owner, MAX_REGISTERED_ACCOUNTS_PER_OWNER
+            ));
+        }
+        *num_accounts += 1;
+        self.owners.insert(account, owner);
+        Ok(account)
+    }
+
+    /// Removes the registration of the account of `owner` with the given
+    /// subaccount.
+    pub fn unregister(
+        &mut self,
+        owner: PrincipalId,
+        subaccount: Option<Subaccount>,
+    ) -> Result<AccountIdentifier, String> {
+        let account = AccountIdentifier::new(owner, subaccount);
+        if self.owners.remove(&account).is_none() {
+            return Err(format!("Account {} is not registered", account));
+        }
+        if let Some(num_accounts) = self.num_accounts_by_owner.get_mut(&owner) {
+            *num_accounts -= 1;
+            if *num_accounts == 0 {
+                self.num_accounts_by_owner.remove(&owner);
+            }
+        }
+        Ok(account)
+    }
+
+    pub fn owner_of(&self, account: &AccountIdentifier) -> Option<PrincipalId> {
+        self.owners.get(account).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.owners.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.owners.is_empty()
+    }
+}
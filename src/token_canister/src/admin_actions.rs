@@ -0,0 +1,200 @@
+//! Sensitive admin changes are not made right away: they are proposed, can
+//! be cancelled while their delay runs, and can only be executed once it is
+//! over. Every step is recorded on chain in an `Operation::AdminAudit`
+//! block, so that a compromised admin key cannot change the ledger without
+//! holders having time to notice.
+
+use crate::access_policy::AccessPolicyConfig;
+use crate::account_identifier::AccountIdentifier;
+use crate::ic_token::TOKENs;
+use crate::roles::Role;
+use crate::TimeStamp;
+
+use candid::CandidType;
+use ic_base_types::PrincipalId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// How long proposed actions wait before they can be executed, until the
+/// delay is changed by an action itself.
+pub const DEFAULT_ADMIN_ACTION_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+/// The most actions that can be pending at a time.
+pub const MAX_PENDING_ADMIN_ACTIONS: usize = 100;
+
+/// A change that only takes effect after the admin action delay.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum AdminAction {
+    /// Authorizes a minter, or updates its quota and expiry.
+    SetMinter {
+        principal: PrincipalId,
+        quota: Option<TOKENs>,
+        expires_at: Option<TimeStamp>,
+    },
+    /// Revokes the minting rights of a principal.
+    RemoveMinter { principal: PrincipalId },
+    /// Replaces who may send tokens and which canisters may be notified.
+    SetAccessPolicy(AccessPolicyConfig),
+    /// Credits fees to the account instead of burning them, or burns them
+    /// again with `None`.
+    SetFeeCollector(Option<AccountIdentifier>),
+    /// Changes the delay of the actions proposed from then on.
+    SetAdminActionDelay { seconds: u64 },
+    /// Changes the principal that approves debits of treasury accounts.
+    SetTreasuryGovernance(Option<PrincipalId>),
+}
+
+impl AdminAction {
+    /// The role needed to propose, execute or cancel the action.
+    pub fn role(&self) -> Role {
+        match self {
+            AdminAction::SetMinter { .. } | AdminAction::RemoveMinter { .. } => Role::Minter,
+            AdminAction::SetFeeCollector(_) => Role::FeeManager,
+            AdminAction::SetAccessPolicy(_)
+            | AdminAction::SetAdminActionDelay { .. }
+            | AdminAction::SetTreasuryGovernance(_) => Role::Admin,
+        }
+    }
+}
+
+/// The description recorded in audit blocks. It must not change for existing
+/// actions, as clients may parse it.
+impl fmt::Display for AdminAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminAction::SetMinter {
+                principal,
+                quota,
+                expires_at,
+            } => {
+                write!(f, "set minter {}", principal)?;
+                if let Some(quota) = quota {
+                    write!(f, " with a quota of {}", quota)?;
+                }
+                if let Some(expires_at) = expires_at {
+                    write!(f, " until {}", expires_at.as_nanos())?;
+                }
+                Ok(())
+            }
+            AdminAction::RemoveMinter { principal } => write!(f, "remove minter {}", principal),
+            AdminAction::SetAccessPolicy(policy) => match policy {
+                AccessPolicyConfig::Open => write!(f, "set the access policy to open"),
+                AccessPolicyConfig::Whitelist(whitelist) => write!(
+                    f,
+                    "set the access policy to a whitelist of {} canisters",
+                    whitelist.canisters.len()
+                ),
+                AccessPolicyConfig::DenyList(denied) => write!(
+                    f,
+                    "set the access policy to a deny list of {} principals",
+                    denied.len()
+                ),
+            },
+            AdminAction::SetFeeCollector(Some(account)) => {
+                write!(f, "collect fees in {}", account)
+            }
+            AdminAction::SetFeeCollector(None) => write!(f, "burn fees"),
+            AdminAction::SetAdminActionDelay { seconds } => {
+                write!(f, "set the admin action delay to {} seconds", seconds)
+            }
+            AdminAction::SetTreasuryGovernance(Some(governance)) => {
+                write!(f, "set the treasury governance to {}", governance)
+            }
+            AdminAction::SetTreasuryGovernance(None) => write!(f, "unset the treasury governance"),
+        }
+    }
+}
+
+/// A proposed action, which can be executed from `executable_at` on.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct PendingAdminAction {
+    pub id: u64,
+    pub action: AdminAction,
+    pub proposed_by: PrincipalId,
+    pub proposed_at: TimeStamp,
+    pub executable_at: TimeStamp,
+}
+
+/// The pending actions and the delay new ones wait.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AdminActions {
+    next_id: u64,
+    pending: BTreeMap<u64, PendingAdminAction>,
+    delay: Duration,
+}
+
+impl Default for AdminActions {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            pending: BTreeMap::new(),
+            delay: DEFAULT_ADMIN_ACTION_DELAY,
+        }
+    }
+}
+
+impl AdminActions {
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay;
+    }
+
+    /// Queues `action`, which can be executed once the delay is over.
+    pub fn propose(
+        &mut self,
+        action: AdminAction,
+        proposed_by: PrincipalId,
+        now: TimeStamp,
+    ) -> Result<PendingAdminAction, String> {
+        if self.pending.len() >= MAX_PENDING_ADMIN_ACTIONS {
+            return Err(format!(
+                "There are already {} pending admin actions",
+                MAX_PENDING_ADMIN_ACTIONS
+            ));
+        }
+        let pending = PendingAdminAction {
+            id: self.next_id,
+            action,
+            proposed_by,
+            proposed_at: now,
+            executable_at: now.saturating_add(self.delay),
+        };
+        self.next_id += 1;
+        self.pending.insert(pending.id, pending.clone());
+        Ok(pending)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&PendingAdminAction> {
+        self.pending.get(&id)
+    }
+
+    /// The action `id`, if its delay is over at `now`.
+    pub fn executable(&self, id: u64, now: TimeStamp) -> Result<&PendingAdminAction, String> {
+        let pending = self
+            .pending
+            .get(&id)
+            .ok_or_else(|| format!("No admin action {} is pending", id))?;
+        if now.is_before(pending.executable_at) {
+            return Err(format!(
+                "Admin action {} cannot be executed before {}",
+                id,
+                pending.executable_at.as_nanos()
+            ));
+        }
+        Ok(pending)
+    }
+
+    /// Drops the action `id`, whether it was executed or cancelled.
+    pub fn remove(&mut self, id: u64) -> Option<PendingAdminAction> {
+        self.pending.remove(&id)
+    }
+
+    /// The pending actions, oldest first.
+    pub fn pending(&self) -> Vec<PendingAdminAction> {
+        self.pending.values().cloned().collect()
+    }
+}
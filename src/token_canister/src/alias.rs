@@ -0,0 +1,271 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+
+use candid::CandidType;
+use ic_base_types::PrincipalId;
+use ic_crypto_sha::Sha256;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const MIN_ALIAS_LENGTH: usize = 3;
+pub const MAX_ALIAS_LENGTH: usize = 32;
+/// The most aliases a principal may hold, so that claiming aliases for free
+/// can't grow the registry without bound.
+pub const MAX_ALIASES_PER_PRINCIPAL: usize = 10;
+
+static ALIAS_REGISTRY_DOMAIN_SEPARATOR: &[u8] = b"\x0Ealias-registry";
+static ALIAS_ENTRY_DOMAIN_SEPARATOR: &[u8] = b"\x0Balias-entry";
+static ALIAS_NODE_DOMAIN_SEPARATOR: &[u8] = b"\x0Aalias-node";
+
+/// The hash of a slot without an entry.
+const EMPTY_SLOT: [u8; 32] = [0; 32];
+
+/// Checks that `name` is 3 to 32 characters long and only consists of
+/// lowercase ASCII letters, digits, '-' and '_', so that names can't be
+/// confused with each other or with account identifiers.
+pub fn validate_alias(name: &str) -> Result<(), String> {
+    if name.len() < MIN_ALIAS_LENGTH || name.len() > MAX_ALIAS_LENGTH {
+        return Err(format!(
+            "Aliases must be between {} and {} characters long",
+            MIN_ALIAS_LENGTH, MAX_ALIAS_LENGTH
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Alias {} may only contain lowercase letters, digits, '-' and '_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+struct AliasEntry {
+    owner: PrincipalId,
+    account: AccountIdentifier,
+    /// The slot of the entry in the registry's hash tree.
+    #[serde(default)]
+    slot: usize,
+}
+
+fn entry_hash(name: &str, account: &AccountIdentifier) -> [u8; 32] {
+    let mut state = Sha256::new();
+    state.write(ALIAS_ENTRY_DOMAIN_SEPARATOR);
+    state.write(&(name.len() as u64).to_be_bytes());
+    state.write(name.as_bytes());
+    state.write(account.as_ref());
+    state.finish()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut state = Sha256::new();
+    state.write(ALIAS_NODE_DOMAIN_SEPARATOR);
+    state.write(left);
+    state.write(right);
+    state.finish()
+}
+
+/// A Merkle tree over numbered slots, so that changing a slot only rehashes
+/// its path to the root.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct SlotTree {
+    /// `levels[0]` holds the hashes of the slots, and every further level
+    /// the hashes of pairs of nodes of the level below, a missing right node
+    /// counting as `EMPTY_SLOT`. The last level holds the root.
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl SlotTree {
+    fn len(&self) -> usize {
+        self.levels.first().map_or(0, Vec::len)
+    }
+
+    /// Sets the hash of `slot`, which is at most one past the last slot.
+    fn set(&mut self, slot: usize, hash: [u8; 32]) {
+        if self.levels.is_empty() {
+            self.levels.push(vec![]);
+        }
+        let (mut index, mut hash, mut level) = (slot, hash, 0);
+        loop {
+            let nodes = &mut self.levels[level];
+            if index == nodes.len() {
+                nodes.push(hash);
+            } else {
+                nodes[index] = hash;
+            }
+            if nodes.len() == 1 {
+                return;
+            }
+            let left = nodes[index & !1];
+            let right = nodes.get(index | 1).copied().unwrap_or(EMPTY_SLOT);
+            hash = node_hash(&left, &right);
+            index /= 2;
+            level += 1;
+            if level == self.levels.len() {
+                self.levels.push(vec![]);
+            }
+        }
+    }
+
+    fn root(&self) -> [u8; 32] {
+        self.levels
+            .last()
+            .and_then(|root| root.first())
+            .copied()
+            .unwrap_or(EMPTY_SLOT)
+    }
+}
+
+/// An opt-in mapping from short names to accounts. A principal may name up
+/// to `MAX_ALIASES_PER_PRINCIPAL` of its own accounts; each account has at
+/// most one name.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AliasRegistry {
+    by_name: BTreeMap<String, AliasEntry>,
+    by_account: BTreeMap<AccountIdentifier, String>,
+    /// How many aliases each principal holds.
+    #[serde(default)]
+    counts: BTreeMap<PrincipalId, usize>,
+    /// The entries hashed into their slots. Slots of released aliases are
+    /// reused.
+    #[serde(default)]
+    tree: SlotTree,
+    #[serde(default)]
+    free_slots: Vec<usize>,
+    /// Hash over all (name, account) pairs, updated on every change.
+    root_hash: Option<[u8; 32]>,
+}
+
+impl AliasRegistry {
+    /// Maps `name` to the account of `owner` with the given subaccount,
+    /// replacing any previous name of that account.
+    pub fn claim(
+        &mut self,
+        owner: PrincipalId,
+        name: String,
+        subaccount: Option<Subaccount>,
+    ) -> Result<AccountIdentifier, String> {
+        validate_alias(&name)?;
+        if let Some(entry) = self.by_name.get(&name) {
+            if entry.owner != owner {
+                return Err(format!("Alias {} is already taken", name));
+            }
+        }
+        let account = AccountIdentifier::new(owner, subaccount);
+        // Renaming an account or moving a name doesn't add an alias.
+        let adds_alias =
+            !self.by_name.contains_key(&name) && !self.by_account.contains_key(&account);
+        if adds_alias && self.counts.get(&owner).copied().unwrap_or(0) >= MAX_ALIASES_PER_PRINCIPAL
+        {
+            return Err(format!(
+                "{} already holds {} aliases",
+                owner, MAX_ALIASES_PER_PRINCIPAL
+            ));
+        }
+        if let Some(previous) = self.by_account.get(&account).cloned() {
+            self.remove(&previous);
+        }
+        self.remove(&name);
+        self.insert(name, owner, account);
+        self.update_root_hash();
+        Ok(account)
+    }
+
+    pub fn release(&mut self, owner: PrincipalId, name: &str) -> Result<(), String> {
+        match self.by_name.get(name) {
+            Some(entry) if entry.owner == owner => {
+                self.remove(name);
+                self.update_root_hash();
+                Ok(())
+            }
+            Some(_) => Err(format!("Alias {} is owned by another principal", name)),
+            None => Err(format!("Alias {} is not registered", name)),
+        }
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<AccountIdentifier> {
+        self.by_name.get(name).map(|entry| entry.account)
+    }
+
+    pub fn alias_of(&self, account: &AccountIdentifier) -> Option<&String> {
+        self.by_account.get(account)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    /// The hash of the registry contents, or `None` if no alias was ever
+    /// claimed.
+    pub fn root_hash(&self) -> Option<[u8; 32]> {
+        self.root_hash
+    }
+
+    /// Gives a registry from before the entries were hashed into slots a
+    /// slot for each entry, and counts the aliases of every principal.
+    pub fn migrate(&mut self) {
+        if !self.tree.levels.is_empty() || self.by_name.is_empty() {
+            return;
+        }
+        let names: Vec<String> = self.by_name.keys().cloned().collect();
+        for (slot, name) in names.iter().enumerate() {
+            let entry = self.by_name.get_mut(name).unwrap();
+            entry.slot = slot;
+            *self.counts.entry(entry.owner).or_default() += 1;
+            let hash = entry_hash(name, &entry.account);
+            self.tree.set(slot, hash);
+        }
+        self.update_root_hash();
+    }
+
+    fn insert(&mut self, name: String, owner: PrincipalId, account: AccountIdentifier) {
+        let slot = self.free_slots.pop().unwrap_or_else(|| self.tree.len());
+        self.tree.set(slot, entry_hash(&name, &account));
+        *self.counts.entry(owner).or_default() += 1;
+        self.by_account.insert(account, name.clone());
+        self.by_name.insert(
+            name,
+            AliasEntry {
+                owner,
+                account,
+                slot,
+            },
+        );
+    }
+
+    fn remove(&mut self, name: &str) {
+        let entry = match self.by_name.remove(name) {
+            Some(entry) => entry,
+            None => return,
+        };
+        self.by_account.remove(&entry.account);
+        self.tree.set(entry.slot, EMPTY_SLOT);
+        self.free_slots.push(entry.slot);
+        if let Some(count) = self.counts.get_mut(&entry.owner) {
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&entry.owner);
+            }
+        }
+    }
+
+    fn update_root_hash(&mut self) {
+        let mut state = Sha256::new();
+        state.write(ALIAS_REGISTRY_DOMAIN_SEPARATOR);
+        state.write(&self.tree.root());
+        self.root_hash = Some(state.finish());
+    }
+}
+
+/// Argument taken by the claim_alias endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct ClaimAliasArgs {
+    pub name: String,
+    pub subaccount: Option<Subaccount>,
+}
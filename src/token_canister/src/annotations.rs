@@ -0,0 +1,133 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+use crate::ic_block::BlockHeight;
+use crate::types::Operation;
+use crate::TimeStamp;
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The longest label, in bytes. Long enough for a hex-encoded hash.
+pub const MAX_ANNOTATION_LENGTH: usize = 128;
+/// The most transactions an account can annotate.
+pub const MAX_ANNOTATIONS_PER_ACCOUNT: usize = 10_000;
+/// The most annotations a get_annotations request returns.
+pub const MAX_ANNOTATIONS_PER_REQUEST: usize = 1_000;
+
+/// Argument taken by the annotate_transaction endpoint. The caller annotates
+/// the transaction at `height` for its account with `subaccount`, which
+/// must have sent or received it. A `label` of `None` removes the
+/// annotation.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct AnnotateTransactionArgs {
+    pub height: BlockHeight,
+    pub subaccount: Option<Subaccount>,
+    pub label: Option<String>,
+}
+
+/// Argument taken by the get_annotations endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct GetAnnotationsArgs {
+    pub account: AccountIdentifier,
+    /// Only annotations of transactions at this height or later are
+    /// returned.
+    pub start: BlockHeight,
+    /// At most `MAX_ANNOTATIONS_PER_REQUEST`.
+    pub limit: u64,
+}
+
+/// A label an account attached to one of its transactions.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    pub height: BlockHeight,
+    pub label: String,
+    pub annotated_at: TimeStamp,
+}
+
+/// Whether `account` sent or received `operation`.
+pub fn is_party_to(operation: &Operation, account: &AccountIdentifier) -> bool {
+    match operation {
+        Operation::Transfer { from, to, .. } => from == account || to == account,
+        Operation::Burn { from, .. } => from == account,
+        Operation::Mint { to, .. } => to == account,
+        Operation::SetMintingAccount { .. } | Operation::AdminAudit { .. } => false,
+    }
+}
+
+/// Labels that the senders and recipients of transactions attached to them
+/// after the fact, e.g. invoice numbers to reconcile payments. They are kept
+/// off the chain, so they can be changed and do not affect block hashes.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Annotations {
+    by_account: BTreeMap<AccountIdentifier, BTreeMap<BlockHeight, Annotation>>,
+}
+
+impl Annotations {
+    /// Annotates the transaction at `height` for `account`, replacing its
+    /// previous annotation. Whether `account` is a party to the transaction
+    /// is up to the caller to check.
+    pub fn set(
+        &mut self,
+        account: AccountIdentifier,
+        height: BlockHeight,
+        label: String,
+        now: TimeStamp,
+    ) -> Result<(), String> {
+        if label.is_empty() || label.len() > MAX_ANNOTATION_LENGTH {
+            return Err(format!(
+                "Annotations must be between 1 and {} bytes long, got {}",
+                MAX_ANNOTATION_LENGTH,
+                label.len()
+            ));
+        }
+        let annotations = self.by_account.entry(account).or_default();
+        if annotations.len() >= MAX_ANNOTATIONS_PER_ACCOUNT && !annotations.contains_key(&height) {
+            return Err(format!(
+                "{} already annotated {} transactions",
+                account, MAX_ANNOTATIONS_PER_ACCOUNT
+            ));
+        }
+        annotations.insert(
+            height,
+            Annotation {
+                height,
+                label,
+                annotated_at: now,
+            },
+        );
+        Ok(())
+    }
+
+    /// Removes the annotation of `account` on the transaction at `height`,
+    /// returning whether there was one.
+    pub fn remove(&mut self, account: &AccountIdentifier, height: BlockHeight) -> bool {
+        let annotations = match self.by_account.get_mut(account) {
+            Some(annotations) => annotations,
+            None => return false,
+        };
+        let removed = annotations.remove(&height).is_some();
+        if annotations.is_empty() {
+            self.by_account.remove(account);
+        }
+        removed
+    }
+
+    /// Up to `limit` annotations of `account`, from height `start` on.
+    pub fn range(
+        &self,
+        account: &AccountIdentifier,
+        start: BlockHeight,
+        limit: usize,
+    ) -> Vec<Annotation> {
+        self.by_account
+            .get(account)
+            .map(|annotations| {
+                annotations
+                    .range(start..)
+                    .take(limit.min(MAX_ANNOTATIONS_PER_REQUEST))
+                    .map(|(_, annotation)| annotation.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
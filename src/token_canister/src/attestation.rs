@@ -0,0 +1,46 @@
+//! Balance attestations let a canister gate access on a balance without
+//! ingesting the ledger API: it asks whether an account holds at least some
+//! amount, and the ledger calls it back with the answer. Calls between
+//! canisters are authenticated by the IC, so a callback whose caller is the
+//! ledger is an attestation by the ledger.
+
+use crate::account_identifier::AccountIdentifier;
+use crate::ic_block::BlockHeight;
+use crate::ic_token::TOKENs;
+use crate::TimeStamp;
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// Argument taken by the attest_balance endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct AttestBalanceArgs {
+    pub account: AccountIdentifier,
+    pub min_amount: TOKENs,
+    /// The method of the calling canister that receives the
+    /// `BalanceAttestation`.
+    pub callback: String,
+    /// Echoed in the attestation, to match it with the request.
+    pub nonce: u64,
+}
+
+/// The argument the callback method is called with.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct BalanceAttestation {
+    pub account: AccountIdentifier,
+    pub min_amount: TOKENs,
+    /// Whether `account` held at least `min_amount`.
+    pub holds: bool,
+    /// The balance was read after the blocks below this height were applied.
+    pub chain_length: BlockHeight,
+    pub attested_at: TimeStamp,
+    pub nonce: u64,
+}
+
+/// Response of the attest_balance endpoint. The attestation stands whatever
+/// the callback returns; its failure, if any, is in `call_result`.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct AttestBalanceResult {
+    pub attestation: BalanceAttestation,
+    pub call_result: Result<(), String>,
+}
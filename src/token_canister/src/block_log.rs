@@ -0,0 +1,170 @@
+//! An append-only log of the unarchived blocks, kept in stable memory. Blocks
+//! are written to it as they are added to the chain, so pre_upgrade does not
+//! serialize them with the rest of the ledger and upgrades no longer take
+//! longer as blocks pile up.
+//!
+//! The serialized ledger occupies the lower half of the 32-bit addressable
+//! stable memory and the log the upper half. Each entry of the log is the
+//! length of a block as a little-endian u32, followed by the block.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::io;
+
+/// Where the log starts in stable memory.
+pub const BLOCK_LOG_OFFSET: u64 = 1 << 31;
+/// How many bytes the log may take.
+pub const BLOCK_LOG_CAPACITY: u64 = 1 << 31;
+/// How many bytes the serialized ledger may take. The first 4 bytes of stable
+/// memory hold its length.
+pub const MAX_SERIALIZED_LEDGER_BYTES: u64 = BLOCK_LOG_OFFSET - 4;
+
+const LENGTH_BYTES: u64 = 4;
+/// How many bytes are moved at once when the log is compacted.
+const COMPACTION_CHUNK_BYTES: u64 = 1 << 20;
+
+/// The memory the log is written to. Offsets are relative to the start of
+/// the log.
+pub trait BlockStorage: Debug + Send + Sync {
+    fn write(&self, offset: u64, bytes: &[u8]);
+    fn read(&self, offset: u64, buf: &mut [u8]);
+}
+
+/// The canister's stable memory, from `BLOCK_LOG_OFFSET` on.
+#[cfg(feature = "canister")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StableBlockStorage;
+
+#[cfg(feature = "canister")]
+impl BlockStorage for StableBlockStorage {
+    fn write(&self, offset: u64, bytes: &[u8]) {
+        use dfn_core::stable;
+
+        const PAGE_SIZE: u64 = 64 * 1024;
+        let end = BLOCK_LOG_OFFSET + offset + bytes.len() as u64;
+        let required_pages = (end + PAGE_SIZE - 1) / PAGE_SIZE;
+        let current_pages = stable::stable64_size();
+        if required_pages > current_pages
+            && stable::stable64_grow(required_pages - current_pages) < 0
+        {
+            panic!("Failed to grow stable memory for the block log");
+        }
+        stable::stable64_write(BLOCK_LOG_OFFSET + offset, bytes);
+    }
+
+    fn read(&self, offset: u64, buf: &mut [u8]) {
+        let len = buf.len() as u64;
+        dfn_core::stable::stable64_read(buf, BLOCK_LOG_OFFSET + offset, len);
+    }
+}
+
+/// The part of the log holding live blocks. Entries before `start` belong to
+/// blocks that were archived or pruned since.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockLogPosition {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl BlockLogPosition {
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Appends `block` to the log, moving the live entries to the front first
+    /// if it does not fit behind them.
+    pub fn append(&mut self, storage: &dyn BlockStorage, block: &[u8]) -> Result<(), String> {
+        let entry_len = LENGTH_BYTES + block.len() as u64;
+        if self.end + entry_len > BLOCK_LOG_CAPACITY && self.start > 0 {
+            self.compact(storage);
+        }
+        if self.end + entry_len > BLOCK_LOG_CAPACITY {
+            return Err(format!(
+                "The block log is full: {} of {} bytes are used",
+                self.end, BLOCK_LOG_CAPACITY
+            ));
+        }
+        let mut entry = Vec::with_capacity(entry_len as usize);
+        entry.extend_from_slice(&(block.len() as u32).to_le_bytes());
+        entry.extend_from_slice(block);
+        storage.write(self.end, &entry);
+        self.end += entry_len;
+        Ok(())
+    }
+
+    /// Drops the oldest entries, which hold blocks of `block_sizes` bytes.
+    pub fn drop_front(&mut self, block_sizes: impl Iterator<Item = usize>) {
+        self.start += block_sizes
+            .map(|size| LENGTH_BYTES + size as u64)
+            .sum::<u64>();
+        assert!(
+            self.start <= self.end,
+            "Dropped more than the block log holds"
+        );
+        if self.is_empty() {
+            *self = Self::default();
+        }
+    }
+
+    /// Reads the live blocks, oldest first.
+    pub fn read_all(&self, storage: &dyn BlockStorage) -> Vec<Vec<u8>> {
+        let mut blocks = vec![];
+        let mut offset = self.start;
+        while offset < self.end {
+            let mut len = [0; LENGTH_BYTES as usize];
+            storage.read(offset, &mut len);
+            let mut block = vec![0; u32::from_le_bytes(len) as usize];
+            storage.read(offset + LENGTH_BYTES, &mut block);
+            offset += LENGTH_BYTES + block.len() as u64;
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    fn compact(&mut self, storage: &dyn BlockStorage) {
+        let len = self.end - self.start;
+        let mut copied = 0;
+        while copied < len {
+            let mut chunk = vec![0; COMPACTION_CHUNK_BYTES.min(len - copied) as usize];
+            storage.read(self.start + copied, &mut chunk);
+            storage.write(copied, &chunk);
+            copied += chunk.len() as u64;
+        }
+        self.start = 0;
+        self.end = len;
+    }
+}
+
+/// A writer that fails instead of writing more than `remaining` bytes, so
+/// that the serialized ledger can't overwrite the block log.
+pub struct BoundedWriter<W> {
+    inner: W,
+    remaining: u64,
+}
+
+impl<W: io::Write> BoundedWriter<W> {
+    pub fn new(inner: W, limit: u64) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+        }
+    }
+}
+
+impl<W: io::Write> io::Write for BoundedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() as u64 > self.remaining {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "The serialized ledger does not fit below the block log",
+            ));
+        }
+        let written = self.inner.write(buf)?;
+        self.remaining -= written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,120 @@
+//! Statistics about the blocks the ledger produced, for capacity planning
+//! and dashboards. They are updated as blocks are added and removed, so that
+//! querying them does not walk the chain.
+
+use crate::types::Operation;
+use crate::TimeStamp;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// The granularity at which recent blocks are counted.
+const BUCKET_NANOS: u64 = 60_000_000_000;
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Response of the block_stats endpoint. Counts cover the blocks produced
+/// since the statistics were introduced, plus the blocks the ledger still
+/// held at that time.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockStats {
+    pub num_burns: u64,
+    pub num_mints: u64,
+    pub num_transfers: u64,
+    pub num_minting_account_changes: u64,
+    /// Blocks recording the steps of timelocked admin actions.
+    pub num_admin_audits: u64,
+    pub average_block_size_bytes: u64,
+    /// Blocks produced over the last hour, counted by the minute.
+    pub blocks_last_hour: u64,
+    /// Blocks produced over the last day, counted by the minute.
+    pub blocks_last_day: u64,
+    /// The encoded size of the blocks the ledger holds, i.e. that were not
+    /// archived or pruned.
+    pub in_memory_chain_size_bytes: u64,
+}
+
+/// Keeps the counts `BlockStats` are computed from.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockStatsTracker {
+    num_burns: u64,
+    num_mints: u64,
+    num_transfers: u64,
+    num_minting_account_changes: u64,
+    #[serde(default)]
+    num_admin_audits: u64,
+    num_blocks: u64,
+    total_block_size_bytes: u64,
+    in_memory_chain_size_bytes: u64,
+    /// How many blocks were produced in each minute of the last day, oldest
+    /// first, keyed by the minutes since the epoch.
+    recent_blocks: VecDeque<(u64, u64)>,
+}
+
+impl BlockStatsTracker {
+    /// Counts a block added to the chain.
+    pub fn record_added(&mut self, operation: &Operation, timestamp: TimeStamp, size_bytes: u64) {
+        match operation {
+            Operation::Burn { .. } => self.num_burns += 1,
+            Operation::Mint { .. } => self.num_mints += 1,
+            Operation::Transfer { .. } => self.num_transfers += 1,
+            Operation::SetMintingAccount { .. } => self.num_minting_account_changes += 1,
+            Operation::AdminAudit { .. } => self.num_admin_audits += 1,
+        }
+        self.num_blocks += 1;
+        self.total_block_size_bytes += size_bytes;
+        self.in_memory_chain_size_bytes += size_bytes;
+
+        let bucket = timestamp.as_nanos() / BUCKET_NANOS;
+        match self.recent_blocks.back_mut() {
+            Some((last, count)) if *last == bucket => *count += 1,
+            _ => self.recent_blocks.push_back((bucket, 1)),
+        }
+        let first_kept = first_bucket_within(bucket, DAY);
+        while matches!(self.recent_blocks.front(), Some((b, _)) if *b < first_kept) {
+            self.recent_blocks.pop_front();
+        }
+    }
+
+    /// Accounts for blocks of `size_bytes` in total that were archived or
+    /// pruned.
+    pub fn record_removed(&mut self, size_bytes: u64) {
+        self.in_memory_chain_size_bytes =
+            self.in_memory_chain_size_bytes.saturating_sub(size_bytes);
+    }
+
+    pub fn stats(&self, now: TimeStamp) -> BlockStats {
+        BlockStats {
+            num_burns: self.num_burns,
+            num_mints: self.num_mints,
+            num_transfers: self.num_transfers,
+            num_minting_account_changes: self.num_minting_account_changes,
+            num_admin_audits: self.num_admin_audits,
+            average_block_size_bytes: self
+                .total_block_size_bytes
+                .checked_div(self.num_blocks)
+                .unwrap_or(0),
+            blocks_last_hour: self.blocks_since(now, HOUR),
+            blocks_last_day: self.blocks_since(now, DAY),
+            in_memory_chain_size_bytes: self.in_memory_chain_size_bytes,
+        }
+    }
+
+    /// The blocks produced in the minute of `now` and the minutes before it
+    /// that `window` spans.
+    fn blocks_since(&self, now: TimeStamp, window: Duration) -> u64 {
+        let bucket = now.as_nanos() / BUCKET_NANOS;
+        let first_counted = first_bucket_within(bucket, window);
+        self.recent_blocks
+            .iter()
+            .filter(|(b, _)| *b >= first_counted && *b <= bucket)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// The oldest of the buckets `window` spans, ending with `bucket`.
+fn first_bucket_within(bucket: u64, window: Duration) -> u64 {
+    (bucket + 1).saturating_sub(window.as_nanos() as u64 / BUCKET_NANOS)
+}
@@ -0,0 +1,231 @@
+//! The ledger's certified data. The ledger certifies the root hash of a small
+//! hash tree holding the tip of the chain, under the labels ICRC-3 indexers
+//! look up, and the root hash of the alias registry once an alias was
+//! claimed.
+//!
+//! The certificate available to queries lags behind the certified data until
+//! the next certification round, so the ledger remembers the last tips it
+//! certified, and can prove recent blocks against whichever the certificate
+//! covers.
+
+use crate::{BlockHeight, EncodedBlock, HashOf};
+use candid::CandidType;
+use ic_crypto_tree_hash::{lookup_path, Digest, Label, LabeledTree, MixedHashTree};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+/// How many of the last certified tips the ledger remembers.
+pub const MAX_RECENT_CERTIFIED_TIPS: usize = 256;
+/// How far below the certified tip a block may be for
+/// get_block_with_certificate. Older blocks can be checked against the tip by
+/// following the parent hashes of the blocks in between.
+pub const MAX_CERTIFIED_BLOCK_DEPTH: u64 = 100;
+
+const LAST_BLOCK_INDEX_LABEL: &[u8] = b"last_block_index";
+const LAST_BLOCK_HASH_LABEL: &[u8] = b"last_block_hash";
+const ALIASES_LABEL: &[u8] = b"aliases";
+
+/// Response of the icrc3_get_tip_certificate endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct TipCertificate {
+    /// The certificate of the ledger's certified data.
+    #[serde(with = "serde_bytes")]
+    pub certificate: Vec<u8>,
+    /// The CBOR-encoded hash tree whose root hash is certified. It reveals
+    /// the tip of the chain and prunes everything else.
+    #[serde(with = "serde_bytes")]
+    pub hash_tree: Vec<u8>,
+}
+
+/// Response of the get_block_with_certificate endpoint. The block is proven
+/// by hashing it and each of its descendants in turn: every hash must be
+/// the parent hash of the next block, and the last one the tip hash revealed
+/// by `hash_tree`, whose root hash the certificate certifies.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct BlockCertificate {
+    pub height: BlockHeight,
+    pub block: EncodedBlock,
+    /// The blocks after `block`, up to the tip that the certificate covers.
+    pub descendants: Vec<EncodedBlock>,
+    #[serde(with = "serde_bytes")]
+    pub certificate: Vec<u8>,
+    /// The CBOR-encoded hash tree of the tip that the certificate covers.
+    #[serde(with = "serde_bytes")]
+    pub hash_tree: Vec<u8>,
+}
+
+/// What the ledger certified at some point: the tip of the chain and the root
+/// hash of the alias registry.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CertifiedTip {
+    pub tip: Option<(BlockHeight, HashOf<EncodedBlock>)>,
+    pub aliases_root_hash: Option<[u8; 32]>,
+}
+
+impl CertifiedTip {
+    pub fn certified_data(&self) -> [u8; 32] {
+        certified_data(self.tip, self.aliases_root_hash)
+    }
+
+    pub fn hash_tree(&self) -> Vec<u8> {
+        tip_hash_tree(self.tip, self.aliases_root_hash)
+    }
+}
+
+/// The last tips the ledger certified, oldest first.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RecentCertifiedTips {
+    tips: VecDeque<CertifiedTip>,
+}
+
+impl RecentCertifiedTips {
+    pub fn record(&mut self, tip: CertifiedTip) {
+        if self.tips.back() == Some(&tip) {
+            return;
+        }
+        if self.tips.len() >= MAX_RECENT_CERTIFIED_TIPS {
+            self.tips.pop_front();
+        }
+        self.tips.push_back(tip);
+    }
+
+    /// The tip whose certified data is `certified_data`, if it is recent.
+    pub fn find(&self, certified_data: &[u8]) -> Option<&CertifiedTip> {
+        self.tips
+            .iter()
+            .rev()
+            .find(|tip| tip.certified_data()[..] == *certified_data)
+    }
+}
+
+#[derive(Deserialize)]
+struct Certificate {
+    tree: MixedHashTree,
+}
+
+/// The certified data of `canister_id` in `certificate`. The signature of
+/// the certificate is not checked, it was handed to the canister by the
+/// system.
+pub fn certified_data_in(certificate: &[u8], canister_id: &[u8]) -> Result<Vec<u8>, String> {
+    let Certificate { tree } =
+        serde_cbor::from_slice(certificate).map_err(|e| format!("Invalid certificate: {}", e))?;
+    let tree =
+        LabeledTree::try_from(tree).map_err(|e| format!("Invalid certificate tree: {:?}", e))?;
+    match lookup_path(&tree, &[b"canister", canister_id, b"certified_data"]) {
+        Some(LabeledTree::Leaf(data)) => Ok(data.clone()),
+        _ => Err("The certificate holds no certified data for this canister".to_string()),
+    }
+}
+
+fn leb128(mut n: u64) -> Vec<u8> {
+    let mut bytes = vec![];
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn decode_leb128(bytes: &[u8]) -> Result<u64, String> {
+    let mut n: u64 = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        let shift = 7 * i as u32;
+        if shift >= 64 || (shift == 63 && byte & 0x7e != 0) {
+            return Err("LEB128 number overflows 64 bits".to_string());
+        }
+        n |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return if i + 1 == bytes.len() {
+                Ok(n)
+            } else {
+                Err("Trailing bytes after LEB128 number".to_string())
+            };
+        }
+    }
+    Err("Truncated LEB128 number".to_string())
+}
+
+fn labeled(label: &[u8], tree: MixedHashTree) -> MixedHashTree {
+    MixedHashTree::Labeled(Label::from(label), Box::new(tree))
+}
+
+fn fork(left: MixedHashTree, right: MixedHashTree) -> MixedHashTree {
+    MixedHashTree::Fork(Box::new((left, right)))
+}
+
+/// The tree the ledger certifies, given the height and hash of the last block
+/// and the root hash of the alias registry. The alias registry is pruned
+/// unless `reveal_aliases` is set, as nothing is looked up in it.
+fn tree(
+    tip: Option<(BlockHeight, HashOf<EncodedBlock>)>,
+    aliases_root_hash: Option<[u8; 32]>,
+    reveal_aliases: bool,
+) -> MixedHashTree {
+    // Labels are sorted, so "aliases" comes before "last_block_hash", which
+    // comes before "last_block_index".
+    let tip_tree = tip.map(|(height, hash)| {
+        fork(
+            labeled(LAST_BLOCK_HASH_LABEL, MixedHashTree::Leaf(hash.as_slice().to_vec())),
+            labeled(LAST_BLOCK_INDEX_LABEL, MixedHashTree::Leaf(leb128(height))),
+        )
+    });
+    let aliases_tree = aliases_root_hash.map(|root_hash| {
+        let aliases = labeled(ALIASES_LABEL, MixedHashTree::Leaf(root_hash.to_vec()));
+        if reveal_aliases {
+            aliases
+        } else {
+            MixedHashTree::Pruned(aliases.digest())
+        }
+    });
+    match (aliases_tree, tip_tree) {
+        (None, None) => MixedHashTree::Empty,
+        (Some(tree), None) | (None, Some(tree)) => tree,
+        (Some(aliases), Some(tip)) => fork(aliases, tip),
+    }
+}
+
+/// The data the ledger certifies: the root hash of the tree holding the
+/// height and hash of the last block and the root hash of the alias
+/// registry.
+pub fn certified_data(
+    tip: Option<(BlockHeight, HashOf<EncodedBlock>)>,
+    aliases_root_hash: Option<[u8; 32]>,
+) -> [u8; 32] {
+    let Digest(root_hash) = tree(tip, aliases_root_hash, true).digest();
+    root_hash
+}
+
+/// Decodes a tree produced by `tip_hash_tree`, returning its root hash and
+/// the tip it reveals.
+pub fn decode_tip_hash_tree(
+    hash_tree: &[u8],
+) -> Result<([u8; 32], Option<(BlockHeight, HashOf<EncodedBlock>)>), String> {
+    let tree: MixedHashTree =
+        serde_cbor::from_slice(hash_tree).map_err(|e| format!("Invalid hash tree: {}", e))?;
+    let Digest(root_hash) = tree.digest();
+    let tree = LabeledTree::try_from(tree).map_err(|e| format!("Invalid hash tree: {:?}", e))?;
+    let leaf = |label: &[u8]| match lookup_path(&tree, &[label]) {
+        Some(LabeledTree::Leaf(data)) => Some(data.clone()),
+        _ => None,
+    };
+    let tip = match (leaf(LAST_BLOCK_INDEX_LABEL), leaf(LAST_BLOCK_HASH_LABEL)) {
+        (Some(height), Some(hash)) => Some((decode_leb128(&height)?, HashOf::try_from(hash)?)),
+        (None, None) => None,
+        _ => return Err("The hash tree reveals only half of the tip".to_string()),
+    };
+    Ok((root_hash, tip))
+}
+
+/// The CBOR encoding of the certified tree, revealing only the tip.
+pub fn tip_hash_tree(
+    tip: Option<(BlockHeight, HashOf<EncodedBlock>)>,
+    aliases_root_hash: Option<[u8; 32]>,
+) -> Vec<u8> {
+    serde_cbor::to_vec(&tree(tip, aliases_root_hash, false))
+        .expect("Encoding the hash tree failed")
+}
@@ -0,0 +1,57 @@
+//! The types and helpers a service needs to talk to the ledger from outside
+//! the IC: endpoint arguments and responses, account identifiers, fees, and
+//! decoding and verification of blocks. Build with
+//! `default-features = false, features = ["client"]` to leave out the
+//! canister endpoints and their dependencies.
+
+pub use crate::account_activity::AccountSummary;
+pub use crate::account_identifier::{AccountIdParseError, AccountIdentifier, Subaccount};
+pub use crate::admin_actions::{
+    AdminAction, PendingAdminAction, DEFAULT_ADMIN_ACTION_DELAY, MAX_PENDING_ADMIN_ACTIONS,
+};
+pub use crate::annotations::{
+    AnnotateTransactionArgs, Annotation, GetAnnotationsArgs, MAX_ANNOTATIONS_PER_REQUEST,
+    MAX_ANNOTATION_LENGTH,
+};
+pub use crate::attestation::{AttestBalanceArgs, AttestBalanceResult, BalanceAttestation};
+pub use crate::block_stats::BlockStats;
+pub use crate::certification::{BlockCertificate, TipCertificate, MAX_CERTIFIED_BLOCK_DEPTH};
+pub use crate::deprecation::DeprecatedEndpoint;
+pub use crate::endpoint_flags::{EndpointDisabled, SetEndpointEnabledArgs, TOGGLEABLE_ENDPOINTS};
+pub use crate::fee_collector::{fee_collector_subaccount, CollectedFees, SweepCollectedFeesArgs};
+pub use crate::hashof::HashOf;
+pub use crate::health::Health;
+pub use crate::ic_block::{
+    ArchiveInfo, Block, BlockArg, BlockHeight, BlockLookup, BlockRes, EncodedBlock,
+    GetArchivesArgs, GetBlocksArgs, GetBlocksError, GetBlocksRes, ICRC3ArchiveInfo, IterBlocksArgs,
+    QueryEncodedBlocksRes, RetentionPolicy, TipOfChainRes, MAX_BLOCKS_PER_REQUEST,
+};
+pub use crate::ic_token::{
+    validate_decimals, TOKENs, DECIMAL_PLACES, MAX_DECIMAL_PLACES, MIN_BURN_AMOUNT,
+    TOKEN_SUBDIVIDABLE_BY, TRANSACTION_FEE,
+};
+pub use crate::maintenance::MaintenanceReport;
+pub use crate::refunds::{RefundArgs, RefundReference};
+pub use crate::roles::{Role, RoleArgs, RoleAssignment};
+pub use crate::supply_history::{
+    SupplyCheckpoint, SupplyHistoryArgs, MAX_SUPPLY_CHECKPOINTS_PER_REQUEST,
+};
+pub use crate::treasury::{
+    TreasuryApproval, TreasuryInfo, MAX_TREASURY_ACCOUNTS, MAX_TREASURY_APPROVALS,
+};
+pub use crate::types::{
+    AdminActionStatus, EncryptedMemo, Memo, MemoBlob, MintReason, Operation, Transaction,
+};
+pub use crate::upgrade::UpgradeEstimate;
+pub use crate::verify::{
+    decode_block, verify_block_certificate, verify_chain, verify_tip_certificate,
+};
+pub use crate::{
+    certified_data, AccountBalanceArgs, AccountsOverflowPolicy, ArchiveLagThrottle, ArgumentError,
+    IdempotencyKey, LedgerCanisterInitPayload, LedgerCanisterUpgradePayload, LedgerInfo,
+    LedgerMetrics, MetadataValue, MintArgs, NotifyCanisterArgs, PaymentError,
+    PendingMintingAccountChange, SendArgs, TimeStamp, TotalSupplyArgs, TransactionNotification,
+    TransferAndCallArgs, TransferAndCallNotification, TransferAndCallResult, TransferError,
+    MAX_ENCRYPTED_MEMO_LENGTH, MAX_IDEMPOTENCY_KEY_LENGTH, MAX_MEMO_BLOB_LENGTH,
+    MAX_SEND_ARG_SIZE_BYTES, MINTING_ACCOUNT_CHANGE_TIMELOCK,
+};
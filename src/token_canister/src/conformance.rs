@@ -0,0 +1,281 @@
+//! Self-checks of a deployed ledger against what ICRC-1 clients expect:
+//! complete metadata, fees that are burned, deduplication of transactions
+//! and the errors for transactions outside the transaction window.
+//!
+//! The checks that execute transactions run them against a scratch ledger
+//! configured like the real one, so the real state is never changed.
+
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+use crate::{
+    BlockHeight, EncodedBlock, HashOf, Ledger, ManualTimeSource, Memo, MetadataValue, Operation,
+    PaymentError, PaymentOptions, TOKENs, TimeStamp, TransferError, TRANSACTION_FEE,
+};
+use candid::CandidType;
+use ic_types::PrincipalId;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The metadata keys every ICRC-1 ledger is expected to report.
+const REQUIRED_METADATA: &[&str] = &[
+    "icrc1:symbol",
+    "icrc1:decimals",
+    "icrc1:fee",
+    "icrc1:max_memo_length",
+];
+
+/// The outcome of a single check.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct ConformanceCheck {
+    pub name: String,
+    pub passed: bool,
+    /// Why the check failed. Empty if it passed.
+    pub details: String,
+}
+
+/// Response of the run_conformance_checks endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct ConformanceReport {
+    /// Whether every check passed.
+    pub passed: bool,
+    pub checks: Vec<ConformanceCheck>,
+}
+
+/// Runs every check against `ledger`.
+pub fn run_conformance_checks(ledger: &Ledger) -> ConformanceReport {
+    let results: Vec<(&str, Result<(), String>)> = vec![
+        ("metadata_complete", check_metadata_complete(ledger)),
+        ("metadata_consistent", check_metadata_consistent(ledger)),
+        ("minting_account_set", check_minting_account_set(ledger)),
+        ("fees_are_burned", check_fees_are_burned(ledger)),
+        ("duplicates_rejected", check_duplicates_rejected(ledger)),
+        (
+            "idempotency_keys_deduplicate",
+            check_idempotency_keys_deduplicate(ledger),
+        ),
+        (
+            "transaction_window_enforced",
+            check_transaction_window(ledger),
+        ),
+    ];
+    let checks: Vec<ConformanceCheck> = results
+        .into_iter()
+        .map(|(name, result)| ConformanceCheck {
+            name: name.to_string(),
+            passed: result.is_ok(),
+            details: result.err().unwrap_or_default(),
+        })
+        .collect();
+    ConformanceReport {
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+    }
+}
+
+fn check_metadata_complete(ledger: &Ledger) -> Result<(), String> {
+    let metadata = ledger.icrc1_metadata();
+    let missing: Vec<&str> = REQUIRED_METADATA
+        .iter()
+        .copied()
+        .filter(|key| !metadata.iter().any(|(k, _)| k == key))
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Missing metadata: {}", missing.join(", ")))
+    }
+}
+
+fn check_metadata_consistent(ledger: &Ledger) -> Result<(), String> {
+    for (key, value) in ledger.icrc1_metadata() {
+        let expected = match key.as_str() {
+            "icrc1:symbol" => MetadataValue::Text(ledger.symbol.clone()),
+            "icrc1:decimals" => MetadataValue::from(ledger.decimals as u64),
+            "icrc1:fee" => MetadataValue::from(TRANSACTION_FEE.get_e8s()),
+            _ => continue,
+        };
+        if value != expected {
+            return Err(format!(
+                "{} is {:?}, but the ledger uses {:?}",
+                key, value, expected
+            ));
+        }
+    }
+    if ledger.symbol.is_empty() {
+        return Err("The symbol is empty".to_string());
+    }
+    Ok(())
+}
+
+fn check_minting_account_set(ledger: &Ledger) -> Result<(), String> {
+    match ledger.minting_account_id {
+        Some(_) => Ok(()),
+        None => Err("The ledger has no minting account".to_string()),
+    }
+}
+
+fn scratch_account(n: u8) -> AccountIdentifier {
+    AccountIdentifier::new(PrincipalId::new_anonymous(), Some(Subaccount([n; 32])))
+}
+
+/// An empty ledger with the transaction window of `ledger` and a clock
+/// stopped at its current time, holding `10 * TRANSACTION_FEE` in
+/// `scratch_account(1)`.
+fn scratch_ledger(ledger: &Ledger) -> Result<Ledger, String> {
+    let mut scratch = Ledger::default();
+    scratch.set_time_source(Arc::new(ManualTimeSource::new(ledger.now())));
+    scratch.transaction_window = ledger.transaction_window;
+    scratch.permitted_drift = ledger.permitted_drift;
+    let amount = TOKENs::from_e8s(TRANSACTION_FEE.get_e8s().saturating_mul(10));
+    let mint = Operation::Mint {
+        to: scratch_account(1),
+        amount,
+        fee: TOKENs::ZERO,
+    };
+    scratch
+        .add_payment(Memo(0), mint, PaymentOptions::default())
+        .map_err(|e| format!("Minting in the scratch ledger failed: {}", e))?;
+    Ok(scratch)
+}
+
+fn transfer() -> Operation {
+    Operation::Transfer {
+        from: scratch_account(1),
+        to: scratch_account(2),
+        amount: TRANSACTION_FEE,
+        fee: TRANSACTION_FEE,
+    }
+}
+
+fn check_fees_are_burned(ledger: &Ledger) -> Result<(), String> {
+    let mut scratch = scratch_ledger(ledger)?;
+    let supply_before = scratch.balances.total_supply();
+    scratch
+        .add_payment(Memo(1), transfer(), PaymentOptions::default())
+        .map_err(|e| format!("The transfer failed: {}", e))?;
+    let burned = (supply_before - scratch.balances.total_supply())?;
+    if burned != TRANSACTION_FEE {
+        return Err(format!(
+            "A transfer burned {} instead of its fee of {}",
+            burned, TRANSACTION_FEE
+        ));
+    }
+    Ok(())
+}
+
+/// Fails unless `result` is the error `expected` describes.
+fn expect_error(
+    result: Result<(BlockHeight, HashOf<EncodedBlock>), PaymentError>,
+    expected: &str,
+    matches: impl Fn(&TransferError) -> bool,
+) -> Result<(), String> {
+    match result {
+        Err(PaymentError::TransferError(e)) if matches(&e) => {
+            if e.to_string().is_empty() {
+                Err(format!("{:?} has no message", e))
+            } else {
+                Ok(())
+            }
+        }
+        Err(e) => Err(format!("Expected {}, got: {}", expected, e)),
+        Ok((height, _)) => Err(format!(
+            "Expected {}, the transaction was applied at {}",
+            expected, height
+        )),
+    }
+}
+
+fn check_duplicates_rejected(ledger: &Ledger) -> Result<(), String> {
+    let mut scratch = scratch_ledger(ledger)?;
+    let created_at = Some(scratch.now());
+    let (height, _) = scratch
+        .add_payment(
+            Memo(1),
+            transfer(),
+            PaymentOptions {
+                created_at_time: created_at,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| format!("The transfer failed: {}", e))?;
+    let again = scratch.add_payment(
+        Memo(1),
+        transfer(),
+        PaymentOptions {
+            created_at_time: created_at,
+            ..Default::default()
+        },
+    );
+    expect_error(again, "TxDuplicate", |e| {
+        *e == TransferError::TxDuplicate {
+            duplicate_of: height,
+        }
+    })
+}
+
+fn check_idempotency_keys_deduplicate(ledger: &Ledger) -> Result<(), String> {
+    let mut scratch = scratch_ledger(ledger)?;
+    let key = Some((scratch_account(1), b"conformance".to_vec()));
+    let (height, _) = scratch
+        .add_payment(
+            Memo(1),
+            transfer(),
+            PaymentOptions {
+                idempotency_key: key.clone(),
+                ..Default::default()
+            },
+        )
+        .map_err(|e| format!("The transfer failed: {}", e))?;
+    // A different memo makes a different transaction with the same key.
+    let again = scratch.add_payment(
+        Memo(2),
+        transfer(),
+        PaymentOptions {
+            idempotency_key: key,
+            ..Default::default()
+        },
+    );
+    expect_error(again, "TxDuplicate", |e| {
+        *e == TransferError::TxDuplicate {
+            duplicate_of: height,
+        }
+    })
+}
+
+fn check_transaction_window(ledger: &Ledger) -> Result<(), String> {
+    let mut scratch = scratch_ledger(ledger)?;
+    let now = scratch.now();
+    let margin = Duration::from_secs(1);
+
+    let too_old = TimeStamp::from_nanos(
+        now.as_nanos()
+            .saturating_sub((scratch.transaction_window + margin).as_nanos() as u64),
+    );
+    // A clock close to the epoch cannot express a transaction old enough.
+    if too_old.as_nanos() > 0 {
+        let old = scratch.add_payment(
+            Memo(1),
+            transfer(),
+            PaymentOptions {
+                created_at_time: Some(too_old),
+                ..Default::default()
+            },
+        );
+        expect_error(old, "TxTooOld", |e| {
+            matches!(e, TransferError::TxTooOld { .. })
+        })?;
+    }
+
+    let in_future = now.saturating_add(scratch.permitted_drift + margin);
+    let future = scratch.add_payment(
+        Memo(2),
+        transfer(),
+        PaymentOptions {
+            created_at_time: Some(in_future),
+            ..Default::default()
+        },
+    );
+    expect_error(future, "TxCreatedInFuture", |e| {
+        matches!(e, TransferError::TxCreatedInFuture { .. })
+    })
+}
@@ -0,0 +1,50 @@
+//! Support for moving integrators off deprecated endpoints: the blocks made
+//! through them are tagged, and their use is counted in the metrics, so it
+//! can be told who still relies on them before they are disabled.
+
+use crate::types::MemoBlob;
+use crate::TimeStamp;
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// Tags the blocks made through a deprecated endpoint, in their memo blob.
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub enum DeprecatedEndpoint {
+    SendDfx,
+}
+
+impl DeprecatedEndpoint {
+    pub fn to_memo_blob(&self) -> MemoBlob {
+        MemoBlob::new(serde_cbor::ser::to_vec_packed(self).unwrap())
+            .expect("Deprecated endpoint tags always fit in a memo blob")
+    }
+
+    pub fn from_memo_blob(blob: &MemoBlob) -> Result<Self, String> {
+        serde_cbor::from_slice(&blob.0)
+            .map_err(|e| format!("Invalid deprecated endpoint tag: {}", e))
+    }
+}
+
+/// How much the deprecated endpoints are still used.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct DeprecatedEndpointUsage {
+    send_dfx_calls: u64,
+    send_dfx_last_call: Option<TimeStamp>,
+}
+
+impl DeprecatedEndpointUsage {
+    /// Counts a successful call of send_dfx.
+    pub fn record_send_dfx(&mut self, now: TimeStamp) {
+        self.send_dfx_calls = self.send_dfx_calls.saturating_add(1);
+        self.send_dfx_last_call = Some(now);
+    }
+
+    pub fn send_dfx_calls(&self) -> u64 {
+        self.send_dfx_calls
+    }
+
+    pub fn send_dfx_last_call(&self) -> Option<TimeStamp> {
+        self.send_dfx_last_call
+    }
+}
@@ -0,0 +1,81 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The endpoints that can be disabled at runtime: the ones that move tokens
+/// on behalf of callers, call other canisters, or serve large responses.
+pub const TOGGLEABLE_ENDPOINTS: &[&str] = &[
+    "send_dfx",
+    "transfer_and_call",
+    "attest_balance",
+    "submit_signed_transfer",
+    "sweep_subaccounts",
+    "register_payment_callback",
+    "create_stream",
+    "create_pending_transfer",
+    "iter_blocks_pb",
+    "get_blocks_pb",
+];
+
+/// Argument taken by the set_endpoint_enabled endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct SetEndpointEnabledArgs {
+    /// One of `TOGGLEABLE_ENDPOINTS`.
+    pub endpoint: String,
+    pub enabled: bool,
+}
+
+/// The error endpoints fail with while they are disabled.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct EndpointDisabled {
+    pub endpoint: String,
+}
+
+impl fmt::Display for EndpointDisabled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "EndpointDisabled: the {} endpoint is disabled on this ledger",
+            self.endpoint
+        )
+    }
+}
+
+/// The endpoints that are disabled. All endpoints are enabled by default.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct EndpointFlags {
+    disabled: BTreeSet<String>,
+}
+
+impl EndpointFlags {
+    /// Enables or disables `endpoint`, which must be one of
+    /// `TOGGLEABLE_ENDPOINTS`. Returns whether the flag changed.
+    pub fn set_enabled(&mut self, endpoint: &str, enabled: bool) -> Result<bool, String> {
+        if !TOGGLEABLE_ENDPOINTS.contains(&endpoint) {
+            return Err(format!(
+                "{} cannot be disabled, only {} can",
+                endpoint,
+                TOGGLEABLE_ENDPOINTS.join(", ")
+            ));
+        }
+        Ok(if enabled {
+            self.disabled.remove(endpoint)
+        } else {
+            self.disabled.insert(endpoint.to_string())
+        })
+    }
+
+    pub fn check_enabled(&self, endpoint: &str) -> Result<(), EndpointDisabled> {
+        if self.disabled.contains(endpoint) {
+            return Err(EndpointDisabled {
+                endpoint: endpoint.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn disabled(&self) -> Vec<String> {
+        self.disabled.iter().cloned().collect()
+    }
+}
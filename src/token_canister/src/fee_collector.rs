@@ -0,0 +1,35 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+use crate::ic_token::TOKENs;
+
+use candid::CandidType;
+use ic_crypto_sha::Sha256;
+use serde::{Deserialize, Serialize};
+
+static FEE_COLLECTOR_DOMAIN_SEPARATOR: &[u8] = b"\x0Dprotocol-fees";
+
+/// The subaccount of the ledger canister that collects fees while fee
+/// collection is enabled. It is derived from a fixed domain separator, so
+/// anybody can tell the fee account of a ledger from its canister id.
+pub fn fee_collector_subaccount() -> Subaccount {
+    Subaccount(Sha256::hash(FEE_COLLECTOR_DOMAIN_SEPARATOR))
+}
+
+/// Response of the collected_fees endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct CollectedFees {
+    /// The ledger's fee account, whether fee collection is enabled or not.
+    pub account: AccountIdentifier,
+    pub enabled: bool,
+    /// What the fee account holds, i.e. the fees not swept yet.
+    pub balance: TOKENs,
+    /// Every fee credited to a fee collector so far.
+    pub total_collected: TOKENs,
+}
+
+/// Argument taken by the sweep_collected_fees endpoint. Sweeps the whole
+/// balance of the fee account if `amount` is not set.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct SweepCollectedFeesArgs {
+    pub to: AccountIdentifier,
+    pub amount: Option<TOKENs>,
+}
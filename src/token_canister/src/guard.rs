@@ -0,0 +1,64 @@
+//! Guards against interleaved executions of endpoints that await.
+//!
+//! When an endpoint awaits an inter-canister call, other messages run before
+//! it resumes and can change what it validated. An `AccountGuard` marks an
+//! account as having an operation in flight until it is dropped, so that a
+//! second operation from the same account is rejected instead of racing the
+//! first one.
+//!
+//! Guards are only dropped when the message they belong to ends, including
+//! when it traps after an await, provided the calls it awaited were made
+//! with `call_with_cleanup`. The set of guarded accounts is not persisted, as
+//! no message is in flight across an upgrade.
+
+use crate::account_identifier::AccountIdentifier;
+use lazy_static::lazy_static;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref GUARDED_ACCOUNTS: Mutex<BTreeSet<AccountIdentifier>> = Mutex::new(BTreeSet::new());
+}
+
+/// Marks an account as having an operation in flight while it is alive.
+#[derive(Debug)]
+pub struct AccountGuard {
+    account: AccountIdentifier,
+}
+
+impl AccountGuard {
+    /// Guards `account`, unless another operation from it is in flight.
+    pub fn acquire(account: AccountIdentifier) -> Result<Self, String> {
+        if !GUARDED_ACCOUNTS.lock().unwrap().insert(account) {
+            return Err(in_flight(&account));
+        }
+        Ok(Self { account })
+    }
+
+    /// Whether an operation from `account` is in flight.
+    pub fn is_guarded(account: &AccountIdentifier) -> bool {
+        GUARDED_ACCOUNTS.lock().unwrap().contains(account)
+    }
+
+    /// Fails if an operation from `account` is in flight. Operations that
+    /// do not await check this instead of acquiring a guard.
+    pub fn check(account: &AccountIdentifier) -> Result<(), String> {
+        if Self::is_guarded(account) {
+            return Err(in_flight(account));
+        }
+        Ok(())
+    }
+}
+
+fn in_flight(account: &AccountIdentifier) -> String {
+    format!(
+        "Another operation from {} is in flight, retry once it completed",
+        account
+    )
+}
+
+impl Drop for AccountGuard {
+    fn drop(&mut self) {
+        GUARDED_ACCOUNTS.lock().unwrap().remove(&self.account);
+    }
+}
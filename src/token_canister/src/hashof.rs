@@ -2,7 +2,7 @@ use phantom_newtype::Id;
 use candid::CandidType;
 use std::fmt;
 use std::str::FromStr;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 use serde::{
     de::{Deserializer, Visitor},
     Deserialize, Serialize, Serializer,
@@ -23,9 +23,42 @@ impl<T> HashOf<T> {
         self.inner.get()
     }
 
-    pub fn new(bs: [u8; HASH_LENGTH]) -> Self {
+    pub const fn new(bs: [u8; HASH_LENGTH]) -> Self {
         HashOf { inner: Id::new(bs) }
     }
+
+    pub fn as_slice(&self) -> &[u8] {
+        self.inner.get_ref()
+    }
+}
+
+impl<T> AsRef<[u8]> for HashOf<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<T> TryFrom<&[u8]> for HashOf<T> {
+    type Error = String;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, String> {
+        match bytes.try_into() {
+            Ok(ba) => Ok(HashOf::new(ba)),
+            Err(_) => Err(format!(
+                "Expected a Vec of length {} but it was {}",
+                HASH_LENGTH,
+                bytes.len(),
+            )),
+        }
+    }
+}
+
+impl<T> TryFrom<Vec<u8>> for HashOf<T> {
+    type Error = String;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, String> {
+        HashOf::try_from(bytes.as_slice())
+    }
 }
 
 impl<T> fmt::Display for HashOf<T> {
@@ -37,17 +70,14 @@ impl<T> fmt::Display for HashOf<T> {
 
 impl<T> FromStr for HashOf<T> {
     type Err = String;
+    /// Parses a hex string, with or without a `0x` prefix.
     fn from_str(s: &str) -> Result<HashOf<T>, String> {
+        let s = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(s);
         let v = hex::decode(s).map_err(|e| e.to_string())?;
-        let slice = v.as_slice();
-        match slice.try_into() {
-            Ok(ba) => Ok(HashOf::new(ba)),
-            Err(_) => Err(format!(
-                "Expected a Vec of length {} but it was {}",
-                HASH_LENGTH,
-                v.len(),
-            )),
-        }
+        HashOf::try_from(v)
     }
 }
 
@@ -79,7 +109,7 @@ impl<'de, T> Deserialize<'de> for HashOf<T> {
             fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
                 write!(
                     formatter,
-                    "a hash of type {}: a blob with at most {} bytes",
+                    "a hash of type {}: a blob with exactly {} bytes",
                     std::any::type_name::<T>(),
                     HASH_LENGTH
                 )
@@ -89,9 +119,10 @@ impl<'de, T> Deserialize<'de> for HashOf<T> {
             where
                 E: serde::de::Error,
             {
-                Ok(HashOf::new(
-                    v.try_into().expect("hash does not have correct length"),
-                ))
+                match v.try_into() {
+                    Ok(ba) => Ok(HashOf::new(ba)),
+                    Err(_) => Err(E::invalid_length(v.len(), &self)),
+                }
             }
 
             fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
@@ -0,0 +1,87 @@
+//! The ledger's health, as reported by the health query and on /health, so
+//! that load balancers and bots can back off before the ledger fails.
+
+use crate::Ledger;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// Below this cycles balance the ledger risks being frozen.
+pub const MIN_HEALTHY_CYCLES_BALANCE: u64 = 1_000_000_000_000;
+/// Above this heap size the ledger risks running out of its 4 GiB of Wasm
+/// memory.
+pub const MAX_HEALTHY_HEAP_BYTES: u64 = 3 * 1024 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum Health {
+    Healthy,
+    /// The ledger works, but runs out of some resource.
+    Degraded {
+        reasons: Vec<String>,
+    },
+    /// The ledger answers queries but refuses transfers.
+    ReadOnly {
+        reasons: Vec<String>,
+    },
+}
+
+/// The state of the canister the ledger runs in, as opposed to the ledger's
+/// own state. Unknown values are not taken into account.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CanisterResources {
+    pub cycles_balance: Option<u64>,
+    pub heap_bytes: Option<u64>,
+}
+
+/// Derives the health of `ledger` running in a canister with `resources`.
+pub fn health(ledger: &Ledger, resources: &CanisterResources) -> Health {
+    if let Some(reason) = ledger.corruption() {
+        return Health::ReadOnly {
+            reasons: vec![format!("The ledger's state is corrupted: {}", reason)],
+        };
+    }
+
+    if let Some(until) = ledger.read_only_until() {
+        return Health::ReadOnly {
+            reasons: vec![format!(
+                "The ledger is read-only for a migration until {} ns",
+                until.as_nanos()
+            )],
+        };
+    }
+
+    let num_unarchived_blocks = ledger.blockchain.num_unarchived_blocks();
+    if ledger.is_throttled() {
+        return Health::ReadOnly {
+            reasons: vec![format!(
+                "{} blocks wait to be archived, transfers are throttled",
+                num_unarchived_blocks
+            )],
+        };
+    }
+
+    let mut reasons = vec![];
+    if let Some(throttle) = ledger.archive_lag_throttle() {
+        if num_unarchived_blocks > throttle.trigger_threshold {
+            reasons.push(format!(
+                "{} blocks wait to be archived, more than the trigger threshold of {}",
+                num_unarchived_blocks, throttle.trigger_threshold
+            ));
+        }
+    }
+    if let Some(cycles_balance) = resources.cycles_balance {
+        if cycles_balance < MIN_HEALTHY_CYCLES_BALANCE {
+            reasons.push(format!("The cycles balance is low: {}", cycles_balance));
+        }
+    }
+    if let Some(heap_bytes) = resources.heap_bytes {
+        if heap_bytes > MAX_HEALTHY_HEAP_BYTES {
+            reasons.push(format!("The heap is close to full: {} bytes", heap_bytes));
+        }
+    }
+
+    if reasons.is_empty() {
+        Health::Healthy
+    } else {
+        Health::Degraded { reasons }
+    }
+}
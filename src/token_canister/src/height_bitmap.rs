@@ -0,0 +1,260 @@
+//! A compressed set of block heights, in the style of roaring bitmaps. The
+//! heights are split into chunks of 2^16 by their high bits; a chunk with
+//! few heights keeps them as a sorted array of their low bits, and a dense
+//! one as a bitmap. Either way a height takes at most 2 bytes, against the
+//! dozens a hash map entry takes.
+
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+const CHUNK_BITS: u32 = 16;
+const BITMAP_WORDS: usize = (1 << CHUNK_BITS) / 64;
+/// Chunks with more heights than this are stored as bitmaps. At this size
+/// an array takes as much memory as a bitmap.
+const MAX_ARRAY_LEN: usize = 4096;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+enum Chunk {
+    /// The low bits of the heights, sorted.
+    Array(Vec<u16>),
+    /// Bit `low % 64` of word `low / 64` is set for every height.
+    Bitmap(Vec<u64>),
+}
+
+impl Chunk {
+    fn contains(&self, low: u16) -> bool {
+        match self {
+            Chunk::Array(lows) => lows.binary_search(&low).is_ok(),
+            Chunk::Bitmap(words) => words[low as usize / 64] & (1 << (low % 64)) != 0,
+        }
+    }
+
+    fn insert(&mut self, low: u16) -> bool {
+        match self {
+            Chunk::Array(lows) => match lows.binary_search(&low) {
+                Ok(_) => false,
+                Err(i) => {
+                    lows.insert(i, low);
+                    if lows.len() > MAX_ARRAY_LEN {
+                        *self = Chunk::Bitmap(to_bitmap(lows));
+                    }
+                    true
+                }
+            },
+            Chunk::Bitmap(words) => {
+                let (word, bit) = (low as usize / 64, 1 << (low % 64));
+                let inserted = words[word] & bit == 0;
+                words[word] |= bit;
+                inserted
+            }
+        }
+    }
+
+    fn remove(&mut self, low: u16) -> bool {
+        match self {
+            Chunk::Array(lows) => match lows.binary_search(&low) {
+                Ok(i) => {
+                    lows.remove(i);
+                    true
+                }
+                Err(_) => false,
+            },
+            Chunk::Bitmap(words) => {
+                let (word, bit) = (low as usize / 64, 1 << (low % 64));
+                let removed = words[word] & bit != 0;
+                words[word] &= !bit;
+                if removed && self.len() <= MAX_ARRAY_LEN {
+                    *self = Chunk::Array(self.iter().collect());
+                }
+                removed
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Chunk::Array(lows) => lows.len(),
+            Chunk::Bitmap(words) => words.iter().map(|w| w.count_ones() as usize).sum(),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Chunk::Array(lows) => Box::new(lows.iter().copied()),
+            Chunk::Bitmap(words) => Box::new(
+                (0..=u16::MAX)
+                    .filter(move |low| words[*low as usize / 64] & (1 << (low % 64)) != 0),
+            ),
+        }
+    }
+
+    /// Puts a chunk read from stable memory in canonical form, as it was
+    /// not necessarily written by this code.
+    fn normalize(self) -> Result<Self, String> {
+        match self {
+            Chunk::Array(mut lows) => {
+                lows.sort_unstable();
+                lows.dedup();
+                if lows.len() > MAX_ARRAY_LEN {
+                    Ok(Chunk::Bitmap(to_bitmap(&lows)))
+                } else {
+                    Ok(Chunk::Array(lows))
+                }
+            }
+            Chunk::Bitmap(words) if words.len() != BITMAP_WORDS => Err(format!(
+                "A bitmap chunk must have {} words, got {}",
+                BITMAP_WORDS,
+                words.len()
+            )),
+            bitmap => {
+                if bitmap.len() <= MAX_ARRAY_LEN {
+                    Ok(Chunk::Array(bitmap.iter().collect()))
+                } else {
+                    Ok(bitmap)
+                }
+            }
+        }
+    }
+}
+
+fn to_bitmap(lows: &[u16]) -> Vec<u64> {
+    let mut words = vec![0; BITMAP_WORDS];
+    for low in lows {
+        words[*low as usize / 64] |= 1 << (low % 64);
+    }
+    words
+}
+
+fn split(height: u64) -> (u64, u16) {
+    (height >> CHUNK_BITS, height as u16)
+}
+
+/// A set of block heights.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeightBitmap {
+    chunks: BTreeMap<u64, Chunk>,
+    len: u64,
+}
+
+impl HeightBitmap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, height: u64) -> bool {
+        let (key, low) = split(height);
+        self.chunks
+            .get(&key)
+            .map_or(false, |chunk| chunk.contains(low))
+    }
+
+    /// Adds `height`, returning whether it was not in the set yet.
+    pub fn insert(&mut self, height: u64) -> bool {
+        let (key, low) = split(height);
+        let inserted = self
+            .chunks
+            .entry(key)
+            .or_insert_with(|| Chunk::Array(Vec::new()))
+            .insert(low);
+        if inserted {
+            self.len += 1;
+        }
+        inserted
+    }
+
+    /// Removes `height`, returning whether it was in the set.
+    pub fn remove(&mut self, height: u64) -> bool {
+        let (key, low) = split(height);
+        let chunk = match self.chunks.get_mut(&key) {
+            Some(chunk) => chunk,
+            None => return false,
+        };
+        let removed = chunk.remove(low);
+        if removed {
+            self.len -= 1;
+            if chunk.len() == 0 {
+                self.chunks.remove(&key);
+            }
+        }
+        removed
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The heights in the set, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.chunks.iter().flat_map(|(key, chunk)| {
+            chunk
+                .iter()
+                .map(move |low| (key << CHUNK_BITS) | low as u64)
+        })
+    }
+}
+
+/// Serialized as a sequence of `(chunk key, chunk)` pairs.
+impl Serialize for HeightBitmap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.chunks.len()))?;
+        for entry in self.chunks.iter() {
+            seq.serialize_element(&entry)?;
+        }
+        seq.end()
+    }
+}
+
+struct HeightBitmapVisitor;
+
+impl<'de> Visitor<'de> for HeightBitmapVisitor {
+    type Value = HeightBitmap;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of height bitmap chunks, or a map of heights")
+    }
+
+    fn visit_seq<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut bitmap = HeightBitmap::new();
+        while let Some((key, chunk)) = access.next_element::<(u64, Chunk)>()? {
+            if key > u64::MAX >> CHUNK_BITS || bitmap.chunks.contains_key(&key) {
+                return Err(de::Error::custom(format!("Invalid chunk key {}", key)));
+            }
+            let chunk = chunk.normalize().map_err(de::Error::custom)?;
+            let len = chunk.len() as u64;
+            if len > 0 {
+                bitmap.chunks.insert(key, chunk);
+                bitmap.len += len;
+            }
+        }
+        Ok(bitmap)
+    }
+
+    /// Ledgers from before the bitmap stored the notified heights as the
+    /// keys of a map.
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut bitmap = HeightBitmap::new();
+        while let Some((height, ())) = access.next_entry::<u64, ()>()? {
+            bitmap.insert(height);
+        }
+        Ok(bitmap)
+    }
+}
+
+impl<'de> Deserialize<'de> for HeightBitmap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(HeightBitmapVisitor)
+    }
+}
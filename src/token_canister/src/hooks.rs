@@ -0,0 +1,120 @@
+use crate::ic_block::BlockHeight;
+use crate::types::{Memo, Operation};
+
+use candid::CandidType;
+use ic_types::CanisterId;
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of hooks that can be configured.
+pub const MAX_OPERATION_HOOKS: usize = 8;
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub enum HookedOperation {
+    Mint,
+    Burn,
+    Transfer,
+}
+
+impl HookedOperation {
+    pub fn of(operation: &Operation) -> Option<Self> {
+        match operation {
+            Operation::Mint { .. } => Some(Self::Mint),
+            Operation::Burn { .. } => Some(Self::Burn),
+            Operation::Transfer { .. } => Some(Self::Transfer),
+            Operation::SetMintingAccount { .. } | Operation::AdminAudit { .. } => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub enum HookPhase {
+    /// Called before the operation is applied.
+    Before,
+    /// Called after the operation was applied, with its block height.
+    After,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub enum HookMode {
+    /// The ledger does not wait for the hook, and whatever happens to the
+    /// call never affects the operation.
+    FireAndForget,
+    /// The ledger waits for the hook to reply. A `Before` hook that returns
+    /// an error or fails rejects the operation; an `After` hook delays the
+    /// reply to the caller but cannot undo the operation.
+    Blocking,
+}
+
+/// A canister method called around every operation of a kind.
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct OperationHook {
+    pub canister_id: CanisterId,
+    pub method: String,
+    pub operation: HookedOperation,
+    pub phase: HookPhase,
+    pub mode: HookMode,
+}
+
+/// The argument hook methods are called with. Blocking hooks reply with a
+/// `Result<(), String>`.
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct OperationHookArgs {
+    pub phase: HookPhase,
+    pub operation: Operation,
+    pub memo: Memo,
+    pub block_height: Option<BlockHeight>,
+}
+
+/// The hooks configured by the controller.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct OperationHooks {
+    hooks: Vec<OperationHook>,
+}
+
+impl OperationHooks {
+    pub fn set(&mut self, hooks: Vec<OperationHook>) -> Result<(), String> {
+        if hooks.len() > MAX_OPERATION_HOOKS {
+            return Err(format!(
+                "At most {} operation hooks can be configured",
+                MAX_OPERATION_HOOKS
+            ));
+        }
+        self.hooks = hooks;
+        Ok(())
+    }
+
+    pub fn list(&self) -> &[OperationHook] {
+        &self.hooks
+    }
+
+    /// Fails if a blocking hook has to approve `operation` before it is
+    /// applied. Endpoints that do not wait for hooks call this, so that
+    /// such operations are rejected rather than applied without approval.
+    pub fn check_unhooked(&self, operation: &Operation) -> Result<(), String> {
+        let blocking = self
+            .matching(operation, HookPhase::Before)
+            .into_iter()
+            .find(|hook| hook.mode == HookMode::Blocking);
+        match blocking {
+            Some(hook) => Err(format!(
+                "{:?} operations must be approved by hook {} of canister {}, which only \
+                 the transfer and mint endpoints call",
+                hook.operation, hook.method, hook.canister_id
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The hooks to call in `phase` of `operation`.
+    pub fn matching(&self, operation: &Operation, phase: HookPhase) -> Vec<OperationHook> {
+        let kind = match HookedOperation::of(operation) {
+            Some(kind) => kind,
+            None => return vec![],
+        };
+        self.hooks
+            .iter()
+            .filter(|hook| hook.operation == kind && hook.phase == phase)
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,357 @@
+//! The ledger's HTTP interface, served through the http_request query.
+//! Routing is kept independent of the canister API so that it can be
+//! exercised off-chain.
+
+use crate::certification::tip_hash_tree;
+use crate::health::{health, CanisterResources, Health};
+use crate::ic_block::{Block, BlockHeight, EncodedBlock, MAX_BLOCKS_PER_REQUEST};
+use crate::log::{LogEntry, LogLevel, LOGS};
+use crate::{HashOf, Ledger, LedgerMetrics};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    #[serde(with = "serde_bytes")]
+    pub body: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    #[serde(with = "serde_bytes")]
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn new(status_code: u16, content_type: &str, body: Vec<u8>) -> Self {
+        HttpResponse {
+            status_code,
+            headers: vec![
+                ("Content-Type".to_string(), content_type.to_string()),
+                ("Content-Length".to_string(), body.len().to_string()),
+            ],
+            body,
+        }
+    }
+
+    fn text(status_code: u16, body: &str) -> Self {
+        Self::new(status_code, "text/plain", body.as_bytes().to_vec())
+    }
+}
+
+/// A block as served by the /blocks route, in CBOR. `encoded` is the
+/// block's protobuf encoding and `hash` its SHA-256 hash, which the next
+/// block names as its parent, so clients can check the chain without a
+/// protobuf library.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HttpBlock {
+    pub height: BlockHeight,
+    pub hash: HashOf<EncodedBlock>,
+    #[serde(with = "serde_bytes")]
+    pub encoded: Vec<u8>,
+    pub block: Block,
+}
+
+/// What the router needs from the canister environment, as opposed to the
+/// ledger's state.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RequestContext {
+    /// The certificate of the ledger's certified data. Only available in
+    /// non-replicated queries.
+    pub certificate: Option<Vec<u8>>,
+    pub resources: CanisterResources,
+}
+
+impl HttpRequest {
+    /// The path of the request, without the query string.
+    pub fn path(&self) -> &str {
+        match self.url.find('?') {
+            Some(i) => &self.url[..i],
+            None => &self.url,
+        }
+    }
+
+    /// The value of the query string parameter `name`, if present.
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        let query = &self.url[self.url.find('?')? + 1..];
+        query.split('&').find_map(|param| {
+            let mut parts = param.splitn(2, '=');
+            if parts.next() == Some(name) {
+                Some(parts.next().unwrap_or(""))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// The routes served while reads are restricted. HTTP requests are
+/// anonymous, so they cannot come from a listed reader.
+pub const PUBLIC_ROUTES: &[&str] = &["/metrics", "/health"];
+
+/// Answers `request` from the state of `ledger`.
+pub fn serve(request: &HttpRequest, ledger: &Ledger, context: &RequestContext) -> HttpResponse {
+    if request.method != "GET" {
+        return HttpResponse::text(405, "Only GET requests are supported");
+    }
+    if ledger.readers.is_restricted() && !PUBLIC_ROUTES.contains(&request.path()) {
+        return HttpResponse::text(401, "Unauthorized");
+    }
+    match request.path() {
+        "/metrics" => HttpResponse::new(
+            200,
+            "text/plain; version=0.0.4",
+            encode_metrics(&ledger.metrics()).into_bytes(),
+        ),
+        "/tip" => HttpResponse::new(
+            200,
+            "application/json",
+            encode_tip(ledger, context.certificate.as_deref()).into_bytes(),
+        ),
+        "/health" => {
+            let health = health(ledger, &context.resources);
+            // Load balancers only look at the status code. A degraded ledger
+            // still serves requests.
+            let status_code = match health {
+                Health::ReadOnly { .. } => 503,
+                Health::Healthy | Health::Degraded { .. } => 200,
+            };
+            HttpResponse::new(
+                status_code,
+                "application/json",
+                encode_health(&health).into_bytes(),
+            )
+        }
+        "/blocks" => {
+            let param = |name, default| match request.query_param(name).map(str::parse) {
+                None => Ok(default),
+                Some(Ok(value)) => Ok(value),
+                Some(Err(_)) => Err(HttpResponse::text(
+                    400,
+                    &format!("Invalid value for {}", name),
+                )),
+            };
+            let start = match param("start", 0) {
+                Ok(start) => start,
+                Err(response) => return response,
+            };
+            let length = match param("length", MAX_BLOCKS_PER_REQUEST as u64) {
+                Ok(length) => length,
+                Err(response) => return response,
+            };
+            serve_blocks(ledger, start, length)
+        }
+        "/logs" => {
+            let since = match request.query_param("since").map(str::parse) {
+                None => 0,
+                Some(Ok(since)) => since,
+                Some(Err(_)) => return HttpResponse::text(400, "Invalid value for since"),
+            };
+            let entries = LOGS.read().unwrap().entries_since(since);
+            HttpResponse::text(200, &encode_logs(&entries))
+        }
+        _ => HttpResponse::text(404, "Not found"),
+    }
+}
+
+/// Serves up to `length` blocks from height `start` on as a CBOR array of
+/// `HttpBlock`s, at most `MAX_BLOCKS_PER_REQUEST` at a time. The
+/// X-Chain-Length and X-First-Block-Index headers tell which blocks the
+/// ledger holds, and X-Next-Start, present while there are more blocks,
+/// where the next page starts. Blocks below X-First-Block-Index were
+/// archived or pruned.
+pub fn serve_blocks(ledger: &Ledger, start: BlockHeight, length: u64) -> HttpResponse {
+    let blockchain = &ledger.blockchain;
+    let first_block_index = blockchain.first_local_height();
+    let chain_length = blockchain.chain_length();
+    if start < first_block_index {
+        return HttpResponse::text(
+            404,
+            &format!(
+                "Blocks below {} are no longer held by the ledger",
+                first_block_index
+            ),
+        );
+    }
+    let end = start
+        .saturating_add(length.min(MAX_BLOCKS_PER_REQUEST as u64))
+        .min(chain_length)
+        .max(start);
+    let mut blocks = vec![];
+    for height in start..end {
+        let encoded = blockchain
+            .get(height)
+            .expect("Blocks from the first local height on are held by the ledger");
+        match encoded.decode() {
+            Ok(block) => blocks.push(HttpBlock {
+                height,
+                hash: encoded.hash(),
+                encoded: encoded.0.to_vec(),
+                block,
+            }),
+            Err(e) => {
+                return HttpResponse::text(500, &format!("Block {} is invalid: {}", height, e))
+            }
+        }
+    }
+    let mut response = HttpResponse::new(
+        200,
+        "application/cbor",
+        serde_cbor::to_vec(&blocks).unwrap(),
+    );
+    response
+        .headers
+        .push(("X-Chain-Length".to_string(), chain_length.to_string()));
+    response.headers.push((
+        "X-First-Block-Index".to_string(),
+        first_block_index.to_string(),
+    ));
+    if end < chain_length {
+        response
+            .headers
+            .push(("X-Next-Start".to_string(), end.to_string()));
+    }
+    response
+}
+
+/// Renders the log entries at `LogLevel::Info` and above, one per line.
+/// HTTP requests are anonymous, so trace entries are only available through
+/// the controller-only get_logs query.
+pub fn encode_logs(entries: &[LogEntry]) -> String {
+    let mut w = String::new();
+    for entry in entries.iter().filter(|e| e.level >= LogLevel::Info) {
+        writeln!(
+            w,
+            "{} {} {} {}",
+            entry.index,
+            entry.timestamp.as_nanos(),
+            entry.level,
+            entry.message
+        )
+        .unwrap();
+    }
+    w
+}
+
+/// Renders the tip of the chain as JSON: the chain length, the index and
+/// hex-encoded hash of the last block, and the base64-encoded certificate
+/// with the hash tree it certifies, so that probes can check liveness and
+/// that replicas agree on the tip. Absent values are null.
+pub fn encode_tip(ledger: &Ledger, certificate: Option<&[u8]>) -> String {
+    let tip = ledger.tip();
+    let hash_tree = tip_hash_tree(tip, ledger.aliases.root_hash());
+    let json_string = |s: Option<String>| match s {
+        Some(s) => format!("\"{}\"", s),
+        None => "null".to_string(),
+    };
+    format!(
+        "{{\"chain_length\":{},\"last_block_index\":{},\"last_block_hash\":{},\
+         \"certificate\":{},\"hash_tree\":\"{}\"}}",
+        ledger.blockchain.chain_length(),
+        tip.map_or("null".to_string(), |(height, _)| height.to_string()),
+        json_string(tip.map(|(_, hash)| hash.to_string())),
+        json_string(certificate.map(base64::encode)),
+        base64::encode(hash_tree),
+    )
+}
+
+/// Renders `health` as JSON, e.g. `{"status":"degraded","reasons":[...]}`.
+pub fn encode_health(health: &Health) -> String {
+    let (status, reasons): (&str, &[String]) = match health {
+        Health::Healthy => ("healthy", &[]),
+        Health::Degraded { reasons } => ("degraded", reasons),
+        Health::ReadOnly { reasons } => ("read_only", reasons),
+    };
+    let reasons: Vec<String> = reasons
+        .iter()
+        .map(|r| format!("\"{}\"", r.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    format!(
+        "{{\"status\":\"{}\",\"reasons\":[{}]}}",
+        status,
+        reasons.join(",")
+    )
+}
+
+/// Renders `metrics` in the Prometheus text exposition format.
+pub fn encode_metrics(metrics: &LedgerMetrics) -> String {
+    let mut w = String::new();
+    let mut gauge = |name: &str, help: &str, value: u64| {
+        writeln!(w, "# HELP {} {}", name, help).unwrap();
+        writeln!(w, "# TYPE {} gauge", name).unwrap();
+        writeln!(w, "{} {}", name, value).unwrap();
+    };
+    gauge(
+        "ledger_chain_length",
+        "Number of blocks in the chain, including archived and pruned ones.",
+        metrics.chain_length,
+    );
+    gauge(
+        "ledger_num_archived_blocks",
+        "Number of blocks sent to the archive.",
+        metrics.num_archived_blocks,
+    );
+    gauge(
+        "ledger_num_pruned_blocks",
+        "Number of blocks dropped by the retention policy.",
+        metrics.num_pruned_blocks,
+    );
+    gauge(
+        "ledger_num_accounts",
+        "Number of accounts with a balance.",
+        metrics.num_accounts,
+    );
+    gauge(
+        "ledger_transactions_by_hash_len",
+        "Number of transactions remembered to detect duplicates.",
+        metrics.transactions_by_hash_len,
+    );
+    gauge(
+        "ledger_transactions_by_height_len",
+        "Number of transactions in the transaction window.",
+        metrics.transactions_by_height_len,
+    );
+    gauge(
+        "ledger_num_purged_transactions",
+        "Number of transactions that left the transaction window.",
+        metrics.num_purged_transactions,
+    );
+    if let Some(timestamp) = metrics.oldest_remembered_transaction_time {
+        gauge(
+            "ledger_oldest_remembered_transaction_timestamp_seconds",
+            "Block time of the oldest transaction in the transaction window.",
+            timestamp.as_nanos() / 1_000_000_000,
+        );
+    }
+    gauge(
+        "ledger_send_dfx_calls",
+        "Number of transfers made through the deprecated send_dfx endpoint.",
+        metrics.send_dfx_calls,
+    );
+    if let Some(timestamp) = metrics.send_dfx_last_call {
+        gauge(
+            "ledger_send_dfx_last_call_timestamp_seconds",
+            "Time of the last transfer made through the deprecated send_dfx endpoint.",
+            timestamp.as_nanos() / 1_000_000_000,
+        );
+    }
+    writeln!(
+        w,
+        "# HELP ledger_state_hash_info Hash of the ledger state, in the hash label."
+    )
+    .unwrap();
+    writeln!(w, "# TYPE ledger_state_hash_info gauge").unwrap();
+    writeln!(
+        w,
+        "ledger_state_hash_info{{hash=\"{}\"}} 1",
+        metrics.state_hash
+    )
+    .unwrap();
+    w
+}
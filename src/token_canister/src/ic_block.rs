@@ -2,27 +2,36 @@ use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::sync::{Arc, RwLock};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use std::collections::VecDeque;
+use std::fmt;
 
 use serde::{
     Deserialize, Serialize,
 };
 
-use ic_types::CanisterId;
+use ic_types::{CanisterId, PrincipalId};
 use on_wire::{FromWire, IntoWire};
 use candid::CandidType;
 use ic_crypto_sha::Sha256;
 use dfn_protobuf::ProtoBuf;
 
+use crate::account_identifier::AccountIdentifier;
+use crate::block_log::{BlockLogPosition, BlockStorage};
+use crate::block_stats::{BlockStats, BlockStatsTracker};
 use crate::HashOf;
 use crate::TimeStamp;
 use crate::types::{Memo, Operation, Transaction};
-use crate::print;
+use crate::log;
 
 /// Position of a block in the chain. The first block has position 0.
 pub type BlockHeight = u64;
 
+/// The most blocks a single get_blocks, query_encoded_blocks or iter_blocks
+/// request returns. Longer requests are truncated to this many blocks, so
+/// that clients page through the chain in steps of at most this size.
+pub const MAX_BLOCKS_PER_REQUEST: usize = 2000;
+
 #[derive(
     Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
 )]
@@ -59,6 +68,11 @@ pub struct Block {
     pub transaction: Transaction,
     /// Nanoseconds since the Unix epoch.
     pub timestamp: TimeStamp,
+    /// The account the fee of the transaction was credited to. `None` if the
+    /// fee returned to the pool, and then left out of the encoding so that
+    /// existing blocks keep their hashes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_collector: Option<AccountIdentifier>,
 }
 
 impl Block {
@@ -73,6 +87,8 @@ impl Block {
             operation,
             memo,
             created_at_time,
+            encrypted_memo: None,
+            memo_blob: None,
         };
         Ok(Self::new_from_transaction(
             parent_hash,
@@ -90,6 +106,7 @@ impl Block {
             parent_hash,
             transaction,
             timestamp,
+            fee_collector: None,
         }
     }
 
@@ -124,6 +141,69 @@ pub struct Blockchain {
 
     /// How many blocks have been sent to the archive
     pub num_archived_blocks: u64,
+
+    /// How many blocks at the start of the chain were dropped by the
+    /// retention policy. They come before the archived blocks.
+    #[serde(default)]
+    pub num_pruned_blocks: u64,
+    /// Hashes of the newest pruned blocks, at most `MAX_PRUNED_HASHES`,
+    /// ending with the block at height `num_pruned_blocks - 1`, the
+    /// checkpoint the first available block links to.
+    #[serde(default)]
+    pub pruned_hashes: VecDeque<HashOf<EncodedBlock>>,
+    /// The height up to which the blocks were confirmed to be exported.
+    /// Only exported blocks are pruned, so that pruning never drops the only
+    /// copy of a block.
+    #[serde(default)]
+    pub num_exported_blocks: u64,
+
+    /// The archive nodes holding the archived blocks, in order, each with
+    /// the height after the last block it holds.
+    #[serde(default)]
+    pub archive_nodes: Vec<(CanisterId, BlockHeight)>,
+
+    /// Where `blocks` are kept in the block log, if they are persisted.
+    #[serde(default)]
+    block_log: BlockLogPosition,
+    #[serde(skip)]
+    block_storage: Option<Arc<dyn BlockStorage>>,
+
+    /// Statistics about the blocks produced. `None` in a blockchain from
+    /// before they were kept, until `migrate_block_stats` runs.
+    #[serde(default)]
+    block_stats: Option<BlockStatsTracker>,
+}
+
+/// How many hashes of pruned blocks are kept as stubs, so that the newest
+/// pruned part of the chain can still be checked against.
+pub const MAX_PRUNED_HASHES: usize = 10_000;
+
+/// How much history the ledger keeps. A block falls outside the policy once
+/// it is older than `max_age` or is no longer among the newest `max_blocks`
+/// blocks, and is dropped once it was exported. A policy without limits
+/// keeps every block.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_age: Option<Duration>,
+    pub max_blocks: Option<u64>,
+}
+
+impl RetentionPolicy {
+    fn retains(
+        &self,
+        height: BlockHeight,
+        timestamp: TimeStamp,
+        chain_length: u64,
+        now: TimeStamp,
+    ) -> bool {
+        let too_old = self
+            .max_age
+            .map_or(false, |max_age| timestamp.has_elapsed(max_age, now));
+        let too_deep = self
+            .max_blocks
+            .map_or(false, |max_blocks| chain_length - height > max_blocks);
+        !too_old && !too_deep
+    }
 }
 
 impl Default for Blockchain {
@@ -134,11 +214,100 @@ impl Default for Blockchain {
             last_timestamp: SystemTime::UNIX_EPOCH.into(),
             // archive: Arc::new(RwLock::new(None)),
             num_archived_blocks: 0,
+            num_pruned_blocks: 0,
+            pruned_hashes: VecDeque::new(),
+            num_exported_blocks: 0,
+            archive_nodes: vec![],
+            block_log: BlockLogPosition::default(),
+            block_storage: None,
+            block_stats: Some(BlockStatsTracker::default()),
         }
     }
 }
 
 impl Blockchain {
+    /// Persists `blocks` to `storage` from now on. If the block log is
+    /// empty, e.g. on init or after upgrading from a version that serialized
+    /// the blocks with the ledger, the blocks present are written to it.
+    /// Otherwise they were left out of the serialized ledger and are read
+    /// back from it.
+    pub fn set_block_storage(&mut self, storage: Arc<dyn BlockStorage>) -> Result<(), String> {
+        if self.block_log.is_empty() {
+            self.block_log = BlockLogPosition::default();
+            for block in self.blocks.iter() {
+                self.block_log.append(storage.as_ref(), &block.0)?;
+            }
+        } else if self.blocks.is_empty() {
+            self.blocks = self
+                .block_log
+                .read_all(storage.as_ref())
+                .into_iter()
+                .map(|block| EncodedBlock(block.into_boxed_slice()))
+                .collect();
+        } else {
+            return Err(format!(
+                "Found {} serialized blocks besides a non-empty block log",
+                self.blocks.len()
+            ));
+        }
+        self.block_storage = Some(storage);
+        Ok(())
+    }
+
+    /// Whether `blocks` are kept in a block log, so that they need not be
+    /// serialized with the ledger.
+    pub fn blocks_are_persisted(&self) -> bool {
+        self.block_storage.is_some()
+    }
+
+    /// The blocks as they are kept in the block log, if they are persisted.
+    pub fn block_log(&self) -> BlockLogPosition {
+        self.block_log
+    }
+
+    /// Gives a blockchain from before block statistics were kept statistics
+    /// covering the blocks it holds. Must run after the blocks were restored
+    /// from the block log.
+    pub fn migrate_block_stats(&mut self) -> Result<(), String> {
+        if self.block_stats.is_none() {
+            let mut stats = BlockStatsTracker::default();
+            for encoded in self.blocks.iter() {
+                let block = encoded.decode()?;
+                stats.record_added(
+                    &block.transaction.operation,
+                    block.timestamp,
+                    encoded.size_bytes() as u64,
+                );
+            }
+            self.block_stats = Some(stats);
+        }
+        Ok(())
+    }
+
+    fn block_stats_mut(&mut self) -> &mut BlockStatsTracker {
+        self.block_stats
+            .as_mut()
+            .expect("The block statistics were not migrated")
+    }
+
+    /// Statistics about the blocks produced, with recent blocks counted up
+    /// to `now`.
+    pub fn block_stats(&self, now: TimeStamp) -> BlockStats {
+        self.block_stats
+            .as_ref()
+            .expect("The block statistics were not migrated")
+            .stats(now)
+    }
+
+    fn drop_removed_blocks(&mut self, removed: &[EncodedBlock]) {
+        let removed_bytes: u64 = removed.iter().map(|block| block.size_bytes() as u64).sum();
+        self.block_stats_mut().record_removed(removed_bytes);
+        if self.block_storage.is_some() {
+            self.block_log
+                .drop_front(removed.iter().map(|block| block.size_bytes()));
+        }
+    }
+
     pub fn add_block(&mut self, block: Block) -> Result<BlockHeight, String> {
         let raw_block = block.clone().encode()?;
         self.add_block_with_encoded(block, raw_block)
@@ -158,6 +327,14 @@ impl Blockchain {
                     .to_owned(),
             );
         }
+        if let Some(storage) = &self.block_storage {
+            self.block_log.append(storage.as_ref(), &encoded_block.0)?;
+        }
+        self.block_stats_mut().record_added(
+            &block.transaction.operation,
+            block.timestamp,
+            encoded_block.size_bytes() as u64,
+        );
         self.last_hash = Some(encoded_block.hash());
         self.last_timestamp = block.timestamp;
         self.blocks.push(encoded_block);
@@ -165,14 +342,19 @@ impl Blockchain {
     }
 
     pub fn get(&self, height: BlockHeight) -> Option<&EncodedBlock> {
-        if height < self.num_archived_blocks() {
+        if height < self.first_local_height() {
             None
         } else {
             self.blocks
-                .get(usize::try_from(height - self.num_archived_blocks()).unwrap())
+                .get(usize::try_from(height - self.first_local_height()).unwrap())
         }
     }
 
+    /// The height of the first block stored in `blocks`.
+    pub fn first_local_height(&self) -> BlockHeight {
+        self.num_pruned_blocks + self.num_archived_blocks
+    }
+
     pub fn last(&self) -> Option<&EncodedBlock> {
         self.blocks.last()
     }
@@ -185,11 +367,191 @@ impl Blockchain {
         self.blocks.len().try_into().unwrap()
     }
 
+    pub fn num_pruned_blocks(&self) -> u64 {
+        self.num_pruned_blocks
+    }
+
     pub fn chain_length(&self) -> BlockHeight {
-        self.num_archived_blocks() + self.num_unarchived_blocks() as BlockHeight
+        self.first_local_height() + self.num_unarchived_blocks() as BlockHeight
+    }
+
+    /// The hash kept for the pruned block at `height`, if any.
+    pub fn pruned_block_hash(&self, height: BlockHeight) -> Option<HashOf<EncodedBlock>> {
+        let first_stub = self.num_pruned_blocks - self.pruned_hashes.len() as u64;
+        if height < first_stub || height >= self.num_pruned_blocks {
+            return None;
+        }
+        self.pruned_hashes
+            .get(usize::try_from(height - first_stub).unwrap())
+            .copied()
+    }
+
+    /// Records that the blocks below `end` were exported, which lets the
+    /// retention policy prune them.
+    pub fn confirm_exported(&mut self, end: BlockHeight) -> Result<(), String> {
+        if end > self.chain_length() {
+            return Err(format!(
+                "Cannot confirm the export of blocks up to {}, the chain is only {} blocks long",
+                end,
+                self.chain_length()
+            ));
+        }
+        self.num_exported_blocks = self.num_exported_blocks.max(end);
+        Ok(())
+    }
+
+    /// How many of the oldest blocks fall outside `policy` and were
+    /// exported, looking at no more than `limit` of them. The tip is always
+    /// kept.
+    pub fn num_blocks_outside_retention(
+        &self,
+        policy: &RetentionPolicy,
+        now: TimeStamp,
+        limit: usize,
+    ) -> Result<usize, String> {
+        let chain_length = self.chain_length();
+        let num_exported = self
+            .num_exported_blocks
+            .saturating_sub(self.first_local_height());
+        let candidates = self
+            .blocks
+            .len()
+            .saturating_sub(1)
+            .min(limit)
+            .min(usize::try_from(num_exported).unwrap_or(usize::MAX));
+        for (i, block) in self.blocks[..candidates].iter().enumerate() {
+            let height = self.first_local_height() + i as u64;
+            let timestamp = block.decode()?.timestamp;
+            if policy.retains(height, timestamp, chain_length, now) {
+                return Ok(i);
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// Drops the `len` oldest blocks, keeping the hashes of the newest
+    /// `MAX_PRUNED_HASHES` pruned ones. Only blocks that are stored here can
+    /// be pruned, so nothing may have been archived yet, and only once they
+    /// were exported.
+    pub fn prune_blocks(&mut self, len: usize) -> Result<(), String> {
+        if len == 0 {
+            return Ok(());
+        }
+        if self.num_archived_blocks > 0 {
+            return Err("Cannot prune blocks once blocks were archived".to_string());
+        }
+        if self.num_pruned_blocks + len as u64 > self.num_exported_blocks {
+            return Err(format!(
+                "Cannot prune {} blocks, only the blocks up to {} were exported",
+                len, self.num_exported_blocks
+            ));
+        }
+        if len >= self.blocks.len() {
+            return Err(format!(
+                "Cannot prune {} blocks, at least one of the {} present must be kept",
+                len,
+                self.blocks.len()
+            ));
+        }
+        let remaining = self.blocks.split_off(len);
+        let pruned = std::mem::replace(&mut self.blocks, remaining);
+        self.drop_removed_blocks(&pruned);
+        // Checkpoint the hashes before the blocks are dropped.
+        self.pruned_hashes.extend(pruned.iter().map(|block| block.hash()));
+        let excess = self.pruned_hashes.len().saturating_sub(MAX_PRUNED_HASHES);
+        self.pruned_hashes.drain(..excess);
+        self.num_pruned_blocks += len as u64;
+        Ok(())
+    }
+
+    /// Get multiple blocks by height, refusing heights that were pruned. At
+    /// most `MAX_BLOCKS_PER_REQUEST` blocks are returned.
+    pub fn get_blocks(&self, start: BlockHeight, length: usize) -> GetBlocksRes {
+        if start < self.num_pruned_blocks {
+            return GetBlocksRes {
+                blocks: Err(GetBlocksError::Pruned {
+                    earliest_available: self.num_pruned_blocks,
+                }),
+                first_block_index: self.first_local_height(),
+                chain_length: self.chain_length(),
+            };
+        }
+        get_blocks(&self.blocks, self.first_local_height(), start, length)
+    }
+
+    /// Where the block at `height` can be found.
+    pub fn lookup(&self, height: BlockHeight) -> BlockLookup {
+        if height < self.num_pruned_blocks {
+            return BlockLookup::Pruned {
+                earliest_available: self.num_pruned_blocks,
+            };
+        }
+        if height < self.first_local_height() {
+            let canister_id = self
+                .archive_nodes
+                .iter()
+                .find(|(_, end)| height < *end)
+                .map(|(canister_id, _)| *canister_id)
+                .expect("Archived block without an archive node");
+            return BlockLookup::Archived { canister_id };
+        }
+        match self.get(height) {
+            Some(block) => BlockLookup::Found(block.clone()),
+            None => BlockLookup::NotYetProduced {
+                chain_length: self.chain_length(),
+            },
+        }
+    }
+
+    /// Looks up each of `heights`, in the order given. Only the first
+    /// `MAX_BLOCKS_PER_REQUEST` heights are looked up.
+    pub fn lookup_heights(&self, heights: &[BlockHeight]) -> Vec<BlockLookup> {
+        heights
+            .iter()
+            .take(MAX_BLOCKS_PER_REQUEST)
+            .map(|height| self.lookup(*height))
+            .collect()
+    }
+
+    /// The archive nodes holding blocks, oldest first, with the range of
+    /// blocks each of them holds.
+    pub fn archives(&self) -> Vec<ArchiveInfo> {
+        let mut start = self.num_pruned_blocks;
+        let mut archives = vec![];
+        for (canister_id, end) in &self.archive_nodes {
+            if *end > start {
+                archives.push(ArchiveInfo {
+                    canister_id: *canister_id,
+                    block_range_start: start,
+                    block_range_end: end - 1,
+                });
+            }
+            start = *end;
+        }
+        archives
     }
 
-    pub fn remove_archived_blocks(&mut self, len: usize) {
+    /// The archive nodes in the ICRC-3 shape, starting after the node
+    /// `from`, or from the oldest node if `from` is not set or is not one of
+    /// the archive nodes.
+    pub fn icrc3_archives(&self, from: Option<PrincipalId>) -> Vec<ICRC3ArchiveInfo> {
+        let archives = self.archives();
+        let skip = from
+            .and_then(|from| {
+                archives
+                    .iter()
+                    .position(|archive| archive.canister_id.get() == from)
+            })
+            .map_or(0, |position| position + 1);
+        archives
+            .into_iter()
+            .skip(skip)
+            .map(ICRC3ArchiveInfo::from)
+            .collect()
+    }
+
+    /// Drops the `len` oldest blocks, which were sent to `archive_node`.
+    pub fn remove_archived_blocks(&mut self, len: usize, archive_node: CanisterId) {
         // redundant since split_off would panic, but here we can give a more
         // descriptive message
         if len > self.blocks.len() {
@@ -199,8 +561,15 @@ impl Blockchain {
                 len
             );
         }
-        self.blocks = self.blocks.split_off(len);
+        let remaining = self.blocks.split_off(len);
+        let archived = std::mem::replace(&mut self.blocks, remaining);
+        self.drop_removed_blocks(&archived);
         self.num_archived_blocks += len as u64;
+        let end = self.first_local_height();
+        match self.archive_nodes.last_mut() {
+            Some((canister_id, node_end)) if *canister_id == archive_node => *node_end = end,
+            _ => self.archive_nodes.push((archive_node, end)),
+        }
     }
 
     pub fn get_blocks_for_archiving(
@@ -222,7 +591,7 @@ impl Blockchain {
         let blocks_to_archive: VecDeque<EncodedBlock> =
             VecDeque::from(self.blocks[0..num_blocks_to_archive.min(num_blocks_before)].to_vec());
 
-        print(format!(
+        log::trace(format!(
             "get_blocks_for_archiving(): trigger_threshold: {}, num_blocks: {}, blocks before archiving: {}, blocks to archive: {}",
             trigger_threshold,
             num_blocks_to_archive,
@@ -240,6 +609,7 @@ pub struct TipOfChainRes {
     pub tip_index: BlockHeight,
 }
 
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
 pub struct GetBlocksArgs {
     pub start: BlockHeight,
     pub length: usize,
@@ -251,7 +621,59 @@ impl GetBlocksArgs {
     }
 }
 
-pub struct GetBlocksRes(pub Result<Vec<EncodedBlock>, String>);
+/// Why a get_blocks request could not be answered.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum GetBlocksError {
+    /// The requested range starts at a height that the retention policy
+    /// pruned. Blocks from `earliest_available` on can still be fetched.
+    Pruned { earliest_available: BlockHeight },
+    /// The requested range is not stored here.
+    OutOfRange(String),
+}
+
+impl fmt::Display for GetBlocksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GetBlocksError::Pruned { earliest_available } => write!(
+                f,
+                "Requested blocks were pruned. The earliest available block is {}.",
+                earliest_available
+            ),
+            GetBlocksError::OutOfRange(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct GetBlocksRes {
+    pub blocks: Result<Vec<EncodedBlock>, GetBlocksError>,
+    /// The height of the first block in `blocks`. If the request failed, the
+    /// height of the first block that is still available here instead, so
+    /// that a client paging through the chain while blocks are archived can
+    /// tell that the window moved.
+    pub first_block_index: BlockHeight,
+    /// The number of blocks available here, counted from height 0.
+    pub chain_length: u64,
+}
+
+/// Response of the query_encoded_blocks endpoint. The blocks are returned as
+/// stored, so that clients can hash them and check the chain against the
+/// certified tip without decoding and re-encoding them.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct QueryEncodedBlocksRes {
+    pub blocks: Result<Vec<EncodedBlock>, GetBlocksError>,
+    pub first_block_index: BlockHeight,
+    pub chain_length: u64,
+    /// The hash of the last block of the chain, which is certified together
+    /// with its height, `chain_length - 1`.
+    pub tip_hash: Option<HashOf<EncodedBlock>>,
+    /// The root hash of the alias registry, which is certified together
+    /// with `tip_hash` once an alias was claimed.
+    pub aliases_root_hash: Option<[u8; 32]>,
+    /// The certificate of the ledger's certified data, see
+    /// `crate::certified_data`.
+    pub certificate: Option<Vec<u8>>,
+}
 
 pub struct IterBlocksArgs {
     pub start: usize,
@@ -266,6 +688,59 @@ impl IterBlocksArgs {
 
 pub struct IterBlocksRes(pub Vec<EncodedBlock>);
 
+/// Where the block at a given height can be found, returned by the block
+/// endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum BlockLookup {
+    /// The ledger holds the block.
+    Found(EncodedBlock),
+    /// The block was sent to the archive node `canister_id`.
+    Archived { canister_id: CanisterId },
+    /// The retention policy dropped the block. Blocks from
+    /// `earliest_available` on can still be looked up.
+    Pruned { earliest_available: BlockHeight },
+    /// The chain only has `chain_length` blocks so far.
+    NotYetProduced { chain_length: u64 },
+}
+
+/// An archive node and the blocks it holds, returned by the get_archives
+/// endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveInfo {
+    pub canister_id: CanisterId,
+    /// The height of the first block the node holds.
+    pub block_range_start: BlockHeight,
+    /// The height of the last block the node holds, inclusive.
+    pub block_range_end: BlockHeight,
+}
+
+/// Argument of the icrc3_get_archives endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct GetArchivesArgs {
+    /// The last archive node the client has seen. Only the nodes after it
+    /// are returned.
+    pub from: Option<PrincipalId>,
+}
+
+/// An archive node and the blocks it holds, in the shape ICRC-3 indexers
+/// expect. `end` is inclusive.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct ICRC3ArchiveInfo {
+    pub canister_id: PrincipalId,
+    pub start: candid::Nat,
+    pub end: candid::Nat,
+}
+
+impl From<ArchiveInfo> for ICRC3ArchiveInfo {
+    fn from(archive: ArchiveInfo) -> Self {
+        Self {
+            canister_id: archive.canister_id.get(),
+            start: candid::Nat::from(archive.block_range_start),
+            end: candid::Nat::from(archive.block_range_end),
+        }
+    }
+}
+
 // These is going away soon
 pub struct BlockArg(pub BlockHeight);
 pub struct BlockRes(pub Option<Result<EncodedBlock, CanisterId>>);
@@ -277,6 +752,8 @@ pub fn get_blocks(
     range_from: BlockHeight,
     length: usize,
 ) -> GetBlocksRes {
+    let length = length.min(MAX_BLOCKS_PER_REQUEST);
+    let chain_length = range_from_offset + blocks.len() as u64;
     // Inclusive end of the range of *requested* blocks
     let requested_range_to = range_from as usize + length - 1;
     // Inclusive end of the range of *available* blocks
@@ -285,18 +762,27 @@ pub fn get_blocks(
     // [100 .. 109] then requesting blocks at BlockHeight < 100 or BlockHeight
     // > 109 is an error
     if range_from < range_from_offset || requested_range_to > range_to {
-        return GetBlocksRes(Err(format!("Requested blocks outside the range stored in the archive node. Requested [{} .. {}]. Available [{} .. {}].",
-            range_from, requested_range_to, range_from_offset, range_to)));
+        return GetBlocksRes {
+            blocks: Err(GetBlocksError::OutOfRange(format!("Requested blocks outside the range stored in the archive node. Requested [{} .. {}]. Available [{} .. {}].",
+                range_from, requested_range_to, range_from_offset, range_to))),
+            first_block_index: range_from_offset,
+            chain_length,
+        };
     }
     // Example: If the node stores blocks [100 .. 109] then BLOCK_HEIGHT_OFFSET
     // is 100 and the Block with BlockHeight 100 is at index 0
     let offset = (range_from - range_from_offset) as usize;
-    GetBlocksRes(Ok(blocks[offset..offset + length].to_vec()))
+    GetBlocksRes {
+        blocks: Ok(blocks[offset..offset + length].to_vec()),
+        first_block_index: range_from,
+        chain_length,
+    }
 }
 
 // A helper function for ledger/iter_blocks and archive_node/iter_blocks
 // endpoints
 pub fn iter_blocks(blocks: &[EncodedBlock], offset: usize, length: usize) -> IterBlocksRes {
+    let length = length.min(MAX_BLOCKS_PER_REQUEST);
     let start = std::cmp::min(offset, blocks.len());
     let end = std::cmp::min(start + length, blocks.len());
     let blocks = blocks[start..end].to_vec();
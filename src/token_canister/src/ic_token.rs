@@ -1,6 +1,7 @@
 use candid::CandidType;
 use core::ops::{Add, AddAssign, Sub, SubAssign};
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::fmt;
 
 #[derive(
@@ -18,14 +19,18 @@ use std::fmt;
     Default,
 )]
 pub struct TOKENs {
-    /// Number of 10^-18 token.
+    /// Number of base units, 10^-decimals of a token. The ledger's decimals
+    /// default to `DECIMAL_PLACES`.
     /// Named because the equivalent part of a Bitcoin is called a Satoshi
     e8s: u64,
 }
 
+/// The decimals of ledgers that were not given any at init.
 pub const DECIMAL_PLACES: u32 = 18;
-/// How many times can ICPs be divided
-pub const TOKEN_SUBDIVIDABLE_BY: u64 = 100_000_000_000_000_000;
+/// How many times can a token be divided with the default decimals
+pub const TOKEN_SUBDIVIDABLE_BY: u64 = 10u64.pow(DECIMAL_PLACES);
+/// The most decimals a ledger can have: one whole token must fit in a u64.
+pub const MAX_DECIMAL_PLACES: u32 = 19;
 
 pub const TRANSACTION_FEE: TOKENs = TOKENs { e8s: 10_000 };
 pub const MIN_BURN_AMOUNT: TOKENs = TRANSACTION_FEE;
@@ -57,6 +62,10 @@ impl TOKENs {
 
     pub const ZERO: Self = TOKENs { e8s: 0 };
 
+    pub fn is_zero(&self) -> bool {
+        self.e8s == 0
+    }
+
     pub fn from_tokens(usdt: u64) -> Result<Self, String> {
         Self::new(usdt, 0)
     }
@@ -80,6 +89,119 @@ impl TOKENs {
     pub fn unpack(self) -> (u64, u64) {
         (self.get_tokens(), self.get_remainder_e8s())
     }
+
+    /// `self * num / denom`, rounded down. The product is computed without
+    /// overflowing, so this only fails if `denom` is zero or the result does
+    /// not fit in a u64 of base units.
+    pub fn checked_mul_ratio(self, num: u64, denom: u64) -> Option<Self> {
+        if denom == 0 {
+            return None;
+        }
+        let e8s = self.e8s as u128 * num as u128 / denom as u128;
+        u64::try_from(e8s).ok().map(Self::from_e8s)
+    }
+
+    /// `percent` percent of the amount, rounded down. Fails if the result
+    /// does not fit in a u64 of base units, which needs `percent` above 100.
+    pub fn percent_of(self, percent: u64) -> Option<Self> {
+        self.checked_mul_ratio(percent, 100)
+    }
+
+    /// The smaller of the two amounts.
+    pub fn min(self, other: Self) -> Self {
+        std::cmp::min(self, other)
+    }
+
+    /// The larger of the two amounts.
+    pub fn max(self, other: Self) -> Self {
+        std::cmp::max(self, other)
+    }
+
+    /// Renders the amount in whole tokens of a ledger with `decimals`
+    /// decimals, e.g. "1.50000000" for 150_000_000 base units and 8
+    /// decimals.
+    pub fn to_string_with_decimals(self, decimals: u32) -> String {
+        let one_token = match one_token(decimals) {
+            Ok(one_token) => one_token,
+            Err(_) => return format!("{} base units", self.e8s),
+        };
+        if decimals == 0 {
+            return self.e8s.to_string();
+        }
+        format!(
+            "{}.{:0width$}",
+            self.e8s / one_token,
+            self.e8s % one_token,
+            width = decimals as usize
+        )
+    }
+
+    /// Parses an amount in whole tokens of a ledger with `decimals`
+    /// decimals, e.g. "1.5", into base units. At most `decimals` digits may
+    /// follow the decimal point.
+    pub fn parse_with_decimals(s: &str, decimals: u32) -> Result<Self, String> {
+        let one_token = one_token(decimals)?;
+        let (whole, fraction) = match s.find('.') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, ""),
+        };
+        let is_number = |digits: &str| digits.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty() || !is_number(whole) || !is_number(fraction) {
+            return Err(format!("Invalid token amount: {:?}", s));
+        }
+        if fraction.len() > decimals as usize {
+            return Err(format!(
+                "Token amount {:?} has more than {} decimals",
+                s, decimals
+            ));
+        }
+        let overflow = || format!("Token amount {:?} does not fit in a u64", s);
+        let whole: u64 = whole.parse().map_err(|_| overflow())?;
+        let fraction: u64 = if fraction.is_empty() {
+            0
+        } else {
+            let scale = 10u64.pow(decimals - fraction.len() as u32);
+            fraction.parse::<u64>().map_err(|_| overflow())? * scale
+        };
+        let e8s = whole
+            .checked_mul(one_token)
+            .and_then(|e8s| e8s.checked_add(fraction))
+            .ok_or_else(overflow)?;
+        Ok(Self { e8s })
+    }
+}
+
+/// The number of base units in one whole token of a ledger with `decimals`
+/// decimals.
+fn one_token(decimals: u32) -> Result<u64, String> {
+    if decimals > MAX_DECIMAL_PLACES {
+        return Err(format!(
+            "A token can have at most {} decimals, not {}",
+            MAX_DECIMAL_PLACES, decimals
+        ));
+    }
+    Ok(10u64.pow(decimals))
+}
+
+/// Checks that a ledger can have `decimals` decimals. The fee and the
+/// minimum burn amount are fixed in base units, so they must stay below one
+/// whole token; otherwise they were most likely meant for other decimals.
+pub fn validate_decimals(decimals: u32) -> Result<(), String> {
+    let one_token = one_token(decimals)?;
+    for (name, amount) in &[
+        ("transaction fee", TRANSACTION_FEE),
+        ("minimum burn amount", MIN_BURN_AMOUNT),
+    ] {
+        if amount.get_e8s() >= one_token {
+            return Err(format!(
+                "The {} of {} base units is at least one whole token with {} decimals",
+                name,
+                amount.get_e8s(),
+                decimals
+            ));
+        }
+    }
+    Ok(())
 }
 
 impl Add for TOKENs {
@@ -124,11 +246,6 @@ impl SubAssign for TOKENs {
 
 impl fmt::Display for TOKENs {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}.{:08} TOKEN",
-            self.get_tokens(),
-            self.get_remainder_e8s()
-        )
+        write!(f, "{} TOKEN", self.to_string_with_decimals(DECIMAL_PLACES))
     }
 }
\ No newline at end of file
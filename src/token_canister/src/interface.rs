@@ -1,32 +1,79 @@
 use std::collections::{HashMap, HashSet};
 use std::time::Duration;
+use std::sync::Arc;
 
 use crate::AccountIdentifier;
 use crate::protobuf;
 use crate::{LEDGER, TOKENs};
 use crate::{MAX_MESSAGE_SIZE_BYTES, TRANSACTION_FEE, MIN_BURN_AMOUNT};
 use crate::{TimeStamp, HashOf, Subaccount, SendArgs, TransactionNotification, NotifyCanisterArgs};
+use crate::{TransferAndCallArgs, TransferAndCallNotification, TransferAndCallResult};
+use crate::{IdempotencyKey, PaymentError, TransferError};
+use crate::{validate_send_arg_size, validate_send_fields};
+use crate::signed_transfer::{SignedTransferPayload, SubmitSignedTransferArgs};
+use crate::alias::ClaimAliasArgs;
+use crate::{SweepSubaccountsArgs, MAX_SWEEP_SOURCES};
+use crate::LedgerInfo;
+use crate::MintArgs;
+use crate::minters::{Minter, SetMinterArgs};
+use crate::hooks::{HookMode, HookPhase, OperationHook, OperationHookArgs};
+use crate::staking::{
+    rewards_pool_subaccount, stake_subaccount, Stake, StakeArgs, StakeId, StakingConfig,
+    StakingEvent, UnstakeResult,
+};
+use crate::invoices::{
+    invoice_subaccount, CreateInvoiceArgs, Invoice, InvoiceEvent, InvoiceId, InvoiceStatus,
+    REFUNDS_PER_HEARTBEAT,
+};
+use crate::pending_transfers::{
+    pending_transfer_subaccount, CreatePendingTransferArgs, PendingTransfer, PendingTransferEvent,
+    PendingTransferId, FINALIZATIONS_PER_HEARTBEAT,
+};
+use crate::refunds::{RefundArgs, RefundReference};
+use crate::roles::{Role, RoleArgs, RoleAssignment};
+use crate::admin_actions::{AdminAction, PendingAdminAction};
+use crate::treasury::{TreasuryApproval, TreasuryInfo};
+use crate::endpoint_flags::SetEndpointEnabledArgs;
+use crate::deprecation::DeprecatedEndpoint;
+use crate::supply_history::{SupplyHistoryArgs, MAX_SUPPLY_CHECKPOINTS_PER_REQUEST};
+use crate::annotations::{AnnotateTransactionArgs, Annotation, GetAnnotationsArgs};
+use crate::attestation::{AttestBalanceArgs, AttestBalanceResult};
+use crate::maintenance::MaintenanceReport;
+use crate::fee_collector::{fee_collector_subaccount, CollectedFees, SweepCollectedFeesArgs};
+use crate::streams::{stream_subaccount, CreateStreamArgs, Stream, StreamEvent, StreamId};
+use crate::{AccessPolicyConfig, AccountsOverflowPolicy, ArchiveLagThrottle, PendingMintingAccountChange};
+use crate::{LedgerCanisterInitPayload, LedgerCanisterUpgradePayload};
+use crate::payment_callbacks::{
+    PaymentCallback, PendingCallback, RegisterPaymentCallbackArgs, CALLBACKS_PER_HEARTBEAT,
+    MAX_CALLBACK_METHOD_LENGTH,
+};
 use crate::{AccountBalanceArgs, TotalSupplyArgs};
 
-use crate::types::{ Memo, Transaction, Operation};
+use crate::types::{ Memo, Transaction, Operation, EncryptedMemo, MemoBlob};
 
-use crate::ic_block::{TipOfChainRes, BlockRes, BlockArg, GetBlocksArgs, IterBlocksArgs, BlockHeight, EncodedBlock, Blockchain, iter_blocks, get_blocks};
+use crate::ic_block::{MAX_BLOCKS_PER_REQUEST, TipOfChainRes, BlockRes, BlockArg, BlockLookup, GetBlocksArgs, GetArchivesArgs, IterBlocksArgs, BlockHeight, EncodedBlock, Blockchain, QueryEncodedBlocksRes, iter_blocks};
 
 use crate:: { change_notification_state};
-use crate::add_payment;
-use crate::print;
+use crate::{add_payment, PaymentOptions};
+use crate::guard::AccountGuard;
+use crate::log::{self, LogEntry, LogLevel};
+use crate::upgrade::{self, UpgradeEstimate};
+use crate::block_log::{BoundedWriter, StableBlockStorage, MAX_SERIALIZED_LEDGER_BYTES};
+use crate::health::{self, CanisterResources};
+use crate::http::{self, HttpRequest, RequestContext};
+use crate::certification::{tip_hash_tree, BlockCertificate, TipCertificate};
 
 use dfn_candid::{candid, candid_one, CandidOne};
 
-use on_wire::IntoWire;
-use ic_types::CanisterId;
+use on_wire::{FromWire, IntoWire};
+use ic_types::{CanisterId, PrincipalId};
 use ic_cdk_macros::*;
 
 use dfn_protobuf::{protobuf, ProtoBuf};
 use dfn_core::{
     api::{
-        call_bytes_with_cleanup, call_with_cleanup, caller, data_certificate, set_certified_data,
-        Funds,
+        call_bytes_with_cleanup, call_no_reply, call_with_cleanup, caller, data_certificate,
+        set_certified_data, Funds,
     },
     endpoint::over_async_may_reject_explicit,
     over, over_async, over_init, printer, setup, stable, BytesS,
@@ -46,59 +93,118 @@ use dfn_core::{
 /// * `archive_canister` - The canister that manages the store of old blocks.
 /// * `max_message_size_bytes` - The maximum message size that this subnet
 ///   supports. This is used for egressing block to the archive canister.
+/// * `transaction_window` - How long transactions are remembered for
+///   deduplication. Older transactions are rejected as expired.
+/// * `permitted_drift` - How far in the future a transaction's
+///   `created_at_time` may be relative to the ledger's clock.
+/// * `maximum_number_of_accounts` - The cap on the number of accounts with a
+///   non-zero balance.
+/// * `accounts_overflow_trim_quantity` - How many of the accounts with the
+///   lowest balances are removed when the cap is exceeded.
+/// * `accounts_overflow_policy` - Whether to trim accounts or to reject new
+///   accounts when the cap is reached.
+/// * `access_policy` - Who may send tokens and which canisters may be
+///   notified. Defaults to any non-anonymous principal.
+/// * `decimals` - How many decimals a whole token has. Defaults to
+///   `DECIMAL_PLACES`. The fee must stay below one whole token.
 // #[init]
+#[allow(clippy::too_many_arguments)]
 fn init(
     symbol: String,
     minting_account: AccountIdentifier,
     initial_values: HashMap<AccountIdentifier, TOKENs>,
     max_message_size_bytes: Option<usize>,
     transaction_window: Option<Duration>,
+    permitted_drift: Option<Duration>,
+    maximum_number_of_accounts: Option<usize>,
+    accounts_overflow_trim_quantity: Option<usize>,
+    accounts_overflow_policy: Option<AccountsOverflowPolicy>,
+    access_policy: Option<AccessPolicyConfig>,
+    decimals: Option<u32>,
 ) {
-    print(format!(
-        "[ledger] init(): minting account is {}",
+    log::info(format!(
+        "init(): minting account is {}",
         minting_account
     ));
+    let now = LEDGER.read().unwrap().now();
     LEDGER.write().unwrap().from_init(
         symbol,
         initial_values,
         minting_account,
-        dfn_core::api::now().into(),
+        now,
         transaction_window,
+        permitted_drift,
+        maximum_number_of_accounts,
+        accounts_overflow_trim_quantity,
+        accounts_overflow_policy,
+        access_policy,
+        decimals,
     );
+    LEDGER
+        .write()
+        .unwrap()
+        .blockchain
+        .set_block_storage(Arc::new(StableBlockStorage))
+        .expect("Initializing the block log failed");
     match max_message_size_bytes {
         None => {
-            print(format!(
-                "[ledger] init(): using default maximum message size: {}",
+            log::info(format!(
+                "init(): using default maximum message size: {}",
                 MAX_MESSAGE_SIZE_BYTES.read().unwrap()
             ));
         }
         Some(max_message_size_bytes) => {
             *MAX_MESSAGE_SIZE_BYTES.write().unwrap() = max_message_size_bytes;
-            print(format!(
-                "[ledger] init(): using maximum message size: {}",
+            log::info(format!(
+                "init(): using maximum message size: {}",
                 max_message_size_bytes
             ));
         }
     }
-    set_certified_data(
-        &LEDGER
-            .read()
-            .unwrap()
-            .blockchain
-            .last_hash
-            .map(|h| h.into_bytes())
-            .unwrap_or([0u8; 32]),
-    );
+    set_certified_data(&LEDGER.write().unwrap().certify());
+}
+
+#[export_name = "canister_init"]
+fn main() {
+    over_init(
+        |CandidOne(LedgerCanisterInitPayload {
+             symbol,
+             minting_account,
+             initial_values,
+             max_message_size_bytes,
+             transaction_window,
+             permitted_drift,
+             maximum_number_of_accounts,
+             accounts_overflow_trim_quantity,
+             accounts_overflow_policy,
+             access_policy,
+             decimals,
+         })| {
+            init(
+                symbol,
+                minting_account,
+                initial_values,
+                max_message_size_bytes,
+                transaction_window,
+                permitted_drift,
+                maximum_number_of_accounts,
+                accounts_overflow_trim_quantity,
+                accounts_overflow_policy,
+                access_policy,
+                decimals,
+            )
+        },
+    )
 }
 
 fn add_payments(
     memo: Memo,
     operation: Operation,
-    created_at_time: Option<TimeStamp>,
-) -> (BlockHeight, HashOf<EncodedBlock>) {
-    let (height, hash) = add_payment(memo, operation, created_at_time);
-    set_certified_data(&hash.into_bytes());
-    (height, hash)
+    options: PaymentOptions,
+) -> Result<(BlockHeight, HashOf<EncodedBlock>), PaymentError> {
+    let (height, hash) = add_payment(memo, operation, options)?;
+    set_certified_data(&LEDGER.write().unwrap().certify());
+    Ok((height, hash))
 }
 
 /// This is the only operation that changes the state of the canister blocks and
@@ -118,43 +224,386 @@ fn add_payments(
 /// * `from_subaccount` - The subaccount you want to draw funds from
 /// * `to` - The account you want to send the funds to
 /// * `to_subaccount` - The subaccount you want to send funds to
-pub async fn send(
+/// * `idempotency_key` - An optional key identifying this transfer. If a
+///   transfer with the same key was already made from the same account within
+///   the transaction window, its block height is returned and no new transfer
+///   is made
+/// * `encrypted_memo` - An optional opaque blob, encrypted by the sender for
+///   the recipient, that is stored in the block alongside the memo
+/// * `expires_at` - An optional time after which the transfer must not be
+///   executed anymore, e.g. because the wallet that queued it gave up
+/// * `memo_blob` - An optional blob stored in the block, e.g. to tag the
+///   endpoint the transfer was made through
+#[allow(clippy::too_many_arguments)]
+pub async fn transfer(
     memo: Memo,
     amount: TOKENs,
     fee: TOKENs,
     from_subaccount: Option<Subaccount>,
     to: AccountIdentifier,
     created_at_time: Option<TimeStamp>,
+    expires_at: Option<TimeStamp>,
+    idempotency_key: Option<IdempotencyKey>,
+    encrypted_memo: Option<EncryptedMemo>,
+    memo_blob: Option<MemoBlob>,
 ) -> BlockHeight {
     let caller_principal_id = caller();
+    check_can_send(&caller_principal_id);
 
-    if !LEDGER.read().unwrap().can_send(&caller_principal_id) {
+    let from = AccountIdentifier::new(caller_principal_id, from_subaccount);
+
+    validate_send_fields(idempotency_key.as_ref(), encrypted_memo.as_ref())
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    // Another send from the same account must not run while this one awaits
+    // its hooks, as it could use up the balance or the idempotency key.
+    let guard = AccountGuard::acquire(from).unwrap_or_else(|e| panic!("{}", e));
+
+    if let Some(key) = &idempotency_key {
+        if let Some(height) = LEDGER.read().unwrap().find_by_idempotency_key(&from, key) {
+            return height;
+        }
+    }
+
+    let minting_acc = LEDGER
+        .read()
+        .unwrap()
+        .minting_account_id
+        .expect("Minting canister id not initialized");
+    let transfer = operation_for(from, to, amount, fee);
+    run_operation_hooks(HookPhase::Before, &transfer, memo, None)
+        .await
+        .unwrap_or_else(|e| panic!("Transfer rejected: {}", e));
+
+    // Commit point: nothing from here until the block is added awaits, so
+    // the checks below see the state the block is applied to. Whatever may
+    // have changed while the hooks ran is checked again.
+    check_can_send(&caller_principal_id);
+    check_minting_account_unchanged(minting_acc);
+    record_spend(&caller_principal_id, amount, fee);
+    // The ledger rejects debits from guarded accounts, this one included.
+    drop(guard);
+
+    let (height, _) = add_payments(
+        memo,
+        transfer.clone(),
+        PaymentOptions {
+            created_at_time,
+            expires_at,
+            idempotency_key: idempotency_key.map(|key| (from, key)),
+            encrypted_memo,
+            memo_blob,
+        },
+    )
+    .unwrap_or_else(|e| panic!("Transfer failed: {}", e));
+    // Don't put anything that could ever trap after this call or people using this
+    // endpoint. If something did panic the payment would appear to fail, but would
+    // actually succeed on chain.
+    run_after_hooks(&transfer, memo, height).await;
+    // archive_blocks().await;
+    height
+}
+
+/// Transfers `amount` to the account of `to_canister` and then calls its
+/// `method` with a `TransferAndCallNotification` holding the block height
+/// and `args`. The transfer stands whatever the call returns; its reply, or
+/// why it failed, is returned along with the block height.
+///
+/// `to_canister` must be allowed to be notified by the access policy.
+async fn transfer_and_call(
+    TransferAndCallArgs {
+        memo,
+        amount,
+        fee,
+        from_subaccount,
+        to_canister,
+        to_subaccount,
+        method,
+        args,
+    }: TransferAndCallArgs,
+) -> TransferAndCallResult {
+    if method.is_empty() || method.len() > MAX_CALLBACK_METHOD_LENGTH {
+        panic!(
+            "Method names must be between 1 and {} characters long",
+            MAX_CALLBACK_METHOD_LENGTH
+        );
+    }
+    if !LEDGER.read().unwrap().can_be_notified(&to_canister.get()) {
+        panic!(
+            "Notifying {} is not allowed by the access policy of this ledger",
+            to_canister
+        );
+    }
+    let from = caller();
+    let to = AccountIdentifier::new(to_canister.get(), to_subaccount);
+    let block_height = transfer(
+        memo,
+        amount,
+        fee,
+        from_subaccount,
+        to,
+        None,
+        None,
+        None,
+        None,
+        None,
+    )
+    .await;
+
+    // Nothing may trap from here on, or the transfer would appear to fail.
+    let notification = TransferAndCallNotification {
+        transaction: TransactionNotification {
+            from,
+            from_subaccount,
+            to: to_canister,
+            to_subaccount,
+            block_height,
+            amount,
+            memo,
+        },
+        args,
+    };
+    let call_result = match candid::encode_one(&notification) {
+        Ok(bytes) => call_bytes_with_cleanup(to_canister, &method, &bytes, Funds::zero())
+            .await
+            .map_err(|(code, msg)| {
+                log::warn(format!(
+                    "transfer_and_call to {} for block {} failed with code {:?}: {}",
+                    to_canister, block_height, code, msg
+                ));
+                format!("Calling {} failed with code {:?}: {}", method, code, msg)
+            }),
+        Err(e) => Err(format!("Encoding the notification failed: {}", e)),
+    };
+    TransferAndCallResult {
+        block_height,
+        call_result,
+    }
+}
+
+/// Checks whether `account` holds at least `min_amount` and calls the
+/// calling canister's `callback` method with the `BalanceAttestation`, which
+/// is also returned along with the outcome of the call.
+async fn attest_balance(
+    AttestBalanceArgs {
+        account,
+        min_amount,
+        callback,
+        nonce,
+    }: AttestBalanceArgs,
+) -> AttestBalanceResult {
+    let canister_id = caller();
+    if canister_id.is_self_authenticating() || canister_id.is_anonymous() {
+        panic!("Only canisters can request balance attestations");
+    }
+    if callback.is_empty() || callback.len() > MAX_CALLBACK_METHOD_LENGTH {
+        panic!(
+            "Method names must be between 1 and {} characters long",
+            MAX_CALLBACK_METHOD_LENGTH
+        );
+    }
+    let attestation = {
+        let ledger = LEDGER.read().unwrap();
+        ledger.attest_balance(account, min_amount, nonce, ledger.now())
+    };
+    let canister_id = CanisterId::new(canister_id).unwrap();
+    let call_result: Result<(), String> =
+        call_with_cleanup(canister_id, &callback, candid_one, attestation.clone())
+            .await
+            .map_err(|(code, msg)| {
+                format!("Calling {} failed with code {:?}: {}", callback, code, msg)
+            });
+    AttestBalanceResult {
+        attestation,
+        call_result,
+    }
+}
+
+/// Panics unless the caller is the controller, which holds every role, or was
+/// granted `role`. `action` completes the message of the panic.
+fn check_role(role: Role, action: &str) {
+    let caller = caller();
+    if caller != dfn_core::api::controller()
+        && !LEDGER.read().unwrap().roles.has_role(&caller, role)
+    {
+        panic!(
+            "Only the controller or a principal with the {} role can {}",
+            role, action
+        );
+    }
+}
+
+/// Panics with `EndpointDisabled` if the controller disabled `endpoint`.
+fn check_endpoint_enabled(endpoint: &str) {
+    let ledger = LEDGER.read().unwrap();
+    if let Err(e) = ledger.endpoint_flags.check_enabled(endpoint) {
+        panic!("{}", e);
+    }
+}
+
+/// Panics unless the caller may call queries: reads must not be restricted,
+/// or the caller must be the controller or on the reader list.
+fn check_can_read() {
+    let caller = caller();
+    if caller != dfn_core::api::controller() && !LEDGER.read().unwrap().readers.can_read(&caller) {
+        panic!("Unauthorized: {} may not read from this ledger", caller);
+    }
+}
+
+/// Panics unless `caller_principal_id` may send right now: the access policy
+/// must allow it, the ledger must not be read-only, and only the controller
+/// may send while the ledger is throttled.
+fn check_can_send(caller_principal_id: &PrincipalId) {
+    if !LEDGER.read().unwrap().can_send(caller_principal_id) {
         panic!(
-            "Sending from non-self-authenticating principal or non-whitelisted canister is not allowed: {}",
+            "Sending from {} is not allowed by the access policy of this ledger",
             caller_principal_id
         );
     }
 
-    let from = AccountIdentifier::new(caller_principal_id, from_subaccount);
+    if let Some(until) = LEDGER.read().unwrap().read_only_until() {
+        panic!("{}", TransferError::Migrating { until });
+    }
+
+    if let Some(reason) = LEDGER.read().unwrap().corruption() {
+        panic!(
+            "{}",
+            TransferError::LedgerCorrupted {
+                reason: reason.to_string()
+            }
+        );
+    }
+
+    // Only the controller may mint or burn through send while the ledger is
+    // throttled; everybody else's transfers are low priority.
+    if LEDGER.read().unwrap().is_throttled() && *caller_principal_id != dfn_core::api::controller()
+    {
+        panic!("{}", TransferError::TemporarilyUnavailable);
+    }
+}
+
+/// Panics if a blocking hook has to approve `operation`. Endpoints that
+/// apply operations without awaiting their hooks call this first.
+fn check_no_blocking_hooks(operation: &Operation) {
+    LEDGER
+        .read()
+        .unwrap()
+        .operation_hooks
+        .check_unhooked(operation)
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+/// Calls the hooks configured for `phase` of `operation`. Blocking hooks are
+/// awaited in order and the first rejection is returned; fire-and-forget
+/// hooks are called without waiting and their outcome is ignored.
+///
+/// Note that state changes made before a blocking hook are committed when
+/// the ledger waits for it.
+async fn run_operation_hooks(
+    phase: HookPhase,
+    operation: &Operation,
+    memo: Memo,
+    block_height: Option<BlockHeight>,
+) -> Result<(), String> {
+    let hooks = LEDGER
+        .read()
+        .unwrap()
+        .operation_hooks
+        .matching(operation, phase);
+    for hook in hooks {
+        let args = OperationHookArgs {
+            phase,
+            operation: operation.clone(),
+            memo,
+            block_height,
+        };
+        match hook.mode {
+            HookMode::FireAndForget => {
+                dfn_core::api::futures::spawn(async move {
+                    let _: Result<(), (Option<i32>, String)> =
+                        call_with_cleanup(hook.canister_id, &hook.method, candid_one, args)
+                            .await;
+                });
+            }
+            HookMode::Blocking => {
+                let result: Result<Result<(), String>, (Option<i32>, String)> =
+                    call_with_cleanup(hook.canister_id, &hook.method, candid_one, args).await;
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(msg)) => {
+                        return Err(format!(
+                            "hook {} of canister {} rejected the operation: {}",
+                            hook.method, hook.canister_id, msg
+                        ))
+                    }
+                    Err((code, msg)) => {
+                        return Err(format!(
+                            "calling hook {} of canister {} failed with code {:?}: {}",
+                            hook.method, hook.canister_id, code, msg
+                        ))
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the `After` hooks of an operation that was applied in block
+/// `height`. The operation can no longer be undone, so failures are only
+/// logged.
+async fn run_after_hooks(operation: &Operation, memo: Memo, height: BlockHeight) {
+    if let Err(e) = run_operation_hooks(HookPhase::After, operation, memo, Some(height)).await {
+        log::warn(format!("after-hook for block {} failed: {}", height, e));
+    }
+}
+
+/// Counts `amount + fee` against the spend cap of `principal_id`, panicking
+/// if the cap is exceeded.
+fn record_spend(principal_id: &PrincipalId, amount: TOKENs, fee: TOKENs) {
+    let total = (amount + fee).unwrap_or_else(|e| panic!("{}", e));
+    LEDGER
+        .write()
+        .unwrap()
+        .record_spend(principal_id, total)
+        .unwrap_or_else(|e| panic!("Spend cap exceeded: {}", e));
+}
+
+/// Classifies a payment from `from` to `to` as a mint, burn or transfer,
+/// panicking if the fee or amount is not allowed for that kind of operation.
+fn operation_for(
+    from: AccountIdentifier,
+    to: AccountIdentifier,
+    amount: TOKENs,
+    fee: TOKENs,
+) -> Operation {
     let minting_acc = LEDGER
         .read()
         .unwrap()
         .minting_account_id
         .expect("Minting canister id not initialized");
 
-    let transfer = if from == minting_acc {
+    if from == minting_acc {
         assert_eq!(fee, TOKENs::ZERO, "Fee for minting should be zero");
         assert_ne!(
             to, minting_acc,
             "It is illegal to mint to a minting_account"
         );
-        Operation::Mint { to, amount }
+        Operation::Mint {
+            to,
+            amount,
+            fee: TOKENs::ZERO,
+        }
     } else if to == minting_acc {
         assert_eq!(fee, TOKENs::ZERO, "Fee for burning should be zero");
         if amount < MIN_BURN_AMOUNT {
             panic!("Burns lower than {} are not allowed", MIN_BURN_AMOUNT);
         }
-        Operation::Burn { from, amount }
+        Operation::Burn {
+            from,
+            amount,
+            fee: TOKENs::ZERO,
+        }
     } else {
         if fee != TRANSACTION_FEE {
             panic!("Transaction fee should be {}", TRANSACTION_FEE);
@@ -165,136 +614,2246 @@ pub async fn send(
             amount,
             fee,
         }
-    };
-    let (height, _) = add_payments(memo, transfer, created_at_time);
-    // Don't put anything that could ever trap after this call or people using this
-    // endpoint. If something did panic the payment would appear to fail, but would
-    // actually succeed on chain.
-    // archive_blocks().await;
-    height
+    }
 }
 
-/// This gives you the index of the last block added to the chain
-/// together with certification
-fn tip_of_chain() -> TipOfChainRes {
-    let last_block_idx = &LEDGER
+/// Mints tokens, tagging the mint block with the reason for the issuance.
+/// The caller must either own the minting account or be a minter authorized
+/// by the controller, in which case the mint counts against its quota.
+async fn mint(
+    MintArgs {
+        memo,
+        amount,
+        from_subaccount,
+        to,
+        created_at_time,
+        reason,
+    }: MintArgs,
+) -> BlockHeight {
+    let caller_principal_id = caller();
+    let from = AccountIdentifier::new(caller_principal_id, from_subaccount);
+    let minting_acc = LEDGER
         .read()
         .unwrap()
-        .blockchain
-        .chain_length()
-        .checked_sub(1)
-        .unwrap();
-    let certification = data_certificate();
-    TipOfChainRes {
-        certification,
-        tip_index: *last_block_idx,
+        .minting_account_id
+        .expect("Minting canister id not initialized");
+    let delegated = from != minting_acc;
+    let check_minter = || {
+        if delegated {
+            let ledger = LEDGER.read().unwrap();
+            ledger
+                .minters
+                .check_mint(&caller_principal_id, amount, ledger.now())
+                .unwrap_or_else(|e| panic!("{}", e));
+        }
+    };
+    check_minter();
+    if to == minting_acc {
+        panic!("It is illegal to mint to a minting_account");
     }
-}
+    let memo_blob = reason.to_memo_blob().unwrap_or_else(|e| panic!("{}", e));
+    let operation = Operation::Mint {
+        to,
+        amount,
+        fee: TOKENs::ZERO,
+    };
+    run_operation_hooks(HookPhase::Before, &operation, memo, None)
+        .await
+        .unwrap_or_else(|e| panic!("Mint rejected: {}", e));
+    // The quota may have been used up while waiting for the hooks.
+    check_minter();
+    check_minting_account_unchanged(minting_acc);
 
-// This is going away and being replaced by getblocks
-fn block(block_index: BlockHeight) -> Option<Result<EncodedBlock, CanisterId>> {
-    let state = LEDGER.read().unwrap();
-    // if block_index < state.blockchain.num_archived_blocks() {
-        // The block we are looking for better be in the archive because it has
-        // a height smaller than the number of blocks we've archived so far
-        // let result = state
-        //     .find_block_in_archive(block_index)
-        //     .expect("block not found in the archive");
-        // Some(Err(result))
-    // Or the block may be in the ledger, or the block may not exist
-    // } else {
-        print(format!(
-            "[ledger] Checking the ledger for block [{}]",
-            block_index
-        ));
-        state.blockchain.get(block_index).cloned().map(Ok)
-    // }
+    let (height, _) = add_payments(
+        memo,
+        operation.clone(),
+        PaymentOptions {
+            created_at_time,
+            memo_blob: Some(memo_blob),
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("Mint failed: {}", e));
+    if delegated {
+        LEDGER
+            .write()
+            .unwrap()
+            .minters
+            .record_mint(&caller_principal_id, amount);
+    }
+    run_after_hooks(&operation, memo, height).await;
+    height
 }
 
-/// Get an account balance.
-/// If the account does not exist it will return 0 ICPTs
-fn account_balance(account: AccountIdentifier) -> TOKENs {
-    LEDGER.read().unwrap().balances.account_balance(&account)
+/// Locks `amount` of the caller's tokens for `duration` in a subaccount of
+/// the ledger, in exchange for a reward paid out by `unstake`. The staker
+/// pays the transaction fee of the lock. Returns the id of the stake.
+fn stake(
+    StakeArgs {
+        amount,
+        duration,
+        from_subaccount,
+    }: StakeArgs,
+) -> StakeId {
+    let caller_principal_id = caller();
+    if !LEDGER.read().unwrap().can_send(&caller_principal_id) {
+        panic!(
+            "Sending from {} is not allowed by the access policy of this ledger",
+            caller_principal_id
+        );
+    }
+    let from = AccountIdentifier::new(caller_principal_id, from_subaccount);
+    let pool = AccountIdentifier::new(dfn_core::api::id().get(), Some(rewards_pool_subaccount()));
+    let stake = {
+        let mut ledger = LEDGER.write().unwrap();
+        let rewards_pool = ledger.balances.account_balance(&pool);
+        let now = ledger.now();
+        ledger
+            .staking
+            .open(
+                caller_principal_id,
+                from,
+                amount,
+                duration,
+                rewards_pool,
+                now,
+            )
+            .unwrap_or_else(|e| panic!("{}", e))
+    };
+    let locked = AccountIdentifier::new(
+        dfn_core::api::id().get(),
+        Some(stake_subaccount(stake.id)),
+    );
+    let lock = Operation::Transfer {
+        from,
+        to: locked,
+        amount,
+        fee: TRANSACTION_FEE,
+    };
+    check_no_blocking_hooks(&lock);
+    add_payments(
+        Memo(stake.id),
+        lock,
+        PaymentOptions {
+            memo_blob: Some(StakingEvent::Lock { stake_id: stake.id }.to_memo_blob()),
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("Locking stake {} failed: {}", stake.id, e));
+    stake.id
 }
 
-/// The total number of ICPTs not inside the minting canister
-fn total_supply() -> TOKENs {
-    LEDGER.read().unwrap().balances.total_supply()
+/// Returns an unlocked stake to the account it came from, together with its
+/// reward. The reward is paid from the rewards pool and capped by the pool's
+/// balance.
+fn unstake(id: StakeId) -> UnstakeResult {
+    let now = LEDGER.read().unwrap().now();
+    let stake = LEDGER
+        .write()
+        .unwrap()
+        .staking
+        .close(&caller(), id, now)
+        .unwrap_or_else(|e| panic!("{}", e));
+    let ledger_principal_id = dfn_core::api::id().get();
+
+    let unlock = Operation::Transfer {
+        from: AccountIdentifier::new(ledger_principal_id, Some(stake_subaccount(id))),
+        to: stake.account,
+        amount: stake.amount,
+        fee: TOKENs::ZERO,
+    };
+    let (unlock_block, _) = add_payments(
+        Memo(id),
+        unlock,
+        PaymentOptions {
+            memo_blob: Some(StakingEvent::Unlock { stake_id: id }.to_memo_blob()),
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("Unlocking stake {} failed: {}", id, e));
+
+    let pool = AccountIdentifier::new(ledger_principal_id, Some(rewards_pool_subaccount()));
+    let reward = stake.reserved_reward;
+    let reward_block = if reward > TOKENs::ZERO {
+        let payout = Operation::Transfer {
+            from: pool,
+            to: stake.account,
+            amount: reward,
+            fee: TOKENs::ZERO,
+        };
+        let (height, _) = add_payments(
+            Memo(id),
+            payout,
+            PaymentOptions {
+                memo_blob: Some(StakingEvent::Reward { stake_id: id }.to_memo_blob()),
+                ..Default::default()
+            },
+        )
+        .unwrap_or_else(|e| panic!("Paying the reward of stake {} failed: {}", id, e));
+        Some(height)
+    } else {
+        None
+    };
+
+    UnstakeResult {
+        unlock_block,
+        reward,
+        reward_block,
+    }
 }
 
-/// Canister endpoints
-#[update]
-fn send_() {
-    over_async(
-        protobuf,
-        |SendArgs {
-             memo,
-             amount,
-             fee,
-             from_subaccount,
-             to,
-             created_at_time,
-         }| { send(memo, amount, fee, from_subaccount, to, created_at_time) },
+/// Starts streaming `deposit` from the caller to `to` at `rate_per_second`.
+/// The deposit is moved into a subaccount of the ledger, for which the
+/// caller pays the transaction fee. Returns the id of the stream.
+fn create_stream(
+    CreateStreamArgs {
+        to,
+        to_subaccount,
+        rate_per_second,
+        deposit,
+        from_subaccount,
+    }: CreateStreamArgs,
+) -> StreamId {
+    let caller_principal_id = caller();
+    if !LEDGER.read().unwrap().can_send(&caller_principal_id) {
+        panic!(
+            "Sending from {} is not allowed by the access policy of this ledger",
+            caller_principal_id
+        );
+    }
+    let from = AccountIdentifier::new(caller_principal_id, from_subaccount);
+    let now = LEDGER.read().unwrap().now();
+    let stream = LEDGER
+        .write()
+        .unwrap()
+        .streams
+        .open(
+            caller_principal_id,
+            from,
+            to,
+            AccountIdentifier::new(to, to_subaccount),
+            rate_per_second,
+            deposit,
+            now,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+    let escrow = AccountIdentifier::new(
+        dfn_core::api::id().get(),
+        Some(stream_subaccount(stream.id)),
     );
+    let transfer = Operation::Transfer {
+        from,
+        to: escrow,
+        amount: deposit,
+        fee: TRANSACTION_FEE,
+    };
+    check_no_blocking_hooks(&transfer);
+    add_payments(
+        Memo(stream.id),
+        transfer,
+        PaymentOptions {
+            memo_blob: Some(
+                StreamEvent::Deposit {
+                    stream_id: stream.id,
+                }
+                .to_memo_blob(),
+            ),
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("Depositing into stream {} failed: {}", stream.id, e));
+    stream.id
 }
 
-/// Do not use call this from code, this is only here so dfx has something to
-/// call when making a payment. This will be changed in ways that are not
-/// backwards compatible with previous interfaces.
-///
-/// I STRONGLY recommend that you use "send_pb" instead.
-#[export_name = "canister_update send_dfx"]
-fn send_dfx_() {
-    over_async(
-        candid_one,
-        |SendArgs {
-             memo,
-             amount,
-             fee,
-             from_subaccount,
-             to,
-             created_at_time,
-         }| { send(memo, amount, fee, from_subaccount, to, created_at_time) },
-    );
+/// Pays out a settlement of `amount` from the deposit of stream `id` to `to`.
+fn settle_stream(
+    id: StreamId,
+    to: AccountIdentifier,
+    amount: TOKENs,
+    event: StreamEvent,
+) -> BlockHeight {
+    let escrow = AccountIdentifier::new(dfn_core::api::id().get(), Some(stream_subaccount(id)));
+    let (height, _) = add_payments(
+        Memo(id),
+        Operation::Transfer {
+            from: escrow,
+            to,
+            amount,
+            fee: TOKENs::ZERO,
+        },
+        PaymentOptions {
+            memo_blob: Some(event.to_memo_blob()),
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("Settling stream {} failed: {}", id, e));
+    height
 }
 
-#[export_name = "canister_query block_pb"]
-fn block_() {
-    over(protobuf, |BlockArg(height)| BlockRes(block(height)));
+/// Pays the recipient of stream `id` everything that vested so far.
+fn withdraw_from_stream(id: StreamId) -> BlockHeight {
+    let now = LEDGER.read().unwrap().now();
+    let (stream, amount) = LEDGER
+        .write()
+        .unwrap()
+        .streams
+        .withdraw(&caller(), id, now)
+        .unwrap_or_else(|e| panic!("{}", e));
+    if amount == TOKENs::ZERO {
+        panic!("Nothing has vested in stream {} yet", id);
+    }
+    settle_stream(
+        id,
+        stream.recipient_account,
+        amount,
+        StreamEvent::Withdraw { stream_id: id },
+    )
 }
 
-#[export_name = "canister_query tip_of_chain_pb"]
-fn tip_of_chain_() {
-    over(protobuf, |protobuf::TipOfChainRequest {}| tip_of_chain());
+/// Ends stream `id`: the recipient is paid what vested so far and the rest
+/// of the deposit returns to the sender. Returns the settlement blocks.
+fn cancel_stream(id: StreamId) -> Vec<BlockHeight> {
+    let now = LEDGER.read().unwrap().now();
+    let (stream, owed, refund) = LEDGER
+        .write()
+        .unwrap()
+        .streams
+        .cancel(&caller(), id, now)
+        .unwrap_or_else(|e| panic!("{}", e));
+    let mut heights = vec![];
+    if owed > TOKENs::ZERO {
+        heights.push(settle_stream(
+            id,
+            stream.recipient_account,
+            owed,
+            StreamEvent::Withdraw { stream_id: id },
+        ));
+    }
+    if refund > TOKENs::ZERO {
+        heights.push(settle_stream(
+            id,
+            stream.sender_account,
+            refund,
+            StreamEvent::Refund { stream_id: id },
+        ));
+    }
+    heights
 }
 
-#[export_name = "canister_query account_balance_pb"]
-fn account_balance_() {
-    over(protobuf, |AccountBalanceArgs { account }| {
-        account_balance(account)
-    })
+/// Moves `amount` from the caller to a holding account of the ledger, from
+/// which it is paid to the recipient once the cancellation window is over.
+/// Until then the caller can take it back with `cancel_pending_transfer`.
+fn create_pending_transfer(
+    CreatePendingTransferArgs {
+        to,
+        to_subaccount,
+        amount,
+        memo,
+        from_subaccount,
+        cancellation_window_seconds,
+    }: CreatePendingTransferArgs,
+) -> PendingTransferId {
+    let caller_principal_id = caller();
+    if !LEDGER.read().unwrap().can_send(&caller_principal_id) {
+        panic!(
+            "Sending from {} is not allowed by the access policy of this ledger",
+            caller_principal_id
+        );
+    }
+    let from = AccountIdentifier::new(caller_principal_id, from_subaccount);
+    let now = LEDGER.read().unwrap().now();
+    let transfer = LEDGER
+        .write()
+        .unwrap()
+        .pending_transfers
+        .create(
+            caller_principal_id,
+            from,
+            to,
+            AccountIdentifier::new(to, to_subaccount),
+            amount,
+            memo,
+            Duration::from_secs(cancellation_window_seconds),
+            now,
+        )
+        .unwrap_or_else(|e| panic!("{}", e));
+    record_spend(&caller_principal_id, amount, TRANSACTION_FEE);
+    let holding = AccountIdentifier::new(
+        dfn_core::api::id().get(),
+        Some(pending_transfer_subaccount(transfer.id)),
+    );
+    let hold = Operation::Transfer {
+        from,
+        to: holding,
+        amount,
+        fee: TRANSACTION_FEE,
+    };
+    check_no_blocking_hooks(&hold);
+    add_payments(
+        memo,
+        hold,
+        PaymentOptions {
+            memo_blob: Some(
+                PendingTransferEvent::Hold {
+                    pending_transfer_id: transfer.id,
+                }
+                .to_memo_blob(),
+            ),
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("Holding pending transfer {} failed: {}", transfer.id, e));
+    transfer.id
 }
 
-/// See caveats of use on send_dfx
-#[export_name = "canister_query account_balance_dfx"]
-fn account_balance_dfx_() {
-    over(candid_one, |AccountBalanceArgs { account }| {
-        account_balance(account)
-    })
+/// Pays the amount of a pending transfer out of its holding account, to the
+/// recipient when it is finalized and back to the sender when it is
+/// cancelled.
+fn settle_pending_transfer(
+    transfer: &PendingTransfer,
+    event: PendingTransferEvent,
+) -> Result<BlockHeight, PaymentError> {
+    let mut ledger = LEDGER.write().unwrap();
+    let (height, _) = ledger.settle_pending_transfer(dfn_core::api::id().get(), transfer, event)?;
+    set_certified_data(&ledger.certify());
+    Ok(height)
 }
 
-#[export_name = "canister_query total_supply_pb"]
-fn total_supply_() {
-    over(protobuf, |_: TotalSupplyArgs| total_supply())
+/// Returns the amount of pending transfer `id` to the caller, its sender,
+/// while the cancellation window is open.
+fn cancel_pending_transfer(id: PendingTransferId) -> BlockHeight {
+    let now = LEDGER.read().unwrap().now();
+    let transfer = LEDGER
+        .write()
+        .unwrap()
+        .pending_transfers
+        .cancel(&caller(), id, now)
+        .unwrap_or_else(|e| panic!("{}", e));
+    settle_pending_transfer(
+        &transfer,
+        PendingTransferEvent::Cancel {
+            pending_transfer_id: id,
+        },
+    )
+    .unwrap_or_else(|e| panic!("Cancelling pending transfer {} failed: {}", id, e))
 }
 
-/// Get multiple blocks by *offset into the container* (not BlockHeight) and
+/// Pays pending transfer `id` to the caller, its recipient, once the
+/// cancellation window is over, without waiting for the heartbeat to do it.
+fn finalize_pending_transfer(id: PendingTransferId) -> BlockHeight {
+    let now = LEDGER.read().unwrap().now();
+    let transfer = LEDGER
+        .write()
+        .unwrap()
+        .pending_transfers
+        .finalize(&caller(), id, now)
+        .unwrap_or_else(|e| panic!("{}", e));
+    settle_pending_transfer(
+        &transfer,
+        PendingTransferEvent::Finalize {
+            pending_transfer_id: id,
+        },
+    )
+    .unwrap_or_else(|e| panic!("Finalizing pending transfer {} failed: {}", id, e))
+}
+
+/// Pays out the pending transfers whose cancellation window is over. Those
+/// that fail stay pending and are retried by the next heartbeat.
+fn finalize_due_pending_transfers() {
+    let mut ledger = LEDGER.write().unwrap();
+    let now = ledger.now();
+    let results = ledger.finalize_due_pending_transfers(
+        dfn_core::api::id().get(),
+        now,
+        FINALIZATIONS_PER_HEARTBEAT,
+    );
+    let mut finalized = false;
+    for (transfer, result) in results {
+        match result {
+            Ok(_) => finalized = true,
+            Err(e) => log::error(format!(
+                "finalizing pending transfer {} of {} to {} failed: {}",
+                transfer.id, transfer.amount, transfer.recipient_account, e
+            )),
+        }
+    }
+    if finalized {
+        set_certified_data(&ledger.certify());
+    }
+}
+
+/// Creates an invoice for `amount`, payable until `expires_at` to a
+/// dedicated deposit account of the ledger. Once it is fully paid the
+/// caller can collect the payment with `claim_invoice`.
+fn create_invoice(
+    CreateInvoiceArgs {
+        amount,
+        memo,
+        expires_at,
+        to_subaccount,
+    }: CreateInvoiceArgs,
+) -> Invoice {
+    let caller_principal_id = caller();
+    let mut ledger = LEDGER.write().unwrap();
+    let deposit_account = AccountIdentifier::new(
+        dfn_core::api::id().get(),
+        Some(invoice_subaccount(ledger.invoices.next_id())),
+    );
+    let now = ledger.now();
+    ledger
+        .invoices
+        .create(
+            caller_principal_id,
+            AccountIdentifier::new(caller_principal_id, to_subaccount),
+            amount,
+            memo,
+            expires_at,
+            deposit_account,
+            now,
+        )
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Pays the payments of a fully paid invoice out to the merchant.
+fn claim_invoice(id: InvoiceId) -> BlockHeight {
+    let invoice = LEDGER
+        .write()
+        .unwrap()
+        .invoices
+        .claim(&caller(), id)
+        .unwrap_or_else(|e| panic!("{}", e));
+    let payout = Operation::Transfer {
+        from: invoice.deposit_account,
+        to: invoice.merchant_account,
+        amount: invoice.paid,
+        fee: TOKENs::ZERO,
+    };
+    check_no_blocking_hooks(&payout);
+    let (height, _) = add_payments(
+        invoice.memo,
+        payout,
+        PaymentOptions {
+            memo_blob: Some(InvoiceEvent::Claim { invoice_id: id }.to_memo_blob()),
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("Claiming invoice {} failed: {}", id, e));
+    height
+}
+
+/// Returns `amount` of the transfer at `original_height` to its payer. Only
+/// the recipient may do this, at most up to the amount it received, and only
+/// while the ledger still holds the original block. The refund block carries
+/// the memo of the original transfer and a `RefundReference` to it in its
+/// memo blob.
+fn refund(
+    RefundArgs {
+        original_height,
+        amount,
+        subaccount,
+    }: RefundArgs,
+) -> BlockHeight {
+    let caller_principal_id = caller();
+    if !LEDGER.read().unwrap().can_send(&caller_principal_id) {
+        panic!(
+            "Sending from {} is not allowed by the access policy of this ledger",
+            caller_principal_id
+        );
+    }
+    let recipient = AccountIdentifier::new(caller_principal_id, subaccount);
+    let (operation, memo) = {
+        let ledger = LEDGER.read().unwrap();
+        let original = match ledger.blockchain.get(original_height) {
+            Some(encoded) => encoded.decode().unwrap_or_else(|e| panic!("{}", e)),
+            None => panic!("Block {} is not held by the ledger", original_height),
+        };
+        let operation = ledger
+            .refunds
+            .refund_operation(
+                original_height,
+                &original.transaction.operation,
+                &recipient,
+                amount,
+                TRANSACTION_FEE,
+            )
+            .unwrap_or_else(|e| panic!("{}", e));
+        (operation, original.transaction.memo)
+    };
+
+    check_no_blocking_hooks(&operation);
+    record_spend(&caller_principal_id, amount, TRANSACTION_FEE);
+    let (height, _) = add_payments(
+        memo,
+        operation,
+        PaymentOptions {
+            memo_blob: Some(RefundReference { original_height }.to_memo_blob()),
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("Refund failed: {}", e));
+
+    let mut ledger = LEDGER.write().unwrap();
+    let first_local_height = ledger.blockchain.first_local_height();
+    if let Err(e) = ledger
+        .refunds
+        .record(original_height, amount, first_local_height)
+    {
+        log::error(format!(
+            "recording the refund of block {} failed: {}",
+            original_height, e
+        ));
+    }
+    height
+}
+
+/// Closes expired invoices and returns late payments, and partial payments
+/// of expired invoices, to their payers.
+fn process_invoice_refunds() {
+    let mut ledger = LEDGER.write().unwrap();
+    let now = ledger.now();
+    let results = ledger.process_invoice_refunds(now, REFUNDS_PER_HEARTBEAT);
+    let mut refunded = false;
+    for (refund, result) in results {
+        match result {
+            Ok(_) => refunded = true,
+            Err(e) => log::error(format!(
+                "refunding {} to {} for invoice {} failed: {}",
+                refund.amount, refund.to, refund.invoice_id, e
+            )),
+        }
+    }
+    if refunded {
+        set_certified_data(&ledger.certify());
+    }
+}
+
+/// Enables staking with the given parameters, or disables new stakes with
+/// `None`. Only the controller and admins may do this.
+fn set_staking_config(config: Option<StakingConfig>) {
+    check_role(Role::Admin, "configure staking");
+    LEDGER
+        .write()
+        .unwrap()
+        .staking
+        .set_config(config)
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+/// Replaces the hooks called around mints, burns and transfers. Only the
+/// controller and admins may do this.
+fn set_operation_hooks(hooks: Vec<OperationHook>) {
+    check_role(Role::Admin, "configure operation hooks");
+    LEDGER
+        .write()
+        .unwrap()
+        .operation_hooks
+        .set(hooks)
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+/// Queues `action`, which can be executed once the admin action delay is
+/// over. Only the controller and the principals with the role of the action
+/// may do this.
+fn propose_admin_action(action: AdminAction) -> PendingAdminAction {
+    check_role(action.role(), "propose this admin action");
+    let pending = LEDGER
+        .write()
+        .unwrap()
+        .propose_admin_action(action, caller())
+        .unwrap_or_else(|e| panic!("Proposing the admin action failed: {}", e));
+    log::info(format!(
+        "admin action {} proposed by {}: {}, executable at {}",
+        pending.id,
+        pending.proposed_by,
+        pending.action,
+        pending.executable_at.as_nanos()
+    ));
+    pending
+}
+
+/// Makes the change of a pending admin action once its delay is over. Only
+/// the controller and the principals with the role of the action may do
+/// this.
+fn execute_admin_action(id: u64) -> BlockHeight {
+    let action = LEDGER
+        .read()
+        .unwrap()
+        .admin_actions
+        .get(id)
+        .map(|pending| pending.action.clone())
+        .unwrap_or_else(|| panic!("No admin action {} is pending", id));
+    check_role(action.role(), "execute this admin action");
+    let (height, _) = LEDGER
+        .write()
+        .unwrap()
+        .execute_admin_action(id)
+        .unwrap_or_else(|e| panic!("Executing admin action {} failed: {}", id, e));
+    log::info(format!(
+        "admin action {} executed at {}: {}",
+        id, height, action
+    ));
+    height
+}
+
+/// Drops a pending admin action. Only the controller, admins and the
+/// principals with the role of the action may do this.
+fn cancel_admin_action(id: u64) -> PendingAdminAction {
+    let role = LEDGER
+        .read()
+        .unwrap()
+        .admin_actions
+        .get(id)
+        .map(|pending| pending.action.role())
+        .unwrap_or_else(|| panic!("No admin action {} is pending", id));
+    let is_admin = LEDGER
+        .read()
+        .unwrap()
+        .roles
+        .has_role(&caller(), Role::Admin);
+    if !is_admin {
+        check_role(role, "cancel this admin action");
+    }
+    let pending = LEDGER
+        .write()
+        .unwrap()
+        .cancel_admin_action(id)
+        .unwrap_or_else(|e| panic!("Cancelling admin action {} failed: {}", id, e));
+    log::info(format!("admin action {} cancelled: {}", id, pending.action));
+    pending
+}
+
+/// Proposes to authorize a principal, typically a bridge canister, to mint
+/// up to a cumulative quota until an expiry time. Only the controller and
+/// principals with the Minter role may do this.
+fn set_minter(
+    SetMinterArgs {
+        principal,
+        quota,
+        expires_at,
+    }: SetMinterArgs,
+) -> PendingAdminAction {
+    propose_admin_action(AdminAction::SetMinter {
+        principal,
+        quota,
+        expires_at,
+    })
+}
+
+/// Proposes to revoke the minting rights of a principal. Only the controller
+/// and principals with the Minter role may do this.
+fn remove_minter(principal: PrincipalId) -> PendingAdminAction {
+    if LEDGER.read().unwrap().minters.get(&principal).is_none() {
+        panic!("{} is not a minter", principal);
+    }
+    propose_admin_action(AdminAction::RemoveMinter { principal })
+}
+
+/// Lets the calling canister be notified of payments under the whitelist
+/// policy, without letting it send. Users can't register.
+fn register_for_notifications() {
+    let canister_id = caller();
+    if canister_id.is_self_authenticating() || canister_id.is_anonymous() {
+        panic!("Only canisters can register for notifications");
+    }
+    LEDGER
+        .write()
+        .unwrap()
+        .notify_whitelist_mut()
+        .add(canister_id)
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+/// Adds a canister to the notify whitelist. Only the controller and admins
+/// may do this.
+fn add_notify_canister(canister_id: PrincipalId) {
+    check_role(Role::Admin, "manage the notify whitelist");
+    LEDGER
+        .write()
+        .unwrap()
+        .notify_whitelist_mut()
+        .add(canister_id)
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+/// Removes a canister from the notify whitelist. Only the controller and
+/// admins may do this.
+fn remove_notify_canister(canister_id: PrincipalId) {
+    check_role(Role::Admin, "manage the notify whitelist");
+    if !LEDGER
+        .write()
+        .unwrap()
+        .notify_whitelist_mut()
+        .remove(&canister_id)
+    {
+        panic!("{} is not on the notify whitelist", canister_id);
+    }
+}
+
+/// Schedules the replacement of the minting account. Only the controller
+/// (governance) and admins may do this. The change takes effect on the first heartbeat
+/// after `MINTING_ACCOUNT_CHANGE_TIMELOCK`, and is then recorded on chain in
+/// an audit block.
+fn set_minting_account(new: AccountIdentifier) -> PendingMintingAccountChange {
+    check_role(Role::Admin, "change the minting account");
+    let now = LEDGER.read().unwrap().now();
+    let change = LEDGER
+        .write()
+        .unwrap()
+        .schedule_minting_account_change(new, now)
+        .unwrap_or_else(|e| panic!("Changing the minting account failed: {}", e));
+    log::info(format!(
+        "minting account change to {} scheduled for {}",
+        new,
+        change.executable_at.as_nanos()
+    ));
+    change
+}
+
+/// Drops the scheduled change of the minting account. Only the controller
+/// and admins may do this.
+fn cancel_minting_account_change() -> PendingMintingAccountChange {
+    check_role(Role::Admin, "change the minting account");
+    let change = LEDGER
+        .write()
+        .unwrap()
+        .cancel_minting_account_change()
+        .unwrap_or_else(|| panic!("No change of the minting account is scheduled"));
+    log::info(format!(
+        "minting account change to {} cancelled",
+        change.new
+    ));
+    change
+}
+
+/// Makes the scheduled change of the minting account once its timelock is
+/// over.
+fn execute_minting_account_change() {
+    let mut ledger = LEDGER.write().unwrap();
+    let previous = ledger.minting_account_id;
+    let now = ledger.now();
+    match ledger.execute_minting_account_change(now) {
+        Ok(Some((height, _))) => {
+            log::info(format!(
+                "minting account changed from {:?} to {:?} in block {}",
+                previous, ledger.minting_account_id, height
+            ));
+            set_certified_data(&ledger.certify());
+        }
+        Ok(None) => {}
+        Err(e) => log::error(format!("Changing the minting account failed: {}", e)),
+    }
+}
+
+/// Panics if the minting account is no longer `expected`. Operations that
+/// awaited hooks check this before they are recorded, as a mint authorized
+/// by, or a burn to, the previous minting account must not go through.
+fn check_minting_account_unchanged(expected: AccountIdentifier) {
+    if LEDGER.read().unwrap().minting_account_id != Some(expected) {
+        panic!("The minting account changed while the operation was in flight");
+    }
+}
+
+/// Executes a transfer signed by the owner of the source account, on behalf
+/// of whoever submits it. This lets a relayer pay for the ingress message of a
+/// user who cannot make calls to the ledger themselves.
+///
+/// The payload must be bound to this ledger, carry a nonce above the last one
+/// the source account used and have a `created_at_time` inside the
+/// transaction window. Each signed payload can therefore be executed at most
+/// once, and payloads from the same account only in the order of their
+/// nonces.
+pub fn submit_signed_transfer(args: SubmitSignedTransferArgs) -> BlockHeight {
+    let owner = args
+        .verify()
+        .unwrap_or_else(|e| panic!("Invalid signed transfer: {}", e));
+
+    let SignedTransferPayload {
+        memo,
+        amount,
+        fee,
+        from_subaccount,
+        to,
+        created_at_time,
+        nonce,
+        ledger_canister_id,
+    } = args.payload;
+
+    if ledger_canister_id != dfn_core::api::id() {
+        panic!(
+            "Signed transfer is meant for ledger {}, not this one",
+            ledger_canister_id
+        );
+    }
+
+    // The owner is held to the same rules as a caller of transfer.
+    check_can_send(&owner);
+
+    let from = AccountIdentifier::new(owner, from_subaccount);
+    AccountGuard::check(&from).unwrap_or_else(|e| panic!("{}", e));
+    LEDGER
+        .write()
+        .unwrap()
+        .use_signed_transfer_nonce(&from, nonce)
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    let transfer = operation_for(from, to, amount, fee);
+    check_no_blocking_hooks(&transfer);
+    record_spend(&owner, amount, fee);
+    let (height, _) = add_payments(
+        memo,
+        transfer,
+        PaymentOptions {
+            created_at_time: Some(created_at_time),
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("Transfer failed: {}", e));
+    height
+}
+
+/// Consolidates the balances of many of the caller's subaccounts into one.
+/// The whole balance of each non-empty source is transferred in its own
+/// block, but only a single transaction fee is charged for the sweep: it is
+/// paid by the first source that can cover it and the remaining transfers
+/// carry a zero fee. Returns the heights of the created blocks.
+pub fn sweep_subaccounts(
+    SweepSubaccountsArgs {
+        to_subaccount,
+        from_subaccounts,
+        memo,
+    }: SweepSubaccountsArgs,
+) -> Vec<BlockHeight> {
+    let caller_principal_id = caller();
+
+    if !LEDGER.read().unwrap().can_send(&caller_principal_id) {
+        panic!(
+            "Sending from {} is not allowed by the access policy of this ledger",
+            caller_principal_id
+        );
+    }
+
+    if from_subaccounts.len() > MAX_SWEEP_SOURCES {
+        panic!(
+            "Cannot sweep more than {} subaccounts at once",
+            MAX_SWEEP_SOURCES
+        );
+    }
+
+    let to = AccountIdentifier::new(caller_principal_id, to_subaccount);
+    let minting_acc = LEDGER
+        .read()
+        .unwrap()
+        .minting_account_id
+        .expect("Minting canister id not initialized");
+    if to == minting_acc {
+        panic!("Cannot sweep into the minting account");
+    }
+
+    let mut sources = HashSet::new();
+    let mut fee_paid = false;
+    let mut heights = vec![];
+    for subaccount in from_subaccounts {
+        let from = AccountIdentifier::new(caller_principal_id, Some(subaccount));
+        if from == to || from == minting_acc || !sources.insert(from) {
+            panic!("Invalid sweep source {}", from);
+        }
+
+        let balance = LEDGER.read().unwrap().balances.account_balance(&from);
+        let fee = if fee_paid {
+            TOKENs::ZERO
+        } else {
+            TRANSACTION_FEE
+        };
+        if balance <= fee {
+            continue;
+        }
+        let amount = (balance - fee).unwrap();
+        record_spend(&caller_principal_id, amount, fee);
+        let transfer = Operation::Transfer {
+            from,
+            to,
+            amount,
+            fee,
+        };
+        check_no_blocking_hooks(&transfer);
+        let (height, _) = add_payments(memo, transfer, PaymentOptions::default())
+            .unwrap_or_else(|e| panic!("Sweep from {} failed: {}", from, e));
+        fee_paid = true;
+        heights.push(height);
+    }
+    heights
+}
+
+/// This gives you the index of the last block added to the chain
+/// together with certification
+fn tip_of_chain() -> TipOfChainRes {
+    let last_block_idx = &LEDGER
+        .read()
+        .unwrap()
+        .blockchain
+        .chain_length()
+        .checked_sub(1)
+        .unwrap();
+    let certification = data_certificate();
+    TipOfChainRes {
+        certification,
+        tip_index: *last_block_idx,
+    }
+}
+
+// This is going away and being replaced by getblocks
+fn block(block_index: BlockHeight) -> Option<Result<EncodedBlock, CanisterId>> {
+    match LEDGER.read().unwrap().blockchain.lookup(block_index) {
+        BlockLookup::Found(block) => Some(Ok(block)),
+        BlockLookup::Archived { canister_id } => Some(Err(canister_id)),
+        BlockLookup::Pruned { .. } | BlockLookup::NotYetProduced { .. } => None,
+    }
+}
+
+/// Names one of the caller's accounts. Wallets can then display the name
+/// for transfers to that account, backed by the ledger's certified data.
+fn claim_alias(ClaimAliasArgs { name, subaccount }: ClaimAliasArgs) -> AccountIdentifier {
+    let mut ledger = LEDGER.write().unwrap();
+    let account = ledger
+        .aliases
+        .claim(caller(), name, subaccount)
+        .unwrap_or_else(|e| panic!("{}", e));
+    set_certified_data(&ledger.certify());
+    account
+}
+
+/// Removes a name that the caller claimed.
+fn release_alias(name: String) {
+    let mut ledger = LEDGER.write().unwrap();
+    ledger
+        .aliases
+        .release(caller(), &name)
+        .unwrap_or_else(|e| panic!("{}", e));
+    set_certified_data(&ledger.certify());
+}
+
+/// Registers that the caller's account with the given subaccount is theirs,
+/// so that `owner_of` reveals the caller as its owner.
+fn register_account_owner(subaccount: Option<Subaccount>) -> AccountIdentifier {
+    LEDGER
+        .write()
+        .unwrap()
+        .account_owners
+        .register(caller(), subaccount)
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Removes a registration made with `register_account_owner`.
+fn unregister_account_owner(subaccount: Option<Subaccount>) -> AccountIdentifier {
+    LEDGER
+        .write()
+        .unwrap()
+        .account_owners
+        .unregister(caller(), subaccount)
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Registers (or, with a `None` callback, removes) the canister method that
+/// is called with the block height whenever the caller's account is credited.
+fn register_payment_callback(
+    RegisterPaymentCallbackArgs {
+        subaccount,
+        callback,
+    }: RegisterPaymentCallbackArgs,
+) {
+    let account = AccountIdentifier::new(caller(), subaccount);
+    LEDGER
+        .write()
+        .unwrap()
+        .payment_callbacks
+        .register(account, callback)
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+/// Calls the registered payment callbacks for recent credits. The calls are
+/// one-way, so a callee that never replies can't keep the ledger from
+/// stopping. Calls that can't be made are retried on later heartbeats until
+/// they run out of attempts. A failing callback never affects the payment it
+/// announces.
+fn deliver_payment_callbacks() {
+    let send = |pending: &PendingCallback| {
+        call_no_reply(
+            pending.callback.canister_id,
+            &pending.callback.method,
+            candid_one::<(), _>,
+            pending.payment.clone(),
+            Funds::zero(),
+        )
+    };
+    let results = LEDGER
+        .write()
+        .unwrap()
+        .payment_callbacks
+        .deliver(CALLBACKS_PER_HEARTBEAT, send);
+    for (pending, result) in results {
+        if let Err(msg) = result {
+            log::warn(format!(
+                "payment callback to {} for block {} failed: {}",
+                pending.callback.canister_id, pending.payment.block_height, msg
+            ));
+        }
+    }
+}
+
+/// Describes the configuration of this deployment in one call, for
+/// operational tooling and SDKs.
+fn ledger_info() -> LedgerInfo {
+    let ledger = LEDGER.read().unwrap();
+    LedgerInfo {
+        symbol: ledger.symbol.clone(),
+        decimals: ledger.decimals,
+        transaction_fee: TRANSACTION_FEE,
+        min_burn_amount: MIN_BURN_AMOUNT,
+        minting_account: ledger.minting_account_id,
+        transaction_window: ledger.transaction_window,
+        permitted_drift: ledger.permitted_drift,
+        chain_length: ledger.blockchain.chain_length(),
+        num_archived_blocks: ledger.blockchain.num_archived_blocks(),
+        num_pruned_blocks: ledger.blockchain.num_pruned_blocks(),
+        retention_policy: ledger.retention_policy().cloned(),
+        maximum_number_of_accounts: ledger.maximum_number_of_accounts(),
+        accounts_overflow_trim_quantity: ledger.accounts_overflow_trim_quantity(),
+        accounts_overflow_policy: ledger.accounts_overflow_policy(),
+        max_message_size_bytes: *MAX_MESSAGE_SIZE_BYTES.read().unwrap(),
+        max_blocks_per_request: MAX_BLOCKS_PER_REQUEST,
+    }
+}
+
+/// Get an account balance.
+/// If the account does not exist it will return 0 ICPTs
+fn account_balance(account: AccountIdentifier) -> TOKENs {
+    LEDGER.read().unwrap().balances.account_balance(&account)
+}
+
+/// The total number of ICPTs not inside the minting canister
+fn total_supply() -> TOKENs {
+    LEDGER.read().unwrap().balances.total_supply()
+}
+
+/// Canister endpoints
+#[update]
+fn send_() {
+    validate_send_arg_size(dfn_core::api::arg_size() as usize)
+        .unwrap_or_else(|e| panic!("{}", e));
+    over_async(
+        protobuf,
+        |SendArgs {
+             memo,
+             amount,
+             fee,
+             from_subaccount,
+             to,
+             created_at_time,
+             idempotency_key,
+             encrypted_memo,
+             expires_at,
+         }| {
+            transfer(
+                memo,
+                amount,
+                fee,
+                from_subaccount,
+                to,
+                created_at_time,
+                expires_at,
+                idempotency_key,
+                encrypted_memo,
+                None,
+            )
+        },
+    );
+}
+
+/// Do not use call this from code, this is only here so dfx has something to
+/// call when making a payment. This will be changed in ways that are not
+/// backwards compatible with previous interfaces.
+///
+/// I STRONGLY recommend that you use "send_pb" instead.
+///
+/// Deprecated: the blocks it makes are tagged with
+/// `DeprecatedEndpoint::SendDfx` in their memo blob, its use is counted in
+/// the metrics, and it can be disabled with the "send_dfx" endpoint flag.
+#[export_name = "canister_update send_dfx"]
+fn send_dfx_() {
+    check_endpoint_enabled("send_dfx");
+    validate_send_arg_size(dfn_core::api::arg_size() as usize)
+        .unwrap_or_else(|e| panic!("{}", e));
+    over_async(
+        candid_one,
+        |SendArgs {
+             memo,
+             amount,
+             fee,
+             from_subaccount,
+             to,
+             created_at_time,
+             idempotency_key,
+             encrypted_memo,
+             expires_at,
+         }| async move {
+            let height = transfer(
+                memo,
+                amount,
+                fee,
+                from_subaccount,
+                to,
+                created_at_time,
+                expires_at,
+                idempotency_key,
+                encrypted_memo,
+                Some(DeprecatedEndpoint::SendDfx.to_memo_blob()),
+            )
+            .await;
+            let now = LEDGER.read().unwrap().now();
+            LEDGER
+                .write()
+                .unwrap()
+                .deprecated_endpoint_usage
+                .record_send_dfx(now);
+            height
+        },
+    );
+}
+
+#[export_name = "canister_update transfer_and_call"]
+fn transfer_and_call_() {
+    check_endpoint_enabled("transfer_and_call");
+    over_async(candid_one, transfer_and_call);
+}
+
+#[export_name = "canister_update attest_balance"]
+fn attest_balance_() {
+    check_can_read();
+    check_endpoint_enabled("attest_balance");
+    over_async(candid_one, attest_balance);
+}
+
+#[export_name = "canister_update refund"]
+fn refund_() {
+    over(candid_one, refund);
+}
+
+#[export_name = "canister_update mint"]
+fn mint_() {
+    over_async(candid_one, mint);
+}
+
+#[export_name = "canister_update stake"]
+fn stake_() {
+    over(candid_one, stake);
+}
+
+#[export_name = "canister_update unstake"]
+fn unstake_() {
+    over(candid_one, unstake);
+}
+
+#[export_name = "canister_update create_stream"]
+fn create_stream_() {
+    check_endpoint_enabled("create_stream");
+    over(candid_one, create_stream);
+}
+
+#[export_name = "canister_update withdraw_from_stream"]
+fn withdraw_from_stream_() {
+    over(candid_one, withdraw_from_stream);
+}
+
+#[export_name = "canister_update cancel_stream"]
+fn cancel_stream_() {
+    over(candid_one, cancel_stream);
+}
+
+#[export_name = "canister_query get_streams"]
+fn get_streams_() {
+    check_can_read();
+    over(candid_one, |principal_id: PrincipalId| -> Vec<Stream> {
+        LEDGER.read().unwrap().streams.streams_of(&principal_id)
+    });
+}
+
+#[export_name = "canister_update create_pending_transfer"]
+fn create_pending_transfer_() {
+    check_endpoint_enabled("create_pending_transfer");
+    over(candid_one, create_pending_transfer);
+}
+
+#[export_name = "canister_update cancel_pending_transfer"]
+fn cancel_pending_transfer_() {
+    over(candid_one, cancel_pending_transfer);
+}
+
+#[export_name = "canister_update finalize_pending_transfer"]
+fn finalize_pending_transfer_() {
+    over(candid_one, finalize_pending_transfer);
+}
+
+#[export_name = "canister_query get_pending_transfers"]
+fn get_pending_transfers_() {
+    check_can_read();
+    over(
+        candid_one,
+        |principal_id: PrincipalId| -> Vec<PendingTransfer> {
+            LEDGER
+                .read()
+                .unwrap()
+                .pending_transfers
+                .pending_transfers_of(&principal_id)
+        },
+    );
+}
+
+#[export_name = "canister_update create_invoice"]
+fn create_invoice_() {
+    over(candid_one, create_invoice);
+}
+
+#[export_name = "canister_update claim_invoice"]
+fn claim_invoice_() {
+    over(candid_one, claim_invoice);
+}
+
+#[export_name = "canister_query get_invoice"]
+fn get_invoice_() {
+    check_can_read();
+    over(candid_one, |id: InvoiceId| -> Option<Invoice> {
+        LEDGER.read().unwrap().invoices.get(id).cloned()
+    });
+}
+
+/// Checks whether an invoice was paid in full, in part, or expired.
+#[export_name = "canister_query verify_invoice"]
+fn verify_invoice_() {
+    check_can_read();
+    over(candid_one, |id: InvoiceId| -> InvoiceStatus {
+        let ledger = LEDGER.read().unwrap();
+        ledger
+            .invoices
+            .get(id)
+            .unwrap_or_else(|| panic!("Invoice {} does not exist", id))
+            .status(ledger.now())
+    });
+}
+
+#[export_name = "canister_update set_staking_config"]
+fn set_staking_config_() {
+    over(candid_one, set_staking_config);
+}
+
+#[export_name = "canister_query get_stakes"]
+fn get_stakes_() {
+    check_can_read();
+    over(candid_one, |owner: PrincipalId| -> Vec<Stake> {
+        LEDGER.read().unwrap().staking.stakes_of(&owner)
+    });
+}
+
+/// The account that staking rewards are paid from. Transfer tokens to it to
+/// fund rewards.
+#[export_name = "canister_query staking_rewards_pool"]
+fn staking_rewards_pool_() {
+    check_can_read();
+    over(candid, |()| {
+        AccountIdentifier::new(dfn_core::api::id().get(), Some(rewards_pool_subaccount()))
+    });
+}
+
+#[export_name = "canister_update set_operation_hooks"]
+fn set_operation_hooks_() {
+    over(candid_one, set_operation_hooks);
+}
+
+#[export_name = "canister_query get_operation_hooks"]
+fn get_operation_hooks_() {
+    check_can_read();
+    over(candid, |()| -> Vec<OperationHook> {
+        LEDGER.read().unwrap().operation_hooks.list().to_vec()
+    });
+}
+
+#[export_name = "canister_update set_minting_account"]
+fn set_minting_account_() {
+    over(candid_one, set_minting_account);
+}
+
+#[export_name = "canister_update cancel_minting_account_change"]
+fn cancel_minting_account_change_() {
+    over(candid, |()| cancel_minting_account_change());
+}
+
+#[export_name = "canister_query pending_minting_account_change"]
+fn pending_minting_account_change_() {
+    check_can_read();
+    over(candid, |()| -> Option<PendingMintingAccountChange> {
+        LEDGER
+            .read()
+            .unwrap()
+            .pending_minting_account_change()
+            .cloned()
+    });
+}
+
+#[export_name = "canister_update set_minter"]
+fn set_minter_() {
+    over(candid_one, set_minter);
+}
+
+#[export_name = "canister_update remove_minter"]
+fn remove_minter_() {
+    over(candid_one, remove_minter);
+}
+
+#[export_name = "canister_update propose_admin_action"]
+fn propose_admin_action_() {
+    over(candid_one, propose_admin_action);
+}
+
+#[export_name = "canister_update execute_admin_action"]
+fn execute_admin_action_() {
+    over(candid_one, execute_admin_action);
+}
+
+#[export_name = "canister_update cancel_admin_action"]
+fn cancel_admin_action_() {
+    over(candid_one, cancel_admin_action);
+}
+
+/// The admin actions waiting to be executed, oldest first.
+#[export_name = "canister_query pending_admin_actions"]
+fn pending_admin_actions_() {
+    check_can_read();
+    over(candid, |()| -> Vec<PendingAdminAction> {
+        LEDGER.read().unwrap().admin_actions.pending()
+    });
+}
+
+#[export_name = "canister_update register_for_notifications"]
+fn register_for_notifications_() {
+    over(candid, |()| register_for_notifications());
+}
+
+/// Removes the calling canister from the notify whitelist.
+#[export_name = "canister_update unregister_from_notifications"]
+fn unregister_from_notifications_() {
+    over(candid, |()| {
+        LEDGER
+            .write()
+            .unwrap()
+            .notify_whitelist_mut()
+            .remove(&caller());
+    });
+}
+
+#[export_name = "canister_update add_notify_canister"]
+fn add_notify_canister_() {
+    over(candid_one, add_notify_canister);
+}
+
+#[export_name = "canister_update remove_notify_canister"]
+fn remove_notify_canister_() {
+    over(candid_one, remove_notify_canister);
+}
+
+/// The canisters that may be notified under the whitelist policy.
+#[export_name = "canister_query notify_whitelist"]
+fn notify_whitelist_() {
+    check_can_read();
+    over(candid, |()| -> Vec<PrincipalId> {
+        LEDGER.read().unwrap().notify_whitelist().list()
+    });
+}
+
+/// Restricts the queries to the controller and the reader list, or opens
+/// them to everybody again. Only the controller and admins may do this.
+#[export_name = "canister_update set_read_restriction"]
+fn set_read_restriction_() {
+    over(candid_one, |restricted: bool| {
+        check_role(Role::Admin, "restrict reads");
+        LEDGER.write().unwrap().readers.set_restricted(restricted);
+        log::info(format!("reads restricted: {}", restricted));
+    });
+}
+
+/// Adds a principal to the reader list. Only the controller and admins may
+/// do this.
+#[export_name = "canister_update add_reader"]
+fn add_reader_() {
+    over(candid_one, |reader: PrincipalId| {
+        check_role(Role::Admin, "manage the reader list");
+        LEDGER
+            .write()
+            .unwrap()
+            .readers
+            .add(reader)
+            .unwrap_or_else(|e| panic!("{}", e));
+    });
+}
+
+/// Removes a principal from the reader list. Only the controller and admins
+/// may do this.
+#[export_name = "canister_update remove_reader"]
+fn remove_reader_() {
+    over(candid_one, |reader: PrincipalId| {
+        check_role(Role::Admin, "manage the reader list");
+        if !LEDGER.write().unwrap().readers.remove(&reader) {
+            panic!("{} is not on the reader list", reader);
+        }
+    });
+}
+
+/// Gives a role to a principal. Only the controller and admins may do this.
+#[export_name = "canister_update grant_role"]
+fn grant_role_() {
+    over(candid_one, |RoleArgs { principal, role }| {
+        check_role(Role::Admin, "grant roles");
+        LEDGER.write().unwrap().roles.grant(principal, role);
+        log::info(format!("granted the {} role to {}", role, principal));
+    });
+}
+
+/// Takes a role from a principal. Only the controller and admins may do
+/// this.
+#[export_name = "canister_update revoke_role"]
+fn revoke_role_() {
+    over(candid_one, |RoleArgs { principal, role }| {
+        check_role(Role::Admin, "revoke roles");
+        if !LEDGER.write().unwrap().roles.revoke(&principal, role) {
+            panic!("{} does not have the {} role", principal, role);
+        }
+        log::info(format!("revoked the {} role of {}", role, principal));
+    });
+}
+
+/// The principals other than the controller that hold roles.
+#[export_name = "canister_query get_roles"]
+fn get_roles_() {
+    check_can_read();
+    over(candid, |()| -> Vec<RoleAssignment> {
+        LEDGER.read().unwrap().roles.list()
+    });
+}
+
+/// The principals that may call the queries while reads are restricted.
+#[export_name = "canister_query readers"]
+fn readers_() {
+    check_can_read();
+    over(candid, |()| -> Vec<PrincipalId> {
+        LEDGER.read().unwrap().readers.list()
+    });
+}
+
+#[export_name = "canister_query get_minters"]
+fn get_minters_() {
+    check_can_read();
+    over(candid, |()| -> Vec<Minter> { LEDGER.read().unwrap().minters.list() });
+}
+
+/// The ICRC-1 metadata of the token.
+#[export_name = "canister_query icrc1_metadata"]
+fn icrc1_metadata_() {
+    check_can_read();
+    over(candid, |()| LEDGER.read().unwrap().icrc1_metadata());
+}
+
+/// The account mints come from and burns go to. The ledger only knows
+/// accounts by their identifier, so unlike in ICRC-1 this is not an owner
+/// and subaccount pair.
+#[export_name = "canister_query icrc1_minting_account"]
+fn icrc1_minting_account_() {
+    check_can_read();
+    over(candid, |()| LEDGER.read().unwrap().minting_account_id);
+}
+
+#[export_name = "canister_query get_minting_account"]
+fn get_minting_account_() {
+    check_can_read();
+    over(candid, |()| LEDGER.read().unwrap().minting_account_id);
+}
+
+/// The lowest nonce the next pre-signed transfer from `account` may carry,
+/// so that relayers can sign and order submissions.
+#[export_name = "canister_query get_nonce"]
+fn get_nonce_() {
+    check_can_read();
+    over(candid_one, |account: AccountIdentifier| -> u64 {
+        LEDGER.read().unwrap().signed_transfer_nonce(&account)
+    });
+}
+
+#[export_name = "canister_update submit_signed_transfer"]
+fn submit_signed_transfer_() {
+    check_endpoint_enabled("submit_signed_transfer");
+    over(candid_one, submit_signed_transfer);
+}
+
+#[export_name = "canister_update sweep_subaccounts"]
+fn sweep_subaccounts_() {
+    check_endpoint_enabled("sweep_subaccounts");
+    over(candid_one, sweep_subaccounts);
+}
+
+#[export_name = "canister_update register_payment_callback"]
+fn register_payment_callback_() {
+    check_endpoint_enabled("register_payment_callback");
+    over(candid_one, register_payment_callback);
+}
+
+#[export_name = "canister_query payment_callback"]
+fn payment_callback_() {
+    check_can_read();
+    over(candid_one, |account: AccountIdentifier| -> Option<PaymentCallback> {
+        LEDGER
+            .read()
+            .unwrap()
+            .payment_callbacks
+            .get(&account)
+            .cloned()
+    });
+}
+
+/// Rejects oversized or invalid send arguments before they reach consensus,
+/// so that they cost the sender nothing and the ledger no decoding time.
+/// The endpoints check them again, as update calls from other canisters skip
+/// this.
+#[export_name = "canister_inspect_message"]
+fn inspect_message() {
+    let method_name = dfn_core::api::method_name();
+    // Disabled endpoints would fail anyway, so don't let their messages in.
+    if LEDGER
+        .read()
+        .unwrap()
+        .endpoint_flags
+        .check_enabled(&method_name)
+        .is_err()
+    {
+        return;
+    }
+    let valid = match method_name.as_str() {
+        "send_" => {
+            validate_send_arg_size(dfn_core::api::arg_size() as usize).is_ok()
+                && ProtoBuf::<SendArgs>::from_bytes(dfn_core::api::arg_data())
+                    .map(|ProtoBuf(args)| args.validate().is_ok())
+                    .unwrap_or(false)
+        }
+        "send_dfx" => {
+            validate_send_arg_size(dfn_core::api::arg_size() as usize).is_ok()
+                && CandidOne::<SendArgs>::from_bytes(dfn_core::api::arg_data())
+                    .map(|CandidOne(args)| args.validate().is_ok())
+                    .unwrap_or(false)
+        }
+        _ => true,
+    };
+    if valid {
+        dfn_core::api::accept_message();
+    }
+}
+
+#[export_name = "canister_heartbeat"]
+fn heartbeat() {
+    LEDGER.write().unwrap().check_balances();
+    // Nothing is written while the ledger is read-only; the work is picked
+    // up again once it is writable.
+    if LEDGER.read().unwrap().is_read_only() {
+        return;
+    }
+    process_invoice_refunds();
+    finalize_due_pending_transfers();
+    execute_minting_account_change();
+    if let Err(msg) = LEDGER.write().unwrap().apply_retention_policy() {
+        log::error(format!("Applying the retention policy failed: {}", msg));
+    }
+    let now = LEDGER.read().unwrap().now();
+    if LEDGER.read().unwrap().maintenance.is_due(now) {
+        run_maintenance(now);
+    }
+    deliver_payment_callbacks();
+}
+
+fn run_maintenance(now: TimeStamp) -> MaintenanceReport {
+    let report = LEDGER.write().unwrap().run_maintenance(now);
+    log::info(format!(
+        "Maintenance purged {} transactions and pruned {} invoices",
+        report.purged_transactions, report.pruned_invoices
+    ));
+    report
+}
+
+/// Runs maintenance right away instead of waiting for the heartbeat to run
+/// it. Only the controller and admins may do this.
+#[export_name = "canister_update run_maintenance"]
+fn run_maintenance_() {
+    over(candid, |()| -> MaintenanceReport {
+        check_role(Role::Admin, "run maintenance");
+        let now = LEDGER.read().unwrap().now();
+        run_maintenance(now)
+    });
+}
+
+#[export_name = "canister_update claim_alias"]
+fn claim_alias_() {
+    over(candid_one, claim_alias);
+}
+
+#[export_name = "canister_update release_alias"]
+fn release_alias_() {
+    over(candid_one, release_alias);
+}
+
+#[export_name = "canister_query resolve_alias"]
+fn resolve_alias_() {
+    check_can_read();
+    over(candid_one, |name: String| {
+        LEDGER.read().unwrap().aliases.resolve(&name)
+    });
+}
+
+#[export_name = "canister_query alias_of"]
+fn alias_of_() {
+    check_can_read();
+    over(candid_one, |account: AccountIdentifier| {
+        LEDGER.read().unwrap().aliases.alias_of(&account).cloned()
+    });
+}
+
+#[export_name = "canister_update register_account_owner"]
+fn register_account_owner_() {
+    over(candid_one, register_account_owner);
+}
+
+#[export_name = "canister_update unregister_account_owner"]
+fn unregister_account_owner_() {
+    over(candid_one, unregister_account_owner);
+}
+
+/// The principal that registered `account` as theirs, if any. Accounts that
+/// were never registered have no known owner.
+#[export_name = "canister_query owner_of"]
+fn owner_of_() {
+    check_can_read();
+    over(candid_one, |account: AccountIdentifier| -> Option<PrincipalId> {
+        LEDGER.read().unwrap().account_owners.owner_of(&account)
+    });
+}
+
+#[export_name = "canister_query block_pb"]
+fn block_() {
+    check_can_read();
+    over(protobuf, |BlockArg(height)| BlockRes(block(height)));
+}
+
+/// Candid version of block_pb, telling apart blocks that were archived,
+/// pruned or not produced yet.
+#[export_name = "canister_query block"]
+fn block_candid_() {
+    check_can_read();
+    over(candid_one, |height: BlockHeight| {
+        LEDGER.read().unwrap().blockchain.lookup(height)
+    });
+}
+
+/// Looks up a list of heights that need not be contiguous, returning where
+/// each block is in the order requested. Archived blocks must be fetched
+/// from the archive node returned for them. Requests for more than
+/// `MAX_BLOCKS_PER_REQUEST` heights are truncated.
+#[export_name = "canister_query get_blocks_by_heights"]
+fn get_blocks_by_heights_() {
+    check_can_read();
+    over(candid_one, |heights: Vec<BlockHeight>| {
+        LEDGER.read().unwrap().blockchain.lookup_heights(&heights)
+    });
+}
+
+/// Counts of the blocks produced per operation type and over the last hour
+/// and day, and the size of the blocks the ledger holds.
+#[export_name = "canister_query block_stats"]
+fn block_stats_() {
+    check_can_read();
+    over(candid, |()| {
+        let ledger = LEDGER.read().unwrap();
+        ledger.blockchain.block_stats(ledger.now())
+    });
+}
+
+/// The archive nodes and the range of blocks each of them holds, oldest
+/// first.
+#[export_name = "canister_query get_archives"]
+fn get_archives_() {
+    check_can_read();
+    over(candid, |()| LEDGER.read().unwrap().blockchain.archives());
+}
+
+/// ICRC-3 version of get_archives.
+#[export_name = "canister_query icrc3_get_archives"]
+fn icrc3_get_archives_() {
+    check_can_read();
+    over(candid_one, |GetArchivesArgs { from }| {
+        LEDGER.read().unwrap().blockchain.icrc3_archives(from)
+    });
+}
+
+#[export_name = "canister_query tip_of_chain_pb"]
+fn tip_of_chain_() {
+    check_can_read();
+    over(protobuf, |protobuf::TipOfChainRequest {}| tip_of_chain());
+}
+
+#[export_name = "canister_query account_balance_pb"]
+fn account_balance_() {
+    check_can_read();
+    over(protobuf, |AccountBalanceArgs { account }| {
+        account_balance(account)
+    })
+}
+
+/// See caveats of use on send_dfx
+#[export_name = "canister_query account_balance_dfx"]
+fn account_balance_dfx_() {
+    check_can_read();
+    over(candid_one, |AccountBalanceArgs { account }| {
+        account_balance(account)
+    })
+}
+
+/// Checks the ledger against what ICRC-1 clients expect, running the checks
+/// that execute transactions on a scratch ledger. Only built with the `dev`
+/// feature.
+#[cfg(feature = "dev")]
+#[export_name = "canister_update run_conformance_checks"]
+fn run_conformance_checks_() {
+    over(candid, |()| {
+        crate::conformance::run_conformance_checks(&LEDGER.read().unwrap())
+    })
+}
+
+/// The balance of an account with what it received and sent and the heights
+/// of its first and last blocks, so that explorers need not scan the chain.
+#[export_name = "canister_query account_summary"]
+fn account_summary_() {
+    check_can_read();
+    over(candid_one, |account: AccountIdentifier| {
+        LEDGER.read().unwrap().account_summary(&account)
+    })
+}
+
+/// The total supply after each mint and burn from height `start` on, oldest
+/// first. At most `MAX_SUPPLY_CHECKPOINTS_PER_REQUEST` checkpoints are
+/// returned.
+#[export_name = "canister_query supply_history"]
+fn supply_history_() {
+    check_can_read();
+    over(candid_one, |SupplyHistoryArgs { start, limit }| {
+        let limit = limit.min(MAX_SUPPLY_CHECKPOINTS_PER_REQUEST as u64) as usize;
+        LEDGER.read().unwrap().supply_history(start, limit)
+    })
+}
+
+/// Attaches a label to a transaction that the caller's account sent or
+/// received, or removes it if `label` is not set. Annotations are kept off
+/// the chain and can be changed at any time.
+#[export_name = "canister_update annotate_transaction"]
+fn annotate_transaction_() {
+    over(
+        candid_one,
+        |AnnotateTransactionArgs {
+             height,
+             subaccount,
+             label,
+         }| {
+            let account = AccountIdentifier::new(caller(), subaccount);
+            let now = LEDGER.read().unwrap().now();
+            LEDGER
+                .write()
+                .unwrap()
+                .annotate_transaction(account, height, label, now)
+                .unwrap_or_else(|e| panic!("{}", e))
+        },
+    );
+}
+
+/// The annotations `account` attached to its transactions, from height
+/// `start` on, lowest first. At most `MAX_ANNOTATIONS_PER_REQUEST` are
+/// returned.
+#[export_name = "canister_query get_annotations"]
+fn get_annotations_() {
+    check_can_read();
+    over(
+        candid_one,
+        |GetAnnotationsArgs {
+             account,
+             start,
+             limit,
+         }|
+         -> Vec<Annotation> {
+            LEDGER
+                .read()
+                .unwrap()
+                .annotations
+                .range(&account, start, limit as usize)
+        },
+    );
+}
+
+/// The current time of the ledger's clock, against which `created_at_time`
+/// and `expires_at` are checked.
+#[export_name = "canister_query ledger_time"]
+fn ledger_time_() {
+    check_can_read();
+    over(candid, |()| LEDGER.read().unwrap().now());
+}
+
+#[export_name = "canister_query ledger_info"]
+fn ledger_info_() {
+    check_can_read();
+    over(candid, |()| ledger_info());
+}
+
+/// The amount burned so far, not counting fees.
+#[export_name = "canister_query total_burned"]
+fn total_burned_() {
+    check_can_read();
+    over(candid, |()| LEDGER.read().unwrap().total_burned());
+}
+
+/// The hex-encoded hash of the ledger's state. Copies of the same ledger
+/// that applied the same calls must report the same hash.
+#[export_name = "canister_query state_hash"]
+fn state_hash_() {
+    check_can_read();
+    over(candid, |()| {
+        hex::encode(crate::state_hash::state_hash(&LEDGER.read().unwrap()))
+    });
+}
+
+#[export_name = "canister_query ledger_metrics"]
+fn ledger_metrics_() {
+    check_can_read();
+    over(candid, |()| LEDGER.read().unwrap().metrics());
+}
+
+/// The cycles balance and heap size of the canister, for the health checks.
+fn canister_resources() -> CanisterResources {
+    CanisterResources {
+        cycles_balance: Some(dfn_core::api::canister_cycle_balance()),
+        heap_bytes: heap_bytes(),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn heap_bytes() -> Option<u64> {
+    Some(core::arch::wasm32::memory_size(0) as u64 * 65536)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn heap_bytes() -> Option<u64> {
+    None
+}
+
+/// Whether the ledger is healthy, degraded or only serving queries, derived
+/// from the archive lag, the cycles balance and the heap size. Also served
+/// on /health.
+#[export_name = "canister_query health"]
+fn health_() {
+    check_can_read();
+    over(candid, |()| {
+        health::health(&LEDGER.read().unwrap(), &canister_resources())
+    });
+}
+
+/// Sets how far archiving may fall behind before transfers are rejected.
+/// Only the controller and principals with the Archiver role may do this.
+#[export_name = "canister_update set_archive_lag_throttle"]
+fn set_archive_lag_throttle_() {
+    over(candid_one, |throttle: ArchiveLagThrottle| {
+        check_role(Role::Archiver, "configure the archive lag throttle");
+        log::info(format!("archive lag throttle set to {:?}", throttle));
+        LEDGER.write().unwrap().set_archive_lag_throttle(throttle);
+    });
+}
+
+/// Makes the ledger read-only until the given time, e.g. while its state is
+/// exported or checked during a migration, or writable again with `None`.
+/// Queries keep working. Only the controller and principals with the Freezer
+/// role may do this.
+#[export_name = "canister_update set_read_only"]
+fn set_read_only_() {
+    over(candid_one, |until: Option<TimeStamp>| {
+        check_role(Role::Freezer, "make the ledger read-only");
+        LEDGER
+            .write()
+            .unwrap()
+            .set_read_only(until)
+            .unwrap_or_else(|e| panic!("{}", e));
+        match until {
+            Some(until) => log::warn(format!("ledger is read-only until {} ns", until.as_nanos())),
+            None => log::info("ledger is writable again".to_string()),
+        }
+    });
+}
+
+/// When the ledger stops being read-only, if it is now.
+#[export_name = "canister_query read_only_until"]
+fn read_only_until_() {
+    check_can_read();
+    over(candid, |()| -> Option<TimeStamp> {
+        LEDGER.read().unwrap().read_only_until()
+    });
+}
+
+fn fee_account() -> AccountIdentifier {
+    AccountIdentifier::new(dfn_core::api::id().get(), Some(fee_collector_subaccount()))
+}
+
+/// Proposes to credit fees to the ledger's fee account instead of burning
+/// them, or to burn them again. Only the controller and principals with the
+/// FeeManager role may do this.
+#[export_name = "canister_update set_fee_collection"]
+fn set_fee_collection_() {
+    over(candid_one, |enabled: bool| {
+        let account = if enabled { Some(fee_account()) } else { None };
+        propose_admin_action(AdminAction::SetFeeCollector(account))
+    });
+}
+
+/// Enables or disables one of the endpoints that can be turned off at
+/// runtime. Only the controller and admins may do this.
+#[export_name = "canister_update set_endpoint_enabled"]
+fn set_endpoint_enabled_() {
+    over(
+        candid_one,
+        |SetEndpointEnabledArgs { endpoint, enabled }| {
+            check_role(Role::Admin, "enable or disable endpoints");
+            let changed = LEDGER
+                .write()
+                .unwrap()
+                .endpoint_flags
+                .set_enabled(&endpoint, enabled)
+                .unwrap_or_else(|e| panic!("{}", e));
+            if changed {
+                log::info(format!(
+                    "{} {}",
+                    if enabled { "enabled" } else { "disabled" },
+                    endpoint
+                ));
+            }
+        },
+    );
+}
+
+/// The endpoints that are disabled.
+#[export_name = "canister_query disabled_endpoints"]
+fn disabled_endpoints_() {
+    check_can_read();
+    over(candid, |()| -> Vec<String> {
+        LEDGER.read().unwrap().endpoint_flags.disabled()
+    });
+}
+
+/// The ledger's fee account, whether fees are collected into it, and how
+/// much it holds.
+#[export_name = "canister_query collected_fees"]
+fn collected_fees_() {
+    check_can_read();
+    over(candid, |()| {
+        let ledger = LEDGER.read().unwrap();
+        let account = fee_account();
+        CollectedFees {
+            account,
+            enabled: ledger.fee_collector() == Some(account),
+            balance: ledger.balances.account_balance(&account),
+            total_collected: ledger.total_fees_collected(),
+        }
+    });
+}
+
+/// Moves collected fees out of the fee account, without a fee. Only the
+/// controller and principals with the FeeManager role may do this. Returns
+/// the height of the transfer.
+#[export_name = "canister_update sweep_collected_fees"]
+fn sweep_collected_fees_() {
+    over(candid_one, |SweepCollectedFeesArgs { to, amount }| {
+        check_role(Role::FeeManager, "sweep collected fees");
+        let from = fee_account();
+        let balance = LEDGER.read().unwrap().balances.account_balance(&from);
+        let amount = amount.unwrap_or(balance);
+        if amount.is_zero() || amount > balance {
+            panic!("Cannot sweep {} out of the {} collected", amount, balance);
+        }
+        let sweep = Operation::Transfer {
+            from,
+            to,
+            amount,
+            fee: TOKENs::ZERO,
+        };
+        check_no_blocking_hooks(&sweep);
+        let (height, _) = add_payments(Memo(0), sweep, PaymentOptions::default())
+            .unwrap_or_else(|e| panic!("Sweeping collected fees failed: {}", e));
+        log::info(format!(
+            "swept {} of collected fees to {} at {}",
+            amount, to, height
+        ));
+        height
+    });
+}
+
+/// Panics unless the caller is the governance principal of the treasury.
+/// `action` completes the message of the panic.
+fn check_treasury_governance(action: &str) {
+    let caller = caller();
+    if LEDGER.read().unwrap().treasury.governance() != Some(caller) {
+        panic!("Only the treasury governance principal can {}", action);
+    }
+}
+
+/// Proposes to change the principal that approves debits of treasury
+/// accounts. Only the controller and admins may do this.
+#[export_name = "canister_update set_treasury_governance"]
+fn set_treasury_governance_() {
+    over(candid_one, |governance: Option<PrincipalId>| {
+        propose_admin_action(AdminAction::SetTreasuryGovernance(governance))
+    });
+}
+
+/// Declares an account a treasury account, whose debits must then be
+/// approved by the governance principal. Only the controller and admins may
+/// do this.
+#[export_name = "canister_update add_treasury_account"]
+fn add_treasury_account_() {
+    over(candid_one, |account: AccountIdentifier| {
+        check_role(Role::Admin, "declare treasury accounts");
+        LEDGER
+            .write()
+            .unwrap()
+            .treasury
+            .add_account(account)
+            .unwrap_or_else(|e| panic!("{}", e));
+        log::info(format!("{} declared a treasury account", account));
+    });
+}
+
+/// Makes a treasury account an ordinary account again. Only the governance
+/// principal may do this, so that admins cannot lift the protection.
+#[export_name = "canister_update remove_treasury_account"]
+fn remove_treasury_account_() {
+    over(candid_one, |account: AccountIdentifier| {
+        check_treasury_governance("remove treasury accounts");
+        if !LEDGER.write().unwrap().treasury.remove_account(&account) {
+            panic!("{} is not a treasury account", account);
+        }
+        log::info(format!("{} is no longer a treasury account", account));
+    });
+}
+
+/// Approves a single debit of a treasury account, which its owner can then
+/// make. Only the governance principal may do this.
+#[export_name = "canister_update approve_treasury_debit"]
+fn approve_treasury_debit_() {
+    over(candid_one, |approval: TreasuryApproval| {
+        check_treasury_governance("approve treasury debits");
+        LEDGER
+            .write()
+            .unwrap()
+            .treasury
+            .approve(approval)
+            .unwrap_or_else(|e| panic!("{}", e));
+    });
+}
+
+/// Withdraws an approval that was not used yet. Only the governance
+/// principal may do this.
+#[export_name = "canister_update revoke_treasury_approval"]
+fn revoke_treasury_approval_() {
+    over(candid_one, |approval: TreasuryApproval| {
+        check_treasury_governance("revoke treasury approvals");
+        if !LEDGER.write().unwrap().treasury.revoke(&approval) {
+            panic!("The treasury debit was not approved");
+        }
+    });
+}
+
+/// The treasury accounts, their governance principal and the approvals not
+/// used yet.
+#[export_name = "canister_query treasury"]
+fn treasury_() {
+    check_can_read();
+    over(candid, |()| -> TreasuryInfo {
+        LEDGER.read().unwrap().treasury.info()
+    });
+}
+
+/// The log entries with an index of at least `since` that the ledger still
+/// holds. Only the controller and admins may read the log.
+#[export_name = "canister_query get_logs"]
+fn get_logs_() {
+    check_can_read();
+    over(candid_one, |since: u64| -> Vec<LogEntry> {
+        check_role(Role::Admin, "read the logs");
+        log::LOGS.read().unwrap().entries_since(since)
+    });
+}
+
+/// Sets the lowest level that is logged. Only the controller and admins may
+/// do this.
+/// The level is reset to `LogLevel::Info` on upgrade.
+#[export_name = "canister_update set_log_level"]
+fn set_log_level_() {
+    over(candid_one, |level: LogLevel| {
+        check_role(Role::Admin, "set the log level");
+        log::LOGS.write().unwrap().set_level(level);
+    });
+}
+
+/// What pre_upgrade would cost if the ledger were upgraded now, and whether
+/// it would refuse to run. Only the controller and admins may ask.
+#[export_name = "canister_query estimate_upgrade"]
+fn estimate_upgrade_() {
+    check_can_read();
+    over(candid, |()| -> UpgradeEstimate {
+        check_role(Role::Admin, "estimate upgrades");
+        upgrade::estimate_upgrade(&LEDGER.read().unwrap())
+    });
+}
+
+/// Sets the instruction budget pre_upgrade checks its estimate against. Only
+/// the controller and admins may do this.
+#[export_name = "canister_update set_upgrade_instruction_budget"]
+fn set_upgrade_instruction_budget_() {
+    over(candid_one, |budget: u64| {
+        check_role(Role::Admin, "set the upgrade instruction budget");
+        LEDGER.write().unwrap().upgrade_instruction_budget = budget;
+    });
+}
+
+#[export_name = "canister_query http_request"]
+fn http_request_() {
+    over(candid_one, |request: HttpRequest| {
+        let context = RequestContext {
+            certificate: data_certificate(),
+            resources: canister_resources(),
+        };
+        http::serve(&request, &LEDGER.read().unwrap(), &context)
+    });
+}
+
+#[export_name = "canister_query total_supply_pb"]
+fn total_supply_() {
+    check_can_read();
+    over(protobuf, |_: TotalSupplyArgs| total_supply())
+}
+
+/// Get multiple blocks by *offset into the container* (not BlockHeight) and
 /// length. Note that this simply iterates the blocks available in the Ledger
 /// without taking into account the archive. For example, if the ledger contains
 /// blocks with heights [100, 199] then iter_blocks(0, 1) will return the block
 /// with height 100.
 #[export_name = "canister_query iter_blocks_pb"]
 fn iter_blocks_() {
+    check_endpoint_enabled("iter_blocks_pb");
+    check_can_read();
     over(protobuf, |IterBlocksArgs { start, length }| {
         let blocks = &LEDGER.read().unwrap().blockchain.blocks;
         iter_blocks(blocks, start, length)
@@ -302,30 +2861,168 @@ fn iter_blocks_() {
 }
 
 /// Get multiple blocks by BlockHeight and length. If the query is outside the
-/// range stored in the Node the result is an error.
+/// range stored in the Node the result is an error, and a distinct `Pruned`
+/// error if it starts at a height the retention policy dropped. Requests for
+/// more than `MAX_BLOCKS_PER_REQUEST` blocks are truncated.
 #[export_name = "canister_query get_blocks_pb"]
 fn get_blocks_() {
+    check_endpoint_enabled("get_blocks_pb");
+    check_can_read();
     over(protobuf, |GetBlocksArgs { start, length }| {
         let blockchain: &Blockchain = &LEDGER.read().unwrap().blockchain;
-        let start_offset = blockchain.num_archived_blocks();
-        get_blocks(&blockchain.blocks, start_offset, start, length)
+        blockchain.get_blocks(start, length)
+    });
+}
+
+/// Candid version of get_blocks_pb.
+#[export_name = "canister_query get_blocks"]
+fn get_blocks_candid_() {
+    check_can_read();
+    over(candid_one, |GetBlocksArgs { start, length }| {
+        let blockchain: &Blockchain = &LEDGER.read().unwrap().blockchain;
+        blockchain.get_blocks(start, length)
+    });
+}
+
+/// Like get_blocks, but with everything a client needs to check the blocks
+/// against the ledger's certificate: the tip hash, the alias registry's root
+/// hash and the certificate itself.
+#[export_name = "canister_query query_encoded_blocks"]
+fn query_encoded_blocks_() {
+    check_can_read();
+    over(candid_one, |GetBlocksArgs { start, length }| {
+        let ledger = LEDGER.read().unwrap();
+        let res = ledger.blockchain.get_blocks(start, length);
+        QueryEncodedBlocksRes {
+            blocks: res.blocks,
+            first_block_index: res.first_block_index,
+            chain_length: res.chain_length,
+            tip_hash: ledger.blockchain.last_hash,
+            aliases_root_hash: ledger.aliases.root_hash(),
+            certificate: data_certificate(),
+        }
+    });
+}
+
+/// The certificate of the ledger's certified data and the hash tree revealing
+/// the height and hash of the last block in it, for ICRC-3 indexers. Only
+/// available in non-replicated queries.
+#[export_name = "canister_query icrc3_get_tip_certificate"]
+fn icrc3_get_tip_certificate_() {
+    check_can_read();
+    over(candid, |()| {
+        let ledger = LEDGER.read().unwrap();
+        data_certificate().map(|certificate| TipCertificate {
+            certificate,
+            hash_tree: tip_hash_tree(ledger.tip(), ledger.aliases.root_hash()),
+        })
+    });
+}
+
+/// The block at `height`, with what is needed to check it against the
+/// ledger's certificate right away, see `BlockCertificate`. Only available
+/// in non-replicated queries, for blocks at most `MAX_CERTIFIED_BLOCK_DEPTH`
+/// below the certified tip.
+#[export_name = "canister_query get_block_with_certificate"]
+fn get_block_with_certificate_() {
+    check_can_read();
+    over(candid_one, |height: BlockHeight| -> BlockCertificate {
+        let certificate = data_certificate()
+            .unwrap_or_else(|| panic!("Certificates are only available in non-replicated queries"));
+        LEDGER
+            .read()
+            .unwrap()
+            .block_certificate(height, certificate, dfn_core::api::id().get().as_slice())
+            .unwrap_or_else(|e| panic!("{}", e))
+    });
+}
+
+/// The hash kept for a block that the retention policy pruned, if it is
+/// among the newest `MAX_PRUNED_HASHES` pruned ones.
+#[export_name = "canister_query pruned_block_hash"]
+fn pruned_block_hash_() {
+    check_can_read();
+    over(candid_one, |height: BlockHeight| {
+        LEDGER.read().unwrap().blockchain.pruned_block_hash(height)
     });
 }
 
+/// Records that the blocks below `end` were exported, so that the retention
+/// policy may prune them. Only the controller and admins may do this.
+fn confirm_blocks_exported(end: BlockHeight) {
+    check_role(Role::Admin, "confirm block exports");
+    LEDGER
+        .write()
+        .unwrap()
+        .blockchain
+        .confirm_exported(end)
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+#[export_name = "canister_update confirm_blocks_exported"]
+fn confirm_blocks_exported_() {
+    over(candid_one, confirm_blocks_exported);
+}
+
 #[export_name = "canister_post_upgrade"]
 fn post_upgrade() {
-    over_init(|_: BytesS| {
+    over_init(|BytesS(args): BytesS| {
         let mut ledger = LEDGER.write().unwrap();
         *ledger = serde_cbor::from_reader(&mut stable::StableReader::new())
             .expect("Decoding stable memory failed");
+        ledger
+            .blockchain
+            .set_block_storage(Arc::new(StableBlockStorage))
+            .expect("Restoring the blocks from the block log failed");
+        ledger.balances.recount();
+        ledger.seed_clock();
+        ledger
+            .blockchain
+            .migrate_block_stats()
+            .expect("Computing the block statistics failed");
+        ledger.migrate_notify_whitelist();
+        ledger.aliases.migrate();
+        ledger.clear_corruption();
 
-        set_certified_data(
-            &ledger
-                .blockchain
-                .last_hash
-                .map(|h| h.into_bytes())
-                .unwrap_or([0u8; 32]),
-        );
+        if !args.is_empty() {
+            let LedgerCanisterUpgradePayload {
+                maximum_number_of_accounts,
+                accounts_overflow_trim_quantity,
+                accounts_overflow_policy,
+                access_policy,
+                retention_policy,
+                archive_lag_throttle,
+                reject_unspendable_recipients,
+                burn_account,
+            } = CandidOne::<LedgerCanisterUpgradePayload>::from_bytes(args)
+                .expect("Decoding the upgrade argument failed")
+                .0;
+            ledger
+                .set_account_limits(maximum_number_of_accounts, accounts_overflow_trim_quantity)
+                .expect("Invalid account limits");
+            if let Some(policy) = accounts_overflow_policy {
+                ledger.set_accounts_overflow_policy(policy);
+            }
+            if let Some(policy) = access_policy {
+                ledger.set_access_policy(policy);
+            }
+            if let Some(policy) = retention_policy {
+                ledger.set_retention_policy(policy);
+            }
+            if let Some(throttle) = archive_lag_throttle {
+                ledger.set_archive_lag_throttle(throttle);
+            }
+            if let Some(reject) = reject_unspendable_recipients {
+                ledger.set_reject_unspendable_recipients(reject);
+            }
+            if let Some(account) = burn_account {
+                ledger
+                    .set_burn_account(account)
+                    .expect("Invalid burn account");
+            }
+        }
+
+        set_certified_data(&ledger.certify());
     })
 }
 
@@ -337,11 +3034,25 @@ fn pre_upgrade() {
         printer::hook();
     });
 
-    let ledger = LEDGER
-        .read()
+    let mut ledger = LEDGER
+        .write()
         // This should never happen, but it's better to be safe than sorry
         .unwrap_or_else(|poisoned| poisoned.into_inner());
-    let mut writer = stable::StableWriter::new();
+    // The blocks are already in the block log.
+    if ledger.blockchain.blocks_are_persisted() {
+        ledger.blockchain.blocks.clear();
+    }
+    let estimate = upgrade::estimate_upgrade(&ledger);
+    if !estimate.within_budget() {
+        panic!(
+            "Refusing to upgrade: serializing about {} bytes of state is estimated to take {} \
+             instructions, more than the budget of {}. Raise the budget with \
+             set_upgrade_instruction_budget if the estimate is too pessimistic.",
+            estimate.state_size_bytes, estimate.instructions, estimate.instruction_budget
+        );
+    }
+    let mut writer =
+        BoundedWriter::new(stable::StableWriter::new(), MAX_SERIALIZED_LEDGER_BYTES);
     serde_cbor::to_writer(&mut writer, &*ledger).unwrap();
     writer
         .flush()
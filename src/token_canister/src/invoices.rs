@@ -0,0 +1,329 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+use crate::ic_token::TOKENs;
+use crate::types::{Memo, MemoBlob};
+use crate::TimeStamp;
+
+use candid::CandidType;
+use ic_base_types::PrincipalId;
+use ic_crypto_sha::Sha256;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::time::Duration;
+
+pub type InvoiceId = u64;
+
+/// The maximum number of payments recorded per invoice. Further payments
+/// before the expiry are refunded.
+pub const MAX_PAYMENTS_PER_INVOICE: usize = 100;
+/// How many refunds are made per heartbeat.
+pub const REFUNDS_PER_HEARTBEAT: usize = 10;
+/// How many expired invoices are closed per heartbeat.
+pub const EXPIRIES_PER_HEARTBEAT: usize = 100;
+/// The most invoices a merchant may have open at once, so that creating
+/// invoices for free can't grow the open invoices without bound.
+pub const MAX_OPEN_INVOICES_PER_MERCHANT: usize = 1_000;
+/// How long claimed and expired invoices are kept after their expiry before
+/// maintenance prunes them. Payments to the deposit account of a pruned
+/// invoice are no longer refunded.
+pub const INVOICE_RETENTION: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+static INVOICE_SUBACCOUNT_DOMAIN_SEPARATOR: &[u8] = b"\x0Finvoice-account";
+
+/// The subaccount of the ledger canister that payments of an invoice are
+/// made to.
+pub fn invoice_subaccount(id: InvoiceId) -> Subaccount {
+    let mut state = Sha256::new();
+    state.write(INVOICE_SUBACCOUNT_DOMAIN_SEPARATOR);
+    state.write(&id.to_be_bytes());
+    Subaccount(state.finish())
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct Invoice {
+    pub id: InvoiceId,
+    pub merchant: PrincipalId,
+    /// Where the payment goes when the merchant claims the invoice.
+    pub merchant_account: AccountIdentifier,
+    pub amount: TOKENs,
+    pub memo: Memo,
+    pub expires_at: TimeStamp,
+    /// The account payers transfer to.
+    pub deposit_account: AccountIdentifier,
+    /// The payments received before the expiry, by payer.
+    pub payments: Vec<(AccountIdentifier, TOKENs)>,
+    pub paid: TOKENs,
+    pub claimed: bool,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub enum InvoiceStatus {
+    Unpaid,
+    PartiallyPaid { paid: TOKENs },
+    Paid,
+    /// The merchant collected the payment.
+    Claimed,
+    /// The invoice expired before being fully paid. Partial payments are
+    /// refunded.
+    Expired,
+}
+
+impl Invoice {
+    pub fn status(&self, now: TimeStamp) -> InvoiceStatus {
+        if self.claimed {
+            InvoiceStatus::Claimed
+        } else if self.paid >= self.amount {
+            InvoiceStatus::Paid
+        } else if !now.is_before(self.expires_at) {
+            InvoiceStatus::Expired
+        } else if self.paid == TOKENs::ZERO {
+            InvoiceStatus::Unpaid
+        } else {
+            InvoiceStatus::PartiallyPaid { paid: self.paid }
+        }
+    }
+}
+
+/// Tags the blocks created by invoices, in their memo blob.
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub enum InvoiceEvent {
+    Claim { invoice_id: InvoiceId },
+    Refund { invoice_id: InvoiceId },
+}
+
+impl InvoiceEvent {
+    pub fn to_memo_blob(&self) -> MemoBlob {
+        MemoBlob::new(serde_cbor::ser::to_vec_packed(self).unwrap())
+            .expect("Invoice events always fit in a memo blob")
+    }
+}
+
+/// A payment to an invoice that has to be returned to its payer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PendingRefund {
+    pub invoice_id: InvoiceId,
+    pub from: AccountIdentifier,
+    pub to: AccountIdentifier,
+    pub amount: TOKENs,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Invoices {
+    invoices: BTreeMap<InvoiceId, Invoice>,
+    by_deposit_account: BTreeMap<AccountIdentifier, InvoiceId>,
+    /// Invoices that are neither paid nor expired yet, by expiry.
+    open: BTreeSet<(TimeStamp, InvoiceId)>,
+    /// How many invoices each merchant has open.
+    #[serde(default)]
+    open_by_merchant: BTreeMap<PrincipalId, usize>,
+    pending_refunds: VecDeque<PendingRefund>,
+    next_invoice_id: InvoiceId,
+    /// The invoice the next call of `prune` starts scanning from.
+    #[serde(default)]
+    prune_cursor: InvoiceId,
+}
+
+impl Invoices {
+    /// The id the next invoice will get.
+    pub fn next_id(&self) -> InvoiceId {
+        self.next_invoice_id
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(
+        &mut self,
+        merchant: PrincipalId,
+        merchant_account: AccountIdentifier,
+        amount: TOKENs,
+        memo: Memo,
+        expires_at: TimeStamp,
+        deposit_account: AccountIdentifier,
+        now: TimeStamp,
+    ) -> Result<Invoice, String> {
+        if amount == TOKENs::ZERO {
+            return Err("Invoices must be for a positive amount".to_string());
+        }
+        if !now.is_before(expires_at) {
+            return Err("Invoices must expire in the future".to_string());
+        }
+        let num_open = self.open_by_merchant.entry(merchant).or_default();
+        if *num_open >= MAX_OPEN_INVOICES_PER_MERCHANT {
+            return Err(format!(
+                "{} already has {} open invoices",
+                merchant, MAX_OPEN_INVOICES_PER_MERCHANT
+            ));
+        }
+        *num_open += 1;
+        let invoice = Invoice {
+            id: self.next_invoice_id,
+            merchant,
+            merchant_account,
+            amount,
+            memo,
+            expires_at,
+            deposit_account,
+            payments: vec![],
+            paid: TOKENs::ZERO,
+            claimed: false,
+        };
+        self.next_invoice_id += 1;
+        self.by_deposit_account.insert(deposit_account, invoice.id);
+        self.open.insert((expires_at, invoice.id));
+        self.invoices.insert(invoice.id, invoice.clone());
+        Ok(invoice)
+    }
+
+    pub fn get(&self, id: InvoiceId) -> Option<&Invoice> {
+        self.invoices.get(&id)
+    }
+
+    /// Records a transfer of `amount` from `from` to `to` if `to` is the
+    /// deposit account of an invoice. Payments that arrive after the invoice
+    /// expired or was fully paid are queued for a refund.
+    pub fn on_credit(
+        &mut self,
+        from: AccountIdentifier,
+        to: AccountIdentifier,
+        amount: TOKENs,
+        now: TimeStamp,
+    ) {
+        let id = match self.by_deposit_account.get(&to) {
+            Some(id) => *id,
+            None => return,
+        };
+        let invoice = self.invoices.get_mut(&id).unwrap();
+        match invoice.status(now) {
+            InvoiceStatus::Unpaid | InvoiceStatus::PartiallyPaid { .. }
+                if invoice.payments.len() < MAX_PAYMENTS_PER_INVOICE =>
+            {
+                invoice.payments.push((from, amount));
+                invoice.paid += amount;
+                if invoice.paid >= invoice.amount {
+                    let (expires_at, merchant) = (invoice.expires_at, invoice.merchant);
+                    self.close(expires_at, id, merchant);
+                }
+            }
+            _ => self.pending_refunds.push_back(PendingRefund {
+                invoice_id: id,
+                from: to,
+                to: from,
+                amount,
+            }),
+        }
+    }
+
+    fn close(&mut self, expires_at: TimeStamp, id: InvoiceId, merchant: PrincipalId) {
+        self.open.remove(&(expires_at, id));
+        if let Some(num_open) = self.open_by_merchant.get_mut(&merchant) {
+            *num_open -= 1;
+            if *num_open == 0 {
+                self.open_by_merchant.remove(&merchant);
+            }
+        }
+    }
+
+    /// Closes at most `max` of the invoices that expired by `now` without
+    /// being fully paid, oldest first, queueing refunds of their partial
+    /// payments. Returns how many were closed.
+    pub fn expire(&mut self, now: TimeStamp, max: usize) -> usize {
+        let expired: Vec<(TimeStamp, InvoiceId)> = self
+            .open
+            .iter()
+            .take(max)
+            .take_while(|(expires_at, _)| !now.is_before(*expires_at))
+            .cloned()
+            .collect();
+        for (expires_at, id) in expired.iter().copied() {
+            let merchant = self.invoices[&id].merchant;
+            self.close(expires_at, id, merchant);
+            let invoice = self.invoices.get_mut(&id).unwrap();
+            for (payer, amount) in invoice.payments.drain(..) {
+                self.pending_refunds.push_back(PendingRefund {
+                    invoice_id: id,
+                    from: invoice.deposit_account,
+                    to: payer,
+                    amount,
+                });
+            }
+            invoice.paid = TOKENs::ZERO;
+        }
+        expired.len()
+    }
+
+    /// Marks the paid invoice `id` of `merchant` as claimed, returning it.
+    pub fn claim(&mut self, merchant: &PrincipalId, id: InvoiceId) -> Result<Invoice, String> {
+        let invoice = self
+            .invoices
+            .get_mut(&id)
+            .ok_or_else(|| format!("Invoice {} does not exist", id))?;
+        if invoice.merchant != *merchant {
+            return Err(format!("Invoice {} belongs to another merchant", id));
+        }
+        if invoice.claimed {
+            return Err(format!("Invoice {} was already claimed", id));
+        }
+        if invoice.paid < invoice.amount {
+            return Err(format!("Invoice {} is not fully paid", id));
+        }
+        invoice.claimed = true;
+        Ok(invoice.clone())
+    }
+
+    /// Removes and returns at most `max` of the queued refunds, oldest
+    /// first. A refund that could not be made must be given back with
+    /// `requeue_refund`.
+    pub fn take_refunds(&mut self, max: usize) -> Vec<PendingRefund> {
+        let n = max.min(self.pending_refunds.len());
+        self.pending_refunds.drain(..n).collect()
+    }
+
+    /// Queues `refund` again, behind the others, after it failed.
+    pub fn requeue_refund(&mut self, refund: PendingRefund) {
+        self.pending_refunds.push_back(refund);
+    }
+
+    pub fn num_pending_refunds(&self) -> usize {
+        self.pending_refunds.len()
+    }
+
+    /// Removes the invoices that were claimed, or expired without payments
+    /// to refund, more than `INVOICE_RETENTION` ago. At most `max` invoices
+    /// are scanned, resuming where the previous call stopped. Returns how
+    /// many were removed.
+    pub fn prune(&mut self, now: TimeStamp, max: usize) -> usize {
+        let scanned: Vec<InvoiceId> = self
+            .invoices
+            .range(self.prune_cursor..)
+            .take(max)
+            .map(|(id, _)| *id)
+            .collect();
+        self.prune_cursor = match scanned.last() {
+            Some(last) if scanned.len() == max => last + 1,
+            _ => 0,
+        };
+        let mut pruned = 0;
+        for id in scanned {
+            let invoice = &self.invoices[&id];
+            let closed = match invoice.status(now) {
+                InvoiceStatus::Claimed => true,
+                InvoiceStatus::Expired => invoice.payments.is_empty(),
+                _ => false,
+            };
+            if closed && invoice.expires_at.has_elapsed(INVOICE_RETENTION, now) {
+                self.by_deposit_account.remove(&invoice.deposit_account);
+                self.invoices.remove(&id);
+                pruned += 1;
+            }
+        }
+        pruned
+    }
+}
+
+/// Argument taken by the create_invoice endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct CreateInvoiceArgs {
+    pub amount: TOKENs,
+    pub memo: Memo,
+    pub expires_at: TimeStamp,
+    /// The subaccount of the caller the payment is paid out to.
+    pub to_subaccount: Option<Subaccount>,
+}
@@ -1,45 +1,116 @@
 use candid::CandidType;
 use ic_types::{CanisterId, PrincipalId};
-use intmap::IntMap;
 use lazy_static::lazy_static;
 use phantom_newtype::Id;
-use serde::{
-    de::{Deserializer, MapAccess, Visitor},
-    ser::SerializeMap,
-    Deserialize, Serialize, Serializer,
-};
+use serde::{Deserialize, Serialize};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::convert::TryInto;
 use std::fmt;
 use std::hash::Hash;
-use std::marker::PhantomData;
 use std::str::FromStr;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
+pub mod access_policy;
+pub mod admin_actions;
+pub mod account_activity;
+pub mod annotations;
+pub mod attestation;
+pub mod certification;
+pub mod conformance;
+pub mod deprecation;
+pub mod endpoint_flags;
+pub mod fee_collector;
 pub mod account_identifier;
+pub mod account_owners;
+pub mod alias;
+pub mod block_log;
+pub mod block_stats;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod ic_token;
 pub mod ic_block;
+#[cfg(feature = "canister")]
 pub mod interface;
+pub mod invoices;
+pub mod log;
+pub mod maintenance;
+pub mod minters;
 pub mod hashof;
+pub mod guard;
+pub mod health;
+pub mod height_bitmap;
+pub mod hooks;
+pub mod http;
+pub mod payment_callbacks;
+pub mod pending_transfers;
+pub mod refunds;
+pub mod roles;
+pub mod signed_transfer;
+pub mod state_hash;
+pub mod staking;
+pub mod streams;
+pub mod supply_history;
+pub mod treasury;
 pub mod types;
+pub mod upgrade;
+#[cfg(feature = "verify")]
+pub mod verify;
 
 #[path = "../gen/ic_ledger.pb.v1.rs"]
 #[rustfmt::skip]
 pub mod protobuf;
+pub mod time_source;
 pub mod timestamp;
 pub mod validate_endpoints;
 
-use dfn_core::api::now;
+pub use time_source::{ledger_time, IcTimeSource, ManualTimeSource, TimeSource};
+pub use certification::{certified_data, BlockCertificate, CertifiedTip, RecentCertifiedTips};
 pub use account_identifier::{AccountIdentifier, Subaccount};
 pub use ic_token::{TOKENs, DECIMAL_PLACES, TOKEN_SUBDIVIDABLE_BY, MIN_BURN_AMOUNT, TRANSACTION_FEE};
-pub use ic_block::{ Block, Blockchain, EncodedBlock, BlockHeight, get_blocks };
+pub use ic_token::{validate_decimals, MAX_DECIMAL_PLACES};
+pub use ic_block::{
+    Block, Blockchain, EncodedBlock, BlockHeight, GetBlocksError, RetentionPolicy,
+    get_blocks, MAX_BLOCKS_PER_REQUEST,
+};
 pub use protobuf::TimeStamp;
-pub use types::{ Operation, Transaction, Memo};
+pub use types::{ Operation, Transaction, Memo, EncryptedMemo, MemoBlob, MintReason};
+pub use types::AdminActionStatus;
+pub use types::MAX_ENCRYPTED_MEMO_LENGTH;
+pub use types::MAX_MEMO_BLOB_LENGTH;
 pub use hashof::HashOf;
+pub use alias::AliasRegistry;
+pub use account_owners::AccountOwners;
+pub use account_activity::{AccountActivities, AccountSummary};
+pub use supply_history::{SupplyCheckpoint, SupplyHistory, SupplyHistoryArgs};
+pub use payment_callbacks::PaymentCallbacks;
+pub use minters::Minters;
+pub use admin_actions::{AdminAction, AdminActions, PendingAdminAction};
+pub use hooks::OperationHooks;
+pub use staking::Staking;
+pub use streams::Streams;
+pub use invoices::Invoices;
+pub use pending_transfers::PendingTransfers;
+use pending_transfers::{pending_transfer_subaccount, PendingTransfer, PendingTransferEvent};
+use invoices::{InvoiceEvent, PendingRefund, EXPIRIES_PER_HEARTBEAT};
+use guard::AccountGuard;
+use state_hash::BalancesHash;
+pub use refunds::Refunds;
+pub use roles::{Role, RoleRegistry};
+pub use treasury::{Treasury, TreasuryApproval};
+pub use endpoint_flags::EndpointFlags;
+pub use height_bitmap::HeightBitmap;
+pub use deprecation::DeprecatedEndpointUsage;
+pub use annotations::{Annotation, Annotations};
+pub use attestation::BalanceAttestation;
+pub use maintenance::{Maintenance, MaintenanceReport, MAX_MAINTENANCE_WORK};
+pub use access_policy::{
+    AccessPolicy, AccessPolicyConfig, NotifyWhitelist, ReaderList, SendWhitelist, SpendTracker,
+};
 
 // Helper to print messages in magenta
+#[cfg(feature = "canister")]
 pub fn print<S: std::convert::AsRef<str>>(s: S)
 where
     yansi::Paint<S>: std::string::ToString,
@@ -47,8 +118,158 @@ where
     dfn_core::api::print(yansi::Paint::magenta(s).to_string());
 }
 
+#[cfg(not(feature = "canister"))]
+pub fn print<S: std::convert::AsRef<str>>(s: S)
+where
+    yansi::Paint<S>: std::string::ToString,
+{
+    eprintln!("{}", yansi::Paint::magenta(s).to_string());
+}
+
 pub type Certification = Option<Vec<u8>>;
 
+/// Why the ledger refused to apply a transaction.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum TransferError {
+    /// The transaction was created more than `allowed_window_nanos` before
+    /// the ledger's current time.
+    TxTooOld { allowed_window_nanos: u64 },
+    /// The transaction's `created_at_time` is further in the future than the
+    /// permitted drift. `ledger_time` is the ledger's clock so that the client
+    /// can correct its own.
+    TxCreatedInFuture { ledger_time: TimeStamp },
+    /// An identical transaction was already applied in the block
+    /// `duplicate_of`.
+    TxDuplicate { duplicate_of: BlockHeight },
+    /// The transaction would create a new account but the ledger already
+    /// holds `limit` accounts.
+    TooManyAccounts { limit: u64 },
+    /// The client asked for the transaction not to be executed after
+    /// `expires_at`, which is before `ledger_time`.
+    TxExpired {
+        expires_at: TimeStamp,
+        ledger_time: TimeStamp,
+    },
+    /// Archiving is falling behind, so the ledger only accepts mints and
+    /// burns until it catches up. The transaction can be retried later.
+    TemporarilyUnavailable,
+    /// Nobody controls the recipient `to`, so the tokens would be lost. See
+    /// `AccountIdentifier::is_unspendable`.
+    BadRecipient { to: AccountIdentifier },
+    /// The ledger is read-only while it is being migrated. The transaction
+    /// can be retried after `until`.
+    Migrating { until: TimeStamp },
+    /// The ledger found its state inconsistent, e.g. the minted tokens no
+    /// longer add up, and is read-only until it is upgraded with a fix.
+    LedgerCorrupted { reason: String },
+    /// `from` is a treasury account and the governance principal did not
+    /// approve the debit.
+    TreasuryApprovalRequired { from: AccountIdentifier },
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TxTooOld {
+                allowed_window_nanos,
+            } => write!(
+                f,
+                "Rejecting expired transaction: transactions older than {} seconds are not accepted",
+                Duration::from_nanos(*allowed_window_nanos).as_secs()
+            ),
+            Self::TxCreatedInFuture { ledger_time } => write!(
+                f,
+                "Rejecting transaction with timestamp in the future: ledger time is {} ns",
+                ledger_time.as_nanos()
+            ),
+            Self::TxDuplicate { duplicate_of } => write!(
+                f,
+                "Transaction already exists on chain at block {}",
+                duplicate_of
+            ),
+            Self::TxExpired {
+                expires_at,
+                ledger_time,
+            } => write!(
+                f,
+                "Rejecting transaction that expired at {} ns: ledger time is {} ns",
+                expires_at.as_nanos(),
+                ledger_time.as_nanos()
+            ),
+            Self::TooManyAccounts { limit } => write!(
+                f,
+                "Rejecting transaction: the ledger already holds the maximum of {} accounts",
+                limit
+            ),
+            Self::TemporarilyUnavailable => write!(
+                f,
+                "Rejecting transaction: archiving is behind, only mints and burns are accepted \
+                 until it catches up"
+            ),
+            Self::BadRecipient { to } => write!(
+                f,
+                "Rejecting transaction: nobody controls the recipient {}, the tokens would be lost",
+                to
+            ),
+            Self::Migrating { until } => write!(
+                f,
+                "Rejecting transaction: the ledger is read-only for a migration until {} ns",
+                until.as_nanos()
+            ),
+            Self::LedgerCorrupted { reason } => write!(
+                f,
+                "Rejecting transaction: the ledger is read-only because its state is corrupted: {}",
+                reason
+            ),
+            Self::TreasuryApprovalRequired { from } => write!(
+                f,
+                "Rejecting transaction: debits of the treasury account {} must be approved by \
+                 the governance principal",
+                from
+            ),
+        }
+    }
+}
+
+/// An error returned by `Ledger::add_payment`.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum PaymentError {
+    /// The ledger is in an unexpected state, e.g. the block could not be
+    /// appended to the chain.
+    Reject(String),
+    TransferError(TransferError),
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Reject(msg) => write!(f, "{}", msg),
+            Self::TransferError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<TransferError> for PaymentError {
+    fn from(e: TransferError) -> Self {
+        Self::TransferError(e)
+    }
+}
+
+/// The optional parts of a payment passed to `Ledger::add_payment`. Callers
+/// set the ones they need and take the rest from `Default`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaymentOptions {
+    /// Deduplicates the payment within the transaction window. Defaults to
+    /// the time the block is added.
+    pub created_at_time: Option<TimeStamp>,
+    /// The payment is rejected once this time has passed.
+    pub expires_at: Option<TimeStamp>,
+    /// Deduplicates the payment per account, independently of its contents.
+    pub idempotency_key: Option<(AccountIdentifier, IdempotencyKey)>,
+    pub encrypted_memo: Option<EncryptedMemo>,
+    pub memo_blob: Option<MemoBlob>,
+}
+
 pub type LedgerBalances = Balances<HashMap<AccountIdentifier, TOKENs>>;
 
 pub trait BalancesStore {
@@ -96,6 +317,15 @@ pub struct Balances<S: BalancesStore> {
     // account balances at the tip of the chain
     pub store: S,
     pub icpt_pool: TOKENs,
+    /// The sum of the balances in `store`, kept up to date by `debit` and
+    /// `credit` so that `check_supply` takes constant time. It is not
+    /// persisted but recounted after an upgrade.
+    #[serde(skip)]
+    balances_e8s: u128,
+    /// The hash of the balances in `store`, kept up to date and recounted
+    /// the same way as `balances_e8s`.
+    #[serde(skip)]
+    balances_hash: BalancesHash,
 }
 
 impl<S: Default + BalancesStore> Default for Balances<S> {
@@ -109,10 +339,60 @@ impl<S: Default + BalancesStore> Balances<S> {
         Self {
             store: S::default(),
             icpt_pool: TOKENs::MAX,
+            balances_e8s: 0,
+            balances_hash: BalancesHash::default(),
+        }
+    }
+
+    pub fn balances_hash(&self) -> &BalancesHash {
+        &self.balances_hash
+    }
+
+    fn rehash(&mut self, account: &AccountIdentifier, old: TOKENs, new: TOKENs) {
+        if !old.is_zero() {
+            self.balances_hash.remove(account, old);
+        }
+        if !new.is_zero() {
+            self.balances_hash.insert(account, new);
         }
     }
 
-    pub fn add_payment(&mut self, payment: &Operation) {
+    /// Fails if the balances and the pool of unminted tokens don't add up to
+    /// `TOKENs::MAX`.
+    pub fn check_supply(&self) -> Result<(), String> {
+        let total = self.balances_e8s + self.icpt_pool.get_e8s() as u128;
+        if total != TOKENs::MAX.get_e8s() as u128 {
+            return Err(format!(
+                "The balances and the pool add up to {} e8s instead of {}",
+                total,
+                TOKENs::MAX.get_e8s()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Applies `payment` to the balances. Fails without changing anything if
+    /// the pool of unminted tokens would over- or underflow, which means the
+    /// balances are inconsistent with the chain.
+    pub fn add_payment(&mut self, payment: &Operation) -> Result<(), String> {
+        self.add_payment_with_fee_collector(payment, None)
+    }
+
+    /// Like `add_payment`, but credits the fee to `fee_collector` instead of
+    /// returning it to the pool, if it is set.
+    pub fn add_payment_with_fee_collector(
+        &mut self,
+        payment: &Operation,
+        fee_collector: Option<&AccountIdentifier>,
+    ) -> Result<(), String> {
+        let collected_fee = match (fee_collector, payment) {
+            (None, _)
+            | (Some(_), Operation::SetMintingAccount { .. })
+            | (Some(_), Operation::AdminAudit { .. }) => TOKENs::ZERO,
+            (Some(_), Operation::Transfer { fee, .. })
+            | (Some(_), Operation::Burn { fee, .. })
+            | (Some(_), Operation::Mint { fee, .. }) => *fee,
+        };
         match payment {
             Operation::Transfer {
                 from,
@@ -120,25 +400,41 @@ impl<S: Default + BalancesStore> Balances<S> {
                 amount,
                 fee,
             } => {
-                let debit_amount = (*amount + *fee).expect("amount + fee failed");
+                let debit_amount = (*amount + *fee)?;
+                let pool = (self.icpt_pool + (*fee - collected_fee)?)
+                    .map_err(|e| format!("Returning the fee to the pool failed: {}", e))?;
                 self.debit(from, debit_amount);
                 self.credit(to, *amount);
-                self.icpt_pool += *fee;
+                self.icpt_pool = pool;
             }
-            Operation::Burn { from, amount, .. } => {
-                self.debit(from, *amount);
-                self.icpt_pool += *amount;
+            Operation::Burn { from, amount, fee } => {
+                let debit_amount = (*amount + *fee)?;
+                let pool = (self.icpt_pool + (debit_amount - collected_fee)?)
+                    .map_err(|e| format!("Returning burned tokens to the pool failed: {}", e))?;
+                self.debit(from, debit_amount);
+                self.icpt_pool = pool;
             }
-            Operation::Mint { to, amount, .. } => {
-                self.credit(to, *amount);
-                self.icpt_pool -= *amount;
+            Operation::Mint { to, amount, fee } => {
+                let credit_amount = (*amount - *fee)?;
+                let pool = (self.icpt_pool - (credit_amount + collected_fee)?)
+                    .map_err(|e| format!("Taking minted tokens from the pool failed: {}", e))?;
+                self.credit(to, credit_amount);
+                self.icpt_pool = pool;
+            }
+            Operation::SetMintingAccount { .. } | Operation::AdminAudit { .. } => {}
+        }
+        if let Some(fee_collector) = fee_collector {
+            if !collected_fee.is_zero() {
+                self.credit(fee_collector, collected_fee);
             }
         }
+        Ok(())
     }
 
     // Debiting an account will automatically remove it from the `inner`
     // HashMap if the balance reaches zero.
     pub fn debit(&mut self, from: &AccountIdentifier, amount: TOKENs) {
+        let old = self.account_balance(from);
         self.store.update(*from, |prev| {
             let mut balance = match prev {
                 Some(x) => *x,
@@ -153,11 +449,14 @@ impl<S: Default + BalancesStore> Balances<S> {
             balance -= amount;
             balance
         });
+        self.balances_e8s -= amount.get_e8s() as u128;
+        self.rehash(from, old, self.account_balance(from));
     }
 
     // Crediting an account will automatically add it to the `inner` HashMap if
     // not already present.
     pub fn credit(&mut self, to: &AccountIdentifier, amount: TOKENs) {
+        let old = self.account_balance(to);
         self.store.update(*to, |prev| {
             let mut balance = match prev {
                 Some(x) => *x,
@@ -166,6 +465,8 @@ impl<S: Default + BalancesStore> Balances<S> {
             balance += amount;
             balance
         });
+        self.balances_e8s += amount.get_e8s() as u128;
+        self.rehash(to, old, self.account_balance(to));
     }
 
     pub fn account_balance(&self, account: &AccountIdentifier) -> TOKENs {
@@ -178,20 +479,30 @@ impl<S: Default + BalancesStore> Balances<S> {
     /// Returns the total quantity of ICPs that are "in existence" -- that
     /// is, excluding un-minted "potential" ICPs.
     pub fn total_supply(&self) -> TOKENs {
-        (TOKENs::MAX - self.icpt_pool).unwrap_or_else(|e| {
-            panic!(
-                "It is expected that the icpt_pool is always smaller than \
-            or equal to TOKENs::MAX, yet subtracting it lead to the following error: {}",
-                e
-            )
-        })
+        // The pool is at most `TOKENs::MAX`, so this cannot underflow.
+        TOKENs::from_e8s(TOKENs::MAX.get_e8s() - self.icpt_pool.get_e8s())
     }
 }
 
 impl LedgerBalances {
+    /// Recounts the sum and the hash of the balances, which are not
+    /// persisted.
+    pub fn recount(&mut self) {
+        self.balances_e8s = self
+            .store
+            .values()
+            .map(|balance| balance.get_e8s() as u128)
+            .sum();
+        let mut balances_hash = BalancesHash::default();
+        for (account, balance) in self.store.iter() {
+            balances_hash.insert(account, *balance);
+        }
+        self.balances_hash = balances_hash;
+    }
+
     // Find the specified number of accounts with lowest balances so that their
     // balances can be reclaimed.
-    fn select_accounts_to_trim(&mut self, num_accounts: usize) -> Vec<(TOKENs, AccountIdentifier)> {
+    pub fn select_accounts_to_trim(&mut self, num_accounts: usize) -> Vec<(TOKENs, AccountIdentifier)> {
         let mut to_trim: std::collections::BinaryHeap<(TOKENs, AccountIdentifier)> =
             std::collections::BinaryHeap::new();
 
@@ -217,64 +528,54 @@ impl LedgerBalances {
     }
 }
 
-
-fn serialize_int_map<S>(im: &IntMap<()>, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    let mut map = serializer.serialize_map(Some(im.len()))?;
-    for (k, v) in im.iter() {
-        map.serialize_entry(k, v)?;
-    }
-    map.end()
+/// What the ledger does when the number of accounts reaches
+/// `maximum_number_of_accounts`.
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountsOverflowPolicy {
+    /// Once the cap is exceeded by `accounts_overflow_trim_quantity`
+    /// accounts, burn the balances of that many accounts with the lowest
+    /// balances.
+    Trim,
+    /// Reject transactions that would create a new account.
+    RejectNewAccounts,
 }
 
-struct IntMapVisitor<V> {
-    marker: PhantomData<fn() -> IntMap<V>>,
+/// Throttles the ledger when archiving cannot keep up with new blocks, to
+/// protect its memory without pausing it entirely.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveLagThrottle {
+    /// The number of unarchived blocks at which archiving is triggered.
+    pub trigger_threshold: u64,
+    /// Transfers are rejected while more than `trigger_threshold *
+    /// max_lag_multiple` blocks are waiting to be archived.
+    pub max_lag_multiple: u64,
 }
 
-impl<V> IntMapVisitor<V> {
-    fn new() -> Self {
-        IntMapVisitor {
-            marker: PhantomData,
-        }
+impl Default for AccountsOverflowPolicy {
+    fn default() -> Self {
+        Self::Trim
     }
 }
 
-impl<'de, V> Visitor<'de> for IntMapVisitor<V>
-where
-    V: Deserialize<'de>,
-{
-    type Value = IntMap<V>;
-
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a very special map")
-    }
-
-    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
-    where
-        M: MapAccess<'de>,
-    {
-        let mut map = IntMap::with_capacity(access.size_hint().unwrap_or(0));
+/// How long a change of the minting account is announced before it takes
+/// effect, so that it can't be made in a hurry by a compromised controller.
+pub const MINTING_ACCOUNT_CHANGE_TIMELOCK: Duration = Duration::from_secs(48 * 60 * 60);
 
-        while let Some((key, value)) = access.next_entry()? {
-            map.insert(key, value);
-        }
-
-        Ok(map)
-    }
-}
-
-fn deserialize_int_map<'de, D>(deserializer: D) -> Result<IntMap<()>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    deserializer.deserialize_map(IntMapVisitor::new())
+/// A change of the minting account that takes effect at `executable_at`.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct PendingMintingAccountChange {
+    pub new: AccountIdentifier,
+    pub scheduled_at: TimeStamp,
+    pub executable_at: TimeStamp,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Ledger {
     pub symbol: String,
+    /// How many decimals a whole token has. Amounts are always stored in
+    /// base units; this only affects how they are displayed and parsed.
+    #[serde(default = "default_decimals")]
+    pub decimals: u32,
     pub balances: LedgerBalances,
     pub blockchain: Blockchain,
     // A cap on the maximum number of accounts
@@ -282,16 +583,35 @@ pub struct Ledger {
     // When maximum number of accounts is exceeded, a specified number of
     // accounts with lowest balances are removed
     accounts_overflow_trim_quantity: usize,
+    #[serde(default)]
+    accounts_overflow_policy: AccountsOverflowPolicy,
     pub minting_account_id: Option<AccountIdentifier>,
+    /// The scheduled change of the minting account, if any.
+    #[serde(default)]
+    pending_minting_account_change: Option<PendingMintingAccountChange>,
+    /// Transfers to this account are recorded as burns, whatever their
+    /// amount.
+    #[serde(default)]
+    burn_account_id: Option<AccountIdentifier>,
+    /// Where fees are credited instead of being burned. `None` burns them.
+    #[serde(default)]
+    fee_collector: Option<AccountIdentifier>,
+    /// The fees credited to fee collectors so far.
+    #[serde(default)]
+    total_fees_collected: TOKENs,
+    /// The amount burned so far, fees excluded. Burns made before this was
+    /// tracked are not included.
+    #[serde(default)]
+    total_burned: TOKENs,
     // This is a set of blockheights that have been notified
-    #[serde(
-        serialize_with = "serialize_int_map",
-        deserialize_with = "deserialize_int_map",
-        default = "IntMap::new"
-    )]
-    pub blocks_notified: IntMap<()>,
+    #[serde(default)]
+    pub blocks_notified: HeightBitmap,
     /// How long transactions are remembered to detect duplicates.
     pub transaction_window: Duration,
+    /// How far ahead of the ledger's clock a transaction's `created_at_time`
+    /// may be before it is rejected.
+    #[serde(default = "default_permitted_drift")]
+    pub permitted_drift: Duration,
     /// For each transaction, record the block in which the
     /// transaction was created. This only contains transactions from
     /// the last `transaction_window` period.
@@ -300,29 +620,219 @@ pub struct Ledger {
     /// index / block timestamp. (Block timestamps are monotonically
     /// non-decreasing, so this is the same.)
     transactions_by_height: VecDeque<TransactionInfo>,
-    // Used to prevent non-whitelisted canisters from sending tokens
-    // send_whitelist: HashSet<CanisterId>,
+    /// Idempotency keys supplied by senders in the last
+    /// `transaction_window` period, scoped by the sending account, together
+    /// with the height and timestamp of the block that their transaction
+    /// created.
+    #[serde(default)]
+    transactions_by_idempotency_key:
+        BTreeMap<(AccountIdentifier, IdempotencyKey), (BlockHeight, TimeStamp)>,
+    /// The lowest nonce a pre-signed transfer from each account may carry:
+    /// one more than the last nonce it used. A single counter is kept per
+    /// account, whatever the nonces used.
+    #[serde(default)]
+    signed_transfer_nonces: BTreeMap<AccountIdentifier, u64>,
+    /// Human-readable names that principals chose for their accounts.
+    #[serde(default)]
+    pub aliases: AliasRegistry,
+    /// Accounts whose owners registered that the accounts are theirs.
+    #[serde(default)]
+    pub account_owners: AccountOwners,
+    /// Canisters to call when an account is credited.
+    #[serde(default)]
+    pub payment_callbacks: PaymentCallbacks,
+    /// Principals that may mint besides the owner of the minting account.
+    #[serde(default)]
+    pub minters: Minters,
+    /// Decides who may send tokens and which canisters may be notified.
+    #[serde(default)]
+    access_policy: AccessPolicyConfig,
+    /// The canisters that may be notified under the whitelist policy. `None`
+    /// on ledgers from before it was split from the send whitelist, until
+    /// `migrate_notify_whitelist` runs.
+    #[serde(default)]
+    notify_whitelist: Option<NotifyWhitelist>,
+    /// Who may call the queries.
+    #[serde(default)]
+    pub readers: ReaderList,
+    /// The administrative roles held by principals besides the controller.
+    #[serde(default)]
+    pub roles: RoleRegistry,
+    /// Sensitive admin changes waiting for their timelock.
+    #[serde(default)]
+    pub admin_actions: AdminActions,
+    /// Accounts whose debits must be approved by the governance principal.
+    #[serde(default)]
+    pub treasury: Treasury,
+    /// The endpoints disabled at runtime.
+    #[serde(default)]
+    pub endpoint_flags: EndpointFlags,
+    /// The last tips the ledger certified, to prove recent blocks against
+    /// certificates that lag behind.
+    #[serde(default)]
+    recent_certified_tips: RecentCertifiedTips,
+    /// How much the deprecated endpoints are still used.
+    #[serde(default)]
+    pub deprecated_endpoint_usage: DeprecatedEndpointUsage,
+    /// Labels that parties attached to their transactions after the fact.
+    #[serde(default)]
+    pub annotations: Annotations,
+    /// When the stale auxiliary state was last cleaned up.
+    #[serde(default)]
+    pub maintenance: Maintenance,
+    /// Recent spending of canisters with a spend cap.
+    #[serde(default)]
+    spend_tracker: SpendTracker,
+    /// Canisters called around mints, burns and transfers.
+    #[serde(default)]
+    pub operation_hooks: OperationHooks,
+    /// Tokens locked by their owners in exchange for rewards.
+    #[serde(default)]
+    pub staking: Staking,
+    /// Deposits vesting continuously from a sender to a recipient.
+    #[serde(default)]
+    pub streams: Streams,
+    /// Payment requests of merchants, with their deposit accounts.
+    #[serde(default)]
+    pub invoices: Invoices,
+    /// Transfers that their senders can still cancel.
+    #[serde(default)]
+    pub pending_transfers: PendingTransfers,
+    /// How much of recent transfers their recipients refunded.
+    #[serde(default)]
+    pub refunds: Refunds,
+    /// What accounts received and sent, for the account_summary endpoint.
+    #[serde(default)]
+    pub account_activities: AccountActivities,
+    /// The total supply after every mint and burn.
+    #[serde(default)]
+    pub supply_history: SupplyHistory,
+    /// How much of the chain to keep. `None` keeps every block.
+    #[serde(default)]
+    retention_policy: Option<RetentionPolicy>,
+    /// When to reject transfers because archiving is behind. `None` never
+    /// throttles.
+    #[serde(default)]
+    archive_lag_throttle: Option<ArchiveLagThrottle>,
+    /// While the ledger's time is before this, every transaction is rejected
+    /// with `TransferError::Migrating`.
+    #[serde(default)]
+    read_only_until: Option<TimeStamp>,
+    /// Why the ledger found its state inconsistent, if it did. Every
+    /// transaction is rejected with `TransferError::LedgerCorrupted` until
+    /// the next upgrade clears it.
+    #[serde(default)]
+    corruption: Option<String>,
+    /// Whether transfers and mints to unspendable accounts are rejected.
+    #[serde(default = "default_reject_unspendable_recipients")]
+    reject_unspendable_recipients: bool,
+    /// How many transactions left the transaction window so far.
+    #[serde(default)]
+    num_purged_transactions: u64,
+    /// pre_upgrade refuses to run if it is estimated to take more
+    /// instructions than this.
+    #[serde(default = "default_upgrade_instruction_budget")]
+    pub upgrade_instruction_budget: u64,
+    /// The clock the ledger reads the current time from.
+    #[serde(skip, default = "default_time_source")]
+    time_source: Arc<dyn TimeSource>,
+    /// The time of the newest block the ledger added, which `now` never
+    /// goes back before, even after an upgrade.
+    #[serde(default)]
+    last_time: TimeStamp,
+}
+
+fn default_permitted_drift() -> Duration {
+    ic_types::ingress::PERMITTED_DRIFT
+}
+
+fn default_decimals() -> u32 {
+    DECIMAL_PLACES
+}
+
+fn default_reject_unspendable_recipients() -> bool {
+    true
 }
 
+fn default_upgrade_instruction_budget() -> u64 {
+    upgrade::DEFAULT_UPGRADE_INSTRUCTION_BUDGET
+}
+
+fn default_time_source() -> Arc<dyn TimeSource> {
+    Arc::new(IcTimeSource)
+}
+
+/// The maximum number of blocks pruned at once, which bounds the work done
+/// per heartbeat.
+pub const MAX_BLOCKS_TO_PRUNE: usize = 1_000;
+
+/// The longest the ledger can be made read-only for at once.
+pub const MAX_READ_ONLY_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
 #[derive(Serialize, Deserialize, Debug)]
 struct TransactionInfo {
     block_timestamp: TimeStamp,
     transaction_hash: HashOf<Transaction>,
+    #[serde(default)]
+    idempotency_key: Option<(AccountIdentifier, IdempotencyKey)>,
 }
 
 impl Default for Ledger {
     fn default() -> Self {
         Self {
             symbol: "".to_string(),
+            decimals: default_decimals(),
             balances: LedgerBalances::default(),
             blockchain: Blockchain::default(),
             maximum_number_of_accounts: 50_000_000,
             accounts_overflow_trim_quantity: 100_000,
+            accounts_overflow_policy: AccountsOverflowPolicy::default(),
             minting_account_id: None,
-            blocks_notified: IntMap::new(),
+            pending_minting_account_change: None,
+            burn_account_id: None,
+            fee_collector: None,
+            total_fees_collected: TOKENs::ZERO,
+            total_burned: TOKENs::ZERO,
+            blocks_notified: HeightBitmap::new(),
             transaction_window: Duration::from_secs(24 * 60 * 60),
+            permitted_drift: default_permitted_drift(),
             transactions_by_hash: BTreeMap::new(),
             transactions_by_height: VecDeque::new(),
+            transactions_by_idempotency_key: BTreeMap::new(),
+            signed_transfer_nonces: BTreeMap::new(),
+            aliases: AliasRegistry::default(),
+            account_owners: AccountOwners::default(),
+            payment_callbacks: PaymentCallbacks::default(),
+            minters: Minters::default(),
+            access_policy: AccessPolicyConfig::default(),
+            notify_whitelist: Some(NotifyWhitelist::default()),
+            readers: ReaderList::default(),
+            roles: RoleRegistry::default(),
+            admin_actions: AdminActions::default(),
+            treasury: Treasury::default(),
+            endpoint_flags: EndpointFlags::default(),
+            recent_certified_tips: RecentCertifiedTips::default(),
+            deprecated_endpoint_usage: DeprecatedEndpointUsage::default(),
+            annotations: Annotations::default(),
+            maintenance: Maintenance::default(),
+            spend_tracker: SpendTracker::default(),
+            operation_hooks: OperationHooks::default(),
+            staking: Staking::default(),
+            streams: Streams::default(),
+            invoices: Invoices::default(),
+            pending_transfers: PendingTransfers::default(),
+            refunds: Refunds::default(),
+            account_activities: AccountActivities::default(),
+            supply_history: SupplyHistory::default(),
+            retention_policy: None,
+            archive_lag_throttle: None,
+            read_only_until: None,
+            corruption: None,
+            reject_unspendable_recipients: default_reject_unspendable_recipients(),
+            num_purged_transactions: 0,
+            upgrade_instruction_budget: default_upgrade_instruction_budget(),
+            time_source: default_time_source(),
+            last_time: TimeStamp::from_nanos(0),
         }
     }
 }
@@ -333,59 +843,235 @@ impl Ledger {
         &mut self,
         memo: Memo,
         payment: Operation,
-        created_at_time: Option<TimeStamp>,
-    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), String> {
-        self.add_payment_with_timestamp(memo, payment, created_at_time, dfn_core::api::now().into())
+        options: PaymentOptions,
+    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), PaymentError> {
+        // Never stamp a block earlier than its parent, even if the chain
+        // was initialized with a timestamp ahead of the clock.
+        let now = std::cmp::max(self.now(), self.blockchain.last_timestamp);
+        self.last_time = now;
+        self.add_payment_with_timestamp(memo, payment, options, now)
     }
 
-    /// Internal version of `add_payment` that takes a timestamp, for
-    /// testing.
+    /// Version of `add_payment` that takes the current time as an argument.
     fn add_payment_with_timestamp(
         &mut self,
         memo: Memo,
         payment: Operation,
-        created_at_time: Option<TimeStamp>,
+        options: PaymentOptions,
         now: TimeStamp,
-    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), String> {
-        self.purge_old_transactions(now);
+    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), PaymentError> {
+        let PaymentOptions {
+            created_at_time,
+            expires_at,
+            idempotency_key,
+            encrypted_memo,
+            memo_blob,
+        } = options;
+        self.purge_old_transactions(now, usize::MAX);
+
+        // Transfers to the burn account destroy the tokens.
+        let payment = match payment {
+            Operation::Transfer {
+                from,
+                to,
+                amount,
+                fee,
+            } if Some(to) == self.burn_account_id => Operation::Burn { from, amount, fee },
+            payment => payment,
+        };
+
+        if let Some((duplicate_of, _)) = idempotency_key
+            .as_ref()
+            .and_then(|k| self.transactions_by_idempotency_key.get(k))
+        {
+            return Err(TransferError::TxDuplicate {
+                duplicate_of: *duplicate_of,
+            }
+            .into());
+        }
 
         let created_at_time = created_at_time.unwrap_or(now);
 
-        if created_at_time + self.transaction_window < now {
-            return Err("Rejecting expired transaction.".to_owned());
+        if created_at_time
+            .saturating_add(self.transaction_window)
+            .is_before(now)
+        {
+            return Err(TransferError::TxTooOld {
+                allowed_window_nanos: self.transaction_window.as_nanos() as u64,
+            }
+            .into());
+        }
+
+        if created_at_time.is_after(now.saturating_add(self.permitted_drift)) {
+            return Err(TransferError::TxCreatedInFuture { ledger_time: now }.into());
+        }
+
+        if let Some(expires_at) = expires_at {
+            if expires_at.is_before(now) {
+                return Err(TransferError::TxExpired {
+                    expires_at,
+                    ledger_time: now,
+                }
+                .into());
+            }
+        }
+
+        if let Some(until) = self.read_only_until {
+            if now.is_before(until) {
+                return Err(TransferError::Migrating { until }.into());
+            }
+        }
+
+        if let Some(reason) = &self.corruption {
+            return Err(TransferError::LedgerCorrupted {
+                reason: reason.clone(),
+            }
+            .into());
+        }
+
+        // Debits from an account whose operation awaits, e.g. a transfer
+        // waiting for its hooks, would spend the balance that operation
+        // checked. It releases its guard before adding its own block.
+        if let Operation::Transfer { from, .. } | Operation::Burn { from, .. } = &payment {
+            AccountGuard::check(from).map_err(PaymentError::Reject)?;
+        }
+
+        if self.is_throttled() {
+            if let Operation::Transfer { .. } = payment {
+                return Err(TransferError::TemporarilyUnavailable.into());
+            }
+        }
+
+        if self.reject_unspendable_recipients {
+            if let Operation::Transfer { to, .. } | Operation::Mint { to, .. } = &payment {
+                if to.is_unspendable() {
+                    return Err(TransferError::BadRecipient { to: *to }.into());
+                }
+            }
         }
 
-        if created_at_time > now + ic_types::ingress::PERMITTED_DRIFT {
-            return Err("Rejecting transaction with timestamp in the future.".to_owned());
+        let treasury_debit = match &payment {
+            Operation::Transfer {
+                from, to, amount, ..
+            } => Some(TreasuryApproval {
+                from: *from,
+                to: Some(*to),
+                amount: *amount,
+            }),
+            Operation::Burn { from, amount, .. } => Some(TreasuryApproval {
+                from: *from,
+                to: None,
+                amount: *amount,
+            }),
+            _ => None,
+        };
+        if let Some(debit) = &treasury_debit {
+            if !self.treasury.allows(debit) {
+                return Err(TransferError::TreasuryApprovalRequired { from: debit.from }.into());
+            }
         }
 
         let transaction = Transaction {
             operation: payment.clone(),
             memo,
             created_at_time,
+            encrypted_memo,
+            memo_blob,
         };
 
         let transaction_hash = transaction.hash();
 
-        if self.transactions_by_hash.contains_key(&transaction_hash) {
-            return Err("Transaction already exists on chain.".to_owned());
+        if let Some(duplicate_of) = self.transactions_by_hash.get(&transaction_hash) {
+            return Err(TransferError::TxDuplicate {
+                duplicate_of: *duplicate_of,
+            }
+            .into());
         }
 
-        let block = Block::new_from_transaction(self.blockchain.last_hash, transaction, now);
+        if self.accounts_overflow_policy == AccountsOverflowPolicy::RejectNewAccounts
+            && self.balances.store.len() >= self.maximum_number_of_accounts
+        {
+            if let Operation::Transfer { to, amount, .. } | Operation::Mint { to, amount, .. } =
+                &payment
+            {
+                if *amount != TOKENs::ZERO && self.balances.store.get_balance(to).is_none() {
+                    return Err(TransferError::TooManyAccounts {
+                        limit: self.maximum_number_of_accounts as u64,
+                    }
+                    .into());
+                }
+            }
+        }
+
+        let mut block = Block::new_from_transaction(self.blockchain.last_hash, transaction, now);
         let block_timestamp = block.timestamp;
+        let fee = match payment {
+            Operation::Transfer { fee, .. }
+            | Operation::Burn { fee, .. }
+            | Operation::Mint { fee, .. } => fee,
+            Operation::SetMintingAccount { .. } | Operation::AdminAudit { .. } => TOKENs::ZERO,
+        };
+        if !fee.is_zero() {
+            block.fee_collector = self.fee_collector;
+        }
+
+        if let Err(reason) = self
+            .balances
+            .add_payment_with_fee_collector(&payment, block.fee_collector.as_ref())
+        {
+            self.mark_corrupted(reason.clone());
+            return Err(TransferError::LedgerCorrupted { reason }.into());
+        }
+        if let Operation::Burn { amount, .. } = payment {
+            self.total_burned += amount;
+        }
+        if block.fee_collector.is_some() {
+            self.total_fees_collected = (self.total_fees_collected + fee).unwrap_or(TOKENs::MAX);
+        }
 
-        self.balances.add_payment(&payment);
+        let height = self
+            .blockchain
+            .add_block(block)
+            .map_err(PaymentError::Reject)?;
 
-        let height = self.blockchain.add_block(block)?;
+        self.record_block(&payment, height, block_timestamp);
+        if let Some(debit) = &treasury_debit {
+            self.treasury.consume(debit);
+        }
 
         self.transactions_by_hash.insert(transaction_hash, height);
+        if let Some(key) = &idempotency_key {
+            self.transactions_by_idempotency_key
+                .insert(key.clone(), (height, block_timestamp));
+        }
         self.transactions_by_height.push_back(TransactionInfo {
             block_timestamp,
             transaction_hash,
+            idempotency_key,
         });
 
-        let to_trim = if self.balances.store.len()
-            >= self.maximum_number_of_accounts + self.accounts_overflow_trim_quantity
+        match payment {
+            Operation::Transfer { to, amount, .. } => {
+                self.payment_callbacks.on_credit(to, height, amount, memo);
+            }
+            Operation::Mint { to, amount, fee } => {
+                let credit_amount = (amount - fee).expect("amount - fee failed");
+                self.payment_callbacks.on_credit(to, height, credit_amount, memo);
+            }
+            Operation::Burn { .. }
+            | Operation::SetMintingAccount { .. }
+            | Operation::AdminAudit { .. } => {}
+        }
+        if let Operation::Transfer {
+            from, to, amount, ..
+        } = payment
+        {
+            self.invoices.on_credit(from, to, amount, now);
+        }
+
+        let to_trim = if self.accounts_overflow_policy == AccountsOverflowPolicy::Trim
+            && self.balances.store.len()
+                >= self.maximum_number_of_accounts + self.accounts_overflow_trim_quantity
         {
             self.balances
                 .select_accounts_to_trim(self.accounts_overflow_trim_quantity)
@@ -397,56 +1083,128 @@ impl Ledger {
             let operation = Operation::Burn {
                 from: account,
                 amount: balance,
+                fee: TOKENs::ZERO,
             };
-            self.balances.add_payment(&operation);
-            self.blockchain
+            if let Err(reason) = self.balances.add_payment(&operation) {
+                self.mark_corrupted(reason);
+                break;
+            }
+            let height = self
+                .blockchain
                 .add_block(Block::new_from_transaction(
                     self.blockchain.last_hash,
                     Transaction {
-                        operation,
+                        operation: operation.clone(),
                         memo: Memo::default(),
                         created_at_time: now,
+                        encrypted_memo: None,
+                        memo_blob: None,
                     },
                     now,
                 ))
                 .unwrap();
+            self.record_block(&operation, height, now);
         }
 
         Ok((height, self.blockchain.last_hash.unwrap()))
     }
 
-    /// Remove transactions older than `transaction_window`.
-    fn purge_old_transactions(&mut self, now: TimeStamp) {
+    /// The balance and activity of `account`.
+    pub fn account_summary(&self, account: &AccountIdentifier) -> AccountSummary {
+        self.account_activities
+            .summary(account, self.balances.account_balance(account))
+    }
+
+    /// The supply checkpoints from height `start` on, at most `limit` of
+    /// them.
+    pub fn supply_history(&self, start: BlockHeight, limit: usize) -> Vec<SupplyCheckpoint> {
+        self.supply_history.range(start, limit)
+    }
+
+    /// Counts `operation`, applied in the block at `height`, in the activity
+    /// of the accounts it touched, forgetting those it emptied, and
+    /// checkpoints the supply if it is a mint or burn.
+    fn record_block(&mut self, operation: &Operation, height: BlockHeight, timestamp: TimeStamp) {
+        if let Operation::Mint { .. } | Operation::Burn { .. } = operation {
+            self.supply_history.record(SupplyCheckpoint {
+                height,
+                timestamp,
+                total_supply: self.balances.total_supply(),
+            });
+        }
+        self.account_activities.record(operation, height);
+        let touched = match operation {
+            Operation::Transfer { from, to, .. } => vec![*from, *to],
+            Operation::Burn { from, .. } => vec![*from],
+            Operation::Mint { to, .. } => vec![*to],
+            Operation::SetMintingAccount { .. } | Operation::AdminAudit { .. } => vec![],
+        };
+        for account in touched {
+            if self.balances.account_balance(&account) == TOKENs::ZERO {
+                self.account_activities.forget(&account);
+            }
+        }
+    }
+
+    /// Remove up to `max` transactions older than `transaction_window`,
+    /// returning how many were removed.
+    fn purge_old_transactions(&mut self, now: TimeStamp, max: usize) -> usize {
+        let mut purged = 0;
         while let Some(TransactionInfo {
             block_timestamp,
             transaction_hash,
+            idempotency_key,
         }) = self.transactions_by_height.front()
         {
-            if *block_timestamp + self.transaction_window > now {
+            if purged == max || !block_timestamp.has_elapsed(self.transaction_window, now) {
                 // Stop at a sufficiently recent block.
                 break;
             }
             let removed = self.transactions_by_hash.remove(transaction_hash);
             assert!(removed.is_some());
+            if let Some(key) = idempotency_key {
+                self.transactions_by_idempotency_key.remove(key);
+            }
 
             // After 24 hours we don't need to store notification state because it isn't
             // accessible. We don't inspect the result because we don't care whether a
             // notification at this block height was made or not.
-            match removed {
-                Some(bh) => self.blocks_notified.remove(bh),
-                None => None,
-            };
+            if let Some(height) = removed {
+                self.blocks_notified.remove(height);
+            }
             self.transactions_by_height.pop_front();
+            self.num_purged_transactions += 1;
+            purged += 1;
+        }
+        purged
+    }
+
+    /// Removes a bounded amount of stale auxiliary state, see
+    /// `crate::maintenance`.
+    pub fn run_maintenance(&mut self, now: TimeStamp) -> MaintenanceReport {
+        let purged_transactions = self.purge_old_transactions(now, MAX_MAINTENANCE_WORK);
+        let pruned_invoices = self.invoices.prune(now, MAX_MAINTENANCE_WORK);
+        self.maintenance.record_run(now);
+        MaintenanceReport {
+            purged_transactions: purged_transactions as u64,
+            pruned_invoices: pruned_invoices as u64,
+            ran_at: now,
         }
     }
 
     /// This adds a pre created block to the ledger. This should only be used
     /// during canister migration or upgrade
     pub fn add_block(&mut self, block: Block) -> Result<BlockHeight, String> {
-        self.balances.add_payment(&block.transaction.operation);
-        self.blockchain.add_block(block)
+        let operation = block.transaction.operation.clone();
+        let timestamp = block.timestamp;
+        self.balances
+            .add_payment_with_fee_collector(&operation, block.fee_collector.as_ref())?;
+        let height = self.blockchain.add_block(block)?;
+        self.record_block(&operation, height, timestamp);
+        Ok(height)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn from_init(
         &mut self,
         symbol: String,
@@ -454,18 +1212,47 @@ impl Ledger {
         minting_account: AccountIdentifier,
         timestamp: TimeStamp,
         transaction_window: Option<Duration>,
+        permitted_drift: Option<Duration>,
+        maximum_number_of_accounts: Option<usize>,
+        accounts_overflow_trim_quantity: Option<usize>,
+        accounts_overflow_policy: Option<AccountsOverflowPolicy>,
+        access_policy: Option<AccessPolicyConfig>,
+        decimals: Option<u32>,
     ) {
         self.symbol = symbol;
+        if let Some(decimals) = decimals {
+            validate_decimals(decimals).expect("Invalid decimals");
+            self.decimals = decimals;
+        }
         self.balances.icpt_pool = TOKENs::MAX;
         self.minting_account_id = Some(minting_account);
         if let Some(t) = transaction_window {
             self.transaction_window = t;
         }
+        if let Some(d) = permitted_drift {
+            self.permitted_drift = d;
+        }
+        self.set_account_limits(maximum_number_of_accounts, accounts_overflow_trim_quantity)
+            .expect("Invalid account limits");
+        if let Some(policy) = accounts_overflow_policy {
+            self.accounts_overflow_policy = policy;
+        }
+        if let Some(policy) = access_policy {
+            self.access_policy = policy;
+        }
 
         for (to, amount) in initial_values.into_iter() {
             self.add_payment_with_timestamp(
                 Memo::default(),
-                Operation::Mint { to, amount },
+                Operation::Mint {
+                    to,
+                    amount,
+                    fee: TOKENs::ZERO,
+                },
+                None,
+                None,
+                None,
+                None,
                 None,
                 timestamp,
             )
@@ -473,21 +1260,308 @@ impl Ledger {
         }
     }
 
+    /// Checks that `new` could replace the minting account now.
+    fn validate_minting_account_change(
+        &self,
+        new: &AccountIdentifier,
+    ) -> Result<AccountIdentifier, String> {
+        let previous = self
+            .minting_account_id
+            .ok_or_else(|| "Minting account not initialized".to_string())?;
+        if *new == previous {
+            return Err(format!("{} is already the minting account", new));
+        }
+        if Some(*new) == self.burn_account_id {
+            return Err(format!("{} is the burn account", new));
+        }
+        if self.balances.account_balance(new) != TOKENs::ZERO {
+            return Err(format!(
+                "The new minting account {} must have a zero balance",
+                new
+            ));
+        }
+        Ok(previous)
+    }
+
+    /// Replaces the minting account with `new`, recording the change in an
+    /// audit block. `new` must not hold any funds, as transfers out of the
+    /// minting account are mints.
+    ///
+    /// This takes effect immediately; the canister schedules changes with
+    /// `schedule_minting_account_change` instead.
+    pub fn set_minting_account(
+        &mut self,
+        new: AccountIdentifier,
+    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), PaymentError> {
+        let previous = self
+            .validate_minting_account_change(&new)
+            .map_err(PaymentError::Reject)?;
+        let result = self.add_payment(
+            Memo::default(),
+            Operation::SetMintingAccount { previous, new },
+            PaymentOptions::default(),
+        )?;
+        self.minting_account_id = Some(new);
+        Ok(result)
+    }
+
+    /// Schedules the replacement of the minting account by `new` after
+    /// `MINTING_ACCOUNT_CHANGE_TIMELOCK`. Only one change can be scheduled at
+    /// a time.
+    pub fn schedule_minting_account_change(
+        &mut self,
+        new: AccountIdentifier,
+        now: TimeStamp,
+    ) -> Result<PendingMintingAccountChange, String> {
+        if let Some(pending) = &self.pending_minting_account_change {
+            return Err(format!(
+                "A change of the minting account to {} is already scheduled",
+                pending.new
+            ));
+        }
+        self.validate_minting_account_change(&new)?;
+        let change = PendingMintingAccountChange {
+            new,
+            scheduled_at: now,
+            executable_at: now + MINTING_ACCOUNT_CHANGE_TIMELOCK,
+        };
+        self.pending_minting_account_change = Some(change.clone());
+        Ok(change)
+    }
+
+    pub fn pending_minting_account_change(&self) -> Option<&PendingMintingAccountChange> {
+        self.pending_minting_account_change.as_ref()
+    }
+
+    pub fn cancel_minting_account_change(&mut self) -> Option<PendingMintingAccountChange> {
+        self.pending_minting_account_change.take()
+    }
+
+    /// Makes the scheduled change of the minting account if its timelock is
+    /// over at `now`. A change that became invalid in the meantime, e.g.
+    /// because the new account received funds, is dropped.
+    pub fn execute_minting_account_change(
+        &mut self,
+        now: TimeStamp,
+    ) -> Result<Option<(BlockHeight, HashOf<EncodedBlock>)>, PaymentError> {
+        match &self.pending_minting_account_change {
+            Some(change) if !now.is_before(change.executable_at) && !self.is_read_only() => {
+                let new = change.new;
+                self.pending_minting_account_change = None;
+                self.set_minting_account(new).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Pays the amount of a pending transfer out of its holding account of
+    /// `ledger_canister_id`, to the recipient when it is finalized and back
+    /// to the sender when it is cancelled.
+    pub fn settle_pending_transfer(
+        &mut self,
+        ledger_canister_id: PrincipalId,
+        transfer: &PendingTransfer,
+        event: PendingTransferEvent,
+    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), PaymentError> {
+        let holding = AccountIdentifier::new(
+            ledger_canister_id,
+            Some(pending_transfer_subaccount(transfer.id)),
+        );
+        let to = match event {
+            PendingTransferEvent::Cancel { .. } => transfer.sender_account,
+            _ => transfer.recipient_account,
+        };
+        self.add_payment(
+            transfer.memo,
+            Operation::Transfer {
+                from: holding,
+                to,
+                amount: transfer.amount,
+                fee: TOKENs::ZERO,
+            },
+            PaymentOptions {
+                memo_blob: Some(event.to_memo_blob()),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Finalizes at most `max` pending transfers whose cancellation window is
+    /// over at `now`. A transfer stays pending until its block is added, so
+    /// one whose payout fails, e.g. while the ledger is throttled, is
+    /// retried later.
+    pub fn finalize_due_pending_transfers(
+        &mut self,
+        ledger_canister_id: PrincipalId,
+        now: TimeStamp,
+        max: usize,
+    ) -> Vec<(PendingTransfer, Result<BlockHeight, PaymentError>)> {
+        let due = self.pending_transfers.due(now, max);
+        due.into_iter()
+            .map(|transfer| {
+                let event = PendingTransferEvent::Finalize {
+                    pending_transfer_id: transfer.id,
+                };
+                let result = self
+                    .settle_pending_transfer(ledger_canister_id, &transfer, event)
+                    .map(|(height, _)| {
+                        self.pending_transfers.settle(transfer.id);
+                        height
+                    });
+                (transfer, result)
+            })
+            .collect()
+    }
+
+    /// Closes the invoices that expired by `now` and makes at most `max` of
+    /// the queued refunds. A refund is queued again if its block can't be
+    /// added, e.g. while the ledger is throttled, so it is retried later.
+    pub fn process_invoice_refunds(
+        &mut self,
+        now: TimeStamp,
+        max: usize,
+    ) -> Vec<(PendingRefund, Result<BlockHeight, PaymentError>)> {
+        self.invoices.expire(now, EXPIRIES_PER_HEARTBEAT);
+        let refunds = self.invoices.take_refunds(max);
+        refunds
+            .into_iter()
+            .map(|refund| {
+                let result = self
+                    .add_payment(
+                        Memo(refund.invoice_id),
+                        Operation::Transfer {
+                            from: refund.from,
+                            to: refund.to,
+                            amount: refund.amount,
+                            fee: TOKENs::ZERO,
+                        },
+                        PaymentOptions {
+                            memo_blob: Some(
+                                InvoiceEvent::Refund {
+                                    invoice_id: refund.invoice_id,
+                                }
+                                .to_memo_blob(),
+                            ),
+                            ..Default::default()
+                        },
+                    )
+                    .map(|(height, _)| height);
+                if result.is_err() {
+                    self.invoices.requeue_refund(refund.clone());
+                }
+                (refund, result)
+            })
+            .collect()
+    }
+
+    /// Queues `action`, recording the proposal in an audit block.
+    pub fn propose_admin_action(
+        &mut self,
+        action: AdminAction,
+        proposed_by: PrincipalId,
+    ) -> Result<PendingAdminAction, PaymentError> {
+        self.validate_admin_action(&action)
+            .map_err(PaymentError::Reject)?;
+        let now = self.now();
+        let pending = self
+            .admin_actions
+            .propose(action, proposed_by, now)
+            .map_err(PaymentError::Reject)?;
+        if let Err(e) = self.add_admin_audit(&pending, AdminActionStatus::Proposed) {
+            self.admin_actions.remove(pending.id);
+            return Err(e);
+        }
+        Ok(pending)
+    }
+
+    /// Makes the change of the action `id` once its delay is over, recording
+    /// it in an audit block.
+    pub fn execute_admin_action(
+        &mut self,
+        id: u64,
+    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), PaymentError> {
+        let pending = self
+            .admin_actions
+            .executable(id, self.now())
+            .map_err(PaymentError::Reject)?
+            .clone();
+        self.validate_admin_action(&pending.action)
+            .map_err(PaymentError::Reject)?;
+        let result = self.add_admin_audit(&pending, AdminActionStatus::Executed)?;
+        self.admin_actions.remove(id);
+        match pending.action {
+            AdminAction::SetMinter {
+                principal,
+                quota,
+                expires_at,
+            } => self.minters.set(principal, quota, expires_at),
+            AdminAction::RemoveMinter { principal } => {
+                self.minters.remove(&principal);
+            }
+            AdminAction::SetAccessPolicy(policy) => self.set_access_policy(policy),
+            AdminAction::SetFeeCollector(account) => self.fee_collector = account,
+            AdminAction::SetAdminActionDelay { seconds } => {
+                self.admin_actions.set_delay(Duration::from_secs(seconds))
+            }
+            AdminAction::SetTreasuryGovernance(governance) => self
+                .treasury
+                .set_governance(governance)
+                .expect("the treasury governance was validated"),
+        }
+        Ok(result)
+    }
+
+    /// Checks that `action` can be applied to the ledger as it is. Actions
+    /// are checked when proposed and again when executed, as the ledger may
+    /// have changed in between.
+    fn validate_admin_action(&self, action: &AdminAction) -> Result<(), String> {
+        match action {
+            AdminAction::SetFeeCollector(Some(account)) => self.validate_fee_collector(account),
+            AdminAction::SetTreasuryGovernance(governance) => {
+                self.treasury.validate_governance(*governance)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Drops the pending action `id`, recording it in an audit block.
+    pub fn cancel_admin_action(&mut self, id: u64) -> Result<PendingAdminAction, PaymentError> {
+        let pending =
+            self.admin_actions.get(id).cloned().ok_or_else(|| {
+                PaymentError::Reject(format!("No admin action {} is pending", id))
+            })?;
+        self.add_admin_audit(&pending, AdminActionStatus::Cancelled)?;
+        self.admin_actions.remove(id);
+        Ok(pending)
+    }
+
+    fn add_admin_audit(
+        &mut self,
+        pending: &PendingAdminAction,
+        status: AdminActionStatus,
+    ) -> Result<(BlockHeight, HashOf<EncodedBlock>), PaymentError> {
+        let operation = Operation::AdminAudit {
+            action_id: pending.id,
+            status,
+            description: pending.action.to_string(),
+        };
+        self.add_payment(Memo::default(), operation, PaymentOptions::default())
+    }
+
     pub fn change_notification_state(
         &mut self,
         height: BlockHeight,
         block_timestamp: TimeStamp,
         new_state: bool,
-        now: TimeStamp,
     ) -> Result<(), String> {
-        if block_timestamp + self.transaction_window <= now {
+        if block_timestamp.has_elapsed(self.transaction_window, self.now()) {
             return Err(format!(
                 "You cannot send a notification for a transaction that is more than {} seconds old",
                 self.transaction_window.as_secs(),
             ));
         }
 
-        let is_notified = self.blocks_notified.get(height).is_some();
+        let is_notified = self.blocks_notified.contains(height);
 
         match (is_notified, new_state) {
             (true, true) | (false, false) => {
@@ -498,14 +1572,129 @@ impl Ledger {
                 Ok(())
             }
             (false, true) => {
-                self.blocks_notified.insert(height, ());
+                self.blocks_notified.insert(height);
+                Ok(())
+            }
+        }
+    }
+
+    /// The data this canister certifies, see `certified_data`.
+    pub fn certified_data(&self) -> [u8; 32] {
+        certified_data(self.tip(), self.aliases.root_hash())
+    }
+
+    /// Like `certified_data`, but remembers the tip it certifies, for
+    /// `block_certificate`. Call it whenever the certified data is set.
+    pub fn certify(&mut self) -> [u8; 32] {
+        let tip = CertifiedTip {
+            tip: self.tip(),
+            aliases_root_hash: self.aliases.root_hash(),
+        };
+        self.recent_certified_tips.record(tip);
+        tip.certified_data()
+    }
+
+    /// Everything needed to check the block at `height` against
+    /// `certificate`, the certificate handed to the ledger canister
+    /// `canister_id`. The block must be held by the ledger, covered by the
+    /// certificate, and at most `MAX_CERTIFIED_BLOCK_DEPTH` below the tip
+    /// the certificate covers.
+    pub fn block_certificate(
+        &self,
+        height: BlockHeight,
+        certificate: Vec<u8>,
+        canister_id: &[u8],
+    ) -> Result<BlockCertificate, String> {
+        let data = certification::certified_data_in(&certificate, canister_id)?;
+        let tip = self.recent_certified_tips.find(&data).ok_or_else(|| {
+            "The certificate does not cover a recent tip of the ledger, retry later".to_string()
+        })?;
+        let tip_height = match tip.tip {
+            Some((tip_height, _)) if height <= tip_height => tip_height,
+            _ => return Err(format!("Block {} is not certified yet", height)),
+        };
+        if tip_height - height > certification::MAX_CERTIFIED_BLOCK_DEPTH {
+            return Err(format!(
+                "Block {} is more than {} blocks below the certified tip {}",
+                height,
+                certification::MAX_CERTIFIED_BLOCK_DEPTH,
+                tip_height
+            ));
+        }
+        let get = |height| {
+            self.blockchain
+                .get(height)
+                .cloned()
+                .ok_or_else(|| format!("Block {} is not held by the ledger", height))
+        };
+        Ok(BlockCertificate {
+            height,
+            block: get(height)?,
+            descendants: (height + 1..=tip_height)
+                .map(get)
+                .collect::<Result<_, _>>()?,
+            certificate,
+            hash_tree: tip.hash_tree(),
+        })
+    }
+
+    /// Annotates the transaction at `height` for `account`, or removes its
+    /// annotation if `label` is `None`. `account` must have sent or received
+    /// the transaction, and its block must not be archived yet, to check it.
+    pub fn annotate_transaction(
+        &mut self,
+        account: AccountIdentifier,
+        height: BlockHeight,
+        label: Option<String>,
+        now: TimeStamp,
+    ) -> Result<(), String> {
+        let block = self
+            .blockchain
+            .get(height)
+            .ok_or_else(|| format!("Block {} is not held by the ledger", height))?
+            .decode()?;
+        if !annotations::is_party_to(&block.transaction().operation, &account) {
+            return Err(format!(
+                "{} did not send or receive the transaction at height {}",
+                account, height
+            ));
+        }
+        match label {
+            Some(label) => self.annotations.set(account, height, label, now),
+            None => {
+                self.annotations.remove(&account, height);
                 Ok(())
             }
         }
     }
 
-    pub fn remove_archived_blocks(&mut self, len: usize) {
-        self.blockchain.remove_archived_blocks(len);
+    /// Attests whether `account` holds at least `min_amount` right now.
+    pub fn attest_balance(
+        &self,
+        account: AccountIdentifier,
+        min_amount: TOKENs,
+        nonce: u64,
+        now: TimeStamp,
+    ) -> BalanceAttestation {
+        BalanceAttestation {
+            account,
+            min_amount,
+            holds: self.balances.account_balance(&account) >= min_amount,
+            chain_length: self.blockchain.chain_length(),
+            attested_at: now,
+            nonce,
+        }
+    }
+
+    /// The height and hash of the last block, if there is one.
+    pub fn tip(&self) -> Option<(BlockHeight, HashOf<EncodedBlock>)> {
+        self.blockchain
+            .last_hash
+            .map(|hash| (self.blockchain.chain_length() - 1, hash))
+    }
+
+    pub fn remove_archived_blocks(&mut self, len: usize, archive_node: CanisterId) {
+        self.blockchain.remove_archived_blocks(len, archive_node);
     }
 
     pub fn get_blocks_for_archiving(
@@ -518,7 +1707,270 @@ impl Ledger {
     }
 
     pub fn can_send(&self, principal_id: &PrincipalId) -> bool {
-        !principal_id.is_anonymous()
+        self.access_policy
+            .policy(self.notify_whitelist())
+            .can_send(principal_id)
+    }
+
+    pub fn can_be_notified(&self, canister_id: &PrincipalId) -> bool {
+        self.access_policy
+            .policy(self.notify_whitelist())
+            .can_be_notified(canister_id)
+    }
+
+    pub fn notify_whitelist(&self) -> &NotifyWhitelist {
+        self.notify_whitelist
+            .as_ref()
+            .expect("The notify whitelist was not migrated")
+    }
+
+    pub fn notify_whitelist_mut(&mut self) -> &mut NotifyWhitelist {
+        self.notify_whitelist
+            .as_mut()
+            .expect("The notify whitelist was not migrated")
+    }
+
+    /// Gives a ledger from before the notify whitelist existed one holding
+    /// the canisters of the send whitelist, which used to be the ones that
+    /// could be notified.
+    pub fn migrate_notify_whitelist(&mut self) {
+        if self.notify_whitelist.is_none() {
+            let canisters = match &self.access_policy {
+                AccessPolicyConfig::Whitelist(whitelist) => whitelist.canisters.clone(),
+                AccessPolicyConfig::Open | AccessPolicyConfig::DenyList(_) => BTreeSet::new(),
+            };
+            self.notify_whitelist = Some(NotifyWhitelist::new(canisters));
+        }
+    }
+
+    pub fn access_policy(&self) -> &AccessPolicyConfig {
+        &self.access_policy
+    }
+
+    pub fn set_access_policy(&mut self, access_policy: AccessPolicyConfig) {
+        self.access_policy = access_policy;
+    }
+
+    pub fn set_retention_policy(&mut self, policy: RetentionPolicy) {
+        self.retention_policy = Some(policy);
+    }
+
+    pub fn retention_policy(&self) -> Option<&RetentionPolicy> {
+        self.retention_policy.as_ref()
+    }
+
+    pub fn set_archive_lag_throttle(&mut self, throttle: ArchiveLagThrottle) {
+        self.archive_lag_throttle = Some(throttle);
+    }
+
+    /// Makes transfers to `account` burn the tokens. It must not be the
+    /// minting account.
+    pub fn set_burn_account(&mut self, account: AccountIdentifier) -> Result<(), String> {
+        if Some(account) == self.minting_account_id {
+            return Err("The burn account must differ from the minting account".to_string());
+        }
+        self.burn_account_id = Some(account);
+        Ok(())
+    }
+
+    pub fn burn_account(&self) -> Option<AccountIdentifier> {
+        self.burn_account_id
+    }
+
+    /// Credits fees to `account` from now on instead of burning them, or
+    /// burns them again if it is `None`. Every block whose fee was collected
+    /// names the account it went to.
+    pub fn set_fee_collector(&mut self, account: Option<AccountIdentifier>) -> Result<(), String> {
+        if let Some(account) = &account {
+            self.validate_fee_collector(account)?;
+        }
+        self.fee_collector = account;
+        Ok(())
+    }
+
+    fn validate_fee_collector(&self, account: &AccountIdentifier) -> Result<(), String> {
+        if Some(*account) == self.minting_account_id || Some(*account) == self.burn_account_id {
+            return Err(
+                "The fee collector must differ from the minting and burn accounts".to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn fee_collector(&self) -> Option<AccountIdentifier> {
+        self.fee_collector
+    }
+
+    pub fn total_fees_collected(&self) -> TOKENs {
+        self.total_fees_collected
+    }
+
+    pub fn total_burned(&self) -> TOKENs {
+        self.total_burned
+    }
+
+    /// Sets whether transfers and mints to unspendable accounts are rejected
+    /// with `TransferError::BadRecipient`. They are by default.
+    pub fn set_reject_unspendable_recipients(&mut self, reject: bool) {
+        self.reject_unspendable_recipients = reject;
+    }
+
+    pub fn archive_lag_throttle(&self) -> Option<&ArchiveLagThrottle> {
+        self.archive_lag_throttle.as_ref()
+    }
+
+    /// Makes the ledger read-only until `until`, or writable again if it is
+    /// `None`. The deadline can be at most `MAX_READ_ONLY_DURATION` away, so
+    /// that a forgotten migration can't freeze the ledger.
+    pub fn set_read_only(&mut self, until: Option<TimeStamp>) -> Result<(), String> {
+        if let Some(until) = until {
+            if until.is_after(self.now() + MAX_READ_ONLY_DURATION) {
+                return Err(format!(
+                    "The ledger can be read-only for at most {} hours",
+                    MAX_READ_ONLY_DURATION.as_secs() / 3600
+                ));
+            }
+        }
+        self.read_only_until = until;
+        Ok(())
+    }
+
+    /// When the ledger stops being read-only, if it is now.
+    pub fn read_only_until(&self) -> Option<TimeStamp> {
+        self.read_only_until
+            .filter(|until| self.now().is_before(*until))
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only_until().is_some() || self.corruption.is_some()
+    }
+
+    /// Makes the ledger read-only because its state is inconsistent, instead
+    /// of trapping on every later call.
+    pub fn mark_corrupted(&mut self, reason: String) {
+        log::error(format!("The ledger's state is corrupted: {}", reason));
+        self.corruption = Some(reason);
+    }
+
+    /// Marks the state corrupted if the balances and the pool don't add up.
+    /// The heartbeat runs this check, which takes constant time: an update
+    /// call that finds the state corrupted traps, which also rolls back the
+    /// corruption it marked.
+    pub fn check_balances(&mut self) {
+        if self.corruption.is_none() {
+            if let Err(reason) = self.balances.check_supply() {
+                self.mark_corrupted(reason);
+            }
+        }
+    }
+
+    /// Why the ledger found its state inconsistent, if it did.
+    pub fn corruption(&self) -> Option<&str> {
+        self.corruption.as_deref()
+    }
+
+    /// Lets an upgrade that fixed the inconsistency make the ledger writable
+    /// again. If it persists, the next heartbeat finds it again.
+    pub fn clear_corruption(&mut self) {
+        if let Some(reason) = self.corruption.take() {
+            log::info(format!("Clearing the corruption of the ledger: {}", reason));
+        }
+    }
+
+    /// Whether more blocks wait to be archived than the archive lag throttle
+    /// allows.
+    pub fn is_throttled(&self) -> bool {
+        self.archive_lag_throttle.as_ref().map_or(false, |throttle| {
+            let max_lag = throttle
+                .trigger_threshold
+                .saturating_mul(throttle.max_lag_multiple);
+            self.blockchain.num_unarchived_blocks() > max_lag
+        })
+    }
+
+    /// Prunes up to `MAX_BLOCKS_TO_PRUNE` of the oldest exported blocks that
+    /// fall outside the retention policy and returns how many were pruned.
+    pub fn apply_retention_policy(&mut self) -> Result<usize, String> {
+        let policy = match &self.retention_policy {
+            Some(policy) => policy,
+            None => return Ok(0),
+        };
+        let num_blocks =
+            self.blockchain
+                .num_blocks_outside_retention(policy, self.now(), MAX_BLOCKS_TO_PRUNE)?;
+        self.blockchain.prune_blocks(num_blocks)?;
+        Ok(num_blocks)
+    }
+
+    /// Counts `amount` against the spend cap of `principal_id`, failing if
+    /// the cap would be exceeded. Principals without a cap always succeed.
+    pub fn record_spend(
+        &mut self,
+        principal_id: &PrincipalId,
+        amount: TOKENs,
+    ) -> Result<(), String> {
+        let now = self.now();
+        match self.access_policy.spend_cap(principal_id) {
+            Some(cap) => self.spend_tracker.spend(*principal_id, amount, cap, now),
+            None => Ok(()),
+        }
+    }
+
+    /// Changes the cap on the number of accounts and how many accounts are
+    /// trimmed when the cap is exceeded. `None` keeps the current value. The
+    /// trim quantity must be positive and smaller than the cap.
+    pub fn set_account_limits(
+        &mut self,
+        maximum_number_of_accounts: Option<usize>,
+        accounts_overflow_trim_quantity: Option<usize>,
+    ) -> Result<(), String> {
+        let max = maximum_number_of_accounts.unwrap_or(self.maximum_number_of_accounts);
+        let trim =
+            accounts_overflow_trim_quantity.unwrap_or(self.accounts_overflow_trim_quantity);
+        if trim == 0 || trim >= max {
+            return Err(format!(
+                "The trim quantity ({}) must be positive and smaller than the maximum number of accounts ({})",
+                trim, max
+            ));
+        }
+        self.maximum_number_of_accounts = max;
+        self.accounts_overflow_trim_quantity = trim;
+        Ok(())
+    }
+
+    pub fn set_accounts_overflow_policy(&mut self, policy: AccountsOverflowPolicy) {
+        self.accounts_overflow_policy = policy;
+    }
+
+    pub fn accounts_overflow_policy(&self) -> AccountsOverflowPolicy {
+        self.accounts_overflow_policy
+    }
+
+    pub fn maximum_number_of_accounts(&self) -> usize {
+        self.maximum_number_of_accounts
+    }
+
+    pub fn accounts_overflow_trim_quantity(&self) -> usize {
+        self.accounts_overflow_trim_quantity
+    }
+
+    /// The current time according to the ledger's time source, but never
+    /// before the newest block. Reading it changes nothing, so it reads the
+    /// same throughout a message, queries included.
+    pub fn now(&self) -> TimeStamp {
+        std::cmp::max(self.time_source.now(), self.last_time)
+    }
+
+    /// Keeps `now` from going back before the newest block, for state from
+    /// before the ledger tracked it. Run after an upgrade.
+    pub fn seed_clock(&mut self) {
+        self.last_time = std::cmp::max(self.last_time, self.blockchain.last_timestamp);
+    }
+
+    /// Replaces the clock of the ledger, e.g. with a `ManualTimeSource` in
+    /// tests. The time source is not persisted across upgrades.
+    pub fn set_time_source(&mut self, time_source: Arc<dyn TimeSource>) {
+        self.time_source = time_source;
     }
 
     pub fn transactions_by_hash_len(&self) -> usize {
@@ -528,6 +1980,89 @@ impl Ledger {
     pub fn transactions_by_height_len(&self) -> usize {
         self.transactions_by_height.len()
     }
+
+    /// Counters describing the size of the ledger's state.
+    pub fn metrics(&self) -> LedgerMetrics {
+        LedgerMetrics {
+            chain_length: self.blockchain.chain_length(),
+            num_archived_blocks: self.blockchain.num_archived_blocks(),
+            num_pruned_blocks: self.blockchain.num_pruned_blocks(),
+            num_accounts: self.balances.store.len() as u64,
+            transactions_by_hash_len: self.transactions_by_hash.len() as u64,
+            transactions_by_height_len: self.transactions_by_height.len() as u64,
+            num_purged_transactions: self.num_purged_transactions,
+            oldest_remembered_transaction_time: self
+                .transactions_by_height
+                .front()
+                .map(|info| info.block_timestamp),
+            state_hash: hex::encode(state_hash::state_hash(self)),
+            send_dfx_calls: self.deprecated_endpoint_usage.send_dfx_calls(),
+            send_dfx_last_call: self.deprecated_endpoint_usage.send_dfx_last_call(),
+        }
+    }
+
+    /// The ledger's ICRC-1 metadata. Wallets expect at least the symbol,
+    /// decimals, fee and maximum memo length to be listed.
+    pub fn icrc1_metadata(&self) -> Vec<(String, MetadataValue)> {
+        vec![
+            ("icrc1:symbol".to_string(), MetadataValue::Text(self.symbol.clone())),
+            ("icrc1:decimals".to_string(), MetadataValue::from(self.decimals as u64)),
+            ("icrc1:fee".to_string(), MetadataValue::from(TRANSACTION_FEE.get_e8s())),
+            (
+                "icrc1:max_memo_length".to_string(),
+                MetadataValue::from(MAX_MEMO_BLOB_LENGTH as u64),
+            ),
+        ]
+    }
+
+    /// The lowest nonce the next pre-signed transfer from `account` may
+    /// carry.
+    pub fn signed_transfer_nonce(&self, account: &AccountIdentifier) -> u64 {
+        self.signed_transfer_nonces
+            .get(account)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Consumes `nonce` for a pre-signed transfer from `account`. Nonces must
+    /// be strictly increasing, starting from 0, so each signed payload can be
+    /// submitted at most once and in the order of its nonce. Nonces may be
+    /// skipped, e.g. to abandon a payload that was signed but not submitted.
+    pub fn use_signed_transfer_nonce(
+        &mut self,
+        account: &AccountIdentifier,
+        nonce: u64,
+    ) -> Result<(), String> {
+        let expected = self.signed_transfer_nonce(account);
+        if nonce < expected {
+            return Err(format!(
+                "Invalid nonce {} for account {}: expected at least {}",
+                nonce, account, expected
+            ));
+        }
+        let next = nonce
+            .checked_add(1)
+            .ok_or_else(|| format!("Invalid nonce {}: the largest nonce is reserved", nonce))?;
+        self.signed_transfer_nonces.insert(*account, next);
+        Ok(())
+    }
+
+    /// Returns the block created by the transaction that `account` tagged
+    /// with `key`, if it is still within the transaction window.
+    pub fn find_by_idempotency_key(
+        &self,
+        account: &AccountIdentifier,
+        key: &IdempotencyKey,
+    ) -> Option<BlockHeight> {
+        let (height, block_timestamp) = *self
+            .transactions_by_idempotency_key
+            .get(&(*account, key.clone()))?;
+        if block_timestamp.has_elapsed(self.transaction_window, self.now()) {
+            None
+        } else {
+            Some(height)
+        }
+    }
 }
 
 lazy_static! {
@@ -539,13 +2074,9 @@ lazy_static! {
 pub fn add_payment(
     memo: Memo,
     payment: Operation,
-    created_at_time: Option<TimeStamp>,
-) -> (BlockHeight, HashOf<EncodedBlock>) {
-    LEDGER
-        .write()
-        .unwrap()
-        .add_payment(memo, payment, created_at_time)
-        .expect("Transfer failed")
+    options: PaymentOptions,
+) -> Result<(BlockHeight, HashOf<EncodedBlock>), PaymentError> {
+    LEDGER.write().unwrap().add_payment(memo, payment, options)
 }
 
 pub fn change_notification_state(
@@ -553,29 +2084,42 @@ pub fn change_notification_state(
     block_timestamp: TimeStamp,
     new_state: bool,
 ) -> Result<(), String> {
-    LEDGER.write().unwrap().change_notification_state(
-        height,
-        block_timestamp,
-        new_state,
-        now().into(),
-    )
+    LEDGER
+        .write()
+        .unwrap()
+        .change_notification_state(height, block_timestamp, new_state)
 }
 
 // This is how we pass arguments to 'init' in main.rs
 #[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
 pub struct LedgerCanisterInitPayload {
+    pub symbol: String,
     pub minting_account: AccountIdentifier,
     pub initial_values: HashMap<AccountIdentifier, TOKENs>,
     pub max_message_size_bytes: Option<usize>,
     pub transaction_window: Option<Duration>,
+    pub permitted_drift: Option<Duration>,
+    pub maximum_number_of_accounts: Option<usize>,
+    pub accounts_overflow_trim_quantity: Option<usize>,
+    pub accounts_overflow_policy: Option<AccountsOverflowPolicy>,
+    pub access_policy: Option<AccessPolicyConfig>,
+    pub decimals: Option<u32>,
 }
 
 impl LedgerCanisterInitPayload {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        symbol: String,
         minting_account: AccountIdentifier,
         initial_values: HashMap<AccountIdentifier, TOKENs>,
         max_message_size_bytes: Option<usize>,
         transaction_window: Option<Duration>,
+        permitted_drift: Option<Duration>,
+        maximum_number_of_accounts: Option<usize>,
+        accounts_overflow_trim_quantity: Option<usize>,
+        accounts_overflow_policy: Option<AccountsOverflowPolicy>,
+        access_policy: Option<AccessPolicyConfig>,
+        decimals: Option<u32>,
     ) -> Self {
         // verify ledger's invariant about the maximum amount
         let _can_sum = initial_values.values().fold(TOKENs::ZERO, |acc, x| {
@@ -585,15 +2129,58 @@ impl LedgerCanisterInitPayload {
         // Don't allow self-transfers of the minting canister
         assert!(initial_values.get(&minting_account).is_none());
 
+        if let (Some(max), Some(trim)) =
+            (maximum_number_of_accounts, accounts_overflow_trim_quantity)
+        {
+            assert!(
+                0 < trim && trim < max,
+                "The trim quantity must be positive and smaller than the maximum number of accounts"
+            );
+        }
+
+        if let Some(decimals) = decimals {
+            validate_decimals(decimals).expect("Invalid decimals");
+        }
+
         Self {
+            symbol,
             minting_account,
             initial_values,
             max_message_size_bytes,
             transaction_window,
+            permitted_drift,
+            maximum_number_of_accounts,
+            accounts_overflow_trim_quantity,
+            accounts_overflow_policy,
+            access_policy,
+            decimals,
         }
     }
 }
 
+/// Argument optionally passed when upgrading the ledger canister to change
+/// its configuration. Fields that are `None` keep their current value.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq, Default)]
+pub struct LedgerCanisterUpgradePayload {
+    pub maximum_number_of_accounts: Option<usize>,
+    pub accounts_overflow_trim_quantity: Option<usize>,
+    pub accounts_overflow_policy: Option<AccountsOverflowPolicy>,
+    pub access_policy: Option<AccessPolicyConfig>,
+    pub retention_policy: Option<RetentionPolicy>,
+    pub archive_lag_throttle: Option<ArchiveLagThrottle>,
+    pub reject_unspendable_recipients: Option<bool>,
+    pub burn_account: Option<AccountIdentifier>,
+}
+
+/// A caller-chosen identifier for a transfer (e.g. a UUID). Retrying a send
+/// with the same key from the same account within the transaction window
+/// returns the block height of the original transfer instead of creating a
+/// new one.
+pub type IdempotencyKey = Vec<u8>;
+
+/// The maximum length of an `IdempotencyKey` in bytes.
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 32;
+
 /// Argument taken by the send endpoint
 #[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
 pub struct SendArgs {
@@ -603,6 +2190,114 @@ pub struct SendArgs {
     pub from_subaccount: Option<Subaccount>,
     pub to: AccountIdentifier,
     pub created_at_time: Option<TimeStamp>,
+    pub idempotency_key: Option<IdempotencyKey>,
+    pub encrypted_memo: Option<EncryptedMemo>,
+    /// The transfer fails if it is executed after this time.
+    pub expires_at: Option<TimeStamp>,
+}
+
+/// The maximum size in bytes of an encoded `SendArgs`. The largest valid
+/// argument, with a maximal idempotency key and encrypted memo, is well below
+/// this.
+pub const MAX_SEND_ARG_SIZE_BYTES: usize = 1024;
+
+/// Why the ledger refused the argument of a call before looking at it any
+/// further. Subaccounts need no check of their own as their length is fixed
+/// by their type.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum ArgumentError {
+    /// The encoded argument is `size` bytes long, more than the `max` the
+    /// endpoint accepts.
+    TooLarge { size: u64, max: u64 },
+    /// The encrypted memo is empty or longer than `max` bytes.
+    EncryptedMemoLength { length: u64, max: u64 },
+    /// The idempotency key is empty or longer than `max` bytes.
+    IdempotencyKeyLength { length: u64, max: u64 },
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooLarge { size, max } => write!(
+                f,
+                "Rejecting argument of {} bytes: arguments must be at most {} bytes long",
+                size, max
+            ),
+            Self::EncryptedMemoLength { length, max } => write!(
+                f,
+                "Encrypted memos must be between 1 and {} bytes long, got {}",
+                max, length
+            ),
+            Self::IdempotencyKeyLength { length, max } => write!(
+                f,
+                "Idempotency keys must be between 1 and {} bytes long, got {}",
+                max, length
+            ),
+        }
+    }
+}
+
+/// Checks the size of an encoded `SendArgs`, before it is decoded.
+pub fn validate_send_arg_size(size: usize) -> Result<(), ArgumentError> {
+    if size > MAX_SEND_ARG_SIZE_BYTES {
+        return Err(ArgumentError::TooLarge {
+            size: size as u64,
+            max: MAX_SEND_ARG_SIZE_BYTES as u64,
+        });
+    }
+    Ok(())
+}
+
+/// Checks the length of the variable-length fields of a send.
+pub fn validate_send_fields(
+    idempotency_key: Option<&IdempotencyKey>,
+    encrypted_memo: Option<&EncryptedMemo>,
+) -> Result<(), ArgumentError> {
+    if let Some(key) = idempotency_key {
+        if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+            return Err(ArgumentError::IdempotencyKeyLength {
+                length: key.len() as u64,
+                max: MAX_IDEMPOTENCY_KEY_LENGTH as u64,
+            });
+        }
+    }
+    if let Some(memo) = encrypted_memo {
+        if memo.0.is_empty() || memo.0.len() > MAX_ENCRYPTED_MEMO_LENGTH {
+            return Err(ArgumentError::EncryptedMemoLength {
+                length: memo.0.len() as u64,
+                max: MAX_ENCRYPTED_MEMO_LENGTH as u64,
+            });
+        }
+    }
+    Ok(())
+}
+
+impl SendArgs {
+    pub fn validate(&self) -> Result<(), ArgumentError> {
+        validate_send_fields(self.idempotency_key.as_ref(), self.encrypted_memo.as_ref())
+    }
+}
+
+/// Argument taken by the mint endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct MintArgs {
+    pub memo: Memo,
+    pub amount: TOKENs,
+    pub from_subaccount: Option<Subaccount>,
+    pub to: AccountIdentifier,
+    pub created_at_time: Option<TimeStamp>,
+    pub reason: MintReason,
+}
+
+/// The maximum number of source subaccounts in a single sweep.
+pub const MAX_SWEEP_SOURCES: usize = 100;
+
+/// Argument taken by the sweep_subaccounts endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct SweepSubaccountsArgs {
+    pub to_subaccount: Option<Subaccount>,
+    pub from_subaccounts: Vec<Subaccount>,
+    pub memo: Memo,
 }
 
 /// Struct sent by the ledger canister when it notifies a recipient of a payment
@@ -651,6 +2346,38 @@ impl NotifyCanisterArgs {
     }
 }
 
+/// Argument taken by the transfer_and_call endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct TransferAndCallArgs {
+    pub memo: Memo,
+    pub amount: TOKENs,
+    pub fee: TOKENs,
+    pub from_subaccount: Option<Subaccount>,
+    pub to_canister: CanisterId,
+    pub to_subaccount: Option<Subaccount>,
+    /// The method of `to_canister` that is called once the transfer is made.
+    pub method: String,
+    /// Passed on to `method` as is, typically a Candid encoded value the
+    /// recipient defined.
+    pub args: Vec<u8>,
+}
+
+/// Struct the recipient's method is called with by transfer_and_call
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct TransferAndCallNotification {
+    pub transaction: TransactionNotification,
+    pub args: Vec<u8>,
+}
+
+/// Result of the transfer_and_call endpoint. The transfer stands whatever
+/// `call_result` says.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct TransferAndCallResult {
+    pub block_height: BlockHeight,
+    /// The raw reply of the recipient, or why the call failed.
+    pub call_result: Result<Vec<u8>, String>,
+}
+
 /// Argument taken by the account_balance endpoint
 #[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
 pub struct AccountBalanceArgs {
@@ -672,6 +2399,68 @@ impl AccountBalanceArgs {
 #[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
 pub struct TotalSupplyArgs {}
 
+/// The configuration of a ledger deployment, returned by the ledger_info
+/// endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct LedgerInfo {
+    pub symbol: String,
+    pub decimals: u32,
+    pub transaction_fee: TOKENs,
+    pub min_burn_amount: TOKENs,
+    pub minting_account: Option<AccountIdentifier>,
+    pub transaction_window: Duration,
+    pub permitted_drift: Duration,
+    pub chain_length: BlockHeight,
+    pub num_archived_blocks: u64,
+    pub num_pruned_blocks: u64,
+    pub retention_policy: Option<RetentionPolicy>,
+    pub maximum_number_of_accounts: usize,
+    pub accounts_overflow_trim_quantity: usize,
+    pub accounts_overflow_policy: AccountsOverflowPolicy,
+    pub max_message_size_bytes: usize,
+    /// The most blocks a single block request returns.
+    pub max_blocks_per_request: usize,
+}
+
+/// A value of the ledger's ICRC-1 metadata.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub enum MetadataValue {
+    Nat(candid::Nat),
+    Int(candid::Int),
+    Text(String),
+    Blob(#[serde(with = "serde_bytes")] Vec<u8>),
+}
+
+impl From<u64> for MetadataValue {
+    fn from(n: u64) -> Self {
+        MetadataValue::Nat(candid::Nat::from(n))
+    }
+}
+
+/// Counters describing the size of the ledger's state, returned by the
+/// ledger_metrics endpoint and served on /metrics.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct LedgerMetrics {
+    pub chain_length: BlockHeight,
+    pub num_archived_blocks: u64,
+    pub num_pruned_blocks: u64,
+    pub num_accounts: u64,
+    /// The sizes of the structures used to detect duplicate transactions.
+    /// They only hold the transactions of the last `transaction_window`.
+    pub transactions_by_hash_len: u64,
+    pub transactions_by_height_len: u64,
+    /// How many transactions left the transaction window so far.
+    pub num_purged_transactions: u64,
+    /// The block time of the oldest transaction in the transaction window.
+    pub oldest_remembered_transaction_time: Option<TimeStamp>,
+    /// The hex-encoded `state_hash::state_hash` of the ledger.
+    pub state_hash: String,
+    /// How many transfers were made through the deprecated send_dfx
+    /// endpoint, and when the last one was.
+    pub send_dfx_calls: u64,
+    pub send_dfx_last_call: Option<TimeStamp>,
+}
+
 #[derive(CandidType, Deserialize)]
 pub enum CyclesResponse {
     CanisterCreated(CanisterId),
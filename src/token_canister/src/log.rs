@@ -0,0 +1,169 @@
+//! The ledger's log. Messages go to the replica log as before and into a
+//! bounded in-memory buffer, so that they can still be read through the
+//! get_logs query or the /logs route after the replica log rotated. The buffer
+//! does not survive upgrades, and neither does the log level.
+//!
+//! Account identifiers are redacted to their first
+//! `REDACTED_ACCOUNT_PREFIX_LENGTH` hex digits before a message is written
+//! anywhere, as the replica log is readable by node operators.
+
+use crate::{ledger_time, print, TimeStamp};
+use candid::CandidType;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::RwLock;
+
+/// The number of entries the buffer holds. Older entries are dropped.
+pub const MAX_LOG_ENTRIES: usize = 1_000;
+/// Longer messages are truncated to this many bytes.
+pub const MAX_LOG_MESSAGE_LENGTH: usize = 1_024;
+/// The number of hex digits of an account identifier that are kept in logs.
+pub const REDACTED_ACCOUNT_PREFIX_LENGTH: usize = 8;
+/// The length of the hex encoding of an account identifier.
+const ACCOUNT_IDENTIFIER_HEX_LENGTH: usize = 64;
+
+#[derive(
+    Serialize, Deserialize, CandidType, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum LogLevel {
+    Trace,
+    Info,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Trace => write!(f, "TRACE"),
+            Self::Info => write!(f, "INFO"),
+            Self::Warn => write!(f, "WARN"),
+            Self::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct LogEntry {
+    /// The position of the entry in the log since the canister was installed
+    /// or last upgraded.
+    pub index: u64,
+    pub timestamp: TimeStamp,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+/// A ring buffer holding the latest `capacity` log entries at `level` or
+/// above.
+#[derive(Debug)]
+pub struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    next_index: u64,
+    capacity: usize,
+    level: LogLevel,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            next_index: 0,
+            capacity,
+            level: LogLevel::Info,
+        }
+    }
+
+    /// The lowest level that is logged. Messages below it are dropped.
+    pub fn level(&self) -> LogLevel {
+        self.level
+    }
+
+    pub fn set_level(&mut self, level: LogLevel) {
+        self.level = level;
+    }
+
+    pub fn push(&mut self, timestamp: TimeStamp, level: LogLevel, mut message: String) {
+        if message.len() > MAX_LOG_MESSAGE_LENGTH {
+            let mut end = MAX_LOG_MESSAGE_LENGTH;
+            while !message.is_char_boundary(end) {
+                end -= 1;
+            }
+            message.truncate(end);
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry {
+            index: self.next_index,
+            timestamp,
+            level,
+            message,
+        });
+        self.next_index += 1;
+    }
+
+    /// The entries with an index of at least `since` that are still in the
+    /// buffer, oldest first.
+    pub fn entries_since(&self, since: u64) -> Vec<LogEntry> {
+        let first_index = self.next_index - self.entries.len() as u64;
+        let skip = since.saturating_sub(first_index) as usize;
+        self.entries.iter().skip(skip).cloned().collect()
+    }
+}
+
+lazy_static! {
+    pub static ref LOGS: RwLock<LogBuffer> = RwLock::new(LogBuffer::new(MAX_LOG_ENTRIES));
+}
+
+/// Replaces every run of exactly 64 hex digits, i.e. every hex-encoded
+/// account identifier, by its first `REDACTED_ACCOUNT_PREFIX_LENGTH` digits
+/// followed by "...".
+pub fn redact_account_identifiers(message: &str) -> String {
+    fn flush(redacted: &mut String, run: &str) {
+        if run.len() == ACCOUNT_IDENTIFIER_HEX_LENGTH {
+            redacted.push_str(&run[..REDACTED_ACCOUNT_PREFIX_LENGTH]);
+            redacted.push_str("...");
+        } else {
+            redacted.push_str(run);
+        }
+    }
+    let mut redacted = String::with_capacity(message.len());
+    let mut run_start = 0;
+    for (i, c) in message.char_indices() {
+        if !c.is_ascii_hexdigit() {
+            flush(&mut redacted, &message[run_start..i]);
+            redacted.push(c);
+            run_start = i + c.len_utf8();
+        }
+    }
+    flush(&mut redacted, &message[run_start..]);
+    redacted
+}
+
+pub fn log(level: LogLevel, message: String) {
+    let mut logs = LOGS.write().unwrap();
+    if level < logs.level() {
+        return;
+    }
+    let message = redact_account_identifiers(&message);
+    print(format!("[ledger] {} {}", level, message));
+    logs.push(ledger_time(), level, message);
+}
+
+pub fn trace(message: String) {
+    log(LogLevel::Trace, message)
+}
+
+pub fn info(message: String) {
+    log(LogLevel::Info, message)
+}
+
+pub fn warn(message: String) {
+    log(LogLevel::Warn, message)
+}
+
+pub fn error(message: String) {
+    log(LogLevel::Error, message)
+}
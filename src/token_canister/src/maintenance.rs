@@ -0,0 +1,49 @@
+//! Periodic cleanup of the auxiliary state that would otherwise grow
+//! without bound: deduplication and notification records older than the
+//! transaction window, and invoices that were closed long ago. Every run does
+//! a bounded amount of work, so that it fits in a heartbeat; what is left is
+//! picked up by the next run.
+
+use crate::TimeStamp;
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How often the heartbeat runs maintenance.
+pub const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// The most records of each kind a run removes or scans.
+pub const MAX_MAINTENANCE_WORK: usize = 1000;
+
+/// What a maintenance run removed.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Transactions that left the transaction window, along with their
+    /// deduplication and notification records.
+    pub purged_transactions: u64,
+    pub pruned_invoices: u64,
+    pub ran_at: TimeStamp,
+}
+
+/// When maintenance last ran.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct Maintenance {
+    last_run: Option<TimeStamp>,
+}
+
+impl Maintenance {
+    pub fn last_run(&self) -> Option<TimeStamp> {
+        self.last_run
+    }
+
+    /// Whether `MAINTENANCE_INTERVAL` elapsed since the last run.
+    pub fn is_due(&self, now: TimeStamp) -> bool {
+        self.last_run.map_or(true, |last_run| {
+            last_run.has_elapsed(MAINTENANCE_INTERVAL, now)
+        })
+    }
+
+    pub fn record_run(&mut self, now: TimeStamp) {
+        self.last_run = Some(now);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::ic_token::TOKENs;
+use crate::TimeStamp;
+
+use candid::CandidType;
+use ic_base_types::PrincipalId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A principal allowed to mint tokens, besides the owner of the minting
+/// account.
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct Minter {
+    pub principal: PrincipalId,
+    /// The total amount the minter may ever mint, or `None` for no limit.
+    pub quota: Option<TOKENs>,
+    /// The amount minted so far.
+    pub minted: TOKENs,
+    /// After this time the minter can no longer mint.
+    pub expires_at: Option<TimeStamp>,
+}
+
+/// The minters that the controller delegated issuance to.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Minters {
+    by_principal: BTreeMap<PrincipalId, Minter>,
+}
+
+impl Minters {
+    /// Authorizes `principal` to mint up to `quota` in total until
+    /// `expires_at`. Updating an existing minter keeps the amount it already
+    /// minted, which counts against the new quota.
+    pub fn set(
+        &mut self,
+        principal: PrincipalId,
+        quota: Option<TOKENs>,
+        expires_at: Option<TimeStamp>,
+    ) {
+        let minted = self
+            .by_principal
+            .get(&principal)
+            .map(|m| m.minted)
+            .unwrap_or(TOKENs::ZERO);
+        self.by_principal.insert(
+            principal,
+            Minter {
+                principal,
+                quota,
+                minted,
+                expires_at,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, principal: &PrincipalId) -> Option<Minter> {
+        self.by_principal.remove(principal)
+    }
+
+    pub fn get(&self, principal: &PrincipalId) -> Option<&Minter> {
+        self.by_principal.get(principal)
+    }
+
+    pub fn list(&self) -> Vec<Minter> {
+        self.by_principal.values().cloned().collect()
+    }
+
+    /// Checks that `principal` may mint `amount` at time `now`.
+    pub fn check_mint(
+        &self,
+        principal: &PrincipalId,
+        amount: TOKENs,
+        now: TimeStamp,
+    ) -> Result<(), String> {
+        let minter = self
+            .by_principal
+            .get(principal)
+            .ok_or_else(|| format!("{} is not authorized to mint", principal))?;
+        if let Some(expires_at) = minter.expires_at {
+            if !now.is_before(expires_at) {
+                return Err(format!("The minting rights of {} expired", principal));
+            }
+        }
+        if let Some(quota) = minter.quota {
+            let total = (minter.minted + amount)?;
+            if total > quota {
+                return Err(format!(
+                    "Minting {} would exceed the quota of {}: {} of {} already minted",
+                    amount, principal, minter.minted, quota
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts a successful mint of `amount` against the quota of `principal`.
+    pub fn record_mint(&mut self, principal: &PrincipalId, amount: TOKENs) {
+        if let Some(minter) = self.by_principal.get_mut(principal) {
+            minter.minted = (minter.minted + amount).expect("Minted amount overflowed");
+        }
+    }
+}
+
+/// Argument taken by the set_minter endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct SetMinterArgs {
+    pub principal: PrincipalId,
+    pub quota: Option<TOKENs>,
+    pub expires_at: Option<TimeStamp>,
+}
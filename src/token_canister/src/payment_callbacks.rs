@@ -0,0 +1,141 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+use crate::ic_block::BlockHeight;
+use crate::ic_token::TOKENs;
+use crate::types::Memo;
+
+use candid::CandidType;
+use ic_types::CanisterId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// How many times delivering a callback is attempted before it is dropped.
+pub const MAX_CALLBACK_ATTEMPTS: u32 = 5;
+/// The maximum number of callbacks waiting to be delivered. Credits arriving
+/// while the queue is full are not announced.
+pub const MAX_PENDING_CALLBACKS: usize = 10_000;
+pub const MAX_CALLBACK_METHOD_LENGTH: usize = 64;
+/// How many callbacks are delivered per heartbeat.
+pub const CALLBACKS_PER_HEARTBEAT: usize = 10;
+
+/// A method of a canister that is called whenever an account is credited.
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct PaymentCallback {
+    pub canister_id: CanisterId,
+    pub method: String,
+}
+
+/// The argument the callback method is called with.
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct PaymentReceived {
+    pub block_height: BlockHeight,
+    pub to: AccountIdentifier,
+    pub amount: TOKENs,
+    pub memo: Memo,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PendingCallback {
+    pub callback: PaymentCallback,
+    pub payment: PaymentReceived,
+    pub attempts: u32,
+}
+
+/// Callbacks registered by account owners, and the queue of credits that
+/// still have to be announced to them.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PaymentCallbacks {
+    registered: BTreeMap<AccountIdentifier, PaymentCallback>,
+    pending: VecDeque<PendingCallback>,
+}
+
+impl PaymentCallbacks {
+    /// Sets the callback of `account`, or removes it if `callback` is `None`.
+    pub fn register(
+        &mut self,
+        account: AccountIdentifier,
+        callback: Option<PaymentCallback>,
+    ) -> Result<(), String> {
+        match callback {
+            Some(callback) => {
+                if callback.method.is_empty()
+                    || callback.method.len() > MAX_CALLBACK_METHOD_LENGTH
+                {
+                    return Err(format!(
+                        "Callback method names must be between 1 and {} characters long",
+                        MAX_CALLBACK_METHOD_LENGTH
+                    ));
+                }
+                self.registered.insert(account, callback);
+            }
+            None => {
+                self.registered.remove(&account);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, account: &AccountIdentifier) -> Option<&PaymentCallback> {
+        self.registered.get(account)
+    }
+
+    /// Queues a callback announcing the credit of `to` in `block_height`, if
+    /// the owner of `to` registered one.
+    pub fn on_credit(
+        &mut self,
+        to: AccountIdentifier,
+        block_height: BlockHeight,
+        amount: TOKENs,
+        memo: Memo,
+    ) {
+        if let Some(callback) = self.registered.get(&to) {
+            if self.pending.len() >= MAX_PENDING_CALLBACKS {
+                return;
+            }
+            self.pending.push_back(PendingCallback {
+                callback: callback.clone(),
+                payment: PaymentReceived {
+                    block_height,
+                    to,
+                    amount,
+                    memo,
+                },
+                attempts: 0,
+            });
+        }
+    }
+
+    /// Hands up to `max` callbacks from the front of the queue to `send`.
+    /// A callback leaves the queue only once `send` succeeds; failed ones go
+    /// to the back of the queue, until they run out of attempts.
+    pub fn deliver(
+        &mut self,
+        max: usize,
+        mut send: impl FnMut(&PendingCallback) -> Result<(), String>,
+    ) -> Vec<(PendingCallback, Result<(), String>)> {
+        let mut results = vec![];
+        for _ in 0..max.min(self.pending.len()) {
+            let result = send(self.pending.front().unwrap());
+            let mut pending = self.pending.pop_front().unwrap();
+            if result.is_err() {
+                pending.attempts += 1;
+                if pending.attempts < MAX_CALLBACK_ATTEMPTS {
+                    self.pending.push_back(pending.clone());
+                }
+            }
+            results.push((pending, result));
+        }
+        results
+    }
+
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Argument taken by the register_payment_callback endpoint. A `None`
+/// callback unregisters the current one.
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct RegisterPaymentCallbackArgs {
+    pub subaccount: Option<Subaccount>,
+    pub callback: Option<PaymentCallback>,
+}
@@ -0,0 +1,228 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+use crate::ic_token::TOKENs;
+use crate::types::{Memo, MemoBlob};
+use crate::TimeStamp;
+
+use candid::CandidType;
+use ic_base_types::PrincipalId;
+use ic_crypto_sha::Sha256;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+pub type PendingTransferId = u64;
+
+/// The longest window during which a sender can cancel a pending transfer.
+pub const MAX_CANCELLATION_WINDOW: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// How many pending transfers are finalized per heartbeat.
+pub const FINALIZATIONS_PER_HEARTBEAT: usize = 10;
+
+static PENDING_TRANSFER_SUBACCOUNT_DOMAIN_SEPARATOR: &[u8] = b"\x10pending-transfer";
+
+/// The subaccount of the ledger canister that holds the amount of a pending
+/// transfer until it is finalized or cancelled.
+pub fn pending_transfer_subaccount(id: PendingTransferId) -> Subaccount {
+    let mut state = Sha256::new();
+    state.write(PENDING_TRANSFER_SUBACCOUNT_DOMAIN_SEPARATOR);
+    state.write(&id.to_be_bytes());
+    Subaccount(state.finish())
+}
+
+/// A transfer whose amount is held by the ledger until `finalizes_at`. Until
+/// then the sender can cancel it and get the amount back.
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct PendingTransfer {
+    pub id: PendingTransferId,
+    pub sender: PrincipalId,
+    /// The account the amount came from, and returns to on cancellation.
+    pub sender_account: AccountIdentifier,
+    pub recipient: PrincipalId,
+    pub recipient_account: AccountIdentifier,
+    pub amount: TOKENs,
+    pub memo: Memo,
+    pub created_at: TimeStamp,
+    pub finalizes_at: TimeStamp,
+}
+
+/// Tags the blocks of pending transfers, in their memo blob.
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub enum PendingTransferEvent {
+    Hold {
+        pending_transfer_id: PendingTransferId,
+    },
+    Finalize {
+        pending_transfer_id: PendingTransferId,
+    },
+    Cancel {
+        pending_transfer_id: PendingTransferId,
+    },
+}
+
+impl PendingTransferEvent {
+    pub fn to_memo_blob(&self) -> MemoBlob {
+        MemoBlob::new(serde_cbor::ser::to_vec_packed(self).unwrap())
+            .expect("Pending transfer events always fit in a memo blob")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PendingTransfers {
+    transfers: BTreeMap<PendingTransferId, PendingTransfer>,
+    /// The pending transfers, by the time they finalize.
+    by_finalization: BTreeSet<(TimeStamp, PendingTransferId)>,
+    next_pending_transfer_id: PendingTransferId,
+}
+
+impl PendingTransfers {
+    /// The id the next pending transfer will get.
+    pub fn next_id(&self) -> PendingTransferId {
+        self.next_pending_transfer_id
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create(
+        &mut self,
+        sender: PrincipalId,
+        sender_account: AccountIdentifier,
+        recipient: PrincipalId,
+        recipient_account: AccountIdentifier,
+        amount: TOKENs,
+        memo: Memo,
+        cancellation_window: Duration,
+        now: TimeStamp,
+    ) -> Result<PendingTransfer, String> {
+        if amount == TOKENs::ZERO {
+            return Err("Pending transfers must not be empty".to_string());
+        }
+        if cancellation_window > MAX_CANCELLATION_WINDOW {
+            return Err(format!(
+                "The cancellation window can't be longer than {} days",
+                MAX_CANCELLATION_WINDOW.as_secs() / (24 * 60 * 60)
+            ));
+        }
+        let transfer = PendingTransfer {
+            id: self.next_pending_transfer_id,
+            sender,
+            sender_account,
+            recipient,
+            recipient_account,
+            amount,
+            memo,
+            created_at: now,
+            finalizes_at: now + cancellation_window,
+        };
+        self.next_pending_transfer_id += 1;
+        self.by_finalization
+            .insert((transfer.finalizes_at, transfer.id));
+        self.transfers.insert(transfer.id, transfer.clone());
+        Ok(transfer)
+    }
+
+    /// Removes the pending transfer `id` of `sender`, if its window is still
+    /// open at `now`.
+    pub fn cancel(
+        &mut self,
+        sender: &PrincipalId,
+        id: PendingTransferId,
+        now: TimeStamp,
+    ) -> Result<PendingTransfer, String> {
+        let transfer = self.get_existing(id)?;
+        if transfer.sender != *sender {
+            return Err(format!(
+                "Only the sender can cancel pending transfer {}",
+                id
+            ));
+        }
+        if !now.is_before(transfer.finalizes_at) {
+            return Err(format!(
+                "The cancellation window of pending transfer {} is over",
+                id
+            ));
+        }
+        Ok(self.remove(id))
+    }
+
+    /// Removes the pending transfer `id` of `recipient`, if its window is
+    /// over at `now`.
+    pub fn finalize(
+        &mut self,
+        recipient: &PrincipalId,
+        id: PendingTransferId,
+        now: TimeStamp,
+    ) -> Result<PendingTransfer, String> {
+        let transfer = self.get_existing(id)?;
+        if transfer.recipient != *recipient {
+            return Err(format!(
+                "Only the recipient can finalize pending transfer {}",
+                id
+            ));
+        }
+        if now.is_before(transfer.finalizes_at) {
+            return Err(format!(
+                "Pending transfer {} can still be cancelled until {}",
+                id,
+                transfer.finalizes_at.as_nanos()
+            ));
+        }
+        Ok(self.remove(id))
+    }
+
+    /// At most `max` pending transfers whose window is over at `now`, oldest
+    /// first. They stay pending until `settle` removes them.
+    pub fn due(&self, now: TimeStamp, max: usize) -> Vec<PendingTransfer> {
+        self.by_finalization
+            .iter()
+            .take_while(|(finalizes_at, _)| !now.is_before(*finalizes_at))
+            .take(max)
+            .map(|(_, id)| self.transfers[id].clone())
+            .collect()
+    }
+
+    /// Removes the pending transfer `id` once its amount was paid out.
+    pub fn settle(&mut self, id: PendingTransferId) -> Option<PendingTransfer> {
+        if self.transfers.contains_key(&id) {
+            Some(self.remove(id))
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, id: PendingTransferId) -> Option<&PendingTransfer> {
+        self.transfers.get(&id)
+    }
+
+    /// The pending transfers that `principal_id` sends or receives.
+    pub fn pending_transfers_of(&self, principal_id: &PrincipalId) -> Vec<PendingTransfer> {
+        self.transfers
+            .values()
+            .filter(|t| t.sender == *principal_id || t.recipient == *principal_id)
+            .cloned()
+            .collect()
+    }
+
+    fn get_existing(&self, id: PendingTransferId) -> Result<&PendingTransfer, String> {
+        self.transfers
+            .get(&id)
+            .ok_or_else(|| format!("Pending transfer {} does not exist", id))
+    }
+
+    fn remove(&mut self, id: PendingTransferId) -> PendingTransfer {
+        let transfer = self.transfers.remove(&id).unwrap();
+        self.by_finalization
+            .remove(&(transfer.finalizes_at, transfer.id));
+        transfer
+    }
+}
+
+/// Argument taken by the create_pending_transfer endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct CreatePendingTransferArgs {
+    pub to: PrincipalId,
+    pub to_subaccount: Option<Subaccount>,
+    pub amount: TOKENs,
+    pub memo: Memo,
+    pub from_subaccount: Option<Subaccount>,
+    /// How long the sender can cancel the transfer for, at most
+    /// `MAX_CANCELLATION_WINDOW`.
+    pub cancellation_window_seconds: u64,
+}
@@ -0,0 +1,115 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+use crate::ic_block::BlockHeight;
+use crate::ic_token::TOKENs;
+use crate::types::{MemoBlob, Operation};
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Argument taken by the refund endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct RefundArgs {
+    /// The height of the transfer being refunded.
+    pub original_height: BlockHeight,
+    pub amount: TOKENs,
+    /// The subaccount of the caller that received the original transfer.
+    pub subaccount: Option<Subaccount>,
+}
+
+/// Links a refund to the transfer it returns, in the memo blob of the refund
+/// block.
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub struct RefundReference {
+    pub original_height: BlockHeight,
+}
+
+impl RefundReference {
+    pub fn to_memo_blob(&self) -> MemoBlob {
+        MemoBlob::new(serde_cbor::ser::to_vec_packed(self).unwrap())
+            .expect("Refund references always fit in a memo blob")
+    }
+
+    pub fn from_memo_blob(blob: &MemoBlob) -> Result<Self, String> {
+        serde_cbor::from_slice(&blob.0).map_err(|e| format!("Invalid refund reference: {}", e))
+    }
+}
+
+/// How much of each transfer was refunded so far. Only transfers whose
+/// blocks are still held by the ledger can be refunded, so the amounts of
+/// older ones are forgotten.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Refunds {
+    refunded: BTreeMap<BlockHeight, TOKENs>,
+}
+
+impl Refunds {
+    /// The amount of the transfer at `original_height` refunded so far.
+    pub fn refunded(&self, original_height: BlockHeight) -> TOKENs {
+        self.refunded
+            .get(&original_height)
+            .copied()
+            .unwrap_or(TOKENs::ZERO)
+    }
+
+    /// The transfer returning `amount` of `original`, the operation at
+    /// `original_height`, to its payer. Only the recipient of `original` may
+    /// refund it, and never more than it received in total.
+    pub fn refund_operation(
+        &self,
+        original_height: BlockHeight,
+        original: &Operation,
+        recipient: &AccountIdentifier,
+        amount: TOKENs,
+        fee: TOKENs,
+    ) -> Result<Operation, String> {
+        let (payer, original_amount) = match original {
+            Operation::Transfer {
+                from,
+                to,
+                amount: original_amount,
+                ..
+            } if to == recipient => (*from, *original_amount),
+            Operation::Transfer { .. } => {
+                return Err(format!(
+                    "{} did not receive the transfer in block {}",
+                    recipient, original_height
+                ))
+            }
+            _ => return Err(format!("Block {} is not a transfer", original_height)),
+        };
+        if amount == TOKENs::ZERO {
+            return Err("Refunds must not be empty".to_string());
+        }
+        let refunded = (self.refunded(original_height) + amount)?;
+        if refunded > original_amount {
+            return Err(format!(
+                "Only {} of the {} transferred in block {} is left to refund",
+                (original_amount - self.refunded(original_height))?,
+                original_amount,
+                original_height
+            ));
+        }
+        Ok(Operation::Transfer {
+            from: *recipient,
+            to: payer,
+            amount,
+            fee,
+        })
+    }
+
+    /// Records that `amount` of the transfer at `original_height` was
+    /// refunded, and forgets the refunds of blocks before
+    /// `first_local_height`, which can't be refunded anymore.
+    pub fn record(
+        &mut self,
+        original_height: BlockHeight,
+        amount: TOKENs,
+        first_local_height: BlockHeight,
+    ) -> Result<(), String> {
+        let refunded = (self.refunded(original_height) + amount)?;
+        self.refunded.insert(original_height, refunded);
+        self.refunded = self.refunded.split_off(&first_local_height);
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use candid::CandidType;
+use ic_base_types::PrincipalId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// An administrative duty that can be assigned to principals besides the
+/// controller, which holds every role.
+#[derive(
+    Serialize, Deserialize, CandidType, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum Role {
+    /// Grants and revokes roles, and configures the ledger: staking,
+    /// operation hooks, the minting account, the notify whitelist, the
+    /// reader list, logging and upgrades.
+    Admin,
+    /// Proposes turning fee collection on and off, and sweeps the collected
+    /// fees.
+    FeeManager,
+    /// Makes the ledger read-only.
+    Freezer,
+    /// Proposes changes to the minters that issuance is delegated to.
+    Minter,
+    /// Configures how the ledger reacts to archiving falling behind.
+    Archiver,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A principal and the roles it holds, returned by the get_roles endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct RoleAssignment {
+    pub principal: PrincipalId,
+    pub roles: Vec<Role>,
+}
+
+/// Argument taken by the grant_role and revoke_role endpoints.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct RoleArgs {
+    pub principal: PrincipalId,
+    pub role: Role,
+}
+
+/// The roles assigned to principals other than the controller.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RoleRegistry {
+    by_principal: BTreeMap<PrincipalId, BTreeSet<Role>>,
+}
+
+impl RoleRegistry {
+    /// Gives `role` to `principal`. Granting a role twice is not an error.
+    pub fn grant(&mut self, principal: PrincipalId, role: Role) {
+        self.by_principal.entry(principal).or_default().insert(role);
+    }
+
+    /// Takes `role` from `principal`, returning whether it held it.
+    pub fn revoke(&mut self, principal: &PrincipalId, role: Role) -> bool {
+        let roles = match self.by_principal.get_mut(principal) {
+            Some(roles) => roles,
+            None => return false,
+        };
+        let revoked = roles.remove(&role);
+        if roles.is_empty() {
+            self.by_principal.remove(principal);
+        }
+        revoked
+    }
+
+    pub fn has_role(&self, principal: &PrincipalId, role: Role) -> bool {
+        self.by_principal
+            .get(principal)
+            .map_or(false, |roles| roles.contains(&role))
+    }
+
+    pub fn roles_of(&self, principal: &PrincipalId) -> Vec<Role> {
+        self.by_principal
+            .get(principal)
+            .map(|roles| roles.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn list(&self) -> Vec<RoleAssignment> {
+        self.by_principal
+            .iter()
+            .map(|(principal, roles)| RoleAssignment {
+                principal: *principal,
+                roles: roles.iter().copied().collect(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,113 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+use crate::ic_token::TOKENs;
+use crate::types::Memo;
+use crate::TimeStamp;
+
+use candid::CandidType;
+use ic_base_types::{CanisterId, PrincipalId};
+use ic_crypto_sha::Sha256;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// Domain separator prepended to the payload hash before signing, so that a
+/// signature over a transfer can never be mistaken for a signature over
+/// anything else.
+static SIGNED_TRANSFER_DOMAIN_SEPARATOR: &[u8] = b"\x0Fsigned-transfer";
+
+/// DER prefix of an Ed25519 `SubjectPublicKeyInfo`, followed by the 32-byte
+/// raw public key.
+const ED25519_DER_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+/// DER prefix of a secp256k1 `SubjectPublicKeyInfo`, followed by the 65-byte
+/// uncompressed public key.
+const SECP256K1_DER_PREFIX: [u8; 23] = [
+    0x30, 0x56, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x05,
+    0x2b, 0x81, 0x04, 0x00, 0x0a, 0x03, 0x42, 0x00,
+];
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub enum SignatureScheme {
+    Ed25519,
+    EcdsaSecp256k1,
+}
+
+/// A transfer authorized by a signature of the owner of the source account,
+/// rather than by the identity of the caller.
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct SignedTransferPayload {
+    pub memo: Memo,
+    pub amount: TOKENs,
+    pub fee: TOKENs,
+    pub from_subaccount: Option<Subaccount>,
+    pub to: AccountIdentifier,
+    pub created_at_time: TimeStamp,
+    /// Must be at least the nonce returned by get_nonce for the source
+    /// account, which is one more than the last nonce it used.
+    pub nonce: u64,
+    /// The ledger the transfer is meant for, so that a signature cannot be
+    /// replayed against another ledger.
+    pub ledger_canister_id: CanisterId,
+}
+
+impl SignedTransferPayload {
+    /// The bytes the owner signs: the domain separator followed by the
+    /// SHA-256 hash of the CBOR-encoded payload. Ed25519 signs these bytes
+    /// directly; ECDSA signs their SHA-256 hash.
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let payload_hash = Sha256::hash(&serde_cbor::ser::to_vec_packed(self).unwrap());
+        [SIGNED_TRANSFER_DOMAIN_SEPARATOR, &payload_hash[..]].concat()
+    }
+}
+
+/// Argument taken by the submit_signed_transfer endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct SubmitSignedTransferArgs {
+    pub payload: SignedTransferPayload,
+    pub scheme: SignatureScheme,
+    /// The DER-encoded public key of the owner. The owner's principal is the
+    /// self-authenticating principal derived from this key.
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl SubmitSignedTransferArgs {
+    /// Verifies the signature over the payload and returns the principal
+    /// owning the source account.
+    pub fn verify(&self) -> Result<PrincipalId, String> {
+        let message = self.payload.signed_bytes();
+        match self.scheme {
+            SignatureScheme::Ed25519 => {
+                let raw_key = strip_der_prefix(&self.public_key, &ED25519_DER_PREFIX)?;
+                let public_key = ed25519_dalek::PublicKey::from_bytes(raw_key)
+                    .map_err(|e| format!("Invalid Ed25519 public key: {}", e))?;
+                let signature = ed25519_dalek::Signature::try_from(&self.signature[..])
+                    .map_err(|e| format!("Invalid Ed25519 signature: {}", e))?;
+                public_key
+                    .verify_strict(&message, &signature)
+                    .map_err(|_| "Ed25519 signature verification failed".to_string())?;
+            }
+            SignatureScheme::EcdsaSecp256k1 => {
+                let raw_key = strip_der_prefix(&self.public_key, &SECP256K1_DER_PREFIX)?;
+                let public_key = libsecp256k1::PublicKey::parse_slice(raw_key, None)
+                    .map_err(|e| format!("Invalid secp256k1 public key: {:?}", e))?;
+                let signature = libsecp256k1::Signature::parse_standard_slice(&self.signature)
+                    .map_err(|e| format!("Invalid secp256k1 signature: {:?}", e))?;
+                let digest = libsecp256k1::Message::parse(&Sha256::hash(&message));
+                if !libsecp256k1::verify(&digest, &signature, &public_key) {
+                    return Err("ECDSA signature verification failed".to_string());
+                }
+            }
+        }
+        Ok(PrincipalId::new_self_authenticating(&self.public_key))
+    }
+}
+
+fn strip_der_prefix<'a>(der: &'a [u8], prefix: &[u8]) -> Result<&'a [u8], String> {
+    if der.starts_with(prefix) {
+        Ok(&der[prefix.len()..])
+    } else {
+        Err("Public key is not DER-encoded with the expected algorithm identifier".to_string())
+    }
+}
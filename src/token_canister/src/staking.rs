@@ -0,0 +1,235 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+use crate::ic_block::BlockHeight;
+use crate::ic_token::TOKENs;
+use crate::types::MemoBlob;
+use crate::TimeStamp;
+
+use candid::CandidType;
+use ic_base_types::PrincipalId;
+use ic_crypto_sha::Sha256;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::time::Duration;
+
+pub type StakeId = u64;
+
+const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+static STAKE_SUBACCOUNT_DOMAIN_SEPARATOR: &[u8] = b"\x0Dstake-account";
+static REWARDS_POOL_DOMAIN_SEPARATOR: &[u8] = b"\x0Cstaking-pool";
+
+/// The subaccount of the ledger canister that holds the tokens of a stake
+/// while they are locked.
+pub fn stake_subaccount(id: StakeId) -> Subaccount {
+    let mut state = Sha256::new();
+    state.write(STAKE_SUBACCOUNT_DOMAIN_SEPARATOR);
+    state.write(&id.to_be_bytes());
+    Subaccount(state.finish())
+}
+
+/// The subaccount of the ledger canister that rewards are paid from. Anyone
+/// can fund it with a plain transfer. A stake's reward is set aside from it
+/// when the stake is made, so stakes the pool can't cover are rejected.
+pub fn rewards_pool_subaccount() -> Subaccount {
+    Subaccount(Sha256::hash(REWARDS_POOL_DOMAIN_SEPARATOR))
+}
+
+/// Set by the controller to enable staking.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct StakingConfig {
+    /// Yearly reward as a fraction of the staked amount, in basis points.
+    pub annual_reward_rate_basis_points: u64,
+    pub min_duration: Duration,
+    pub max_duration: Duration,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct Stake {
+    pub id: StakeId,
+    pub owner: PrincipalId,
+    /// The account the stake came from, and that it and its reward return to.
+    pub account: AccountIdentifier,
+    pub amount: TOKENs,
+    pub locked_at: TimeStamp,
+    pub unlocks_at: TimeStamp,
+    /// The reward rate when the stake was made.
+    pub annual_reward_rate_basis_points: u64,
+    /// The reward set aside from the rewards pool when the stake was made,
+    /// paid out in full when it is unstaked.
+    #[serde(default)]
+    pub reserved_reward: TOKENs,
+}
+
+impl Stake {
+    /// The reward accrued over the full lock duration.
+    pub fn reward(&self) -> TOKENs {
+        let seconds = self
+            .unlocks_at
+            .duration_since(self.locked_at)
+            .unwrap_or_default()
+            .as_secs();
+        let denom = 10_000 * SECONDS_PER_YEAR;
+        self.annual_reward_rate_basis_points
+            .checked_mul(seconds)
+            .and_then(|num| self.amount.checked_mul_ratio(num, denom))
+            .unwrap_or(TOKENs::MAX)
+    }
+}
+
+/// Tags the blocks created by staking, in their memo blob.
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub enum StakingEvent {
+    Lock { stake_id: StakeId },
+    Unlock { stake_id: StakeId },
+    Reward { stake_id: StakeId },
+}
+
+impl StakingEvent {
+    pub fn to_memo_blob(&self) -> MemoBlob {
+        MemoBlob::new(serde_cbor::ser::to_vec_packed(self).unwrap())
+            .expect("Staking events always fit in a memo blob")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Staking {
+    config: Option<StakingConfig>,
+    stakes: BTreeMap<StakeId, Stake>,
+    next_stake_id: StakeId,
+    /// The sum of the rewards reserved for open stakes.
+    #[serde(default)]
+    reserved_rewards: TOKENs,
+}
+
+impl Staking {
+    pub fn config(&self) -> Option<&StakingConfig> {
+        self.config.as_ref()
+    }
+
+    /// Sets the staking configuration, or disables new stakes with `None`.
+    /// Existing stakes keep the rate they were made with.
+    pub fn set_config(&mut self, config: Option<StakingConfig>) -> Result<(), String> {
+        if let Some(config) = &config {
+            if config.min_duration > config.max_duration {
+                return Err("The minimum stake duration exceeds the maximum".to_string());
+            }
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    pub fn reserved_rewards(&self) -> TOKENs {
+        self.reserved_rewards
+    }
+
+    /// Registers a new stake of `amount` from `account` locked for
+    /// `duration`, returning it. Its reward is reserved from the
+    /// `rewards_pool` balance, and the stake is rejected if what is not
+    /// reserved yet can't cover it. The caller must then move the tokens
+    /// into the stake's subaccount.
+    #[allow(clippy::too_many_arguments)]
+    pub fn open(
+        &mut self,
+        owner: PrincipalId,
+        account: AccountIdentifier,
+        amount: TOKENs,
+        duration: Duration,
+        rewards_pool: TOKENs,
+        now: TimeStamp,
+    ) -> Result<Stake, String> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| "Staking is not enabled".to_string())?;
+        if duration < config.min_duration || duration > config.max_duration {
+            return Err(format!(
+                "Stakes must be locked for between {} and {} seconds",
+                config.min_duration.as_secs(),
+                config.max_duration.as_secs()
+            ));
+        }
+        if amount == TOKENs::ZERO {
+            return Err("Cannot stake zero tokens".to_string());
+        }
+        let mut stake = Stake {
+            id: self.next_stake_id,
+            owner,
+            account,
+            amount,
+            locked_at: now,
+            unlocks_at: now
+                .checked_add(duration)
+                .ok_or_else(|| "Stake duration is too long".to_string())?,
+            annual_reward_rate_basis_points: config.annual_reward_rate_basis_points,
+            reserved_reward: TOKENs::ZERO,
+        };
+        let reward = stake.reward();
+        let unreserved = (rewards_pool - self.reserved_rewards).unwrap_or(TOKENs::ZERO);
+        if reward > unreserved {
+            return Err(format!(
+                "The rewards pool can only cover {} more in rewards, but the stake would earn {}",
+                unreserved, reward
+            ));
+        }
+        stake.reserved_reward = reward;
+        self.reserved_rewards += reward;
+        self.next_stake_id += 1;
+        self.stakes.insert(stake.id, stake.clone());
+        Ok(stake)
+    }
+
+    /// Removes the stake `id` of `owner` if it is unlocked at `now`, releasing
+    /// its reserved reward for the caller to pay out.
+    pub fn close(
+        &mut self,
+        owner: &PrincipalId,
+        id: StakeId,
+        now: TimeStamp,
+    ) -> Result<Stake, String> {
+        match self.stakes.get(&id) {
+            None => Err(format!("Stake {} does not exist", id)),
+            Some(stake) if stake.owner != *owner => {
+                Err(format!("Stake {} belongs to another principal", id))
+            }
+            Some(stake) if now.is_before(stake.unlocks_at) => Err(format!(
+                "Stake {} is locked for another {} seconds",
+                id,
+                stake.unlocks_at.duration_since(now).unwrap().as_secs()
+            )),
+            Some(_) => {
+                let stake = self.stakes.remove(&id).unwrap();
+                self.reserved_rewards -= stake.reserved_reward;
+                Ok(stake)
+            }
+        }
+    }
+
+    pub fn get(&self, id: StakeId) -> Option<&Stake> {
+        self.stakes.get(&id)
+    }
+
+    pub fn stakes_of(&self, owner: &PrincipalId) -> Vec<Stake> {
+        self.stakes
+            .values()
+            .filter(|stake| stake.owner == *owner)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Argument taken by the stake endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct StakeArgs {
+    pub amount: TOKENs,
+    pub duration: Duration,
+    pub from_subaccount: Option<Subaccount>,
+}
+
+/// The result of the unstake endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct UnstakeResult {
+    pub unlock_block: BlockHeight,
+    pub reward: TOKENs,
+    pub reward_block: Option<BlockHeight>,
+}
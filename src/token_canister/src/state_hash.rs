@@ -0,0 +1,97 @@
+//! A hash over the parts of the ledger's state that every copy of the same
+//! ledger must agree on, so that operators running several copies in testing
+//! can spot divergence caused by nondeterminism. It covers the balances, the
+//! tip of the chain, the configuration and the alias registry. The ledger has
+//! no allowances yet; they belong here once it does.
+
+use crate::account_identifier::AccountIdentifier;
+use crate::ic_token::TOKENs;
+use crate::Ledger;
+use ic_crypto_sha::Sha256;
+use serde::Serialize;
+
+static STATE_HASH_DOMAIN_SEPARATOR: &[u8] = b"\x0Cledger-state";
+static BALANCE_DOMAIN_SEPARATOR: &[u8] = b"\x07balance";
+
+fn write_u64(state: &mut Sha256, n: u64) {
+    state.write(&n.to_be_bytes());
+}
+
+/// Hashes the CBOR encoding of `value`, prefixed with its length. Only used
+/// for values without hash maps, whose encoding is deterministic.
+fn write_cbor<T: Serialize>(state: &mut Sha256, value: &T) {
+    let bytes = serde_cbor::ser::to_vec_packed(value).expect("Encoding the state failed");
+    write_u64(state, bytes.len() as u64);
+    state.write(&bytes);
+}
+
+/// The hash of the balances: the sum, modulo 2^256, of the hashes of the
+/// individual balances. Unlike hashing the sorted balances, it is updated in
+/// constant time whenever a balance changes, so the state hash never has to
+/// visit every account.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BalancesHash([u8; 32]);
+
+fn balance_hash(account: &AccountIdentifier, balance: TOKENs) -> [u8; 32] {
+    let mut state = Sha256::new();
+    state.write(BALANCE_DOMAIN_SEPARATOR);
+    state.write(account.as_ref());
+    write_u64(&mut state, balance.get_e8s());
+    state.finish()
+}
+
+impl BalancesHash {
+    pub fn insert(&mut self, account: &AccountIdentifier, balance: TOKENs) {
+        let hash = balance_hash(account, balance);
+        let mut carry = 0;
+        for i in (0..32).rev() {
+            let sum = self.0[i] as u16 + hash[i] as u16 + carry;
+            self.0[i] = sum as u8;
+            carry = sum >> 8;
+        }
+    }
+
+    pub fn remove(&mut self, account: &AccountIdentifier, balance: TOKENs) {
+        let hash = balance_hash(account, balance);
+        let mut borrow = 0;
+        for i in (0..32).rev() {
+            let difference = self.0[i] as i16 - hash[i] as i16 - borrow;
+            self.0[i] = difference as u8;
+            borrow = (difference < 0) as i16;
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+pub fn state_hash(ledger: &Ledger) -> [u8; 32] {
+    let mut state = Sha256::new();
+    state.write(STATE_HASH_DOMAIN_SEPARATOR);
+    state.write(ledger.balances.balances_hash().as_bytes());
+    write_u64(&mut state, ledger.balances.icpt_pool.get_e8s());
+
+    write_u64(&mut state, ledger.blockchain.chain_length());
+    match ledger.tip() {
+        Some((_, hash)) => state.write(hash.as_slice()),
+        None => state.write(&[0; 32]),
+    }
+
+    write_cbor(&mut state, &ledger.symbol);
+    write_u64(&mut state, ledger.decimals as u64);
+    write_cbor(&mut state, &ledger.minting_account_id);
+    write_cbor(&mut state, &ledger.burn_account_id);
+    write_u64(&mut state, ledger.transaction_window.as_nanos() as u64);
+    write_u64(&mut state, ledger.permitted_drift.as_nanos() as u64);
+    write_u64(&mut state, ledger.maximum_number_of_accounts as u64);
+    write_u64(&mut state, ledger.accounts_overflow_trim_quantity as u64);
+    write_cbor(&mut state, &ledger.accounts_overflow_policy);
+    write_cbor(&mut state, &ledger.access_policy);
+    write_cbor(&mut state, &ledger.notify_whitelist);
+    write_cbor(&mut state, &ledger.retention_policy);
+    write_cbor(&mut state, &ledger.archive_lag_throttle);
+
+    state.write(&ledger.aliases.root_hash().unwrap_or([0; 32]));
+    state.finish()
+}
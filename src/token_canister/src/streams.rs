@@ -0,0 +1,183 @@
+use crate::account_identifier::{AccountIdentifier, Subaccount};
+use crate::ic_token::TOKENs;
+use crate::types::MemoBlob;
+use crate::TimeStamp;
+
+use candid::CandidType;
+use ic_base_types::PrincipalId;
+use ic_crypto_sha::Sha256;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+pub type StreamId = u64;
+
+static STREAM_SUBACCOUNT_DOMAIN_SEPARATOR: &[u8] = b"\x0Estream-account";
+
+/// The subaccount of the ledger canister that holds the deposit of a stream.
+pub fn stream_subaccount(id: StreamId) -> Subaccount {
+    let mut state = Sha256::new();
+    state.write(STREAM_SUBACCOUNT_DOMAIN_SEPARATOR);
+    state.write(&id.to_be_bytes());
+    Subaccount(state.finish())
+}
+
+/// A deposit that vests continuously from a sender to a recipient at a fixed
+/// rate, until it is used up or cancelled.
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct Stream {
+    pub id: StreamId,
+    pub sender: PrincipalId,
+    /// The account the deposit came from, and that what is left of it
+    /// returns to when the stream is cancelled.
+    pub sender_account: AccountIdentifier,
+    pub recipient: PrincipalId,
+    pub recipient_account: AccountIdentifier,
+    pub rate_per_second: TOKENs,
+    pub deposit: TOKENs,
+    pub started_at: TimeStamp,
+    /// The part of the deposit the recipient already withdrew.
+    pub withdrawn: TOKENs,
+}
+
+impl Stream {
+    /// The part of the deposit that vested by `now`.
+    pub fn vested(&self, now: TimeStamp) -> TOKENs {
+        let seconds = now
+            .duration_since(self.started_at)
+            .unwrap_or_default()
+            .as_secs() as u128;
+        let e8s = self.rate_per_second.get_e8s() as u128 * seconds;
+        let e8s: u64 = e8s.try_into().unwrap_or(u64::MAX);
+        std::cmp::min(TOKENs::from_e8s(e8s), self.deposit)
+    }
+
+    /// The part of the deposit the recipient can withdraw at `now`.
+    pub fn withdrawable(&self, now: TimeStamp) -> TOKENs {
+        (self.vested(now) - self.withdrawn).unwrap_or(TOKENs::ZERO)
+    }
+}
+
+/// Tags the settlement blocks of streams, in their memo blob.
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq)]
+pub enum StreamEvent {
+    Deposit { stream_id: StreamId },
+    Withdraw { stream_id: StreamId },
+    Refund { stream_id: StreamId },
+}
+
+impl StreamEvent {
+    pub fn to_memo_blob(&self) -> MemoBlob {
+        MemoBlob::new(serde_cbor::ser::to_vec_packed(self).unwrap())
+            .expect("Stream events always fit in a memo blob")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Streams {
+    streams: BTreeMap<StreamId, Stream>,
+    next_stream_id: StreamId,
+}
+
+impl Streams {
+    #[allow(clippy::too_many_arguments)]
+    pub fn open(
+        &mut self,
+        sender: PrincipalId,
+        sender_account: AccountIdentifier,
+        recipient: PrincipalId,
+        recipient_account: AccountIdentifier,
+        rate_per_second: TOKENs,
+        deposit: TOKENs,
+        now: TimeStamp,
+    ) -> Result<Stream, String> {
+        if rate_per_second == TOKENs::ZERO || deposit == TOKENs::ZERO {
+            return Err("Streams need a positive rate and deposit".to_string());
+        }
+        let stream = Stream {
+            id: self.next_stream_id,
+            sender,
+            sender_account,
+            recipient,
+            recipient_account,
+            rate_per_second,
+            deposit,
+            started_at: now,
+            withdrawn: TOKENs::ZERO,
+        };
+        self.next_stream_id += 1;
+        self.streams.insert(stream.id, stream.clone());
+        Ok(stream)
+    }
+
+    /// Marks everything that vested by `now` as withdrawn by the recipient,
+    /// returning the stream before the withdrawal and the amount. Streams
+    /// whose deposit is fully withdrawn are removed.
+    pub fn withdraw(
+        &mut self,
+        recipient: &PrincipalId,
+        id: StreamId,
+        now: TimeStamp,
+    ) -> Result<(Stream, TOKENs), String> {
+        let stream = self
+            .streams
+            .get_mut(&id)
+            .ok_or_else(|| format!("Stream {} does not exist", id))?;
+        if stream.recipient != *recipient {
+            return Err(format!("Only the recipient can withdraw from stream {}", id));
+        }
+        let amount = stream.withdrawable(now);
+        let before = stream.clone();
+        stream.withdrawn = (stream.withdrawn + amount)?;
+        if stream.withdrawn == stream.deposit {
+            self.streams.remove(&id);
+        }
+        Ok((before, amount))
+    }
+
+    /// Ends the stream `id` of `sender` at `now`, returning it together with
+    /// the amount still owed to the recipient and the amount returned to the
+    /// sender.
+    pub fn cancel(
+        &mut self,
+        sender: &PrincipalId,
+        id: StreamId,
+        now: TimeStamp,
+    ) -> Result<(Stream, TOKENs, TOKENs), String> {
+        match self.streams.get(&id) {
+            None => Err(format!("Stream {} does not exist", id)),
+            Some(stream) if stream.sender != *sender => {
+                Err(format!("Only the sender can cancel stream {}", id))
+            }
+            Some(_) => {
+                let stream = self.streams.remove(&id).unwrap();
+                let owed = stream.withdrawable(now);
+                let refund = ((stream.deposit - stream.withdrawn)? - owed)?;
+                Ok((stream, owed, refund))
+            }
+        }
+    }
+
+    pub fn get(&self, id: StreamId) -> Option<&Stream> {
+        self.streams.get(&id)
+    }
+
+    /// The streams that `principal_id` sends or receives.
+    pub fn streams_of(&self, principal_id: &PrincipalId) -> Vec<Stream> {
+        self.streams
+            .values()
+            .filter(|s| s.sender == *principal_id || s.recipient == *principal_id)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Argument taken by the create_stream endpoint
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub struct CreateStreamArgs {
+    pub to: PrincipalId,
+    pub to_subaccount: Option<Subaccount>,
+    pub rate_per_second: TOKENs,
+    pub deposit: TOKENs,
+    pub from_subaccount: Option<Subaccount>,
+}
@@ -0,0 +1,61 @@
+use crate::ic_block::BlockHeight;
+use crate::ic_token::TOKENs;
+use crate::TimeStamp;
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// The most checkpoints a supply_history request returns.
+pub const MAX_SUPPLY_CHECKPOINTS_PER_REQUEST: usize = 2000;
+
+/// The total supply right after the mint or burn at `height`.
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SupplyCheckpoint {
+    pub height: BlockHeight,
+    pub timestamp: TimeStamp,
+    pub total_supply: TOKENs,
+}
+
+/// Argument taken by the supply_history endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct SupplyHistoryArgs {
+    /// Only checkpoints at this height or later are returned.
+    pub start: BlockHeight,
+    /// At most `MAX_SUPPLY_CHECKPOINTS_PER_REQUEST`.
+    pub limit: u64,
+}
+
+/// A checkpoint of the total supply for every mint and burn, oldest first.
+/// Fees burned by transfers also lower the supply, so between two
+/// checkpoints it can drop by the fees of the transfers in between.
+/// Mints and burns from before the history was kept are not recorded.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct SupplyHistory {
+    checkpoints: Vec<SupplyCheckpoint>,
+}
+
+impl SupplyHistory {
+    pub fn record(&mut self, checkpoint: SupplyCheckpoint) {
+        self.checkpoints.push(checkpoint);
+    }
+
+    /// Up to `limit` checkpoints from height `start` on.
+    pub fn range(&self, start: BlockHeight, limit: usize) -> Vec<SupplyCheckpoint> {
+        let first = self
+            .checkpoints
+            .partition_point(|checkpoint| checkpoint.height < start);
+        self.checkpoints[first..]
+            .iter()
+            .take(limit.min(MAX_SUPPLY_CHECKPOINTS_PER_REQUEST))
+            .copied()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checkpoints.is_empty()
+    }
+}
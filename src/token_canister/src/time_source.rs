@@ -0,0 +1,68 @@
+use crate::TimeStamp;
+
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The IC's clock, which reads the same throughout a message. Code with
+/// access to the ledger should use `Ledger::now` instead, which also keeps
+/// the time from going backwards across upgrades.
+pub fn ledger_time() -> TimeStamp {
+    raw_time()
+}
+
+#[cfg(feature = "canister")]
+fn raw_time() -> TimeStamp {
+    TimeStamp::from(dfn_core::api::now())
+}
+
+/// Outside of a canister, e.g. when the ledger is used as a library, the
+/// clock of the host stands in for the IC's.
+#[cfg(not(feature = "canister"))]
+fn raw_time() -> TimeStamp {
+    TimeStamp::from(std::time::SystemTime::now())
+}
+
+/// Where a `Ledger` reads the current time from.
+pub trait TimeSource: Debug + Send + Sync {
+    fn now(&self) -> TimeStamp;
+}
+
+/// The clock of the canister, as returned by `ledger_time`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IcTimeSource;
+
+impl TimeSource for IcTimeSource {
+    fn now(&self) -> TimeStamp {
+        ledger_time()
+    }
+}
+
+/// A clock that only moves when told to, so that tests can step through
+/// the transaction window deterministically.
+#[derive(Debug)]
+pub struct ManualTimeSource {
+    now_nanos: AtomicU64,
+}
+
+impl ManualTimeSource {
+    pub fn new(now: TimeStamp) -> Self {
+        Self {
+            now_nanos: AtomicU64::new(now.as_nanos()),
+        }
+    }
+
+    pub fn set(&self, now: TimeStamp) {
+        self.now_nanos.store(now.as_nanos(), Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, d: Duration) {
+        self.set(self.now() + d);
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> TimeStamp {
+        TimeStamp::from_nanos(self.now_nanos.load(Ordering::Relaxed))
+    }
+}
@@ -11,21 +11,73 @@ impl TimeStamp {
         }
     }
 
-    pub fn from_nanos_since_unix_epoch(nanos: u64) -> Self {
+    pub const fn from_nanos(nanos: u64) -> Self {
         Self {
             timestamp_nanos: nanos,
         }
     }
 
+    pub const fn as_nanos(&self) -> u64 {
+        self.timestamp_nanos
+    }
+
+    pub fn from_nanos_since_unix_epoch(nanos: u64) -> Self {
+        Self::from_nanos(nanos)
+    }
+
     pub fn as_nanos_since_unix_epoch(&self) -> u64 {
+        self.as_nanos()
+    }
+
+    /// Returns `self + d`, or `None` if the result does not fit in a u64 of
+    /// nanoseconds.
+    pub fn checked_add(self, d: Duration) -> Option<Self> {
+        let d: u64 = d.as_nanos().try_into().ok()?;
+        self.timestamp_nanos.checked_add(d).map(Self::from_nanos)
+    }
+
+    /// Returns `self - d`, or `None` if the result would be before the Unix
+    /// epoch.
+    pub fn checked_sub(self, d: Duration) -> Option<Self> {
+        let d: u64 = d.as_nanos().try_into().ok()?;
+        self.timestamp_nanos.checked_sub(d).map(Self::from_nanos)
+    }
+
+    pub fn saturating_add(self, d: Duration) -> Self {
+        self.checked_add(d).unwrap_or(Self::from_nanos(u64::MAX))
+    }
+
+    pub fn saturating_sub(self, d: Duration) -> Self {
+        self.checked_sub(d).unwrap_or(Self::from_nanos(0))
+    }
+
+    /// The amount of time elapsed from `earlier` to `self`, or `None` if
+    /// `earlier` is later than `self`.
+    pub fn duration_since(&self, earlier: TimeStamp) -> Option<Duration> {
         self.timestamp_nanos
+            .checked_sub(earlier.timestamp_nanos)
+            .map(Duration::from_nanos)
+    }
+
+    pub fn is_before(&self, other: TimeStamp) -> bool {
+        *self < other
+    }
+
+    pub fn is_after(&self, other: TimeStamp) -> bool {
+        *self > other
+    }
+
+    /// Whether `window` has fully elapsed since `self` at time `now`, i.e.
+    /// `self + window <= now`.
+    pub fn has_elapsed(&self, window: Duration, now: TimeStamp) -> bool {
+        self.saturating_add(window) <= now
     }
 }
 
 impl From<SystemTime> for TimeStamp {
     fn from(t: SystemTime) -> Self {
         let d = t.duration_since(SystemTime::UNIX_EPOCH).unwrap();
-        Self::from_nanos_since_unix_epoch(d.as_nanos().try_into().unwrap())
+        Self::from_nanos(d.as_nanos().try_into().unwrap())
     }
 }
 
@@ -39,7 +91,8 @@ impl Add<Duration> for TimeStamp {
     type Output = Self;
 
     fn add(self, d: Duration) -> Self {
-        (SystemTime::from(self) + d).into()
+        self.checked_add(d)
+            .expect("TimeStamp overflowed when adding a Duration")
     }
 }
 
@@ -47,6 +100,7 @@ impl Sub<Duration> for TimeStamp {
     type Output = Self;
 
     fn sub(self, d: Duration) -> Self {
-        (SystemTime::from(self) - d).into()
+        self.checked_sub(d)
+            .expect("TimeStamp underflowed when subtracting a Duration")
     }
 }
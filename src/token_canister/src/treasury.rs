@@ -0,0 +1,143 @@
+//! Treasury accounts hold funds that their owners cannot spend on their own:
+//! every debit must first be approved by the governance principal, so a
+//! leaked key of a treasury account is not enough to move its funds.
+
+use crate::account_identifier::AccountIdentifier;
+use crate::ic_token::TOKENs;
+
+use candid::CandidType;
+use ic_base_types::PrincipalId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// The most accounts that can be declared treasury accounts.
+pub const MAX_TREASURY_ACCOUNTS: usize = 100;
+/// The most approvals that can wait to be used at a time.
+pub const MAX_TREASURY_APPROVALS: usize = 1000;
+
+/// A debit of a treasury account approved by the governance principal. It
+/// allows exactly one transfer of `amount` from `from` to `to`, or one burn
+/// of `amount` from `from` if `to` is not set.
+#[derive(
+    Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct TreasuryApproval {
+    pub from: AccountIdentifier,
+    pub to: Option<AccountIdentifier>,
+    pub amount: TOKENs,
+}
+
+/// Response of the treasury endpoint.
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct TreasuryInfo {
+    pub governance: Option<PrincipalId>,
+    pub accounts: Vec<AccountIdentifier>,
+    pub approvals: Vec<TreasuryApproval>,
+}
+
+/// The treasury accounts, the principal that co-approves their debits, and
+/// the approvals not used yet.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Treasury {
+    governance: Option<PrincipalId>,
+    accounts: BTreeSet<AccountIdentifier>,
+    approvals: BTreeSet<TreasuryApproval>,
+}
+
+impl Treasury {
+    pub fn governance(&self) -> Option<PrincipalId> {
+        self.governance
+    }
+
+    /// Checks that the principal that approves debits can be changed to
+    /// `governance`. It cannot be unset while there are treasury accounts,
+    /// as their funds would be stuck.
+    pub fn validate_governance(&self, governance: Option<PrincipalId>) -> Result<(), String> {
+        if governance.is_none() && !self.accounts.is_empty() {
+            return Err(
+                "The governance principal cannot be unset while there are treasury accounts"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn set_governance(&mut self, governance: Option<PrincipalId>) -> Result<(), String> {
+        self.validate_governance(governance)?;
+        if governance != self.governance {
+            // Approvals are given by a principal, not for it.
+            self.approvals.clear();
+        }
+        self.governance = governance;
+        Ok(())
+    }
+
+    pub fn is_treasury(&self, account: &AccountIdentifier) -> bool {
+        self.accounts.contains(account)
+    }
+
+    /// Declares `account` a treasury account. This needs a governance
+    /// principal to approve its debits.
+    pub fn add_account(&mut self, account: AccountIdentifier) -> Result<(), String> {
+        if self.governance.is_none() {
+            return Err(
+                "A governance principal must be set before declaring treasury accounts".to_string(),
+            );
+        }
+        if self.accounts.len() >= MAX_TREASURY_ACCOUNTS && !self.accounts.contains(&account) {
+            return Err(format!(
+                "There are already {} treasury accounts",
+                MAX_TREASURY_ACCOUNTS
+            ));
+        }
+        self.accounts.insert(account);
+        Ok(())
+    }
+
+    /// Makes `account` an ordinary account again, dropping its approvals.
+    /// Returns whether it was a treasury account.
+    pub fn remove_account(&mut self, account: &AccountIdentifier) -> bool {
+        self.approvals.retain(|approval| approval.from != *account);
+        self.accounts.remove(account)
+    }
+
+    /// Records an approval. Approving the same debit twice still only allows
+    /// it once.
+    pub fn approve(&mut self, approval: TreasuryApproval) -> Result<(), String> {
+        if !self.accounts.contains(&approval.from) {
+            return Err(format!("{} is not a treasury account", approval.from));
+        }
+        if self.approvals.len() >= MAX_TREASURY_APPROVALS && !self.approvals.contains(&approval) {
+            return Err(format!(
+                "There are already {} unused treasury approvals",
+                MAX_TREASURY_APPROVALS
+            ));
+        }
+        self.approvals.insert(approval);
+        Ok(())
+    }
+
+    /// Drops an approval before it is used, returning whether it existed.
+    pub fn revoke(&mut self, approval: &TreasuryApproval) -> bool {
+        self.approvals.remove(approval)
+    }
+
+    /// Whether the debit is allowed: `from` is not a treasury account, or
+    /// the debit was approved.
+    pub fn allows(&self, approval: &TreasuryApproval) -> bool {
+        !self.accounts.contains(&approval.from) || self.approvals.contains(approval)
+    }
+
+    /// Uses up the approval of a debit that was applied.
+    pub fn consume(&mut self, approval: &TreasuryApproval) {
+        self.approvals.remove(approval);
+    }
+
+    pub fn info(&self) -> TreasuryInfo {
+        TreasuryInfo {
+            governance: self.governance,
+            accounts: self.accounts.iter().copied().collect(),
+            approvals: self.approvals.iter().cloned().collect(),
+        }
+    }
+}
@@ -21,6 +21,15 @@ pub struct Transaction {
 
     /// The time this transaction was created.
     pub created_at_time: TimeStamp,
+
+    /// An opaque reference that the payer and payee encrypted off-chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_memo: Option<EncryptedMemo>,
+
+    /// Structured, readable data attached to the transaction, e.g. the
+    /// reason for a mint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo_blob: Option<MemoBlob>,
 }
 
 impl Transaction {
@@ -42,6 +51,8 @@ impl Transaction {
             operation,
             memo,
             created_at_time,
+            encrypted_memo: None,
+            memo_blob: None,
         }
     }
 
@@ -57,13 +68,23 @@ impl Transaction {
     Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq, PartialOrd, Ord,
 )]
 pub enum Operation {
+    /// Destroys `amount` from `from`, which also pays `fee`.
     Burn {
         from: AccountIdentifier,
         amount: TOKENs,
+        /// The fee charged for the burn. It is zero today and left out of
+        /// the encoding when it is, so that the hashes of existing blocks
+        /// and transactions do not change.
+        #[serde(default, skip_serializing_if = "TOKENs::is_zero")]
+        fee: TOKENs,
     },
+    /// Creates `amount`, of which `to` receives what is left after `fee`.
     Mint {
         to: AccountIdentifier,
         amount: TOKENs,
+        /// The fee charged for the mint, encoded like the fee of a burn.
+        #[serde(default, skip_serializing_if = "TOKENs::is_zero")]
+        fee: TOKENs,
     },
     Transfer {
         from: AccountIdentifier,
@@ -71,6 +92,29 @@ pub enum Operation {
         amount: TOKENs,
         fee: TOKENs,
     },
+    /// Records that the controller replaced the minting account. It does not
+    /// move any funds.
+    SetMintingAccount {
+        previous: AccountIdentifier,
+        new: AccountIdentifier,
+    },
+    /// Records a step of a timelocked admin action, see
+    /// `crate::admin_actions`. It does not move any funds.
+    AdminAudit {
+        action_id: u64,
+        status: AdminActionStatus,
+        description: String,
+    },
+}
+
+/// The step of a timelocked admin action an `Operation::AdminAudit` records.
+#[derive(
+    Serialize, Deserialize, CandidType, Clone, Copy, Hash, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum AdminActionStatus {
+    Proposed,
+    Executed,
+    Cancelled,
 }
 
 #[derive(
@@ -82,4 +126,109 @@ impl Default for Memo {
     fn default() -> Memo {
         Memo(0)
     }
-}
\ No newline at end of file
+}
+
+impl Memo {
+    /// Renders the memo for people, e.g. in consent messages and block
+    /// explorers: the decimal value, followed by the hex value when it
+    /// differs, as in `42 (0x2a)`.
+    pub fn to_display(&self) -> String {
+        if self.0 < 10 {
+            self.0.to_string()
+        } else {
+            format!("{} (0x{:x})", self.0, self.0)
+        }
+    }
+}
+
+/// The maximum length of an `EncryptedMemo` in bytes.
+pub const MAX_ENCRYPTED_MEMO_LENGTH: usize = 256;
+
+/// A confidential reference attached to a transaction, encrypted end-to-end
+/// by the payer for the payee. The ledger stores it in the block but never
+/// interprets it, and it is not shown on hardware wallets.
+#[derive(
+    Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(transparent)]
+pub struct EncryptedMemo(#[serde(with = "serde_bytes")] pub Vec<u8>);
+
+impl EncryptedMemo {
+    pub fn new(bytes: Vec<u8>) -> Result<Self, String> {
+        let memo = Self(bytes);
+        memo.validate()?;
+        Ok(memo)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.0.is_empty() || self.0.len() > MAX_ENCRYPTED_MEMO_LENGTH {
+            return Err(format!(
+                "Encrypted memos must be between 1 and {} bytes long, got {}",
+                MAX_ENCRYPTED_MEMO_LENGTH,
+                self.0.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The maximum length of a `MemoBlob` in bytes.
+pub const MAX_MEMO_BLOB_LENGTH: usize = 64;
+
+/// Readable bytes attached to a transaction and stored in its block. Unlike
+/// `Memo` its contents can be structured, see `MintReason`.
+#[derive(
+    Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(transparent)]
+pub struct MemoBlob(#[serde(with = "serde_bytes")] pub Vec<u8>);
+
+impl MemoBlob {
+    pub fn new(bytes: Vec<u8>) -> Result<Self, String> {
+        let blob = Self(bytes);
+        blob.validate()?;
+        Ok(blob)
+    }
+
+    /// Renders the blob for people: quoted if it is printable UTF-8 text,
+    /// and as `0x`-prefixed hex otherwise.
+    pub fn to_display(&self) -> String {
+        match std::str::from_utf8(&self.0) {
+            Ok(text) if !text.chars().any(char::is_control) => format!("\"{}\"", text),
+            _ => format!("0x{}", hex::encode(&self.0)),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.0.is_empty() || self.0.len() > MAX_MEMO_BLOB_LENGTH {
+            return Err(format!(
+                "Memo blobs must be between 1 and {} bytes long, got {}",
+                MAX_MEMO_BLOB_LENGTH,
+                self.0.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Why tokens were minted. Stored CBOR-encoded in the memo blob of the mint
+/// block, so that the origin of every token can be traced on chain.
+#[derive(Serialize, Deserialize, CandidType, Clone, Hash, Debug, PartialEq, Eq)]
+pub enum MintReason {
+    /// Tokens granted under the grant with the given id.
+    Grant { grant_id: String },
+    /// Tokens issued for a deposit locked on another chain.
+    BridgeDeposit { chain: String, deposit_id: String },
+    Other { description: String },
+}
+
+impl MintReason {
+    pub fn to_memo_blob(&self) -> Result<MemoBlob, String> {
+        MemoBlob::new(serde_cbor::ser::to_vec_packed(self).unwrap())
+            .map_err(|e| format!("Mint reason is too long: {}", e))
+    }
+
+    pub fn from_memo_blob(blob: &MemoBlob) -> Result<Self, String> {
+        serde_cbor::from_slice(&blob.0).map_err(|e| format!("Invalid mint reason: {}", e))
+    }
+}
@@ -0,0 +1,61 @@
+//! Estimates of the cost of pre_upgrade. It serializes the whole ledger to
+//! stable memory, and running out of instructions halfway leaves a canister
+//! that can no longer be upgraded. pre_upgrade therefore refuses to start
+//! when the estimate exceeds the ledger's budget, which aborts the upgrade and
+//! keeps the current version running.
+
+use crate::Ledger;
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+/// The default budget, leaving a margin below the replica's limit of 200B
+/// instructions for an upgrade.
+pub const DEFAULT_UPGRADE_INSTRUCTION_BUDGET: u64 = 150_000_000_000;
+
+/// Rough upper bounds of the serialized size of the entries that dominate the
+/// ledger's state. CBOR encodes each byte of a block as an integer, which
+/// takes up to two bytes.
+const BLOCK_SIZE_FACTOR: u64 = 2;
+const ACCOUNT_SIZE_BYTES: u64 = 80;
+const TRANSACTION_SIZE_BYTES: u64 = 250;
+/// A rough upper bound of the instructions spent encoding a byte and writing
+/// it to stable memory.
+const INSTRUCTIONS_PER_BYTE: u64 = 200;
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct UpgradeEstimate {
+    /// The estimated size of the serialized ledger.
+    pub state_size_bytes: u64,
+    /// The estimated instructions pre_upgrade takes.
+    pub instructions: u64,
+    /// pre_upgrade refuses to run if `instructions` exceeds this.
+    pub instruction_budget: u64,
+}
+
+impl UpgradeEstimate {
+    pub fn within_budget(&self) -> bool {
+        self.instructions <= self.instruction_budget
+    }
+}
+
+pub fn estimate_upgrade(ledger: &Ledger) -> UpgradeEstimate {
+    // Persisted blocks are not serialized.
+    let block_bytes: u64 = if ledger.blockchain.blocks_are_persisted() {
+        0
+    } else {
+        ledger
+            .blockchain
+            .blocks
+            .iter()
+            .map(|block| block.size_bytes() as u64)
+            .sum()
+    };
+    let state_size_bytes = block_bytes * BLOCK_SIZE_FACTOR
+        + ledger.balances.store.len() as u64 * ACCOUNT_SIZE_BYTES
+        + ledger.transactions_by_height.len() as u64 * TRANSACTION_SIZE_BYTES;
+    UpgradeEstimate {
+        state_size_bytes,
+        instructions: state_size_bytes.saturating_mul(INSTRUCTIONS_PER_BYTE),
+        instruction_budget: ledger.upgrade_instruction_budget,
+    }
+}
@@ -1,13 +1,13 @@
 use crate::protobuf;
 use crate::protobuf::transaction::Transfer as PTransfer;
 use crate::{
-    AccountBalanceArgs, AccountIdentifier, CyclesResponse, Memo,
-    NotifyCanisterArgs, Operation, SendArgs, Subaccount, TimeStamp, TotalSupplyArgs,
+    AccountBalanceArgs, AccountIdentifier, AdminActionStatus, CyclesResponse, EncryptedMemo, Memo,
+    MemoBlob, NotifyCanisterArgs, Operation, SendArgs, Subaccount, TimeStamp, TotalSupplyArgs,
     Transaction, TransactionNotification, TRANSACTION_FEE,
 };
 
 use crate::hashof:: { HashOf, HASH_LENGTH };
-use crate::ic_block::{ Block, EncodedBlock, BlockArg, BlockRes, GetBlocksArgs, GetBlocksRes, IterBlocksArgs, IterBlocksRes, TipOfChainRes};
+use crate::ic_block::{ Block, EncodedBlock, BlockArg, BlockRes, GetBlocksArgs, GetBlocksError, GetBlocksRes, IterBlocksArgs, IterBlocksRes, TipOfChainRes};
 use crate::ic_token::TOKENs;
 
 use dfn_protobuf::ToProto;
@@ -145,9 +145,9 @@ impl ToProto for GetBlocksRes {
     type Proto = protobuf::GetBlocksResponse;
 
     fn from_proto(pb: Self::Proto) -> Result<Self, String> {
-        match pb
+        let blocks = match pb
             .get_blocks_content
-            .expect("get_blocks() reponse with no content")
+            .ok_or("get_blocks() response with no content")?
         {
             protobuf::get_blocks_response::GetBlocksContent::Blocks(protobuf::EncodedBlocks {
                 blocks,
@@ -156,16 +156,29 @@ impl ToProto for GetBlocksRes {
                     .into_iter()
                     .map(|protobuf::EncodedBlock { block }| EncodedBlock(block.into_boxed_slice()))
                     .collect();
-                Ok(GetBlocksRes(Ok(blocks)))
+                Ok(blocks)
             }
             protobuf::get_blocks_response::GetBlocksContent::Error(error) => {
-                Ok(GetBlocksRes(Err(error)))
+                Err(GetBlocksError::OutOfRange(error))
             }
-        }
+            protobuf::get_blocks_response::GetBlocksContent::Pruned(protobuf::PrunedBlocks {
+                earliest_available,
+            }) => Err(GetBlocksError::Pruned { earliest_available }),
+        };
+        Ok(GetBlocksRes {
+            blocks,
+            first_block_index: pb.first_block_index,
+            chain_length: pb.chain_length,
+        })
     }
 
     fn into_proto(self) -> Self::Proto {
-        match self.0 {
+        let GetBlocksRes {
+            blocks,
+            first_block_index,
+            chain_length,
+        } = self;
+        match blocks {
             Ok(blocks) => {
                 let blocks = blocks
                     .into_iter()
@@ -174,6 +187,8 @@ impl ToProto for GetBlocksRes {
                     })
                     .collect();
                 protobuf::GetBlocksResponse {
+                    first_block_index,
+                    chain_length,
                     get_blocks_content: Some(
                         protobuf::get_blocks_response::GetBlocksContent::Blocks(
                             protobuf::EncodedBlocks { blocks },
@@ -181,11 +196,20 @@ impl ToProto for GetBlocksRes {
                     ),
                 }
             }
-            Err(err) => protobuf::GetBlocksResponse {
+            Err(GetBlocksError::OutOfRange(err)) => protobuf::GetBlocksResponse {
+                first_block_index,
+                chain_length,
                 get_blocks_content: Some(protobuf::get_blocks_response::GetBlocksContent::Error(
                     err,
                 )),
             },
+            Err(GetBlocksError::Pruned { earliest_available }) => protobuf::GetBlocksResponse {
+                first_block_index,
+                chain_length,
+                get_blocks_content: Some(protobuf::get_blocks_response::GetBlocksContent::Pruned(
+                    protobuf::PrunedBlocks { earliest_available },
+                )),
+            },
         }
     }
 }
@@ -261,7 +285,8 @@ impl ToProto for BlockRes {
                 block,
             })) => Ok(BlockRes(Some(Ok(EncodedBlock(block.into_boxed_slice()))))),
             Some(protobuf::block_response::BlockContent::CanisterId(canister_id)) => {
-                Ok(BlockRes(Some(Err(CanisterId::new(canister_id).unwrap()))))
+                let canister_id = CanisterId::new(canister_id).map_err(|e| e.to_string())?;
+                Ok(BlockRes(Some(Err(canister_id))))
             }
             None => Ok(BlockRes(None)),
         }
@@ -300,6 +325,9 @@ impl ToProto for SendArgs {
             to,
             created_at: _,
             created_at_time,
+            idempotency_key,
+            encrypted_memo,
+            expires_at,
         }: Self::Proto,
     ) -> Result<Self, String> {
         let memo = match memo {
@@ -320,6 +348,11 @@ impl ToProto for SendArgs {
         let to = AccountIdentifier::from_proto(
             to.ok_or("The send endpoint requires a field _to to be filled")?,
         )?;
+        let encrypted_memo = if encrypted_memo.is_empty() {
+            None
+        } else {
+            Some(EncryptedMemo::new(encrypted_memo)?)
+        };
         Ok(SendArgs {
             memo,
             amount: TOKENs::from_proto(amount)?,
@@ -327,6 +360,9 @@ impl ToProto for SendArgs {
             from_subaccount,
             to,
             created_at_time,
+            idempotency_key: idempotency_key.map(|k| k.key),
+            encrypted_memo,
+            expires_at,
         })
     }
     fn into_proto(self) -> Self::Proto {
@@ -337,6 +373,9 @@ impl ToProto for SendArgs {
             from_subaccount,
             to,
             created_at_time,
+            idempotency_key,
+            encrypted_memo,
+            expires_at,
         } = self;
         let amount = amount.into_proto();
         let payment = Some(protobuf::Payment {
@@ -350,6 +389,9 @@ impl ToProto for SendArgs {
             to: Some(to.into_proto()),
             created_at: None,
             created_at_time,
+            idempotency_key: idempotency_key.map(|key| protobuf::IdempotencyKey { key }),
+            encrypted_memo: encrypted_memo.map(|m| m.0).unwrap_or_default(),
+            expires_at,
         }
     }
 }
@@ -521,10 +563,16 @@ impl ToProto for Block {
 
         let timestamp = pb.timestamp.ok_or("This block lacks a timestamp")?;
 
+        let fee_collector = match pb.fee_collector {
+            Some(account) => Some(AccountIdentifier::from_proto(account)?),
+            None => None,
+        };
+
         Ok(Block {
             parent_hash,
             transaction: Transaction::from_proto(transaction)?,
             timestamp: TimeStamp::from_proto(timestamp)?,
+            fee_collector,
         })
     }
 
@@ -533,6 +581,7 @@ impl ToProto for Block {
             parent_hash: self.parent_hash.map(|h| h.into_proto()),
             transaction: Some(self.transaction.into_proto()),
             timestamp: Some(self.timestamp.into_proto()),
+            fee_collector: self.fee_collector.map(|account| account.into_proto()),
         }
     }
 }
@@ -550,16 +599,26 @@ impl ToProto for Transaction {
             PTransfer::Burn(protobuf::Burn {
                 from: Some(from),
                 amount: Some(amount),
+                fee,
             }) => Operation::Burn {
                 from: AccountIdentifier::from_proto(from)?,
                 amount: TOKENs::from_proto(amount)?,
+                fee: match fee {
+                    Some(fee) => TOKENs::from_proto(fee)?,
+                    None => TOKENs::ZERO,
+                },
             },
             PTransfer::Mint(protobuf::Mint {
                 to: Some(to),
                 amount: Some(amount),
+                fee,
             }) => Operation::Mint {
                 to: AccountIdentifier::from_proto(to)?,
                 amount: TOKENs::from_proto(amount)?,
+                fee: match fee {
+                    Some(fee) => TOKENs::from_proto(fee)?,
+                    None => TOKENs::ZERO,
+                },
             },
             PTransfer::Send(protobuf::Send {
                 to: Some(to),
@@ -575,12 +634,45 @@ impl ToProto for Transaction {
                     None => TRANSACTION_FEE,
                 },
             },
+            PTransfer::SetMintingAccount(protobuf::SetMintingAccount {
+                previous: Some(previous),
+                new: Some(new),
+            }) => Operation::SetMintingAccount {
+                previous: AccountIdentifier::from_proto(previous)?,
+                new: AccountIdentifier::from_proto(new)?,
+            },
+            PTransfer::AdminAudit(protobuf::AdminAudit {
+                action_id,
+                status,
+                description,
+            }) => Operation::AdminAudit {
+                action_id,
+                status: match protobuf::AdminActionStatus::from_i32(status) {
+                    Some(protobuf::AdminActionStatus::Proposed) => AdminActionStatus::Proposed,
+                    Some(protobuf::AdminActionStatus::Executed) => AdminActionStatus::Executed,
+                    Some(protobuf::AdminActionStatus::Cancelled) => AdminActionStatus::Cancelled,
+                    None => return Err(format!("Unknown admin action status: {}", status)),
+                },
+                description,
+            },
             t => return Err(format!("Transaction lacked a required field: {:?}", t)),
         };
+        let encrypted_memo = if pb.encrypted_memo.is_empty() {
+            None
+        } else {
+            Some(EncryptedMemo(pb.encrypted_memo))
+        };
+        let memo_blob = if pb.memo_blob.is_empty() {
+            None
+        } else {
+            Some(MemoBlob(pb.memo_blob))
+        };
         Ok(Transaction {
             operation,
             memo,
             created_at_time,
+            encrypted_memo,
+            memo_blob,
         })
     }
 
@@ -589,16 +681,22 @@ impl ToProto for Transaction {
             memo,
             created_at_time,
             operation,
+            encrypted_memo,
+            memo_blob,
         } = self;
         let transfer = match operation {
-            Operation::Burn { from, amount } => PTransfer::Burn(protobuf::Burn {
+            // Zero fees are left out so that existing blocks keep their
+            // encoding.
+            Operation::Burn { from, amount, fee } => PTransfer::Burn(protobuf::Burn {
                 from: Some(from.into_proto()),
                 amount: Some(amount.into_proto()),
+                fee: if fee.is_zero() { None } else { Some(fee.into_proto()) },
             }),
 
-            Operation::Mint { to, amount } => PTransfer::Mint(protobuf::Mint {
+            Operation::Mint { to, amount, fee } => PTransfer::Mint(protobuf::Mint {
                 to: Some(to.into_proto()),
                 amount: Some(amount.into_proto()),
+                fee: if fee.is_zero() { None } else { Some(fee.into_proto()) },
             }),
 
             Operation::Transfer {
@@ -612,11 +710,34 @@ impl ToProto for Transaction {
                 from: Some(from.into_proto()),
                 max_fee: Some(fee.into_proto()),
             }),
+
+            Operation::SetMintingAccount { previous, new } => {
+                PTransfer::SetMintingAccount(protobuf::SetMintingAccount {
+                    previous: Some(previous.into_proto()),
+                    new: Some(new.into_proto()),
+                })
+            }
+
+            Operation::AdminAudit {
+                action_id,
+                status,
+                description,
+            } => PTransfer::AdminAudit(protobuf::AdminAudit {
+                action_id,
+                status: match status {
+                    AdminActionStatus::Proposed => protobuf::AdminActionStatus::Proposed,
+                    AdminActionStatus::Executed => protobuf::AdminActionStatus::Executed,
+                    AdminActionStatus::Cancelled => protobuf::AdminActionStatus::Cancelled,
+                } as i32,
+                description,
+            }),
         };
         protobuf::Transaction {
             memo: Some(protobuf::Memo { memo: memo.0 }),
             created_at: None,
             created_at_time: Some(created_at_time),
+            encrypted_memo: encrypted_memo.map(|m| m.0).unwrap_or_default(),
+            memo_blob: memo_blob.map(|m| m.0).unwrap_or_default(),
             transfer: Some(transfer),
         }
     }
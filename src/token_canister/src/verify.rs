@@ -0,0 +1,94 @@
+//! Verification of what the ledger hands out, for wallets and backends that
+//! do not trust the node they query: blocks, ranges of blocks, tip
+//! certificates and block certificates. Build with
+//! `default-features = false, features = ["verify"]` to get this without
+//! the canister endpoints and their dependencies.
+//!
+//! Certificates are only checked to certify what they are presented with;
+//! their signature must still be checked against the IC root key, e.g. by
+//! the agent that fetched them.
+
+use crate::certification::{
+    certified_data_in, decode_tip_hash_tree, BlockCertificate, TipCertificate,
+};
+use crate::{Block, BlockHeight, EncodedBlock, HashOf};
+
+/// Decodes `encoded`, checking that it is the block with hash
+/// `expected_hash`.
+pub fn decode_block(
+    encoded: &EncodedBlock,
+    expected_hash: HashOf<EncodedBlock>,
+) -> Result<Block, String> {
+    let hash = encoded.hash();
+    if hash != expected_hash {
+        return Err(format!(
+            "Block hash mismatch: expected {} but the block hashes to {}",
+            expected_hash, hash
+        ));
+    }
+    encoded.decode()
+}
+
+/// Checks that `blocks` are consecutive blocks of a chain whose block
+/// preceding the first one has hash `parent_hash` (`None` if `blocks` start
+/// at the genesis block), and returns the hash of the last block.
+pub fn verify_chain(
+    parent_hash: Option<HashOf<EncodedBlock>>,
+    blocks: &[EncodedBlock],
+) -> Result<Option<HashOf<EncodedBlock>>, String> {
+    let mut last_hash = parent_hash;
+    for (i, encoded) in blocks.iter().enumerate() {
+        let block = encoded.decode()?;
+        if block.parent_hash != last_hash {
+            return Err(format!(
+                "Block {} of the range does not point at its predecessor",
+                i
+            ));
+        }
+        last_hash = Some(encoded.hash());
+    }
+    Ok(last_hash)
+}
+
+/// Checks that the hash tree of `tip_certificate` is certified by its
+/// certificate for the ledger `canister_id`, and returns the height and hash
+/// of the tip it reveals, if the ledger has blocks.
+pub fn verify_tip_certificate(
+    tip_certificate: &TipCertificate,
+    canister_id: &[u8],
+) -> Result<Option<(BlockHeight, HashOf<EncodedBlock>)>, String> {
+    let (root_hash, tip) = decode_tip_hash_tree(&tip_certificate.hash_tree)?;
+    let certified_data = certified_data_in(&tip_certificate.certificate, canister_id)?;
+    if certified_data[..] != root_hash[..] {
+        return Err("The hash tree is not the one the certificate certifies".to_string());
+    }
+    Ok(tip)
+}
+
+/// Checks `block_certificate` for the ledger `canister_id`, see
+/// `BlockCertificate`, and returns the block it proves.
+pub fn verify_block_certificate(
+    block_certificate: &BlockCertificate,
+    canister_id: &[u8],
+) -> Result<Block, String> {
+    let (tip_height, tip_hash) = verify_tip_certificate(
+        &TipCertificate {
+            certificate: block_certificate.certificate.clone(),
+            hash_tree: block_certificate.hash_tree.clone(),
+        },
+        canister_id,
+    )?
+    .ok_or_else(|| "The certificate covers an empty chain".to_string())?;
+    let expected_tip_height = block_certificate.height + block_certificate.descendants.len() as u64;
+    if tip_height != expected_tip_height {
+        return Err(format!(
+            "The descendants end at height {} but the certified tip is at {}",
+            expected_tip_height, tip_height
+        ));
+    }
+    let block_hash = block_certificate.block.hash();
+    if verify_chain(Some(block_hash), &block_certificate.descendants)? != Some(tip_hash) {
+        return Err("The block does not lead to the certified tip".to_string());
+    }
+    block_certificate.block.decode()
+}
@@ -0,0 +1,45 @@
+#![allow(clippy::unwrap_used)]
+
+use ic_types::PrincipalId;
+use token_canister::account_owners::MAX_REGISTERED_ACCOUNTS_PER_OWNER;
+use token_canister::{AccountIdentifier, AccountOwners, Subaccount};
+
+fn subaccount(i: u32) -> Subaccount {
+    let mut bytes = [0; 32];
+    bytes[28..].copy_from_slice(&i.to_be_bytes());
+    Subaccount(bytes)
+}
+
+#[test]
+fn only_registered_accounts_have_an_owner() {
+    let owner = PrincipalId::new_user_test_id(1);
+    let mut owners = AccountOwners::default();
+    let account = owners.register(owner, Some(subaccount(1))).unwrap();
+
+    assert_eq!(account, AccountIdentifier::new(owner, Some(subaccount(1))));
+    assert_eq!(owners.owner_of(&account), Some(owner));
+    assert_eq!(
+        owners.owner_of(&AccountIdentifier::new(owner, Some(subaccount(2)))),
+        None
+    );
+
+    assert_eq!(owners.unregister(owner, Some(subaccount(1))), Ok(account));
+    assert_eq!(owners.owner_of(&account), None);
+    assert!(owners.unregister(owner, Some(subaccount(1))).is_err());
+}
+
+#[test]
+fn registrations_per_owner_are_bounded() {
+    let owner = PrincipalId::new_user_test_id(1);
+    let mut owners = AccountOwners::default();
+    for i in 0..MAX_REGISTERED_ACCOUNTS_PER_OWNER as u32 {
+        owners.register(owner, Some(subaccount(i))).unwrap();
+    }
+    // Registering an account again does not count against the limit.
+    owners.register(owner, Some(subaccount(0))).unwrap();
+    assert!(owners.register(owner, None).is_err());
+
+    owners.unregister(owner, Some(subaccount(0))).unwrap();
+    owners.register(owner, None).unwrap();
+    assert_eq!(owners.len(), MAX_REGISTERED_ACCOUNTS_PER_OWNER);
+}
@@ -0,0 +1,71 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment};
+use std::sync::Arc;
+use token_canister::{AccountSummary, Ledger, ManualTimeSource, Operation, TOKENs, TimeStamp};
+
+fn transfer(from: u8, to: u8, amount: u64) -> Operation {
+    Operation::Transfer {
+        from: account(from),
+        to: account(to),
+        amount: TOKENs::from_e8s(amount),
+        fee: TOKENs::from_e8s(10),
+    }
+}
+
+#[test]
+fn summaries_follow_the_chain() {
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        1_000_000_000,
+    ))));
+    assert_eq!(
+        ledger.account_summary(&account(1)),
+        AccountSummary {
+            balance: TOKENs::ZERO,
+            first_seen_height: None,
+            last_activity_height: None,
+            total_received: TOKENs::ZERO,
+            total_sent: TOKENs::ZERO,
+        }
+    );
+
+    let minted = add_payment(
+        &mut ledger,
+        0,
+        Operation::Mint {
+            to: account(1),
+            amount: TOKENs::from_e8s(1_000),
+            fee: TOKENs::ZERO,
+        },
+    )
+    .unwrap();
+    let first = add_payment(&mut ledger, 0, transfer(1, 2, 100)).unwrap();
+    let second = add_payment(&mut ledger, 0, transfer(1, 2, 200)).unwrap();
+
+    assert_eq!(
+        ledger.account_summary(&account(1)),
+        AccountSummary {
+            balance: TOKENs::from_e8s(680),
+            first_seen_height: Some(minted),
+            last_activity_height: Some(second),
+            total_received: TOKENs::from_e8s(1_000),
+            total_sent: TOKENs::from_e8s(320),
+        }
+    );
+    assert_eq!(
+        ledger.account_summary(&account(2)),
+        AccountSummary {
+            balance: TOKENs::from_e8s(300),
+            first_seen_height: Some(first),
+            last_activity_height: Some(second),
+            total_received: TOKENs::from_e8s(300),
+            total_sent: TOKENs::ZERO,
+        }
+    );
+
+    // Emptied accounts are forgotten, like their balances.
+    add_payment(&mut ledger, 0, transfer(2, 3, 290)).unwrap();
+    assert_eq!(ledger.account_summary(&account(2)).first_seen_height, None);
+}
@@ -0,0 +1,121 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, GENESIS_TIME_NANOS};
+use ic_base_types::PrincipalId;
+use std::sync::Arc;
+use std::time::Duration;
+use token_canister::admin_actions::DEFAULT_ADMIN_ACTION_DELAY;
+use token_canister::{
+    AdminAction, AdminActionStatus, Ledger, ManualTimeSource, Operation, PaymentError, TimeStamp,
+};
+
+fn last_operation(ledger: &Ledger) -> Operation {
+    ledger
+        .blockchain
+        .blocks
+        .last()
+        .unwrap()
+        .decode()
+        .unwrap()
+        .transaction
+        .operation
+}
+
+#[test]
+fn actions_are_executed_after_the_delay() {
+    let clock = Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        GENESIS_TIME_NANOS,
+    )));
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(clock.clone());
+    let admin = PrincipalId::new_user_test_id(1);
+    let minter = PrincipalId::new_user_test_id(2);
+
+    let pending = ledger
+        .propose_admin_action(
+            AdminAction::SetMinter {
+                principal: minter,
+                quota: None,
+                expires_at: None,
+            },
+            admin,
+        )
+        .unwrap();
+    assert_eq!(
+        pending.executable_at,
+        pending.proposed_at + DEFAULT_ADMIN_ACTION_DELAY
+    );
+    assert_eq!(
+        last_operation(&ledger),
+        Operation::AdminAudit {
+            action_id: pending.id,
+            status: AdminActionStatus::Proposed,
+            description: pending.action.to_string(),
+        }
+    );
+
+    assert!(matches!(
+        ledger.execute_admin_action(pending.id),
+        Err(PaymentError::Reject(_))
+    ));
+    assert!(ledger.minters.get(&minter).is_none());
+
+    clock.set(pending.executable_at);
+    ledger.execute_admin_action(pending.id).unwrap();
+    assert!(ledger.minters.get(&minter).is_some());
+    assert!(ledger.admin_actions.pending().is_empty());
+    assert!(matches!(
+        last_operation(&ledger),
+        Operation::AdminAudit {
+            status: AdminActionStatus::Executed,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn cancelled_actions_are_never_executed() {
+    let clock = Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        GENESIS_TIME_NANOS,
+    )));
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(clock.clone());
+    let admin = PrincipalId::new_user_test_id(1);
+
+    let pending = ledger
+        .propose_admin_action(AdminAction::SetAdminActionDelay { seconds: 60 }, admin)
+        .unwrap();
+    let cancelled = ledger.cancel_admin_action(pending.id).unwrap();
+    assert_eq!(cancelled, pending);
+    assert!(matches!(
+        last_operation(&ledger),
+        Operation::AdminAudit {
+            status: AdminActionStatus::Cancelled,
+            ..
+        }
+    ));
+
+    clock.set(pending.executable_at);
+    assert!(ledger.execute_admin_action(pending.id).is_err());
+    assert_eq!(ledger.admin_actions.delay(), DEFAULT_ADMIN_ACTION_DELAY);
+
+    let pending = ledger
+        .propose_admin_action(AdminAction::SetAdminActionDelay { seconds: 60 }, admin)
+        .unwrap();
+    clock.set(pending.executable_at);
+    ledger.execute_admin_action(pending.id).unwrap();
+    assert_eq!(ledger.admin_actions.delay(), Duration::from_secs(60));
+}
+
+#[test]
+fn invalid_actions_are_rejected_when_proposed() {
+    let mut ledger = Ledger::default();
+    ledger.minting_account_id = Some(account(0));
+    let proposal = ledger.propose_admin_action(
+        AdminAction::SetFeeCollector(Some(account(0))),
+        PrincipalId::new_user_test_id(1),
+    );
+    assert!(proposal.is_err());
+    assert!(ledger.admin_actions.pending().is_empty());
+}
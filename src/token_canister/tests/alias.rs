@@ -0,0 +1,78 @@
+#![allow(clippy::unwrap_used)]
+
+use ic_base_types::PrincipalId;
+use token_canister::alias::MAX_ALIASES_PER_PRINCIPAL;
+use token_canister::{AccountIdentifier, AliasRegistry, Subaccount};
+
+fn subaccount(n: usize) -> Option<Subaccount> {
+    let mut bytes = [0; 32];
+    bytes[..8].copy_from_slice(&(n as u64).to_be_bytes());
+    Some(Subaccount(bytes))
+}
+
+#[test]
+fn principals_hold_a_bounded_number_of_aliases() {
+    let owner = PrincipalId::new_user_test_id(1);
+    let mut registry = AliasRegistry::default();
+    for i in 0..MAX_ALIASES_PER_PRINCIPAL {
+        registry
+            .claim(owner, format!("name-{}", i), subaccount(i))
+            .unwrap();
+    }
+    assert!(registry
+        .claim(owner, "one-too-many".to_string(), subaccount(100))
+        .is_err());
+
+    // Renaming an account and moving a name to another account don't add
+    // aliases.
+    registry
+        .claim(owner, "renamed".to_string(), subaccount(0))
+        .unwrap();
+    assert_eq!(registry.resolve("name-0"), None);
+    registry
+        .claim(owner, "renamed".to_string(), subaccount(100))
+        .unwrap();
+    assert_eq!(
+        registry.resolve("renamed"),
+        Some(AccountIdentifier::new(owner, subaccount(100)))
+    );
+    assert_eq!(registry.len(), MAX_ALIASES_PER_PRINCIPAL);
+
+    registry.release(owner, "name-1").unwrap();
+    registry
+        .claim(owner, "freed".to_string(), subaccount(1))
+        .unwrap();
+    // Other principals have their own allowance.
+    registry
+        .claim(PrincipalId::new_user_test_id(2), "other".to_string(), None)
+        .unwrap();
+}
+
+#[test]
+fn every_change_changes_the_root_hash() {
+    let owner = PrincipalId::new_user_test_id(1);
+    let mut a = AliasRegistry::default();
+    let mut b = AliasRegistry::default();
+    assert_eq!(a.root_hash(), None);
+
+    let mut seen = vec![];
+    for i in 0..5 {
+        a.claim(owner, format!("name-{}", i), subaccount(i))
+            .unwrap();
+        b.claim(owner, format!("name-{}", i), subaccount(i))
+            .unwrap();
+        assert_eq!(a.root_hash(), b.root_hash());
+        assert!(!seen.contains(&a.root_hash()));
+        seen.push(a.root_hash());
+    }
+
+    a.release(owner, "name-2").unwrap();
+    assert!(!seen.contains(&a.root_hash()));
+    seen.push(a.root_hash());
+    // The name takes the freed slot.
+    a.claim(owner, "name-2".to_string(), subaccount(2)).unwrap();
+    assert_eq!(a.root_hash(), b.root_hash());
+
+    a.claim(owner, "name-2".to_string(), subaccount(7)).unwrap();
+    assert!(!seen.contains(&a.root_hash()));
+}
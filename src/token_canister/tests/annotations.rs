@@ -0,0 +1,82 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, LedgerBuilder, GENESIS_TIME_NANOS};
+use token_canister::annotations::MAX_ANNOTATION_LENGTH;
+use token_canister::{Ledger, Operation, TOKENs, TimeStamp};
+
+/// A ledger where account 1 was minted tokens at height 0 and sent some of
+/// them to account 2 at height 1.
+fn ledger() -> Ledger {
+    LedgerBuilder::new()
+        .mint(account(1), 100_000)
+        .operation(Operation::Transfer {
+            from: account(1),
+            to: account(2),
+            amount: TOKENs::from_e8s(1_000),
+            fee: TOKENs::from_e8s(10),
+        })
+        .build()
+}
+
+#[test]
+fn both_parties_annotate_their_transaction() {
+    let mut ledger = ledger();
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    ledger
+        .annotate_transaction(account(1), 1, Some("invoice 42".to_string()), now)
+        .unwrap();
+    ledger
+        .annotate_transaction(account(2), 1, Some("order 7".to_string()), now)
+        .unwrap();
+
+    let annotations = ledger.annotations.range(&account(1), 0, 10);
+    assert_eq!(annotations.len(), 1);
+    assert_eq!(annotations[0].height, 1);
+    assert_eq!(annotations[0].label, "invoice 42");
+    assert_eq!(annotations[0].annotated_at, now);
+    assert_eq!(
+        ledger.annotations.range(&account(2), 0, 10)[0].label,
+        "order 7"
+    );
+    assert!(ledger.annotations.range(&account(1), 2, 10).is_empty());
+
+    ledger
+        .annotate_transaction(account(1), 1, None, now)
+        .unwrap();
+    assert!(ledger.annotations.range(&account(1), 0, 10).is_empty());
+    assert_eq!(ledger.annotations.range(&account(2), 0, 10).len(), 1);
+}
+
+#[test]
+fn only_parties_annotate_a_transaction() {
+    let mut ledger = ledger();
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    assert!(ledger
+        .annotate_transaction(account(3), 1, Some("mine".to_string()), now)
+        .is_err());
+    // Account 2 was not involved in the mint.
+    assert!(ledger
+        .annotate_transaction(account(2), 0, Some("mine".to_string()), now)
+        .is_err());
+    assert!(ledger
+        .annotate_transaction(account(1), 2, Some("future".to_string()), now)
+        .is_err());
+}
+
+#[test]
+fn labels_are_bounded() {
+    let mut ledger = ledger();
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    let long = "x".repeat(MAX_ANNOTATION_LENGTH + 1);
+    assert!(ledger
+        .annotate_transaction(account(1), 1, Some(long), now)
+        .is_err());
+    assert!(ledger
+        .annotate_transaction(account(1), 1, Some(String::new()), now)
+        .is_err());
+    let hash = "ab".repeat(32);
+    ledger
+        .annotate_transaction(account(1), 1, Some(hash), now)
+        .unwrap();
+}
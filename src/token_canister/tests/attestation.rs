@@ -0,0 +1,37 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint, GENESIS_TIME_NANOS};
+use std::sync::Arc;
+use token_canister::{Ledger, ManualTimeSource, TOKENs, TimeStamp};
+
+#[test]
+fn attestations_compare_the_balance_to_the_minimum() {
+    let mut ledger = Ledger::default();
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    ledger.set_time_source(Arc::new(ManualTimeSource::new(now)));
+    add_payment(&mut ledger, 0, mint(account(1), 1_000)).unwrap();
+
+    let attestation = ledger.attest_balance(account(1), TOKENs::from_e8s(1_000), 7, now);
+    assert!(attestation.holds);
+    assert_eq!(attestation.account, account(1));
+    assert_eq!(attestation.chain_length, 1);
+    assert_eq!(attestation.attested_at, now);
+    assert_eq!(attestation.nonce, 7);
+
+    assert!(
+        !ledger
+            .attest_balance(account(1), TOKENs::from_e8s(1_001), 8, now)
+            .holds
+    );
+    assert!(
+        !ledger
+            .attest_balance(account(2), TOKENs::from_e8s(1), 9, now)
+            .holds
+    );
+    assert!(
+        ledger
+            .attest_balance(account(2), TOKENs::ZERO, 10, now)
+            .holds
+    );
+}
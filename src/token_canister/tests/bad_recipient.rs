@@ -0,0 +1,25 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint};
+use ic_types::PrincipalId;
+use token_canister::{AccountIdentifier, Ledger, PaymentError, TransferError};
+
+#[test]
+fn unspendable_recipients_are_rejected() {
+    let anonymous = AccountIdentifier::new(PrincipalId::new_anonymous(), None);
+    let zero = AccountIdentifier { hash: [0; 28] };
+    let mut ledger = Ledger::default();
+    for to in [anonymous, zero].iter() {
+        assert_eq!(
+            add_payment(&mut ledger, 0, mint(*to, 1)),
+            Err(PaymentError::TransferError(TransferError::BadRecipient {
+                to: *to
+            }))
+        );
+    }
+    add_payment(&mut ledger, 0, mint(account(1), 1)).unwrap();
+
+    ledger.set_reject_unspendable_recipients(false);
+    add_payment(&mut ledger, 0, mint(zero, 1)).unwrap();
+}
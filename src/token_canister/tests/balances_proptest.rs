@@ -0,0 +1,236 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, GENESIS_TIME_NANOS};
+use proptest::prelude::*;
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use std::time::Duration;
+use token_canister::{
+    AccountIdentifier, Ledger, LedgerBalances, ManualTimeSource, Memo, Operation, PaymentOptions,
+    TOKENs, TimeSource, TimeStamp,
+};
+
+const NUM_ACCOUNTS: u8 = 6;
+const MAXIMUM_NUMBER_OF_ACCOUNTS: usize = 4;
+const ACCOUNTS_OVERFLOW_TRIM_QUANTITY: usize = 2;
+const TRANSACTION_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+enum Action {
+    Mint {
+        to: u8,
+        amount: u64,
+    },
+    Transfer {
+        from: u8,
+        to: u8,
+        amount: u64,
+        fee: u64,
+    },
+    Burn {
+        from: u8,
+        amount: u64,
+    },
+    AdvanceTime {
+        secs: u64,
+    },
+}
+
+/// An action together with the memo and idempotency key it is submitted
+/// with. Both are drawn from small ranges so that duplicates happen.
+#[derive(Clone, Debug)]
+struct Step {
+    action: Action,
+    memo: u64,
+    idempotency_key: Option<u8>,
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        (0..NUM_ACCOUNTS, 1..1_000u64).prop_map(|(to, amount)| Action::Mint { to, amount }),
+        (0..NUM_ACCOUNTS, 0..NUM_ACCOUNTS, 0..1_000u64, 0..20u64)
+            .prop_map(|(from, to, amount, fee)| Action::Transfer {
+                from,
+                to,
+                amount,
+                fee
+            }),
+        (0..NUM_ACCOUNTS, 1..1_000u64).prop_map(|(from, amount)| Action::Burn { from, amount }),
+        (0..15u64).prop_map(|secs| Action::AdvanceTime { secs }),
+    ]
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    (action(), 0..3u64, proptest::option::of(0..3u8)).prop_map(
+        |(action, memo, idempotency_key)| Step {
+            action,
+            memo,
+            idempotency_key,
+        },
+    )
+}
+
+fn new_ledger(clock: Arc<ManualTimeSource>) -> Ledger {
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(clock);
+    ledger
+        .set_account_limits(
+            Some(MAXIMUM_NUMBER_OF_ACCOUNTS),
+            Some(ACCOUNTS_OVERFLOW_TRIM_QUANTITY),
+        )
+        .unwrap();
+    ledger.transaction_window = TRANSACTION_WINDOW;
+    // account(0) has the all-zero hash, which the ledger would refuse.
+    ledger.set_reject_unspendable_recipients(false);
+    ledger
+}
+
+/// The account an operation takes funds from, and how much it takes.
+fn debit_of(operation: &Operation) -> Option<(AccountIdentifier, TOKENs)> {
+    match operation {
+        Operation::Transfer {
+            from, amount, fee, ..
+        } => Some((*from, (*amount + *fee).unwrap())),
+        Operation::Burn { from, amount, fee } => Some((*from, (*amount + *fee).unwrap())),
+        Operation::Mint { .. }
+        | Operation::SetMintingAccount { .. }
+        | Operation::AdminAudit { .. } => None,
+    }
+}
+
+fn assert_supply_is_conserved(balances: &LedgerBalances) {
+    let mut total = balances.icpt_pool;
+    for balance in balances.store.values() {
+        total = (total + *balance).unwrap();
+    }
+    assert_eq!(total, TOKENs::MAX);
+}
+
+/// Replaying the chain from scratch must give the balances the ledger holds,
+/// including the burns of trimmed accounts.
+fn assert_chain_matches_balances(ledger: &Ledger) {
+    let mut replayed = LedgerBalances::default();
+    for block in ledger.blockchain.blocks.iter() {
+        let block = block.decode().unwrap();
+        replayed
+            .add_payment_with_fee_collector(
+                &block.transaction.operation,
+                block.fee_collector.as_ref(),
+            )
+            .unwrap();
+    }
+    assert_eq!(replayed, ledger.balances);
+}
+
+proptest! {
+    #[test]
+    fn ledger_invariants_hold_for_random_operations(
+        steps in proptest::collection::vec(step(), 1..60)
+    ) {
+        let clock = Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(GENESIS_TIME_NANOS)));
+        let mut ledger = new_ledger(clock.clone());
+        // The block timestamps and idempotency keys of the transactions that
+        // should still be in the transaction window.
+        let mut window: VecDeque<(TimeStamp, Option<(AccountIdentifier, Vec<u8>)>)> =
+            VecDeque::new();
+
+        for Step { action, memo, idempotency_key } in steps {
+            let operation = match action {
+                Action::AdvanceTime { secs } => {
+                    clock.advance(Duration::from_secs(secs));
+                    continue;
+                }
+                Action::Mint { to, amount } => Operation::Mint {
+                    to: account(to),
+                    amount: TOKENs::from_e8s(amount),
+                    fee: TOKENs::ZERO,
+                },
+                Action::Transfer { from, to, amount, fee } => Operation::Transfer {
+                    from: account(from),
+                    to: account(to),
+                    amount: TOKENs::from_e8s(amount),
+                    fee: TOKENs::from_e8s(fee),
+                },
+                Action::Burn { from, amount } => Operation::Burn {
+                    from: account(from),
+                    amount: TOKENs::from_e8s(amount),
+                    fee: TOKENs::ZERO,
+                },
+            };
+
+            if let Some((from, debit)) = debit_of(&operation) {
+                let balance = ledger.balances.account_balance(&from);
+                if balance == TOKENs::ZERO || balance < debit {
+                    // Overdrafts and debits of empty accounts must be refused
+                    // rather than wrap around.
+                    let mut balances = ledger.balances.clone();
+                    let res = catch_unwind(AssertUnwindSafe(|| balances.debit(&from, debit)));
+                    prop_assert!(res.is_err(), "Overdraft of {} by {} succeeded", from, debit);
+                    continue;
+                }
+            }
+
+            let owner = debit_of(&operation).map_or(account(0), |(from, _)| from);
+            let key = idempotency_key.map(|k| (owner, vec![k]));
+            let res = ledger.add_payment(
+                Memo(memo),
+                operation,
+                PaymentOptions {
+                    idempotency_key: key.clone(),
+                    ..Default::default()
+                },
+            );
+
+            let now = clock.now();
+
+            while let Some((timestamp, _)) = window.front() {
+                if !timestamp.has_elapsed(TRANSACTION_WINDOW, now) {
+                    break;
+                }
+                window.pop_front();
+            }
+            if res.is_ok() {
+                window.push_back((now, key));
+            }
+
+            assert_supply_is_conserved(&ledger.balances);
+            assert_chain_matches_balances(&ledger);
+            prop_assert_eq!(ledger.transactions_by_hash_len(), window.len());
+            prop_assert_eq!(ledger.transactions_by_height_len(), window.len());
+            for (_, key) in window.iter() {
+                if let Some((account, key)) = key {
+                    prop_assert!(ledger.find_by_idempotency_key(account, key).is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn debit_never_underflows(
+        balance in 0..1_000u64,
+        amount in 0..2_000u64,
+    ) {
+        let mut balances = LedgerBalances::default();
+        balances
+            .add_payment(&Operation::Mint {
+                to: account(1),
+                amount: TOKENs::from_e8s(balance),
+                fee: TOKENs::ZERO,
+            })
+            .unwrap();
+        let res = catch_unwind(AssertUnwindSafe(|| {
+            balances.debit(&account(1), TOKENs::from_e8s(amount))
+        }));
+        if amount <= balance && balance > 0 {
+            prop_assert!(res.is_ok());
+            prop_assert_eq!(
+                balances.account_balance(&account(1)),
+                TOKENs::from_e8s(balance - amount)
+            );
+        } else {
+            prop_assert!(res.is_err());
+        }
+    }
+}
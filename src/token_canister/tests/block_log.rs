@@ -0,0 +1,96 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint};
+use ic_types::CanisterId;
+use std::sync::{Arc, Mutex};
+use token_canister::block_log::BlockStorage;
+use token_canister::{Ledger, RetentionPolicy};
+
+/// Stable memory on the heap.
+#[derive(Debug, Default)]
+struct MemoryStorage(Mutex<Vec<u8>>);
+
+impl BlockStorage for MemoryStorage {
+    fn write(&self, offset: u64, bytes: &[u8]) {
+        let mut memory = self.0.lock().unwrap();
+        let end = offset as usize + bytes.len();
+        if memory.len() < end {
+            memory.resize(end, 0);
+        }
+        memory[offset as usize..end].copy_from_slice(bytes);
+    }
+
+    fn read(&self, offset: u64, buf: &mut [u8]) {
+        let memory = self.0.lock().unwrap();
+        buf.copy_from_slice(&memory[offset as usize..offset as usize + buf.len()]);
+    }
+}
+
+/// Serializes `ledger` the way pre_upgrade does and restores it from
+/// `storage` the way post_upgrade does.
+fn upgrade(mut ledger: Ledger, storage: &Arc<MemoryStorage>) -> Ledger {
+    ledger.blockchain.blocks.clear();
+    let bytes = serde_cbor::to_vec(&ledger).unwrap();
+    let mut ledger: Ledger = serde_cbor::from_slice(&bytes).unwrap();
+    ledger
+        .blockchain
+        .set_block_storage(storage.clone())
+        .unwrap();
+    ledger
+}
+
+#[test]
+fn blocks_survive_upgrades_without_being_serialized() {
+    let storage = Arc::new(MemoryStorage::default());
+    let mut ledger = Ledger::default();
+    add_payment(&mut ledger, 0, mint(account(1), 1)).unwrap();
+    // Blocks added before the storage is set are written to it.
+    ledger
+        .blockchain
+        .set_block_storage(storage.clone())
+        .unwrap();
+    for i in 1..5 {
+        add_payment(&mut ledger, i, mint(account(1), i + 1)).unwrap();
+    }
+    let blocks = ledger.blockchain.blocks.clone();
+
+    let ledger = upgrade(ledger, &storage);
+    assert_eq!(ledger.blockchain.blocks, blocks);
+}
+
+#[test]
+fn archived_and_pruned_blocks_leave_the_log() {
+    let storage = Arc::new(MemoryStorage::default());
+    let mut ledger = Ledger::default();
+    ledger
+        .blockchain
+        .set_block_storage(storage.clone())
+        .unwrap();
+    for i in 0..6 {
+        add_payment(&mut ledger, i, mint(account(1), i + 1)).unwrap();
+    }
+    ledger.set_retention_policy(RetentionPolicy {
+        max_age: None,
+        max_blocks: Some(4),
+    });
+    ledger.blockchain.confirm_exported(6).unwrap();
+    assert_eq!(ledger.apply_retention_policy().unwrap(), 2);
+    let blocks = ledger.blockchain.blocks.clone();
+
+    let mut ledger = upgrade(ledger, &storage);
+    assert_eq!(ledger.blockchain.blocks, blocks);
+
+    let before = ledger.blockchain.block_log();
+    ledger
+        .blockchain
+        .remove_archived_blocks(3, CanisterId::from_u64(1));
+    assert!(ledger.blockchain.block_log().start > before.start);
+    assert_eq!(ledger.blockchain.block_log().end, before.end);
+
+    ledger
+        .blockchain
+        .remove_archived_blocks(1, CanisterId::from_u64(1));
+    assert!(ledger.blockchain.block_log().is_empty());
+    assert_eq!(ledger.blockchain.block_log().end, 0);
+}
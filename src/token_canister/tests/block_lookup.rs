@@ -0,0 +1,152 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::account;
+use ic_types::CanisterId;
+use token_canister::ic_block::{
+    iter_blocks, ArchiveInfo, BlockLookup, ICRC3ArchiveInfo, MAX_BLOCKS_PER_REQUEST,
+};
+use token_canister::{Block, Blockchain, Memo, Operation, TOKENs, TimeStamp};
+
+fn blockchain(num_blocks: u64) -> Blockchain {
+    let mut blockchain = Blockchain::default();
+    for i in 0..num_blocks {
+        let block = Block::new(
+            blockchain.last_hash,
+            Operation::Mint {
+                to: account(1),
+                amount: TOKENs::from_e8s(1),
+                fee: TOKENs::ZERO,
+            },
+            Memo(i),
+            TimeStamp::from_nanos(i),
+            TimeStamp::from_nanos(i),
+        )
+        .unwrap();
+        blockchain.add_block(block).unwrap();
+    }
+    blockchain
+}
+
+#[test]
+fn lookup_tells_where_a_block_is() {
+    let mut blockchain = blockchain(10);
+    blockchain.confirm_exported(2).unwrap();
+    blockchain.prune_blocks(2).unwrap();
+    let first_node = CanisterId::from_u64(1);
+    let second_node = CanisterId::from_u64(2);
+    blockchain.remove_archived_blocks(2, first_node);
+    blockchain.remove_archived_blocks(1, first_node);
+    blockchain.remove_archived_blocks(2, second_node);
+
+    assert_eq!(
+        blockchain.lookup(1),
+        BlockLookup::Pruned {
+            earliest_available: 2
+        }
+    );
+    for height in 2..5 {
+        assert_eq!(
+            blockchain.lookup(height),
+            BlockLookup::Archived {
+                canister_id: first_node
+            }
+        );
+    }
+    for height in 5..7 {
+        assert_eq!(
+            blockchain.lookup(height),
+            BlockLookup::Archived {
+                canister_id: second_node
+            }
+        );
+    }
+    assert_eq!(
+        blockchain.lookup(7),
+        BlockLookup::Found(blockchain.blocks[0].clone())
+    );
+    assert_eq!(
+        blockchain.lookup(10),
+        BlockLookup::NotYetProduced { chain_length: 10 }
+    );
+}
+
+#[test]
+fn archives_are_listed_with_their_block_ranges() {
+    let mut blockchain = blockchain(10);
+    blockchain.confirm_exported(2).unwrap();
+    blockchain.prune_blocks(2).unwrap();
+    let first_node = CanisterId::from_u64(1);
+    let second_node = CanisterId::from_u64(2);
+    assert!(blockchain.archives().is_empty());
+    blockchain.remove_archived_blocks(2, first_node);
+    blockchain.remove_archived_blocks(1, first_node);
+    blockchain.remove_archived_blocks(2, second_node);
+
+    assert_eq!(
+        blockchain.archives(),
+        vec![
+            ArchiveInfo {
+                canister_id: first_node,
+                block_range_start: 2,
+                block_range_end: 4,
+            },
+            ArchiveInfo {
+                canister_id: second_node,
+                block_range_start: 5,
+                block_range_end: 6,
+            },
+        ]
+    );
+    assert_eq!(blockchain.icrc3_archives(None).len(), 2);
+    assert_eq!(
+        blockchain.icrc3_archives(Some(first_node.get())),
+        vec![ICRC3ArchiveInfo {
+            canister_id: second_node.get(),
+            start: 5_u64.into(),
+            end: 6_u64.into(),
+        }]
+    );
+    assert!(blockchain
+        .icrc3_archives(Some(second_node.get()))
+        .is_empty());
+}
+
+#[test]
+fn long_block_requests_are_truncated() {
+    let blockchain = blockchain(MAX_BLOCKS_PER_REQUEST as u64 + 1);
+    let res = blockchain.get_blocks(0, MAX_BLOCKS_PER_REQUEST + 1);
+    assert_eq!(res.blocks.unwrap().len(), MAX_BLOCKS_PER_REQUEST);
+    assert_eq!(res.first_block_index, 0);
+    assert_eq!(
+        iter_blocks(&blockchain.blocks, 0, usize::MAX).0.len(),
+        MAX_BLOCKS_PER_REQUEST
+    );
+}
+
+#[test]
+fn sparse_heights_are_looked_up_in_order() {
+    let mut blockchain = blockchain(10);
+    blockchain.confirm_exported(2).unwrap();
+    blockchain.prune_blocks(2).unwrap();
+    let node = CanisterId::from_u64(1);
+    blockchain.remove_archived_blocks(3, node);
+
+    assert_eq!(
+        blockchain.lookup_heights(&[7, 1, 3, 7, 12]),
+        vec![
+            BlockLookup::Found(blockchain.blocks[2].clone()),
+            BlockLookup::Pruned {
+                earliest_available: 2
+            },
+            BlockLookup::Archived { canister_id: node },
+            BlockLookup::Found(blockchain.blocks[2].clone()),
+            BlockLookup::NotYetProduced { chain_length: 10 },
+        ]
+    );
+    let heights = vec![9; MAX_BLOCKS_PER_REQUEST + 1];
+    assert_eq!(
+        blockchain.lookup_heights(&heights).len(),
+        MAX_BLOCKS_PER_REQUEST
+    );
+}
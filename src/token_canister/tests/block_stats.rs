@@ -0,0 +1,84 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::account;
+use ic_types::CanisterId;
+use std::time::Duration;
+use token_canister::{Block, Blockchain, Memo, Operation, TOKENs, TimeStamp};
+
+fn add_block(blockchain: &mut Blockchain, operation: Operation, timestamp: TimeStamp) -> u64 {
+    let block = Block::new(
+        blockchain.last_hash,
+        operation,
+        Memo(0),
+        timestamp,
+        timestamp,
+    )
+    .unwrap();
+    let size = block.clone().encode().unwrap().size_bytes() as u64;
+    blockchain.add_block(block).unwrap();
+    size
+}
+
+fn mint() -> Operation {
+    Operation::Mint {
+        to: account(1),
+        amount: TOKENs::from_e8s(1),
+        fee: TOKENs::ZERO,
+    }
+}
+
+fn transfer() -> Operation {
+    Operation::Transfer {
+        from: account(1),
+        to: account(2),
+        amount: TOKENs::from_e8s(1),
+        fee: TOKENs::from_e8s(1),
+    }
+}
+
+#[test]
+fn operations_and_sizes_are_counted() {
+    let mut blockchain = Blockchain::default();
+    let now = TimeStamp::from_nanos(1_000);
+    let mint_size = add_block(&mut blockchain, mint(), now);
+    let transfer_size = add_block(&mut blockchain, transfer(), now);
+    add_block(&mut blockchain, transfer(), now);
+
+    let stats = blockchain.block_stats(now);
+    assert_eq!(stats.num_mints, 1);
+    assert_eq!(stats.num_transfers, 2);
+    assert_eq!(stats.num_burns, 0);
+    assert_eq!(
+        stats.average_block_size_bytes,
+        (mint_size + 2 * transfer_size) / 3
+    );
+    assert_eq!(
+        stats.in_memory_chain_size_bytes,
+        mint_size + 2 * transfer_size
+    );
+
+    blockchain.remove_archived_blocks(1, CanisterId::from_u64(1));
+    let stats = blockchain.block_stats(now);
+    assert_eq!(stats.in_memory_chain_size_bytes, 2 * transfer_size);
+    assert_eq!(stats.num_mints, 1);
+}
+
+#[test]
+fn recent_blocks_are_counted_by_the_minute() {
+    let mut blockchain = Blockchain::default();
+    let start = TimeStamp::from_nanos(0);
+    add_block(&mut blockchain, mint(), start);
+    let two_hours_later = start + Duration::from_secs(2 * 60 * 60);
+    add_block(&mut blockchain, transfer(), two_hours_later);
+    add_block(&mut blockchain, transfer(), two_hours_later);
+
+    let stats = blockchain.block_stats(two_hours_later);
+    assert_eq!(stats.blocks_last_hour, 2);
+    assert_eq!(stats.blocks_last_day, 3);
+
+    let next_day = start + Duration::from_secs(25 * 60 * 60);
+    let stats = blockchain.block_stats(next_day);
+    assert_eq!(stats.blocks_last_hour, 0);
+    assert_eq!(stats.blocks_last_day, 2);
+}
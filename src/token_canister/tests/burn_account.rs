@@ -0,0 +1,42 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment};
+use token_canister::{Ledger, Operation, TOKENs, TRANSACTION_FEE};
+
+#[test]
+fn transfers_to_the_burn_account_are_burns() {
+    let mut ledger = Ledger::default();
+    ledger.set_burn_account(account(9)).unwrap();
+    let mint = Operation::Mint {
+        to: account(1),
+        amount: TOKENs::from_e8s(100_000),
+        fee: TOKENs::ZERO,
+    };
+    add_payment(&mut ledger, 0, mint).unwrap();
+    let supply = ledger.balances.total_supply();
+
+    // Far below MIN_BURN_AMOUNT.
+    let transfer = Operation::Transfer {
+        from: account(1),
+        to: account(9),
+        amount: TOKENs::from_e8s(1),
+        fee: TRANSACTION_FEE,
+    };
+    let height = add_payment(&mut ledger, 1, transfer).unwrap();
+    let block = ledger.blockchain.get(height).unwrap().decode().unwrap();
+    assert_eq!(
+        block.transaction.operation,
+        Operation::Burn {
+            from: account(1),
+            amount: TOKENs::from_e8s(1),
+            fee: TRANSACTION_FEE,
+        }
+    );
+    assert_eq!(ledger.balances.account_balance(&account(9)), TOKENs::ZERO);
+    assert_eq!(ledger.total_burned(), TOKENs::from_e8s(1));
+    assert_eq!(
+        ledger.balances.total_supply(),
+        (supply - (TOKENs::from_e8s(1) + TRANSACTION_FEE).unwrap()).unwrap()
+    );
+}
@@ -0,0 +1,118 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint, GENESIS_TIME_NANOS};
+use ic_crypto_tree_hash::{Digest, MixedHashTree};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use token_canister::certification::{certified_data, tip_hash_tree};
+use token_canister::{EncodedBlock, HashOf, Ledger, ManualTimeSource, TimeStamp};
+
+const TIP_HASH: HashOf<EncodedBlock> = HashOf::new([7; 32]);
+
+fn decode(tree: &[u8]) -> MixedHashTree {
+    serde_cbor::from_slice(tree).unwrap()
+}
+
+#[test]
+fn the_tip_hash_tree_has_the_certified_root_hash() {
+    for aliases_root_hash in [None, Some([9; 32])].iter().copied() {
+        for tip in [None, Some((300, TIP_HASH))].iter().copied() {
+            let Digest(root_hash) = decode(&tip_hash_tree(tip, aliases_root_hash)).digest();
+            assert_eq!(root_hash, certified_data(tip, aliases_root_hash));
+        }
+    }
+}
+
+#[test]
+fn the_tip_hash_tree_reveals_the_last_block() {
+    let tree = decode(&tip_hash_tree(Some((300, TIP_HASH)), Some([9; 32])));
+    let (aliases, tip) = match tree {
+        MixedHashTree::Fork(lr) => *lr,
+        tree => panic!("Expected a fork, got {:?}", tree),
+    };
+    assert!(matches!(aliases, MixedHashTree::Pruned(_)));
+    let expected = MixedHashTree::Fork(Box::new((
+        MixedHashTree::Labeled(
+            "last_block_hash".into(),
+            Box::new(MixedHashTree::Leaf(vec![7; 32])),
+        ),
+        MixedHashTree::Labeled(
+            "last_block_index".into(),
+            // 300 in LEB128.
+            Box::new(MixedHashTree::Leaf(vec![0xac, 0x02])),
+        ),
+    )));
+    assert_eq!(tip, expected);
+}
+
+#[test]
+fn the_certified_data_changes_with_the_tip() {
+    assert_ne!(
+        certified_data(Some((1, TIP_HASH)), None),
+        certified_data(Some((2, TIP_HASH)), None)
+    );
+}
+
+const CANISTER_ID: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 2, 1, 1];
+
+/// A certificate, without a signature, certifying `data` for the ledger.
+fn certificate(data: [u8; 32]) -> Vec<u8> {
+    let labeled = |label: &[u8], tree| MixedHashTree::Labeled(label.into(), Box::new(tree));
+    let tree = labeled(
+        b"canister",
+        labeled(
+            CANISTER_ID,
+            labeled(b"certified_data", MixedHashTree::Leaf(data.to_vec())),
+        ),
+    );
+    let certificate: BTreeMap<&str, MixedHashTree> = vec![("tree", tree)].into_iter().collect();
+    serde_cbor::to_vec(&certificate).unwrap()
+}
+
+#[test]
+fn recent_blocks_are_proven_against_a_lagging_certificate() {
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        GENESIS_TIME_NANOS,
+    ))));
+    add_payment(&mut ledger, 0, mint(account(1), 1_000)).unwrap();
+    ledger.certify();
+    add_payment(&mut ledger, 1, mint(account(1), 1_000)).unwrap();
+    let certified = ledger.certify();
+    add_payment(&mut ledger, 2, mint(account(1), 1_000)).unwrap();
+    ledger.certify();
+
+    // The certificate still covers the tip at height 1.
+    let proof = ledger
+        .block_certificate(0, certificate(certified), CANISTER_ID)
+        .unwrap();
+    assert_eq!(proof.descendants.len(), 1);
+    assert_eq!(
+        proof.descendants[0].decode().unwrap().parent_hash,
+        Some(proof.block.hash())
+    );
+    let tree = decode(&proof.hash_tree);
+    assert_eq!(tree.digest(), Digest(certified));
+
+    #[cfg(feature = "verify")]
+    {
+        use token_canister::verify::verify_block_certificate;
+        use token_canister::Memo;
+
+        let block = verify_block_certificate(&proof, CANISTER_ID).unwrap();
+        assert_eq!(block.transaction.memo, Memo(0));
+
+        let mut tampered = proof.clone();
+        tampered.block = tampered.descendants[0].clone();
+        assert!(verify_block_certificate(&tampered, CANISTER_ID).is_err());
+        assert!(verify_block_certificate(&proof, &[1]).is_err());
+    }
+
+    assert!(ledger
+        .block_certificate(2, certificate(certified), CANISTER_ID)
+        .is_err());
+    assert!(ledger
+        .block_certificate(0, certificate([0; 32]), CANISTER_ID)
+        .is_err());
+}
@@ -0,0 +1,50 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, GENESIS_TIME_NANOS};
+use std::sync::Arc;
+use std::time::Duration;
+use token_canister::{Ledger, ManualTimeSource, TimeSource, TimeStamp};
+
+fn mint(ledger: &mut Ledger, memo: u64) -> TimeStamp {
+    let height = add_payment(ledger, memo, fixtures::mint(account(1), 1)).unwrap();
+    ledger
+        .blockchain
+        .get(height)
+        .unwrap()
+        .decode()
+        .unwrap()
+        .timestamp
+}
+
+#[test]
+fn the_clock_reads_the_same_until_it_moves() {
+    let clock = Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        GENESIS_TIME_NANOS,
+    )));
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(clock.clone());
+    assert_eq!(ledger.now(), clock.now());
+    assert_eq!(ledger.now(), clock.now());
+    assert_eq!(mint(&mut ledger, 0), clock.now());
+    assert_eq!(ledger.now(), clock.now());
+}
+
+#[test]
+fn the_clock_does_not_go_back_before_the_newest_block_across_upgrades() {
+    let clock = Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        GENESIS_TIME_NANOS,
+    )));
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(clock.clone());
+    let minted_at = mint(&mut ledger, 0);
+
+    let bytes = serde_cbor::to_vec(&ledger).unwrap();
+    let mut ledger: Ledger = serde_cbor::from_slice(&bytes).unwrap();
+    ledger.seed_clock();
+    clock.set(minted_at - Duration::from_secs(10));
+    ledger.set_time_source(clock.clone());
+    assert_eq!(ledger.now(), minted_at);
+    // Blocks added meanwhile keep the chain's timestamps in order.
+    assert_eq!(mint(&mut ledger, 1), minted_at);
+}
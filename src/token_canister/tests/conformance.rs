@@ -0,0 +1,36 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, LedgerBuilder};
+use token_canister::conformance::run_conformance_checks;
+
+#[test]
+fn a_configured_ledger_passes() {
+    let ledger = LedgerBuilder::new()
+        .symbol("TKN")
+        .minting_account(account(1))
+        .build();
+    let report = run_conformance_checks(&ledger);
+    assert!(report.passed, "{:?}", report);
+    assert!(report.checks.iter().all(|check| check.details.is_empty()));
+    // The checks leave the ledger alone.
+    assert_eq!(ledger.blockchain.chain_length(), 0);
+}
+
+#[test]
+fn failures_are_reported_per_check() {
+    let mut ledger = LedgerBuilder::new()
+        .symbol("TKN")
+        .minting_account(account(1))
+        .build();
+    ledger.minting_account_id = None;
+    let report = run_conformance_checks(&ledger);
+    assert!(!report.passed);
+    let failed: Vec<_> = report
+        .checks
+        .iter()
+        .filter(|check| !check.passed)
+        .map(|check| check.name.as_str())
+        .collect();
+    assert_eq!(failed, vec!["minting_account_set"]);
+}
@@ -0,0 +1,33 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::GENESIS_TIME_NANOS;
+use token_canister::deprecation::DeprecatedEndpoint;
+use token_canister::http::encode_metrics;
+use token_canister::{Ledger, TimeStamp};
+
+#[test]
+fn send_dfx_tags_round_trip() {
+    let blob = DeprecatedEndpoint::SendDfx.to_memo_blob();
+    assert_eq!(
+        DeprecatedEndpoint::from_memo_blob(&blob),
+        Ok(DeprecatedEndpoint::SendDfx)
+    );
+}
+
+#[test]
+fn send_dfx_calls_are_reported_in_the_metrics() {
+    let mut ledger = Ledger::default();
+    let metrics = ledger.metrics();
+    assert_eq!(metrics.send_dfx_calls, 0);
+    assert_eq!(metrics.send_dfx_last_call, None);
+
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    ledger.deprecated_endpoint_usage.record_send_dfx(now);
+    ledger.deprecated_endpoint_usage.record_send_dfx(now);
+
+    let metrics = ledger.metrics();
+    assert_eq!(metrics.send_dfx_calls, 2);
+    assert_eq!(metrics.send_dfx_last_call, Some(now));
+    assert!(encode_metrics(&metrics).contains("\nledger_send_dfx_calls 2\n"));
+}
@@ -0,0 +1,31 @@
+#![allow(clippy::unwrap_used)]
+use token_canister::endpoint_flags::{EndpointDisabled, EndpointFlags};
+
+#[test]
+fn disabled_endpoints_fail_until_enabled_again() {
+    let mut flags = EndpointFlags::default();
+    assert!(flags.check_enabled("send_dfx").is_ok());
+
+    assert!(flags.set_enabled("send_dfx", false).unwrap());
+    assert!(!flags.set_enabled("send_dfx", false).unwrap());
+    assert_eq!(
+        flags.check_enabled("send_dfx"),
+        Err(EndpointDisabled {
+            endpoint: "send_dfx".to_string()
+        })
+    );
+    assert!(flags.check_enabled("iter_blocks_pb").is_ok());
+    assert_eq!(flags.disabled(), vec!["send_dfx".to_string()]);
+
+    assert!(flags.set_enabled("send_dfx", true).unwrap());
+    assert!(flags.check_enabled("send_dfx").is_ok());
+    assert!(flags.disabled().is_empty());
+}
+
+#[test]
+fn only_toggleable_endpoints_can_be_disabled() {
+    let mut flags = EndpointFlags::default();
+    assert!(flags.set_enabled("set_endpoint_enabled", false).is_err());
+    assert!(flags.set_enabled("account_balance_dfx", false).is_err());
+    assert!(flags.disabled().is_empty());
+}
@@ -0,0 +1,77 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, LedgerBuilder};
+use token_canister::{LedgerBalances, Operation, TOKENs};
+
+fn transfer() -> Operation {
+    Operation::Transfer {
+        from: account(1),
+        to: account(2),
+        amount: TOKENs::from_e8s(1_000),
+        fee: TOKENs::from_e8s(10),
+    }
+}
+
+#[test]
+fn fees_are_credited_to_the_collector() {
+    let mut ledger = LedgerBuilder::new()
+        .minting_account(account(0))
+        .mint(account(1), 100_000)
+        .build();
+    ledger.set_fee_collector(Some(account(9))).unwrap();
+    let supply = ledger.balances.total_supply();
+
+    add_payment(&mut ledger, 1, transfer()).unwrap();
+
+    assert_eq!(
+        ledger.balances.account_balance(&account(9)),
+        TOKENs::from_e8s(10)
+    );
+    assert_eq!(ledger.balances.total_supply(), supply);
+    assert_eq!(ledger.total_fees_collected(), TOKENs::from_e8s(10));
+
+    let block = ledger.blockchain.blocks.last().unwrap().decode().unwrap();
+    assert_eq!(block.fee_collector, Some(account(9)));
+
+    let mut replayed = LedgerBalances::default();
+    for block in ledger.blockchain.blocks.iter() {
+        let block = block.decode().unwrap();
+        replayed
+            .add_payment_with_fee_collector(
+                &block.transaction.operation,
+                block.fee_collector.as_ref(),
+            )
+            .unwrap();
+    }
+    assert_eq!(replayed, ledger.balances);
+}
+
+#[test]
+fn fees_are_burned_without_a_collector() {
+    let mut ledger = LedgerBuilder::new()
+        .minting_account(account(0))
+        .mint(account(1), 100_000)
+        .build();
+    let supply = ledger.balances.total_supply();
+
+    add_payment(&mut ledger, 1, transfer()).unwrap();
+
+    assert_eq!(ledger.balances.account_balance(&account(9)), TOKENs::ZERO);
+    assert_eq!(
+        ledger.balances.total_supply(),
+        (supply - TOKENs::from_e8s(10)).unwrap()
+    );
+    let block = ledger.blockchain.blocks.last().unwrap().decode().unwrap();
+    assert_eq!(block.fee_collector, None);
+}
+
+#[test]
+fn the_minting_account_cannot_collect_fees() {
+    let mut ledger = LedgerBuilder::new()
+        .minting_account(account(0))
+        .mint(account(1), 100_000)
+        .build();
+    assert!(ledger.set_fee_collector(Some(account(0))).is_err());
+    assert_eq!(ledger.fee_collector(), None);
+}
@@ -0,0 +1,256 @@
+//! Canonical blocks and the encodings and hashes deployed archives hold for
+//! them. The expected bytes must never be regenerated from the code under
+//! test: if an encoding changes, the change breaks every parent hash stored
+//! on chain.
+//!
+//! There are no Approve or TransferFrom operations yet. Fixtures for them
+//! belong here once they are added.
+//!
+//! `LedgerBuilder` sets up the ledgers the tests start from, and
+//! `add_payment` adds operations to them.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use token_canister::{
+    AccountIdentifier, AdminActionStatus, Block, BlockHeight, EncryptedMemo, Ledger,
+    ManualTimeSource, Memo, MemoBlob, Operation, PaymentError, PaymentOptions, TOKENs, TimeStamp,
+    Transaction,
+};
+
+pub struct GoldenBlock {
+    pub name: &'static str,
+    pub block: Block,
+    /// Hex of the protobuf encoding of `block`.
+    pub encoded: &'static str,
+    /// Hex of the SHA-256 hash of the encoding.
+    pub hash: &'static str,
+}
+
+pub const GENESIS_TIME_NANOS: u64 = 1_600_000_000_000_000_000;
+
+pub fn account(byte: u8) -> AccountIdentifier {
+    AccountIdentifier { hash: [byte; 28] }
+}
+
+pub fn mint(to: AccountIdentifier, e8s: u64) -> Operation {
+    Operation::Mint {
+        to,
+        amount: TOKENs::from_e8s(e8s),
+        fee: TOKENs::ZERO,
+    }
+}
+
+/// Adds `operation` to `ledger` with `Memo(memo)` and the default options,
+/// returning the height of its block.
+pub fn add_payment(
+    ledger: &mut Ledger,
+    memo: u64,
+    operation: Operation,
+) -> Result<BlockHeight, PaymentError> {
+    ledger
+        .add_payment(Memo(memo), operation, PaymentOptions::default())
+        .map(|(height, _)| height)
+}
+
+/// Builds a ledger whose clock is a `ManualTimeSource` stopped at
+/// `GENESIS_TIME_NANOS`. The operations are added in order, the n-th one
+/// with `Memo(n)`.
+#[derive(Default)]
+pub struct LedgerBuilder {
+    minting_account: Option<AccountIdentifier>,
+    symbol: Option<String>,
+    operations: Vec<Operation>,
+}
+
+impl LedgerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn minting_account(mut self, account: AccountIdentifier) -> Self {
+        self.minting_account = Some(account);
+        self
+    }
+
+    pub fn symbol(mut self, symbol: &str) -> Self {
+        self.symbol = Some(symbol.to_string());
+        self
+    }
+
+    pub fn mint(self, to: AccountIdentifier, e8s: u64) -> Self {
+        self.operation(mint(to, e8s))
+    }
+
+    pub fn operation(mut self, operation: Operation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    pub fn build(self) -> Ledger {
+        let mut ledger = Ledger::default();
+        ledger.set_time_source(Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+            GENESIS_TIME_NANOS,
+        ))));
+        ledger.minting_account_id = self.minting_account;
+        if let Some(symbol) = self.symbol {
+            ledger.symbol = symbol;
+        }
+        for (memo, operation) in self.operations.into_iter().enumerate() {
+            add_payment(&mut ledger, memo as u64, operation).unwrap();
+        }
+        ledger
+    }
+}
+
+fn block(
+    parent_hash: Option<&str>,
+    operation: Operation,
+    memo: u64,
+    created_at_secs: u64,
+    encrypted_memo: Option<EncryptedMemo>,
+    memo_blob: Option<MemoBlob>,
+) -> Block {
+    let created_at_time =
+        TimeStamp::from_nanos(GENESIS_TIME_NANOS + created_at_secs * 1_000_000_000);
+    Block::new_from_transaction(
+        parent_hash.map(|h| h.parse().unwrap()),
+        Transaction {
+            operation,
+            memo: Memo(memo),
+            created_at_time,
+            encrypted_memo,
+            memo_blob,
+        },
+        TimeStamp::from_nanos(created_at_time.as_nanos() + 500),
+    )
+}
+
+/// A chain of blocks covering every `Operation` variant, each block being
+/// the parent of the next one.
+pub fn golden_blocks() -> Vec<GoldenBlock> {
+    vec![
+        GoldenBlock {
+            name: "mint",
+            block: block(
+                None,
+                Operation::Mint {
+                    to: account(0x11),
+                    amount: TOKENs::from_e8s(100_000_000_000),
+                    fee: TOKENs::ZERO,
+                },
+                0,
+                0,
+                None,
+                None,
+            ),
+            encoded: "120a08f48380c5ddf0959a161a3d122d12220a20806a87351111111111111111\
+                11111111111111111111111111111111111111111a070880d0dbc3f402220032\
+                0a08808080c5ddf0959a16",
+            hash: "1fbad538690d908e60ed3face48695b73404fa203a587417790c2931ebfe8bcc",
+        },
+        GoldenBlock {
+            name: "transfer",
+            block: block(
+                Some("1fbad538690d908e60ed3face48695b73404fa203a587417790c2931ebfe8bcc"),
+                Operation::Transfer {
+                    from: account(0x11),
+                    to: account(0x22),
+                    amount: TOKENs::from_e8s(1_000_000_000),
+                    fee: TOKENs::from_e8s(10_000),
+                },
+                42,
+                1,
+                None,
+                None,
+            ),
+            encoded: "0a220a201fbad538690d908e60ed3face48695b73404fa203a587417790c2931\
+                ebfe8bcc120a08f497eba1e1f0959a161a671a550a220a20806a873511111111\
+                11111111111111111111111111111111111111111111111112220a2080459651\
+                222222222222222222222222222222222222222222222222222222221a060880\
+                94ebdc03220308904e2202082a320a088094eba1e1f0959a16",
+            hash: "942d9b6aedb550d5c432eb25ba545aa58d6e7b3ed194948ca15de441adce7110",
+        },
+        GoldenBlock {
+            name: "burn",
+            block: block(
+                Some("942d9b6aedb550d5c432eb25ba545aa58d6e7b3ed194948ca15de441adce7110"),
+                Operation::Burn {
+                    from: account(0x22),
+                    amount: TOKENs::from_e8s(50_000_000),
+                    fee: TOKENs::ZERO,
+                },
+                7,
+                2,
+                None,
+                None,
+            ),
+            encoded: "0a220a20942d9b6aedb550d5c432eb25ba545aa58d6e7b3ed194948ca15de441\
+                adce7110120a08f4abd6fee4f0959a161a3d0a2b0a220a208045965122222222\
+                2222222222222222222222222222222222222222222222221a050880e1eb1722\
+                020807320a0880a8d6fee4f0959a16",
+            hash: "e385e359eb26d80c86a8fae96d09a5fae8c2b344efa38d9c8dcd6e302eff9051",
+        },
+        GoldenBlock {
+            name: "set_minting_account",
+            block: block(
+                Some("e385e359eb26d80c86a8fae96d09a5fae8c2b344efa38d9c8dcd6e302eff9051"),
+                Operation::SetMintingAccount {
+                    previous: account(0x33),
+                    new: account(0x44),
+                },
+                0,
+                3,
+                None,
+                None,
+            ),
+            encoded: "0a220a20e385e359eb26d80c86a8fae96d09a5fae8c2b344efa38d9c8dcd6e30\
+                2eff9051120a08f4bfc1dbe8f0959a161a584a480a220a20805f668d33333333\
+                33333333333333333333333333333333333333333333333312220a20801bb499\
+                444444444444444444444444444444444444444444444444444444442200320a\
+                0880bcc1dbe8f0959a16",
+            hash: "0109ea7194a266a3a9a7deb9e60eb45abfc07c9f83d8b0b2e9788393ac290c15",
+        },
+        GoldenBlock {
+            name: "transfer_with_memos",
+            block: block(
+                Some("0109ea7194a266a3a9a7deb9e60eb45abfc07c9f83d8b0b2e9788393ac290c15"),
+                Operation::Transfer {
+                    from: account(0x22),
+                    to: account(0x11),
+                    amount: TOKENs::from_e8s(25_000_000),
+                    fee: TOKENs::from_e8s(10_000),
+                },
+                9,
+                4,
+                Some(EncryptedMemo(vec![0xde, 0xad, 0xbe, 0xef])),
+                Some(MemoBlob(b"invoice-17".to_vec())),
+            ),
+            encoded: "0a220a200109ea7194a266a3a9a7deb9e60eb45abfc07c9f83d8b0b2e9788393\
+                ac290c15120a08f4d3acb8ecf0959a161a781a540a220a208045965122222222\
+                22222222222222222222222222222222222222222222222212220a20806a8735\
+                111111111111111111111111111111111111111111111111111111111a0508c0\
+                f0f50b220308904e22020809320a0880d0acb8ecf0959a163a04deadbeef420a\
+                696e766f6963652d3137",
+            hash: "92c1b91b434f82677ea98966af413077f6af905d0b0b6656e1bf4eec3574f193",
+        },
+        GoldenBlock {
+            name: "admin_audit",
+            block: block(
+                Some("92c1b91b434f82677ea98966af413077f6af905d0b0b6656e1bf4eec3574f193"),
+                Operation::AdminAudit {
+                    action_id: 3,
+                    status: AdminActionStatus::Executed,
+                    description: "burn fees".to_string(),
+                },
+                0,
+                5,
+                None,
+                None,
+            ),
+            encoded: "0a220a2092c1b91b434f82677ea98966af413077f6af905d0b0b6656e1bf4eec\
+                3574f193120a08f4e79795f0f0959a161a1f520f080310011a096275726e2066\
+                6565732200320a0880e49795f0f0959a16",
+            hash: "83b3141f52cb816cb72e73625365a6ccba974dfe70d2ff70d2897b64bffb7361",
+        },
+    ]
+}
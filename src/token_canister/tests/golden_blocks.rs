@@ -0,0 +1,85 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::golden_blocks;
+use token_canister::{Block, EncodedBlock};
+
+fn encoded(hex_str: &str) -> EncodedBlock {
+    EncodedBlock::from(hex::decode(hex_str).unwrap().into_boxed_slice())
+}
+
+#[test]
+fn should_encode_blocks_to_golden_bytes() {
+    for golden in golden_blocks() {
+        let actual = golden.block.clone().encode().unwrap();
+        assert_eq!(
+            hex::encode(&actual.0),
+            golden.encoded,
+            "encoding of the {} block changed",
+            golden.name
+        );
+    }
+}
+
+#[test]
+fn should_hash_golden_bytes_to_golden_hashes() {
+    for golden in golden_blocks() {
+        assert_eq!(
+            encoded(golden.encoded).hash().to_string(),
+            golden.hash,
+            "hash of the {} block changed",
+            golden.name
+        );
+    }
+}
+
+#[test]
+fn should_chain_golden_blocks() {
+    let blocks = golden_blocks();
+    for pair in blocks.windows(2) {
+        assert_eq!(
+            pair[1].block.parent_hash.unwrap().to_string(),
+            pair[0].hash,
+            "the {} block does not point at the {} block",
+            pair[1].name,
+            pair[0].name
+        );
+    }
+}
+
+#[test]
+fn should_decode_golden_bytes_to_golden_blocks() {
+    for golden in golden_blocks() {
+        assert_eq!(
+            encoded(golden.encoded).decode().unwrap(),
+            golden.block,
+            "decoding of the {} block changed",
+            golden.name
+        );
+    }
+}
+
+#[test]
+fn should_round_trip_golden_blocks_through_cbor() {
+    for golden in golden_blocks() {
+        let bytes = serde_cbor::to_vec(&golden.block).unwrap();
+        let decoded: Block = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, golden.block, "{} block", golden.name);
+        assert_eq!(
+            hex::encode(&decoded.encode().unwrap().0),
+            golden.encoded,
+            "{} block",
+            golden.name
+        );
+    }
+}
+
+#[test]
+fn should_round_trip_golden_transactions_through_cbor() {
+    for golden in golden_blocks() {
+        let transaction = golden.block.transaction;
+        let bytes = serde_cbor::to_vec(&transaction).unwrap();
+        let decoded: token_canister::Transaction = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, transaction, "{} transaction", golden.name);
+    }
+}
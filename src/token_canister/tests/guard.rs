@@ -0,0 +1,47 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint, LedgerBuilder};
+use token_canister::guard::AccountGuard;
+use token_canister::{Operation, PaymentError, TOKENs};
+
+#[test]
+fn an_account_can_only_be_guarded_once() {
+    let guard = AccountGuard::acquire(account(1)).unwrap();
+    assert!(AccountGuard::is_guarded(&account(1)));
+    assert!(AccountGuard::acquire(account(1)).is_err());
+
+    // Other accounts are not affected.
+    let other = AccountGuard::acquire(account(2)).unwrap();
+
+    drop(guard);
+    assert!(!AccountGuard::is_guarded(&account(1)));
+    AccountGuard::acquire(account(1)).unwrap();
+    drop(other);
+}
+
+#[test]
+fn the_ledger_rejects_debits_from_guarded_accounts() {
+    // Tests run concurrently, so this one keeps off the accounts guarded
+    // above.
+    let mut ledger = LedgerBuilder::new().mint(account(3), 1_000).build();
+    let sweep = Operation::Transfer {
+        from: account(3),
+        to: account(4),
+        amount: TOKENs::from_e8s(500),
+        fee: TOKENs::from_e8s(10),
+    };
+
+    // A transfer from account 3 awaits its hooks, so a sweep of it must
+    // wait.
+    let guard = AccountGuard::acquire(account(3)).unwrap();
+    assert!(matches!(
+        add_payment(&mut ledger, 1, sweep.clone()),
+        Err(PaymentError::Reject(_))
+    ));
+    // Credits are not affected.
+    add_payment(&mut ledger, 2, mint(account(3), 1)).unwrap();
+
+    drop(guard);
+    add_payment(&mut ledger, 1, sweep).unwrap();
+}
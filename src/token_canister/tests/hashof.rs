@@ -0,0 +1,33 @@
+#![allow(clippy::unwrap_used)]
+use std::convert::TryFrom;
+use std::str::FromStr;
+use token_canister::{EncodedBlock, HashOf};
+
+const HASH: HashOf<EncodedBlock> = HashOf::new([0xab; 32]);
+
+#[test]
+fn hashes_parse_with_and_without_0x_prefix() {
+    let hex = "ab".repeat(32);
+    assert_eq!(HashOf::<EncodedBlock>::from_str(&hex).unwrap(), HASH);
+    assert_eq!(HashOf::<EncodedBlock>::from_str(&format!("0x{}", hex)).unwrap(), HASH);
+    assert_eq!(HashOf::<EncodedBlock>::from_str(&format!("0X{}", hex)).unwrap(), HASH);
+    assert!(HashOf::<EncodedBlock>::from_str("0xabab").is_err());
+    assert!(HashOf::<EncodedBlock>::from_str("0x0xab").is_err());
+}
+
+#[test]
+fn hashes_convert_from_and_to_bytes() {
+    assert_eq!(HashOf::<EncodedBlock>::try_from(vec![0xab; 32]).unwrap(), HASH);
+    assert!(HashOf::<EncodedBlock>::try_from(vec![0xab; 31]).is_err());
+    assert_eq!(HASH.as_slice(), &[0xab; 32][..]);
+    assert_eq!(HASH.as_ref(), HASH.as_slice());
+}
+
+#[test]
+fn deserializing_a_wrong_length_hash_fails_without_panicking() {
+    let bytes = serde_cbor::to_vec(&serde_bytes::Bytes::new(&[0xab; 31])).unwrap();
+    assert!(serde_cbor::from_slice::<HashOf<EncodedBlock>>(&bytes).is_err());
+
+    let bytes = serde_cbor::to_vec(&HASH).unwrap();
+    assert_eq!(serde_cbor::from_slice::<HashOf<EncodedBlock>>(&bytes).unwrap(), HASH);
+}
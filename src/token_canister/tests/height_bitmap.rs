@@ -0,0 +1,49 @@
+#![allow(clippy::unwrap_used)]
+use std::collections::BTreeMap;
+use token_canister::HeightBitmap;
+
+#[test]
+fn sparse_and_dense_chunks_hold_the_same_heights() {
+    let mut bitmap = HeightBitmap::new();
+    // Enough heights in the first chunk for it to become a bitmap.
+    let dense: Vec<u64> = (0..10_000).map(|i| i * 3).collect();
+    let sparse = vec![1 << 40, (1 << 40) + 7, u64::MAX];
+    for height in dense.iter().chain(sparse.iter()) {
+        assert!(bitmap.insert(*height));
+    }
+    assert!(!bitmap.insert(3));
+    assert_eq!(bitmap.len(), (dense.len() + sparse.len()) as u64);
+    assert!(bitmap.contains(29_997));
+    assert!(!bitmap.contains(29_998));
+    assert!(bitmap.contains(u64::MAX));
+
+    let expected: Vec<u64> = dense.iter().chain(sparse.iter()).copied().collect();
+    assert_eq!(bitmap.iter().collect::<Vec<_>>(), expected);
+
+    for height in &dense {
+        assert!(bitmap.remove(*height));
+    }
+    assert!(!bitmap.remove(0));
+    assert_eq!(bitmap.iter().collect::<Vec<_>>(), sparse);
+}
+
+#[test]
+fn bitmaps_survive_a_round_trip_through_cbor() {
+    let mut bitmap = HeightBitmap::new();
+    for height in (0..100_000).step_by(7).chain(vec![1 << 33]) {
+        bitmap.insert(height);
+    }
+    let bytes = serde_cbor::to_vec(&bitmap).unwrap();
+    let decoded: HeightBitmap = serde_cbor::from_slice(&bytes).unwrap();
+    assert_eq!(decoded, bitmap);
+    assert_eq!(decoded.len(), bitmap.len());
+}
+
+#[test]
+fn maps_of_notified_heights_are_migrated() {
+    // Ledgers used to store the notified heights as the keys of a map.
+    let old: BTreeMap<u64, ()> = vec![(5, ()), (70_000, ()), (3, ())].into_iter().collect();
+    let bytes = serde_cbor::to_vec(&old).unwrap();
+    let migrated: HeightBitmap = serde_cbor::from_slice(&bytes).unwrap();
+    assert_eq!(migrated.iter().collect::<Vec<_>>(), vec![3, 5, 70_000]);
+}
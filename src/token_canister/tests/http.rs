@@ -0,0 +1,211 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint};
+use ic_base_types::PrincipalId;
+use ic_crypto_sha::Sha256;
+use token_canister::health::{
+    health, CanisterResources, Health, MAX_HEALTHY_HEAP_BYTES, MIN_HEALTHY_CYCLES_BALANCE,
+};
+use token_canister::http::{serve, HttpBlock, HttpRequest, HttpResponse, RequestContext};
+use token_canister::log;
+use token_canister::{ArchiveLagThrottle, Ledger};
+
+fn get(url: &str) -> HttpRequest {
+    HttpRequest {
+        method: "GET".to_string(),
+        url: url.to_string(),
+        headers: vec![],
+        body: vec![],
+    }
+}
+
+fn body(response: &HttpResponse) -> String {
+    String::from_utf8(response.body.clone()).unwrap()
+}
+
+#[test]
+fn tip_of_an_empty_ledger() {
+    let response = serve(&get("/tip"), &Ledger::default(), &RequestContext::default());
+    assert_eq!(response.status_code, 200);
+    let body = body(&response);
+    assert!(body.starts_with(
+        "{\"chain_length\":0,\"last_block_index\":null,\"last_block_hash\":null,\
+         \"certificate\":null,\"hash_tree\":\""
+    ));
+}
+
+#[test]
+fn tip_reports_the_last_block_and_the_certificate() {
+    let mut ledger = Ledger::default();
+    for i in 0..3 {
+        add_payment(&mut ledger, i, mint(account(1), 1)).unwrap();
+    }
+    let (_, hash) = ledger.tip().unwrap();
+    let context = RequestContext {
+        certificate: Some(vec![0xde, 0xad, 0xbe, 0xef]),
+    };
+
+    let response = serve(&get("/tip?fresh=1"), &ledger, &context);
+    assert_eq!(response.status_code, 200);
+    assert!(response
+        .headers
+        .contains(&("Content-Type".to_string(), "application/json".to_string())));
+    let body = body(&response);
+    assert!(body.starts_with(&format!(
+        "{{\"chain_length\":3,\"last_block_index\":2,\"last_block_hash\":\"{}\",\
+         \"certificate\":\"3q2+7w==\",\"hash_tree\":\"",
+        hash
+    )));
+}
+
+#[test]
+fn unknown_paths_and_methods_are_refused() {
+    let ledger = Ledger::default();
+    let context = RequestContext::default();
+    assert_eq!(serve(&get("/nope"), &ledger, &context).status_code, 404);
+
+    let mut post = get("/tip");
+    post.method = "POST".to_string();
+    assert_eq!(serve(&post, &ledger, &context).status_code, 405);
+}
+
+#[test]
+fn health_reflects_resources_and_archive_lag() {
+    let mut ledger = Ledger::default();
+    let mut context = RequestContext::default();
+    let response = serve(&get("/health"), &ledger, &context);
+    assert_eq!(response.status_code, 200);
+    assert_eq!(body(&response), "{\"status\":\"healthy\",\"reasons\":[]}");
+
+    context.resources = CanisterResources {
+        cycles_balance: Some(MIN_HEALTHY_CYCLES_BALANCE - 1),
+        heap_bytes: Some(MAX_HEALTHY_HEAP_BYTES + 1),
+    };
+    match health(&ledger, &context.resources) {
+        Health::Degraded { reasons } => assert_eq!(reasons.len(), 2),
+        other => panic!("Expected a degraded ledger, got {:?}", other),
+    }
+    let response = serve(&get("/health"), &ledger, &context);
+    assert_eq!(response.status_code, 200);
+    assert!(body(&response).starts_with("{\"status\":\"degraded\",\"reasons\":[\""));
+
+    ledger.set_archive_lag_throttle(ArchiveLagThrottle {
+        trigger_threshold: 1,
+        max_lag_multiple: 2,
+    });
+    for i in 0..3 {
+        add_payment(&mut ledger, i, mint(account(1), 1)).unwrap();
+    }
+    assert!(matches!(
+        health(&ledger, &CanisterResources::default()),
+        Health::ReadOnly { .. }
+    ));
+    let response = serve(&get("/health"), &ledger, &context);
+    assert_eq!(response.status_code, 503);
+    assert!(body(&response).starts_with("{\"status\":\"read_only\""));
+}
+
+#[test]
+fn logs_hide_trace_entries() {
+    log::trace("a trace message".to_string());
+    log::warn("a warning".to_string());
+    let context = RequestContext::default();
+    let response = serve(&get("/logs"), &Ledger::default(), &context);
+    assert_eq!(response.status_code, 200);
+    let body = body(&response);
+    assert!(body.contains("WARN a warning"));
+    assert!(!body.contains("a trace message"));
+
+    let response = serve(&get("/logs?since=x"), &Ledger::default(), &context);
+    assert_eq!(response.status_code, 400);
+}
+
+#[test]
+fn restricted_reads_only_serve_public_routes() {
+    let mut ledger = Ledger::default();
+    let reader = PrincipalId::new_user_test_id(1);
+    ledger.readers.add(reader).unwrap();
+    assert!(ledger.readers.can_read(&PrincipalId::new_anonymous()));
+    assert_eq!(
+        serve(&get("/tip"), &ledger, &RequestContext::default()).status_code,
+        200
+    );
+
+    ledger.readers.set_restricted(true);
+    assert!(ledger.readers.can_read(&reader));
+    assert!(!ledger.readers.can_read(&PrincipalId::new_anonymous()));
+    for path in &["/tip", "/logs", "/blocks", "/nowhere"] {
+        let response = serve(&get(path), &ledger, &RequestContext::default());
+        assert_eq!(response.status_code, 401, "{}", path);
+    }
+    for path in &["/metrics", "/health"] {
+        let response = serve(&get(path), &ledger, &RequestContext::default());
+        assert_eq!(response.status_code, 200, "{}", path);
+    }
+}
+
+#[test]
+fn blocks_are_served_as_cbor_in_pages() {
+    let mut ledger = Ledger::default();
+    for i in 0..3 {
+        add_payment(&mut ledger, i, mint(account(1), 1)).unwrap();
+    }
+    let header = |response: &HttpResponse, name: &str| {
+        response
+            .headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.clone())
+    };
+
+    let response = serve(
+        &get("/blocks?start=1&length=1"),
+        &ledger,
+        &RequestContext::default(),
+    );
+    assert_eq!(response.status_code, 200);
+    assert_eq!(
+        header(&response, "Content-Type").as_deref(),
+        Some("application/cbor")
+    );
+    assert_eq!(header(&response, "X-Chain-Length").as_deref(), Some("3"));
+    assert_eq!(
+        header(&response, "X-First-Block-Index").as_deref(),
+        Some("0")
+    );
+    assert_eq!(header(&response, "X-Next-Start").as_deref(), Some("2"));
+    let blocks: Vec<HttpBlock> = serde_cbor::from_slice(&response.body).unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].height, 1);
+    let encoded = ledger.blockchain.get(1).unwrap();
+    assert_eq!(blocks[0].hash, encoded.hash());
+    assert_eq!(blocks[0].encoded, encoded.0.to_vec());
+    assert_eq!(blocks[0].block, encoded.decode().unwrap());
+
+    // Clients can check the chain by hashing the encoded blocks.
+    let response = serve(
+        &get("/blocks?start=0&length=2"),
+        &ledger,
+        &RequestContext::default(),
+    );
+    let blocks: Vec<HttpBlock> = serde_cbor::from_slice(&response.body).unwrap();
+    assert_eq!(blocks[0].hash.as_slice(), &Sha256::hash(&blocks[0].encoded));
+    assert_eq!(
+        blocks[1].block.parent_hash.unwrap().as_slice(),
+        &Sha256::hash(&blocks[0].encoded)
+    );
+
+    // The last page has no next start.
+    let response = serve(&get("/blocks?start=2"), &ledger, &RequestContext::default());
+    let blocks: Vec<HttpBlock> = serde_cbor::from_slice(&response.body).unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(header(&response, "X-Next-Start"), None);
+
+    let response = serve(&get("/blocks?start=5"), &ledger, &RequestContext::default());
+    let blocks: Vec<HttpBlock> = serde_cbor::from_slice(&response.body).unwrap();
+    assert!(blocks.is_empty());
+
+    let response = serve(&get("/blocks?start=x"), &ledger, &RequestContext::default());
+    assert_eq!(response.status_code, 400);
+}
@@ -0,0 +1,130 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, LedgerBuilder, GENESIS_TIME_NANOS};
+use ic_base_types::PrincipalId;
+use std::time::Duration;
+use token_canister::invoices::{
+    invoice_subaccount, InvoiceId, EXPIRIES_PER_HEARTBEAT, MAX_OPEN_INVOICES_PER_MERCHANT,
+};
+use token_canister::{
+    AccountIdentifier, ArchiveLagThrottle, Invoices, Memo, Operation, PaymentError, TOKENs,
+    TimeStamp, TransferError,
+};
+
+fn create(
+    invoices: &mut Invoices,
+    merchant: PrincipalId,
+    now: TimeStamp,
+) -> Result<InvoiceId, String> {
+    let deposit = AccountIdentifier::new(merchant, Some(invoice_subaccount(invoices.next_id())));
+    invoices
+        .create(
+            merchant,
+            account(1),
+            TOKENs::from_e8s(100),
+            Memo(0),
+            now + Duration::from_secs(60),
+            deposit,
+            now,
+        )
+        .map(|invoice| invoice.id)
+}
+
+#[test]
+fn merchants_hold_a_bounded_number_of_open_invoices() {
+    let merchant = PrincipalId::new_user_test_id(1);
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    let mut invoices = Invoices::default();
+    for _ in 0..MAX_OPEN_INVOICES_PER_MERCHANT {
+        create(&mut invoices, merchant, now).unwrap();
+    }
+    assert!(create(&mut invoices, merchant, now).is_err());
+    // Other merchants are not affected.
+    create(&mut invoices, PrincipalId::new_user_test_id(2), now).unwrap();
+
+    // Paying an invoice in full closes it and frees a place.
+    let deposit = AccountIdentifier::new(merchant, Some(invoice_subaccount(0)));
+    invoices.on_credit(account(5), deposit, TOKENs::from_e8s(100), now);
+    create(&mut invoices, merchant, now).unwrap();
+    assert!(create(&mut invoices, merchant, now).is_err());
+}
+
+#[test]
+fn expiry_closes_a_bounded_number_of_invoices_per_call() {
+    let merchant = PrincipalId::new_user_test_id(1);
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    let mut invoices = Invoices::default();
+    for _ in 0..EXPIRIES_PER_HEARTBEAT + 1 {
+        create(&mut invoices, merchant, now).unwrap();
+    }
+    assert_eq!(invoices.expire(now, EXPIRIES_PER_HEARTBEAT), 0);
+
+    let later = now + Duration::from_secs(60);
+    assert_eq!(
+        invoices.expire(later, EXPIRIES_PER_HEARTBEAT),
+        EXPIRIES_PER_HEARTBEAT
+    );
+    assert_eq!(invoices.expire(later, EXPIRIES_PER_HEARTBEAT), 1);
+    assert_eq!(invoices.expire(later, EXPIRIES_PER_HEARTBEAT), 0);
+}
+
+#[test]
+fn failed_refunds_are_retried() {
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    let expires_at = now + Duration::from_secs(60);
+    let mut ledger = LedgerBuilder::new().mint(account(5), 1_000).build();
+    ledger
+        .invoices
+        .create(
+            PrincipalId::new_user_test_id(1),
+            account(1),
+            TOKENs::from_e8s(100),
+            Memo(0),
+            expires_at,
+            account(2),
+            now,
+        )
+        .unwrap();
+    // A partial payment, refunded once the invoice expires.
+    let payment = Operation::Transfer {
+        from: account(5),
+        to: account(2),
+        amount: TOKENs::from_e8s(40),
+        fee: TOKENs::from_e8s(10),
+    };
+    add_payment(&mut ledger, 1, payment).unwrap();
+    // Two blocks wait to be archived, more than the throttle allows.
+    ledger.set_archive_lag_throttle(ArchiveLagThrottle {
+        trigger_threshold: 1,
+        max_lag_multiple: 1,
+    });
+
+    let results = ledger.process_invoice_refunds(expires_at, 10);
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        results[0].1,
+        Err(PaymentError::TransferError(
+            TransferError::TemporarilyUnavailable
+        ))
+    );
+    assert_eq!(ledger.invoices.num_pending_refunds(), 1);
+    assert_eq!(
+        ledger.balances.account_balance(&account(2)),
+        TOKENs::from_e8s(40)
+    );
+
+    ledger.set_archive_lag_throttle(ArchiveLagThrottle {
+        trigger_threshold: 10,
+        max_lag_multiple: 1,
+    });
+    let results = ledger.process_invoice_refunds(expires_at, 10);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].1.is_ok());
+    assert_eq!(ledger.invoices.num_pending_refunds(), 0);
+    assert_eq!(ledger.balances.account_balance(&account(2)), TOKENs::ZERO);
+    assert_eq!(
+        ledger.balances.account_balance(&account(5)),
+        TOKENs::from_e8s(990)
+    );
+}
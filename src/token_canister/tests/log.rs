@@ -0,0 +1,54 @@
+#![allow(clippy::unwrap_used)]
+
+use token_canister::log::{
+    redact_account_identifiers, LogBuffer, LogLevel, MAX_LOG_MESSAGE_LENGTH,
+};
+use token_canister::{AccountIdentifier, TimeStamp};
+
+fn messages(buffer: &LogBuffer, since: u64) -> Vec<(u64, String)> {
+    buffer
+        .entries_since(since)
+        .into_iter()
+        .map(|e| (e.index, e.message))
+        .collect()
+}
+
+#[test]
+fn the_buffer_keeps_the_latest_entries() {
+    let mut buffer = LogBuffer::new(3);
+    for i in 0..5 {
+        buffer.push(TimeStamp::from_nanos(i), LogLevel::Info, i.to_string());
+    }
+    let expected: Vec<_> = (2..5).map(|i| (i, i.to_string())).collect();
+    assert_eq!(messages(&buffer, 0), expected);
+    assert_eq!(messages(&buffer, 4), vec![(4, "4".to_string())]);
+    assert_eq!(messages(&buffer, 5), vec![]);
+}
+
+#[test]
+fn long_messages_are_truncated() {
+    let mut buffer = LogBuffer::new(1);
+    buffer.push(
+        TimeStamp::from_nanos(0),
+        LogLevel::Warn,
+        "é".repeat(MAX_LOG_MESSAGE_LENGTH),
+    );
+    let entry = &buffer.entries_since(0)[0];
+    assert_eq!(entry.message.len(), MAX_LOG_MESSAGE_LENGTH);
+    assert_eq!(entry.level, LogLevel::Warn);
+}
+
+#[test]
+fn account_identifiers_are_redacted() {
+    let account = AccountIdentifier { hash: [0xab; 28] };
+    let message = format!("refunding 1 TOKEN to {} failed", account);
+    assert_eq!(
+        redact_account_identifiers(&message),
+        format!("refunding 1 TOKEN to {}... failed", &account.to_hex()[..8])
+    );
+    // Shorter or longer hex strings are left alone.
+    let short = "a".repeat(63);
+    let long = "b".repeat(65);
+    let message = format!("{} {}", short, long);
+    assert_eq!(redact_account_identifiers(&message), message);
+}
@@ -0,0 +1,80 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint, GENESIS_TIME_NANOS};
+use ic_base_types::PrincipalId;
+use std::sync::Arc;
+use std::time::Duration;
+use token_canister::invoices::INVOICE_RETENTION;
+use token_canister::maintenance::MAINTENANCE_INTERVAL;
+use token_canister::{Invoices, Ledger, ManualTimeSource, Memo, TOKENs, TimeSource, TimeStamp};
+
+#[test]
+fn maintenance_purges_transactions_that_left_the_window() {
+    let clock = Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        GENESIS_TIME_NANOS,
+    )));
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(clock.clone());
+    for memo in 0..3 {
+        add_payment(&mut ledger, memo, mint(account(1), 1_000)).unwrap();
+    }
+    ledger
+        .change_notification_state(1, clock.now(), true)
+        .unwrap();
+    assert!(ledger.maintenance.is_due(clock.now()));
+
+    clock.advance(ledger.transaction_window);
+    let report = ledger.run_maintenance(clock.now());
+    assert_eq!(report.purged_transactions, 3);
+    assert_eq!(report.ran_at, clock.now());
+    assert!(ledger.blocks_notified.is_empty());
+    assert_eq!(ledger.maintenance.last_run(), Some(clock.now()));
+    assert!(!ledger.maintenance.is_due(clock.now()));
+
+    clock.advance(MAINTENANCE_INTERVAL);
+    assert!(ledger.maintenance.is_due(clock.now()));
+    assert_eq!(ledger.run_maintenance(clock.now()).purged_transactions, 0);
+}
+
+#[test]
+fn closed_invoices_are_pruned_after_the_retention() {
+    let merchant = PrincipalId::new_user_test_id(1);
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    let expires_at = now + Duration::from_secs(60);
+    let mut invoices = Invoices::default();
+    let create = |invoices: &mut Invoices, deposit: u8, expires_at| {
+        invoices
+            .create(
+                merchant,
+                account(1),
+                TOKENs::from_e8s(100),
+                Memo(0),
+                expires_at,
+                account(deposit),
+                now,
+            )
+            .unwrap()
+            .id
+    };
+    let expired = create(&mut invoices, 2, expires_at);
+    let claimed = create(&mut invoices, 3, expires_at);
+    let paid = create(&mut invoices, 4, expires_at);
+    invoices.on_credit(account(5), account(3), TOKENs::from_e8s(100), now);
+    invoices.on_credit(account(5), account(4), TOKENs::from_e8s(100), now);
+    invoices.claim(&merchant, claimed).unwrap();
+    assert_eq!(invoices.expire(expires_at, 10), 1);
+
+    // Nothing is pruned before the retention is over.
+    assert_eq!(invoices.prune(expires_at, 10), 0);
+
+    let later = expires_at + INVOICE_RETENTION;
+    // Scanning resumes where the previous call stopped.
+    assert_eq!(invoices.prune(later, 1), 1);
+    assert_eq!(invoices.prune(later, 1), 1);
+    assert_eq!(invoices.prune(later, 1), 0);
+    assert!(invoices.get(expired).is_none());
+    assert!(invoices.get(claimed).is_none());
+    // The merchant did not collect this payment yet.
+    assert!(invoices.get(paid).is_some());
+}
@@ -0,0 +1,20 @@
+use token_canister::{Memo, MemoBlob};
+
+#[test]
+fn memos_show_their_hex_value_when_it_differs() {
+    assert_eq!(Memo(0).to_display(), "0");
+    assert_eq!(Memo(9).to_display(), "9");
+    assert_eq!(Memo(42).to_display(), "42 (0x2a)");
+    assert_eq!(
+        Memo(u64::MAX).to_display(),
+        "18446744073709551615 (0xffffffffffffffff)"
+    );
+}
+
+#[test]
+fn memo_blobs_show_printable_text_quoted_and_anything_else_in_hex() {
+    assert_eq!(MemoBlob(b"invoice 17".to_vec()).to_display(), "\"invoice 17\"");
+    assert_eq!(MemoBlob("caf\u{e9}".as_bytes().to_vec()).to_display(), "\"caf\u{e9}\"");
+    assert_eq!(MemoBlob(vec![0xff, 0x00]).to_display(), "0xff00");
+    assert_eq!(MemoBlob(b"a\nb".to_vec()).to_display(), "0x610a62");
+}
@@ -0,0 +1,33 @@
+#![allow(clippy::unwrap_used)]
+
+use token_canister::{
+    Ledger, MetadataValue, DECIMAL_PLACES, MAX_MEMO_BLOB_LENGTH, TRANSACTION_FEE,
+};
+
+fn entry<'a>(metadata: &'a [(String, MetadataValue)], key: &str) -> &'a MetadataValue {
+    &metadata.iter().find(|(k, _)| k == key).unwrap().1
+}
+
+#[test]
+fn metadata_lists_what_wallets_require() {
+    let mut ledger = Ledger::default();
+    ledger.symbol = "TKN".to_string();
+    let metadata = ledger.icrc1_metadata();
+
+    assert_eq!(
+        entry(&metadata, "icrc1:symbol"),
+        &MetadataValue::Text("TKN".to_string())
+    );
+    assert_eq!(
+        entry(&metadata, "icrc1:decimals"),
+        &MetadataValue::from(DECIMAL_PLACES as u64)
+    );
+    assert_eq!(
+        entry(&metadata, "icrc1:fee"),
+        &MetadataValue::from(TRANSACTION_FEE.get_e8s())
+    );
+    assert_eq!(
+        entry(&metadata, "icrc1:max_memo_length"),
+        &MetadataValue::from(MAX_MEMO_BLOB_LENGTH as u64)
+    );
+}
@@ -0,0 +1,65 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, LedgerBuilder};
+use std::time::Duration;
+use token_canister::{Block, Operation, TimeStamp, MINTING_ACCOUNT_CHANGE_TIMELOCK};
+
+#[test]
+fn changes_take_effect_after_the_timelock() {
+    let mut ledger = LedgerBuilder::new().minting_account(account(1)).build();
+    let now = TimeStamp::from_nanos(1_000);
+    let change = ledger
+        .schedule_minting_account_change(account(2), now)
+        .unwrap();
+    assert_eq!(change.executable_at, now + MINTING_ACCOUNT_CHANGE_TIMELOCK);
+
+    let almost = change.executable_at - Duration::from_nanos(1);
+    assert_eq!(ledger.execute_minting_account_change(almost).unwrap(), None);
+    assert_eq!(ledger.minting_account_id, Some(account(1)));
+
+    let (height, _) = ledger
+        .execute_minting_account_change(change.executable_at)
+        .unwrap()
+        .unwrap();
+    assert_eq!(ledger.minting_account_id, Some(account(2)));
+    assert_eq!(ledger.pending_minting_account_change(), None);
+    let block: Block = ledger.blockchain.get(height).unwrap().decode().unwrap();
+    assert_eq!(
+        block.transaction.operation,
+        Operation::SetMintingAccount {
+            previous: account(1),
+            new: account(2),
+        }
+    );
+}
+
+#[test]
+fn only_one_change_can_be_scheduled() {
+    let mut ledger = LedgerBuilder::new().minting_account(account(1)).build();
+    let now = TimeStamp::from_nanos(1_000);
+    ledger
+        .schedule_minting_account_change(account(2), now)
+        .unwrap();
+    assert!(ledger
+        .schedule_minting_account_change(account(3), now)
+        .is_err());
+
+    assert_eq!(
+        ledger.cancel_minting_account_change().unwrap().new,
+        account(2)
+    );
+    ledger
+        .schedule_minting_account_change(account(3), now)
+        .unwrap();
+}
+
+#[test]
+fn invalid_changes_are_rejected() {
+    let mut ledger = LedgerBuilder::new().minting_account(account(1)).build();
+    let now = TimeStamp::from_nanos(1_000);
+    assert!(ledger
+        .schedule_minting_account_change(account(1), now)
+        .is_err());
+    assert_eq!(ledger.pending_minting_account_change(), None);
+}
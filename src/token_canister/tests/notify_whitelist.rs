@@ -0,0 +1,57 @@
+#![allow(clippy::unwrap_used)]
+
+use ic_base_types::PrincipalId;
+use std::collections::BTreeSet;
+use token_canister::{AccessPolicyConfig, Ledger, SendWhitelist};
+
+fn canister(id: u64) -> PrincipalId {
+    PrincipalId::new_user_test_id(id)
+}
+
+fn whitelist_ledger(senders: &[PrincipalId]) -> Ledger {
+    let mut ledger = Ledger::default();
+    ledger.set_access_policy(AccessPolicyConfig::Whitelist(SendWhitelist {
+        canisters: senders.iter().copied().collect(),
+        ..SendWhitelist::default()
+    }));
+    ledger
+}
+
+#[test]
+fn sending_and_being_notified_are_separate_permissions() {
+    let mut ledger = whitelist_ledger(&[canister(1)]);
+    ledger.notify_whitelist_mut().add(canister(2)).unwrap();
+
+    assert!(ledger.can_send(&canister(1)));
+    assert!(!ledger.can_be_notified(&canister(1)));
+    assert!(ledger.can_be_notified(&canister(2)));
+
+    assert!(ledger.notify_whitelist_mut().remove(&canister(2)));
+    assert!(!ledger.notify_whitelist_mut().remove(&canister(2)));
+    assert!(!ledger.can_be_notified(&canister(2)));
+}
+
+#[test]
+fn the_notify_whitelist_only_applies_to_the_whitelist_policy() {
+    let mut ledger = Ledger::default();
+    assert!(ledger.can_be_notified(&canister(3)));
+
+    let denied: BTreeSet<_> = vec![canister(3)].into_iter().collect();
+    ledger.set_access_policy(AccessPolicyConfig::DenyList(denied));
+    ledger.notify_whitelist_mut().add(canister(3)).unwrap();
+    assert!(!ledger.can_be_notified(&canister(3)));
+}
+
+#[test]
+fn ledgers_from_before_the_split_keep_notifying_the_send_whitelist() {
+    let ledger = whitelist_ledger(&[canister(1)]);
+    let mut value = serde_cbor::value::to_value(&ledger).unwrap();
+    if let serde_cbor::Value::Map(fields) = &mut value {
+        fields.remove(&serde_cbor::Value::Text("notify_whitelist".to_string()));
+    }
+    let mut ledger: Ledger = serde_cbor::value::from_value(value).unwrap();
+
+    ledger.migrate_notify_whitelist();
+    assert!(ledger.can_be_notified(&canister(1)));
+    assert_eq!(ledger.notify_whitelist().list(), vec![canister(1)]);
+}
@@ -0,0 +1,56 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::account;
+use ic_types::CanisterId;
+use token_canister::payment_callbacks::{PaymentCallback, MAX_CALLBACK_ATTEMPTS};
+use token_canister::{Memo, PaymentCallbacks, TOKENs};
+
+fn callbacks() -> PaymentCallbacks {
+    let mut callbacks = PaymentCallbacks::default();
+    let callback = PaymentCallback {
+        canister_id: CanisterId::from_u64(1),
+        method: "on_payment".to_string(),
+    };
+    callbacks.register(account(1), Some(callback)).unwrap();
+    callbacks
+}
+
+#[test]
+fn callbacks_leave_the_queue_only_once_sent() {
+    let mut callbacks = callbacks();
+    callbacks.on_credit(account(1), 0, TOKENs::from_e8s(100), Memo(0));
+    callbacks.on_credit(account(1), 1, TOKENs::from_e8s(100), Memo(0));
+    // Nobody registered a callback for this account.
+    callbacks.on_credit(account(2), 2, TOKENs::from_e8s(100), Memo(0));
+    assert_eq!(callbacks.num_pending(), 2);
+
+    let results = callbacks.deliver(10, |pending| {
+        if pending.payment.block_height == 0 {
+            Err("out of queue space".to_string())
+        } else {
+            Ok(())
+        }
+    });
+    assert_eq!(results.len(), 2);
+    assert!(results[0].1.is_err());
+    assert!(results[1].1.is_ok());
+    assert_eq!(callbacks.num_pending(), 1);
+
+    let results = callbacks.deliver(10, |_| Ok(()));
+    assert_eq!(results[0].0.payment.block_height, 0);
+    assert_eq!(results[0].0.attempts, 1);
+    assert_eq!(callbacks.num_pending(), 0);
+}
+
+#[test]
+fn callbacks_are_dropped_after_the_last_attempt() {
+    let mut callbacks = callbacks();
+    callbacks.on_credit(account(1), 0, TOKENs::from_e8s(100), Memo(0));
+    for attempt in 1..=MAX_CALLBACK_ATTEMPTS {
+        let results = callbacks.deliver(10, |_| Err("unreachable".to_string()));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.attempts, attempt);
+    }
+    assert_eq!(callbacks.num_pending(), 0);
+}
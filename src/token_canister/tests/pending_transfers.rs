@@ -0,0 +1,173 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, LedgerBuilder, GENESIS_TIME_NANOS};
+use ic_base_types::PrincipalId;
+use std::time::Duration;
+use token_canister::pending_transfers::{
+    pending_transfer_subaccount, PendingTransfer, MAX_CANCELLATION_WINDOW,
+};
+use token_canister::{
+    AccountIdentifier, ArchiveLagThrottle, Memo, PaymentError, PendingTransfers, TOKENs, TimeStamp,
+    TransferError,
+};
+
+const WINDOW: Duration = Duration::from_secs(3600);
+
+fn sender() -> PrincipalId {
+    PrincipalId::new_user_test_id(1)
+}
+
+fn recipient() -> PrincipalId {
+    PrincipalId::new_user_test_id(2)
+}
+
+fn create(transfers: &mut PendingTransfers, now: TimeStamp) -> PendingTransfer {
+    transfers
+        .create(
+            sender(),
+            account(1),
+            recipient(),
+            account(2),
+            TOKENs::from_e8s(1_000),
+            Memo(0),
+            WINDOW,
+            now,
+        )
+        .unwrap()
+}
+
+#[test]
+fn the_sender_can_cancel_during_the_window_only() {
+    let now = TimeStamp::from_nanos(1_000);
+    let mut transfers = PendingTransfers::default();
+    let first = create(&mut transfers, now);
+    let second = create(&mut transfers, now);
+
+    assert!(transfers.cancel(&recipient(), first.id, now).is_err());
+    assert_eq!(transfers.cancel(&sender(), first.id, now).unwrap(), first);
+    assert!(transfers.get(first.id).is_none());
+
+    assert!(transfers
+        .cancel(&sender(), second.id, now + WINDOW)
+        .is_err());
+}
+
+#[test]
+fn the_recipient_can_finalize_after_the_window_only() {
+    let now = TimeStamp::from_nanos(1_000);
+    let mut transfers = PendingTransfers::default();
+    let transfer = create(&mut transfers, now);
+
+    assert!(transfers.finalize(&recipient(), transfer.id, now).is_err());
+    assert!(transfers
+        .finalize(&sender(), transfer.id, now + WINDOW)
+        .is_err());
+    assert_eq!(
+        transfers
+            .finalize(&recipient(), transfer.id, now + WINDOW)
+            .unwrap(),
+        transfer
+    );
+    assert!(transfers.pending_transfers_of(&sender()).is_empty());
+}
+
+#[test]
+fn due_transfers_are_listed_oldest_first() {
+    let now = TimeStamp::from_nanos(1_000);
+    let mut transfers = PendingTransfers::default();
+    let first = create(&mut transfers, now);
+    let second = create(&mut transfers, now + Duration::from_secs(1));
+    let third = create(&mut transfers, now + Duration::from_secs(2));
+
+    assert!(transfers.due(now, 10).is_empty());
+    let later = now + WINDOW + Duration::from_secs(1);
+    assert_eq!(transfers.due(later, 1), vec![first.clone()]);
+    assert_eq!(
+        transfers.due(later, 10),
+        vec![first.clone(), second.clone()]
+    );
+
+    assert_eq!(transfers.settle(first.id), Some(first.clone()));
+    assert_eq!(transfers.settle(first.id), None);
+    assert_eq!(transfers.due(later, 10), vec![second.clone()]);
+    assert_eq!(
+        transfers.pending_transfers_of(&recipient()),
+        vec![second, third]
+    );
+}
+
+#[test]
+fn failed_finalizations_stay_pending() {
+    let ledger_id = PrincipalId::new_user_test_id(99);
+    let holding = AccountIdentifier::new(ledger_id, Some(pending_transfer_subaccount(0)));
+    let mut ledger = LedgerBuilder::new()
+        .mint(holding, 1_000)
+        .mint(account(3), 1)
+        .build();
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    let transfer = create(&mut ledger.pending_transfers, now);
+    // Two blocks wait to be archived, more than the throttle allows.
+    ledger.set_archive_lag_throttle(ArchiveLagThrottle {
+        trigger_threshold: 1,
+        max_lag_multiple: 1,
+    });
+
+    let results = ledger.finalize_due_pending_transfers(ledger_id, now + WINDOW, 10);
+    assert_eq!(
+        results,
+        vec![(
+            transfer.clone(),
+            Err(PaymentError::TransferError(
+                TransferError::TemporarilyUnavailable
+            ))
+        )]
+    );
+    assert_eq!(ledger.pending_transfers.get(transfer.id), Some(&transfer));
+    assert_eq!(
+        ledger.balances.account_balance(&holding),
+        TOKENs::from_e8s(1_000)
+    );
+
+    ledger.set_archive_lag_throttle(ArchiveLagThrottle {
+        trigger_threshold: 10,
+        max_lag_multiple: 1,
+    });
+    let results = ledger.finalize_due_pending_transfers(ledger_id, now + WINDOW, 10);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].1.is_ok());
+    assert_eq!(ledger.pending_transfers.get(transfer.id), None);
+    assert_eq!(
+        ledger.balances.account_balance(&account(2)),
+        TOKENs::from_e8s(1_000)
+    );
+    assert!(ledger
+        .finalize_due_pending_transfers(ledger_id, now + WINDOW, 10)
+        .is_empty());
+}
+
+#[test]
+fn windows_and_amounts_are_checked() {
+    let now = TimeStamp::from_nanos(1_000);
+    let mut transfers = PendingTransfers::default();
+    let create_with = |transfers: &mut PendingTransfers, amount, window| {
+        transfers.create(
+            sender(),
+            account(1),
+            recipient(),
+            account(2),
+            TOKENs::from_e8s(amount),
+            Memo(0),
+            window,
+            now,
+        )
+    };
+    assert!(create_with(&mut transfers, 0, WINDOW).is_err());
+    assert!(create_with(
+        &mut transfers,
+        1,
+        MAX_CANCELLATION_WINDOW + Duration::from_secs(1)
+    )
+    .is_err());
+    create_with(&mut transfers, 1, MAX_CANCELLATION_WINDOW).unwrap();
+}
@@ -0,0 +1,136 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint};
+use std::sync::Arc;
+use std::time::Duration;
+use token_canister::health::{health, CanisterResources, Health};
+use token_canister::{
+    Ledger, ManualTimeSource, Operation, PaymentError, TOKENs, TimeStamp, TransferError,
+    MAX_READ_ONLY_DURATION,
+};
+
+#[test]
+fn transactions_are_rejected_until_the_deadline() {
+    let clock = Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(1_000_000_000)));
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(clock.clone());
+    let until = clock.now() + Duration::from_secs(60);
+    ledger.set_read_only(Some(until)).unwrap();
+
+    assert!(ledger.is_read_only());
+    assert_eq!(
+        add_payment(&mut ledger, 0, mint(account(1), 1_000)),
+        Err(PaymentError::TransferError(TransferError::Migrating {
+            until
+        }))
+    );
+    assert!(matches!(
+        health(&ledger, &CanisterResources::default()),
+        Health::ReadOnly { .. }
+    ));
+
+    clock.set(until);
+    assert!(!ledger.is_read_only());
+    assert_eq!(ledger.read_only_until(), None);
+    add_payment(&mut ledger, 0, mint(account(1), 1_000)).unwrap();
+}
+
+#[test]
+fn read_only_mode_can_be_lifted_early_but_not_set_for_too_long() {
+    let clock = Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(1_000_000_000)));
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(clock.clone());
+
+    let too_late = clock.now() + MAX_READ_ONLY_DURATION + Duration::from_secs(1);
+    assert!(ledger.set_read_only(Some(too_late)).is_err());
+    assert!(!ledger.is_read_only());
+
+    ledger
+        .set_read_only(Some(clock.now() + MAX_READ_ONLY_DURATION))
+        .unwrap();
+    assert!(ledger.is_read_only());
+    ledger.set_read_only(None).unwrap();
+    add_payment(&mut ledger, 0, mint(account(1), 1_000)).unwrap();
+}
+
+#[test]
+fn an_inconsistent_pool_makes_the_ledger_read_only() {
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        1_000_000_000,
+    ))));
+    // Pretend every token was minted already, so minting more underflows
+    // the pool.
+    ledger.balances.icpt_pool = TOKENs::ZERO;
+    assert_eq!(ledger.balances.total_supply(), TOKENs::MAX);
+
+    assert!(matches!(
+        add_payment(&mut ledger, 0, mint(account(1), 1_000)),
+        Err(PaymentError::TransferError(
+            TransferError::LedgerCorrupted { .. }
+        ))
+    ));
+    assert_eq!(ledger.blockchain.chain_length(), 0);
+    assert_eq!(ledger.balances.account_balance(&account(1)), TOKENs::ZERO);
+    assert!(ledger.is_read_only());
+    assert!(ledger.corruption().is_some());
+    assert!(matches!(
+        health(&ledger, &CanisterResources::default()),
+        Health::ReadOnly { .. }
+    ));
+
+    // Even a consistent transaction is rejected until the corruption is
+    // cleared.
+    ledger.balances.icpt_pool = TOKENs::MAX;
+    assert!(matches!(
+        add_payment(&mut ledger, 0, mint(account(1), 1_000)),
+        Err(PaymentError::TransferError(
+            TransferError::LedgerCorrupted { .. }
+        ))
+    ));
+    ledger.clear_corruption();
+    assert!(!ledger.is_read_only());
+    add_payment(&mut ledger, 0, mint(account(1), 1_000)).unwrap();
+}
+
+#[test]
+fn the_heartbeat_check_keeps_the_ledger_read_only_after_a_trap() {
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        1_000_000_000,
+    ))));
+    add_payment(&mut ledger, 0, mint(account(1), 1_000)).unwrap();
+    ledger.balances.check_supply().unwrap();
+    ledger.check_balances();
+    assert!(!ledger.is_read_only());
+
+    // Lose track of the minted tokens, so that returning a fee to the pool
+    // overflows it.
+    ledger.balances.icpt_pool = TOKENs::MAX;
+    let transfer = Operation::Transfer {
+        from: account(1),
+        to: account(2),
+        amount: TOKENs::from_e8s(100),
+        fee: TOKENs::from_e8s(10),
+    };
+    assert!(matches!(
+        add_payment(&mut ledger, 1, transfer.clone()),
+        Err(PaymentError::TransferError(
+            TransferError::LedgerCorrupted { .. }
+        ))
+    ));
+    // Endpoints trap on the error, which rolls back the corruption the
+    // ledger marked.
+    ledger.clear_corruption();
+    assert!(!ledger.is_read_only());
+
+    ledger.check_balances();
+    assert!(ledger.is_read_only());
+    assert!(matches!(
+        add_payment(&mut ledger, 2, transfer),
+        Err(PaymentError::TransferError(
+            TransferError::LedgerCorrupted { .. }
+        ))
+    ));
+}
@@ -0,0 +1,108 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::account;
+use token_canister::refunds::{RefundReference, Refunds};
+use token_canister::{Operation, TOKENs, TRANSACTION_FEE};
+
+fn transfer(amount: u64) -> Operation {
+    Operation::Transfer {
+        from: account(1),
+        to: account(2),
+        amount: TOKENs::from_e8s(amount),
+        fee: TRANSACTION_FEE,
+    }
+}
+
+#[test]
+fn refunds_go_back_to_the_payer() {
+    let refunds = Refunds::default();
+    let refund = refunds
+        .refund_operation(
+            7,
+            &transfer(1_000),
+            &account(2),
+            TOKENs::from_e8s(400),
+            TRANSACTION_FEE,
+        )
+        .unwrap();
+    assert_eq!(
+        refund,
+        Operation::Transfer {
+            from: account(2),
+            to: account(1),
+            amount: TOKENs::from_e8s(400),
+            fee: TRANSACTION_FEE,
+        }
+    );
+}
+
+#[test]
+fn only_the_recipient_can_refund() {
+    let refunds = Refunds::default();
+    assert!(refunds
+        .refund_operation(
+            7,
+            &transfer(1_000),
+            &account(3),
+            TOKENs::from_e8s(400),
+            TRANSACTION_FEE
+        )
+        .is_err());
+    let mint = Operation::Mint {
+        to: account(2),
+        amount: TOKENs::from_e8s(1_000),
+        fee: TOKENs::ZERO,
+    };
+    assert!(refunds
+        .refund_operation(
+            7,
+            &mint,
+            &account(2),
+            TOKENs::from_e8s(400),
+            TRANSACTION_FEE
+        )
+        .is_err());
+}
+
+#[test]
+fn refunds_never_exceed_the_original_amount() {
+    let mut refunds = Refunds::default();
+    let original = transfer(1_000);
+    let refund = |refunds: &Refunds, amount| {
+        refunds.refund_operation(
+            7,
+            &original,
+            &account(2),
+            TOKENs::from_e8s(amount),
+            TRANSACTION_FEE,
+        )
+    };
+
+    assert!(refund(&refunds, 1_001).is_err());
+    refund(&refunds, 600).unwrap();
+    refunds.record(7, TOKENs::from_e8s(600), 0).unwrap();
+    assert!(refund(&refunds, 401).is_err());
+    refund(&refunds, 400).unwrap();
+    assert!(refund(&refunds, 0).is_err());
+}
+
+#[test]
+fn refunds_of_blocks_no_longer_held_are_forgotten() {
+    let mut refunds = Refunds::default();
+    refunds.record(3, TOKENs::from_e8s(100), 0).unwrap();
+    refunds.record(7, TOKENs::from_e8s(100), 5).unwrap();
+    assert_eq!(refunds.refunded(3), TOKENs::ZERO);
+    assert_eq!(refunds.refunded(7), TOKENs::from_e8s(100));
+}
+
+#[test]
+fn refund_references_round_trip_through_memo_blobs() {
+    let reference = RefundReference {
+        original_height: 42,
+    };
+    assert_eq!(
+        RefundReference::from_memo_blob(&reference.to_memo_blob()).unwrap(),
+        reference
+    );
+}
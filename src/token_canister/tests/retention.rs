@@ -0,0 +1,109 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint, GENESIS_TIME_NANOS};
+use std::sync::Arc;
+use std::time::Duration;
+use token_canister::{GetBlocksError, Ledger, ManualTimeSource, RetentionPolicy, TimeStamp};
+
+fn ledger_with_blocks(num_blocks: u64, clock: Arc<ManualTimeSource>) -> Ledger {
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(clock.clone());
+    for i in 0..num_blocks {
+        add_payment(&mut ledger, i, mint(account(1), 1)).unwrap();
+        clock.advance(Duration::from_secs(1));
+    }
+    ledger
+}
+
+fn clock() -> Arc<ManualTimeSource> {
+    Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        GENESIS_TIME_NANOS,
+    )))
+}
+
+#[test]
+fn pruning_keeps_the_hashes_of_pruned_blocks() {
+    let mut ledger = ledger_with_blocks(10, clock());
+    let hashes: Vec<_> = ledger.blockchain.blocks.iter().map(|b| b.hash()).collect();
+    ledger.set_retention_policy(RetentionPolicy {
+        max_age: None,
+        max_blocks: Some(4),
+    });
+    ledger.blockchain.confirm_exported(10).unwrap();
+
+    assert_eq!(ledger.apply_retention_policy().unwrap(), 6);
+    assert_eq!(ledger.blockchain.num_pruned_blocks(), 6);
+    assert_eq!(ledger.blockchain.chain_length(), 10);
+    for (height, hash) in hashes.iter().enumerate().take(6) {
+        assert_eq!(
+            ledger.blockchain.pruned_block_hash(height as u64),
+            Some(*hash)
+        );
+    }
+    assert_eq!(ledger.blockchain.pruned_block_hash(6), None);
+
+    let res = ledger.blockchain.get_blocks(6, 4);
+    let blocks = res.blocks.unwrap();
+    assert_eq!(blocks[0].decode().unwrap().parent_hash, Some(hashes[5]));
+}
+
+#[test]
+fn only_exported_blocks_are_pruned() {
+    let clock = clock();
+    let mut ledger = ledger_with_blocks(10, clock.clone());
+    // The clock is at 10 seconds, so blocks 0..=2 are at least 8 seconds old.
+    ledger.set_retention_policy(RetentionPolicy {
+        max_age: Some(Duration::from_secs(8)),
+        max_blocks: None,
+    });
+
+    assert_eq!(ledger.apply_retention_policy().unwrap(), 0);
+    ledger.blockchain.confirm_exported(2).unwrap();
+    assert_eq!(ledger.apply_retention_policy().unwrap(), 2);
+    assert!(ledger.blockchain.prune_blocks(1).is_err());
+    assert!(ledger.blockchain.confirm_exported(11).is_err());
+
+    // Nothing more is pruned until time moves on.
+    ledger.blockchain.confirm_exported(10).unwrap();
+    assert_eq!(ledger.apply_retention_policy().unwrap(), 1);
+    assert_eq!(ledger.apply_retention_policy().unwrap(), 0);
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(ledger.apply_retention_policy().unwrap(), 2);
+    assert_eq!(ledger.blockchain.num_pruned_blocks(), 5);
+}
+
+#[test]
+fn get_blocks_refuses_pruned_heights() {
+    let mut ledger = ledger_with_blocks(5, clock());
+    ledger.set_retention_policy(RetentionPolicy {
+        max_age: None,
+        max_blocks: Some(2),
+    });
+    ledger.blockchain.confirm_exported(5).unwrap();
+    ledger.apply_retention_policy().unwrap();
+
+    let res = ledger.blockchain.get_blocks(1, 2);
+    assert_eq!(
+        res.blocks,
+        Err(GetBlocksError::Pruned {
+            earliest_available: 3
+        })
+    );
+    assert_eq!(res.first_block_index, 3);
+    assert!(ledger.blockchain.get(2).is_none());
+    assert!(ledger.blockchain.get(3).is_some());
+}
+
+#[test]
+fn the_tip_is_never_pruned() {
+    let mut ledger = ledger_with_blocks(3, clock());
+    ledger.set_retention_policy(RetentionPolicy {
+        max_age: None,
+        max_blocks: Some(0),
+    });
+    ledger.blockchain.confirm_exported(3).unwrap();
+
+    assert_eq!(ledger.apply_retention_policy().unwrap(), 2);
+    assert_eq!(ledger.blockchain.num_unarchived_blocks(), 1);
+}
@@ -0,0 +1,37 @@
+use ic_base_types::PrincipalId;
+use token_canister::roles::{Role, RoleAssignment, RoleRegistry};
+
+#[test]
+fn roles_are_granted_and_revoked_separately() {
+    let alice = PrincipalId::new_user_test_id(1);
+    let bob = PrincipalId::new_user_test_id(2);
+    let mut roles = RoleRegistry::default();
+    roles.grant(alice, Role::FeeManager);
+    roles.grant(alice, Role::Freezer);
+    roles.grant(alice, Role::Freezer);
+    roles.grant(bob, Role::Admin);
+
+    assert!(roles.has_role(&alice, Role::Freezer));
+    // Admins do not implicitly hold the other roles.
+    assert!(!roles.has_role(&bob, Role::Minter));
+    assert_eq!(
+        roles.list(),
+        vec![
+            RoleAssignment {
+                principal: alice,
+                roles: vec![Role::FeeManager, Role::Freezer],
+            },
+            RoleAssignment {
+                principal: bob,
+                roles: vec![Role::Admin],
+            },
+        ]
+    );
+
+    assert!(roles.revoke(&alice, Role::Freezer));
+    assert!(!roles.revoke(&alice, Role::Freezer));
+    assert_eq!(roles.roles_of(&alice), vec![Role::FeeManager]);
+    assert!(roles.revoke(&bob, Role::Admin));
+    assert!(roles.roles_of(&bob).is_empty());
+    assert_eq!(roles.list().len(), 1);
+}
@@ -0,0 +1,64 @@
+#![allow(clippy::unwrap_used)]
+
+use candid::Encode;
+use token_canister::{
+    validate_send_arg_size, AccountIdentifier, ArgumentError, EncryptedMemo, Memo, SendArgs,
+    Subaccount, TOKENs, TimeStamp, MAX_ENCRYPTED_MEMO_LENGTH, MAX_IDEMPOTENCY_KEY_LENGTH,
+    MAX_SEND_ARG_SIZE_BYTES, TRANSACTION_FEE,
+};
+
+fn send_args() -> SendArgs {
+    SendArgs {
+        memo: Memo(u64::MAX),
+        amount: TOKENs::from_e8s(u64::MAX),
+        fee: TRANSACTION_FEE,
+        from_subaccount: Some(Subaccount([0xff; 32])),
+        to: AccountIdentifier { hash: [0xff; 28] },
+        created_at_time: Some(TimeStamp::from_nanos(u64::MAX)),
+        idempotency_key: Some(vec![0xff; MAX_IDEMPOTENCY_KEY_LENGTH]),
+        encrypted_memo: Some(EncryptedMemo(vec![0xff; MAX_ENCRYPTED_MEMO_LENGTH])),
+        expires_at: Some(TimeStamp::from_nanos(u64::MAX)),
+    }
+}
+
+#[test]
+fn the_largest_valid_argument_fits() {
+    let args = send_args();
+    assert_eq!(args.validate(), Ok(()));
+    let size = Encode!(&args).unwrap().len();
+    assert_eq!(validate_send_arg_size(size), Ok(()));
+}
+
+#[test]
+fn oversized_arguments_are_rejected() {
+    assert_eq!(
+        validate_send_arg_size(MAX_SEND_ARG_SIZE_BYTES + 1),
+        Err(ArgumentError::TooLarge {
+            size: MAX_SEND_ARG_SIZE_BYTES as u64 + 1,
+            max: MAX_SEND_ARG_SIZE_BYTES as u64,
+        })
+    );
+}
+
+#[test]
+fn field_lengths_are_checked() {
+    let mut args = send_args();
+    args.encrypted_memo = Some(EncryptedMemo(vec![0; MAX_ENCRYPTED_MEMO_LENGTH + 1]));
+    assert_eq!(
+        args.validate(),
+        Err(ArgumentError::EncryptedMemoLength {
+            length: MAX_ENCRYPTED_MEMO_LENGTH as u64 + 1,
+            max: MAX_ENCRYPTED_MEMO_LENGTH as u64,
+        })
+    );
+
+    let mut args = send_args();
+    args.idempotency_key = Some(vec![]);
+    assert_eq!(
+        args.validate(),
+        Err(ArgumentError::IdempotencyKeyLength {
+            length: 0,
+            max: MAX_IDEMPOTENCY_KEY_LENGTH as u64,
+        })
+    );
+}
@@ -0,0 +1,39 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::account;
+use token_canister::Ledger;
+
+#[test]
+fn nonces_must_strictly_increase() {
+    let mut ledger = Ledger::default();
+    assert_eq!(ledger.signed_transfer_nonce(&account(1)), 0);
+
+    ledger.use_signed_transfer_nonce(&account(1), 0).unwrap();
+    assert_eq!(ledger.signed_transfer_nonce(&account(1)), 1);
+    assert!(ledger.use_signed_transfer_nonce(&account(1), 0).is_err());
+
+    // Nonces may be skipped, but not used out of order afterwards.
+    ledger.use_signed_transfer_nonce(&account(1), 5).unwrap();
+    assert_eq!(ledger.signed_transfer_nonce(&account(1)), 6);
+    assert!(ledger.use_signed_transfer_nonce(&account(1), 3).is_err());
+    assert!(ledger.use_signed_transfer_nonce(&account(1), 5).is_err());
+
+    // Each account has its own nonces.
+    assert_eq!(ledger.signed_transfer_nonce(&account(2)), 0);
+    ledger.use_signed_transfer_nonce(&account(2), 0).unwrap();
+}
+
+#[test]
+fn the_largest_nonce_is_reserved() {
+    let mut ledger = Ledger::default();
+    assert!(ledger
+        .use_signed_transfer_nonce(&account(1), u64::MAX)
+        .is_err());
+    ledger
+        .use_signed_transfer_nonce(&account(1), u64::MAX - 1)
+        .unwrap();
+    assert!(ledger
+        .use_signed_transfer_nonce(&account(1), u64::MAX - 1)
+        .is_err());
+}
@@ -0,0 +1,70 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, GENESIS_TIME_NANOS};
+use ic_types::PrincipalId;
+use std::time::Duration;
+use token_canister::staking::{Staking, StakingConfig};
+use token_canister::{TOKENs, TimeStamp};
+
+const YEAR: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+// 10% a year, so staking 1000 e8s for a year earns 100 e8s.
+fn staking() -> Staking {
+    let mut staking = Staking::default();
+    staking
+        .set_config(Some(StakingConfig {
+            annual_reward_rate_basis_points: 1_000,
+            min_duration: Duration::from_secs(1),
+            max_duration: YEAR,
+        }))
+        .unwrap();
+    staking
+}
+
+#[test]
+fn stakes_reserve_their_reward_from_the_pool() {
+    let mut staking = staking();
+    let owner = PrincipalId::new_user_test_id(1);
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    let pool = TOKENs::from_e8s(150);
+    let amount = TOKENs::from_e8s(1_000);
+
+    let first = staking
+        .open(owner, account(1), amount, YEAR, pool, now)
+        .unwrap();
+    assert_eq!(first.reserved_reward, TOKENs::from_e8s(100));
+    assert_eq!(staking.reserved_rewards(), TOKENs::from_e8s(100));
+
+    // Only 50 e8s of the pool are left unreserved.
+    assert!(staking
+        .open(owner, account(1), amount, YEAR, pool, now)
+        .is_err());
+    assert_eq!(staking.reserved_rewards(), TOKENs::from_e8s(100));
+    let second = staking
+        .open(owner, account(1), amount, YEAR / 2, pool, now)
+        .unwrap();
+    assert_eq!(second.reserved_reward, TOKENs::from_e8s(50));
+    assert_eq!(staking.reserved_rewards(), TOKENs::from_e8s(150));
+}
+
+#[test]
+fn unstaking_releases_the_reservation() {
+    let mut staking = staking();
+    let owner = PrincipalId::new_user_test_id(1);
+    let now = TimeStamp::from_nanos(GENESIS_TIME_NANOS);
+    let pool = TOKENs::from_e8s(100);
+    let amount = TOKENs::from_e8s(1_000);
+
+    let stake = staking
+        .open(owner, account(1), amount, YEAR, pool, now)
+        .unwrap();
+    assert!(staking.close(&owner, stake.id, now).is_err());
+    assert_eq!(staking.reserved_rewards(), TOKENs::from_e8s(100));
+
+    let closed = staking
+        .close(&owner, stake.id, now.checked_add(YEAR).unwrap())
+        .unwrap();
+    assert_eq!(closed.reserved_reward, TOKENs::from_e8s(100));
+    assert_eq!(staking.reserved_rewards(), TOKENs::ZERO);
+}
@@ -0,0 +1,70 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint, GENESIS_TIME_NANOS};
+use std::sync::Arc;
+use token_canister::state_hash::state_hash;
+use token_canister::{Ledger, ManualTimeSource, Operation, TOKENs, TimeStamp};
+
+fn ledger_with_mints(amounts: &[u64]) -> Ledger {
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        GENESIS_TIME_NANOS,
+    ))));
+    for (i, amount) in amounts.iter().enumerate() {
+        add_payment(&mut ledger, i as u64, mint(account(i as u8 + 1), *amount)).unwrap();
+    }
+    ledger
+}
+
+#[test]
+fn copies_applying_the_same_operations_agree() {
+    let a = ledger_with_mints(&[1, 2, 3]);
+    let b = ledger_with_mints(&[1, 2, 3]);
+    assert_eq!(state_hash(&a), state_hash(&b));
+    assert_eq!(a.metrics().state_hash, hex::encode(state_hash(&a)));
+}
+
+#[test]
+fn the_balances_hash_matches_a_recount() {
+    let mut ledger = ledger_with_mints(&[1, 2, 3]);
+    let operations = [
+        // Empties account 1, so that its balance leaves the hash.
+        Operation::Transfer {
+            from: account(1),
+            to: account(2),
+            amount: TOKENs::from_e8s(1),
+            fee: TOKENs::ZERO,
+        },
+        Operation::Burn {
+            from: account(3),
+            amount: TOKENs::from_e8s(2),
+            fee: TOKENs::ZERO,
+        },
+    ];
+    for (i, operation) in operations.iter().enumerate() {
+        add_payment(&mut ledger, 10 + i as u64, operation.clone()).unwrap();
+    }
+    let kept = *ledger.balances.balances_hash();
+    ledger.balances.recount();
+    assert_eq!(*ledger.balances.balances_hash(), kept);
+    // The same balances reached by other operations hash the same.
+    let mut other = Ledger::default();
+    other.set_time_source(Arc::new(ManualTimeSource::new(TimeStamp::from_nanos(
+        GENESIS_TIME_NANOS,
+    ))));
+    for (to, amount) in &[(2, 3), (3, 1)] {
+        add_payment(&mut other, *to as u64, mint(account(*to), *amount)).unwrap();
+    }
+    assert_eq!(*other.balances.balances_hash(), kept);
+}
+
+#[test]
+fn the_hash_covers_balances_and_configuration() {
+    let a = ledger_with_mints(&[1, 2, 3]);
+    assert_ne!(state_hash(&a), state_hash(&ledger_with_mints(&[1, 2, 4])));
+
+    let mut b = ledger_with_mints(&[1, 2, 3]);
+    b.symbol = "OTHER".to_string();
+    assert_ne!(state_hash(&a), state_hash(&b));
+}
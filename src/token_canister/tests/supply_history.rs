@@ -0,0 +1,65 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment};
+use std::sync::Arc;
+use token_canister::{Ledger, ManualTimeSource, Operation, SupplyCheckpoint, TOKENs, TimeStamp};
+
+#[test]
+fn mints_and_burns_are_checkpointed() {
+    let now = TimeStamp::from_nanos(1_000_000_000);
+    let mut ledger = Ledger::default();
+    ledger.set_time_source(Arc::new(ManualTimeSource::new(now)));
+
+    let mint = add_payment(
+        &mut ledger,
+        0,
+        Operation::Mint {
+            to: account(1),
+            amount: TOKENs::from_e8s(1_000),
+            fee: TOKENs::ZERO,
+        },
+    )
+    .unwrap();
+    add_payment(
+        &mut ledger,
+        0,
+        Operation::Transfer {
+            from: account(1),
+            to: account(2),
+            amount: TOKENs::from_e8s(100),
+            fee: TOKENs::from_e8s(10),
+        },
+    )
+    .unwrap();
+    let burn = add_payment(
+        &mut ledger,
+        0,
+        Operation::Burn {
+            from: account(2),
+            amount: TOKENs::from_e8s(50),
+            fee: TOKENs::ZERO,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        ledger.supply_history(0, 10),
+        vec![
+            SupplyCheckpoint {
+                height: mint,
+                timestamp: now,
+                total_supply: TOKENs::from_e8s(1_000),
+            },
+            // The fee burned by the transfer shows up at the next checkpoint.
+            SupplyCheckpoint {
+                height: burn,
+                timestamp: now,
+                total_supply: TOKENs::from_e8s(940),
+            },
+        ]
+    );
+    assert_eq!(ledger.supply_history(mint + 1, 10).len(), 1);
+    assert_eq!(ledger.supply_history(0, 1).len(), 1);
+    assert!(ledger.supply_history(burn + 1, 10).is_empty());
+}
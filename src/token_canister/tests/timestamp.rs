@@ -0,0 +1,109 @@
+use std::time::Duration;
+use token_canister::TimeStamp;
+
+const MAX: TimeStamp = TimeStamp::from_nanos(u64::MAX);
+
+#[test]
+fn checked_add_fails_past_u64_max() {
+    let t = TimeStamp::from_nanos(10);
+    assert_eq!(
+        t.checked_add(Duration::from_nanos(5)),
+        Some(TimeStamp::from_nanos(15))
+    );
+    assert_eq!(
+        TimeStamp::from_nanos(u64::MAX - 1).checked_add(Duration::from_nanos(1)),
+        Some(MAX)
+    );
+    assert_eq!(MAX.checked_add(Duration::from_nanos(0)), Some(MAX));
+    assert_eq!(MAX.checked_add(Duration::from_nanos(1)), None);
+    // Durations longer than u64::MAX nanoseconds overflow even from zero.
+    assert_eq!(
+        TimeStamp::from_nanos(0).checked_add(Duration::from_secs(u64::MAX)),
+        None
+    );
+}
+
+#[test]
+fn checked_sub_fails_before_the_epoch() {
+    let t = TimeStamp::from_nanos(10);
+    assert_eq!(
+        t.checked_sub(Duration::from_nanos(10)),
+        Some(TimeStamp::from_nanos(0))
+    );
+    assert_eq!(t.checked_sub(Duration::from_nanos(11)), None);
+    assert_eq!(
+        MAX.checked_sub(Duration::from_nanos(u64::MAX)),
+        Some(TimeStamp::from_nanos(0))
+    );
+    assert_eq!(MAX.checked_sub(Duration::from_secs(u64::MAX)), None);
+}
+
+#[test]
+fn saturating_arithmetic_stops_at_the_bounds() {
+    let t = TimeStamp::from_nanos(10);
+    assert_eq!(
+        t.saturating_add(Duration::from_nanos(5)),
+        TimeStamp::from_nanos(15)
+    );
+    assert_eq!(MAX.saturating_add(Duration::from_nanos(1)), MAX);
+    assert_eq!(t.saturating_add(Duration::from_secs(u64::MAX)), MAX);
+    assert_eq!(
+        t.saturating_sub(Duration::from_nanos(11)),
+        TimeStamp::from_nanos(0)
+    );
+    assert_eq!(
+        t.saturating_sub(Duration::from_secs(u64::MAX)),
+        TimeStamp::from_nanos(0)
+    );
+}
+
+#[test]
+#[should_panic(expected = "overflowed")]
+fn adding_past_u64_max_panics() {
+    let _ = MAX + Duration::from_nanos(1);
+}
+
+#[test]
+#[should_panic(expected = "underflowed")]
+fn subtracting_before_the_epoch_panics() {
+    let _ = TimeStamp::from_nanos(0) - Duration::from_nanos(1);
+}
+
+#[test]
+fn windows_elapse_once_now_reaches_their_end() {
+    let start = TimeStamp::from_nanos(100);
+    let window = Duration::from_nanos(50);
+    assert!(!start.has_elapsed(window, TimeStamp::from_nanos(149)));
+    assert!(start.has_elapsed(window, TimeStamp::from_nanos(150)));
+    assert!(start.has_elapsed(Duration::from_nanos(0), start));
+
+    // A window ending past u64::MAX saturates, so it only elapses at
+    // u64::MAX itself.
+    let late = TimeStamp::from_nanos(u64::MAX - 10);
+    assert!(!late.has_elapsed(
+        Duration::from_nanos(20),
+        TimeStamp::from_nanos(u64::MAX - 1)
+    ));
+    assert!(late.has_elapsed(Duration::from_nanos(20), MAX));
+    assert!(late.has_elapsed(Duration::from_secs(u64::MAX), MAX));
+}
+
+#[test]
+fn ordering_and_durations_between_timestamps() {
+    let earlier = TimeStamp::from_nanos(1);
+    let later = TimeStamp::from_nanos(2);
+    assert!(earlier.is_before(later));
+    assert!(!later.is_before(earlier));
+    assert!(!earlier.is_before(earlier));
+    assert!(later.is_after(earlier));
+    assert!(!earlier.is_after(later));
+    assert!(!later.is_after(later));
+
+    assert_eq!(later.duration_since(earlier), Some(Duration::from_nanos(1)));
+    assert_eq!(later.duration_since(later), Some(Duration::from_nanos(0)));
+    assert_eq!(earlier.duration_since(later), None);
+    assert_eq!(
+        MAX.duration_since(TimeStamp::from_nanos(0)),
+        Some(Duration::from_nanos(u64::MAX))
+    );
+}
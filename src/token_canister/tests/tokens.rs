@@ -0,0 +1,109 @@
+use token_canister::{
+    validate_decimals, TOKENs, DECIMAL_PLACES, MAX_DECIMAL_PLACES, TOKEN_SUBDIVIDABLE_BY,
+};
+
+#[test]
+fn display_uses_the_default_decimals() {
+    assert_eq!(10u64.pow(DECIMAL_PLACES), TOKEN_SUBDIVIDABLE_BY);
+    assert_eq!(
+        TOKENs::from_e8s(TOKEN_SUBDIVIDABLE_BY + 5).to_string(),
+        "1.000000000000000005 TOKEN"
+    );
+}
+
+#[test]
+fn amounts_render_with_the_given_decimals() {
+    let amount = TOKENs::from_e8s(150_000_000);
+    assert_eq!(amount.to_string_with_decimals(8), "1.50000000");
+    assert_eq!(amount.to_string_with_decimals(2), "1500000.00");
+    assert_eq!(amount.to_string_with_decimals(0), "150000000");
+    assert_eq!(
+        TOKENs::from_e8s(1).to_string_with_decimals(MAX_DECIMAL_PLACES),
+        "0.0000000000000000001"
+    );
+}
+
+#[test]
+fn amounts_parse_with_the_given_decimals() {
+    let parse = TOKENs::parse_with_decimals;
+    assert_eq!(parse("1.5", 8), Ok(TOKENs::from_e8s(150_000_000)));
+    assert_eq!(parse("1.50000000", 8), Ok(TOKENs::from_e8s(150_000_000)));
+    assert_eq!(parse("42", 0), Ok(TOKENs::from_e8s(42)));
+    assert_eq!(parse("0.00000001", 8), Ok(TOKENs::from_e8s(1)));
+
+    assert!(parse("0.000000001", 8).is_err());
+    assert!(parse("1.5", 0).is_err());
+    assert!(parse("-1", 8).is_err());
+    assert!(parse(".5", 8).is_err());
+    assert!(parse("1,5", 8).is_err());
+    assert!(parse("184467440737.09551616", 8).is_err());
+    assert!(parse("1", MAX_DECIMAL_PLACES + 1).is_err());
+
+    for decimals in &[0, 8, 18] {
+        let amount = TOKENs::from_e8s(123_456_789_012);
+        let rendered = amount.to_string_with_decimals(*decimals);
+        assert_eq!(parse(&rendered, *decimals), Ok(amount));
+    }
+}
+
+#[test]
+fn decimals_must_keep_the_fee_below_one_token() {
+    assert!(validate_decimals(DECIMAL_PLACES).is_ok());
+    assert!(validate_decimals(8).is_ok());
+    assert!(validate_decimals(MAX_DECIMAL_PLACES).is_ok());
+    // The fee of 10_000 base units is a whole token with 4 decimals.
+    assert!(validate_decimals(4).is_err());
+    assert!(validate_decimals(MAX_DECIMAL_PLACES + 1).is_err());
+}
+
+#[test]
+fn ratios_round_down_and_do_not_overflow_midway() {
+    let amount = TOKENs::from_e8s(1_000);
+    assert_eq!(amount.checked_mul_ratio(1, 3), Some(TOKENs::from_e8s(333)));
+    assert_eq!(amount.checked_mul_ratio(2, 3), Some(TOKENs::from_e8s(666)));
+    assert_eq!(amount.checked_mul_ratio(0, 3), Some(TOKENs::ZERO));
+    assert_eq!(amount.checked_mul_ratio(1, 0), None);
+    assert_eq!(
+        TOKENs::ZERO.checked_mul_ratio(u64::MAX, 1),
+        Some(TOKENs::ZERO)
+    );
+
+    // The product exceeds a u64, the result does not.
+    assert_eq!(
+        TOKENs::MAX.checked_mul_ratio(u64::MAX, u64::MAX),
+        Some(TOKENs::MAX)
+    );
+    assert_eq!(
+        TOKENs::MAX.checked_mul_ratio(3, 4),
+        Some(TOKENs::from_e8s(u64::MAX / 4 * 3 + 2))
+    );
+    assert_eq!(TOKENs::MAX.checked_mul_ratio(2, 1), None);
+    assert_eq!(TOKENs::MAX.checked_mul_ratio(u64::MAX, u64::MAX - 1), None);
+}
+
+#[test]
+fn percentages_round_down() {
+    let amount = TOKENs::from_e8s(1_999);
+    assert_eq!(amount.percent_of(0), Some(TOKENs::ZERO));
+    assert_eq!(amount.percent_of(1), Some(TOKENs::from_e8s(19)));
+    assert_eq!(amount.percent_of(50), Some(TOKENs::from_e8s(999)));
+    assert_eq!(amount.percent_of(100), Some(amount));
+    assert_eq!(amount.percent_of(250), Some(TOKENs::from_e8s(4_997)));
+    assert_eq!(TOKENs::MAX.percent_of(100), Some(TOKENs::MAX));
+    assert_eq!(TOKENs::MAX.percent_of(101), None);
+    assert_eq!(
+        TOKENs::from_e8s(1).percent_of(u64::MAX),
+        Some(TOKENs::from_e8s(u64::MAX / 100))
+    );
+}
+
+#[test]
+fn min_and_max_pick_an_amount() {
+    let small = TOKENs::from_e8s(1);
+    let large = TOKENs::MAX;
+    assert_eq!(small.min(large), small);
+    assert_eq!(large.min(small), small);
+    assert_eq!(small.max(large), large);
+    assert_eq!(large.max(small), large);
+    assert_eq!(small.min(small), small);
+}
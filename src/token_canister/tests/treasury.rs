@@ -0,0 +1,83 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, LedgerBuilder};
+use ic_base_types::PrincipalId;
+use token_canister::{Ledger, Operation, PaymentError, TOKENs, TransferError, TreasuryApproval};
+
+/// A ledger whose treasury holds account 1, funded with 100_000 e8s.
+fn treasury_ledger() -> Ledger {
+    let mut ledger = LedgerBuilder::new().mint(account(1), 100_000).build();
+    ledger
+        .treasury
+        .set_governance(Some(PrincipalId::new_user_test_id(7)))
+        .unwrap();
+    ledger.treasury.add_account(account(1)).unwrap();
+    ledger
+}
+
+fn transfer() -> Operation {
+    Operation::Transfer {
+        from: account(1),
+        to: account(2),
+        amount: TOKENs::from_e8s(1_000),
+        fee: TOKENs::from_e8s(10),
+    }
+}
+
+#[test]
+fn treasury_debits_need_an_approval() {
+    let mut ledger = treasury_ledger();
+    assert_eq!(
+        add_payment(&mut ledger, 1, transfer()),
+        Err(PaymentError::TransferError(
+            TransferError::TreasuryApprovalRequired { from: account(1) }
+        ))
+    );
+    assert_eq!(ledger.balances.account_balance(&account(2)), TOKENs::ZERO);
+
+    // An approval for another amount does not help.
+    ledger
+        .treasury
+        .approve(TreasuryApproval {
+            from: account(1),
+            to: Some(account(2)),
+            amount: TOKENs::from_e8s(999),
+        })
+        .unwrap();
+    assert!(add_payment(&mut ledger, 2, transfer()).is_err());
+
+    ledger
+        .treasury
+        .approve(TreasuryApproval {
+            from: account(1),
+            to: Some(account(2)),
+            amount: TOKENs::from_e8s(1_000),
+        })
+        .unwrap();
+    add_payment(&mut ledger, 3, transfer()).unwrap();
+    assert_eq!(
+        ledger.balances.account_balance(&account(2)),
+        TOKENs::from_e8s(1_000)
+    );
+
+    // The approval was used up.
+    assert!(add_payment(&mut ledger, 4, transfer()).is_err());
+    assert_eq!(ledger.treasury.info().approvals.len(), 1);
+}
+
+#[test]
+fn removed_treasury_accounts_are_ordinary_again() {
+    let mut ledger = treasury_ledger();
+    assert!(ledger.treasury.remove_account(&account(1)));
+    add_payment(&mut ledger, 1, transfer()).unwrap();
+}
+
+#[test]
+fn the_governance_cannot_be_unset_while_there_are_treasury_accounts() {
+    let mut ledger = treasury_ledger();
+    assert!(ledger.treasury.set_governance(None).is_err());
+    assert!(ledger.treasury.remove_account(&account(1)));
+    ledger.treasury.set_governance(None).unwrap();
+    assert!(ledger.treasury.add_account(account(1)).is_err());
+}
@@ -0,0 +1,24 @@
+#![allow(clippy::unwrap_used)]
+mod fixtures;
+
+use fixtures::{account, add_payment, mint};
+use token_canister::upgrade::estimate_upgrade;
+use token_canister::Ledger;
+
+#[test]
+fn the_estimate_grows_with_the_state() {
+    let mut ledger = Ledger::default();
+    let empty = estimate_upgrade(&ledger);
+    assert_eq!(empty.state_size_bytes, 0);
+    assert!(empty.within_budget());
+
+    for i in 0..10 {
+        add_payment(&mut ledger, i as u64, mint(account(i + 1), 1)).unwrap();
+    }
+    let estimate = estimate_upgrade(&ledger);
+    assert!(estimate.state_size_bytes > 0);
+    assert!(estimate.instructions > empty.instructions);
+
+    ledger.upgrade_instruction_budget = estimate.instructions - 1;
+    assert!(!estimate_upgrade(&ledger).within_budget());
+}